@@ -1,21 +1,25 @@
 mod logging;
 mod opts;
+mod secret;
 
 use std::collections::BTreeMap;
-use std::str::FromStr as _;
 use std::sync::Arc;
 
 use bfte_consensus_core::module::ModuleKind;
+use bfte_db::Database;
 use bfte_derive_secret::DeriveableSecret;
 use bfte_module::module::{DynModuleInit, IModuleInit};
-use bfte_node::Node;
 use bfte_node::derive_secret_ext::DeriveSecretExt as _;
+use bfte_node::{Node, PruningConfig, StorageMode};
+use bfte_signer::DynSigner;
+use bfte_signer::local::LocalSigner;
+use bfte_signer::remote::RemoteSigner;
 use bfte_util_error::WhateverResult;
 use clap::Parser as _;
-use opts::{Commands, Opts};
+use opts::{Commands, Opts, SecretCommands};
 use snafu::{OptionExt as _, ResultExt};
+use tracing::warn;
 
-#[allow(dead_code)]
 const LOG_TARGET: &str = "bfte::bin";
 
 pub struct Bfte {
@@ -44,53 +48,116 @@ impl Bfte {
 
         let opts = Opts::parse();
 
-        let secret = if let Some(secret_path) = opts.secret_path {
+        let secret = if let Some(secret_path) = opts.secret_path.as_ref() {
+            Some(secret::read_secret_file(secret_path, opts.secret_passphrase.as_deref()).await?)
+        } else {
+            None
+        };
+        let next_secret = if let Some(next_secret_path) = opts.next_secret_path.as_ref() {
             Some(
-                DeriveableSecret::from_str(
-                    tokio::fs::read_to_string(secret_path)
-                        .await
-                        .whatever_context("Failed to read secret file")?
-                        .trim(),
-                )
-                .whatever_context("Failed to parse secret")?,
+                secret::read_secret_file(next_secret_path, opts.secret_passphrase.as_deref())
+                    .await?,
             )
         } else {
             None
         };
-        let peer_pubkey = secret.map(|root_secret| {
-            root_secret
-                .get_peer_seckey()
-                .expect("Just created, must be root")
-                .pubkey()
-        });
-
-        let db_path = if let Some(data_dir) = opts.data_dir {
-            tokio::fs::create_dir_all(&data_dir)
-                .await
-                .whatever_context("Failed to create/open data dir")?;
-            Some(data_dir.join("bfte.redb"))
+        let signer: Option<DynSigner> = if let Some(socket) = opts.remote_signer_socket {
+            Some(Arc::new(
+                RemoteSigner::connect(socket)
+                    .await
+                    .whatever_context("Failed to connect to remote signer")?,
+            ))
         } else {
             None
         };
+        let peer_pubkey = if let Some(signer) = signer.as_ref() {
+            Some(signer.pubkey())
+        } else {
+            secret.map(|root_secret| {
+                root_secret
+                    .get_peer_seckey()
+                    .expect("Just created, must be root")
+                    .pubkey()
+            })
+        };
+
+        if let Some(data_dir) = opts.data_dir.as_ref() {
+            tokio::fs::create_dir_all(data_dir)
+                .await
+                .whatever_context("Failed to create/open data dir")?;
+        }
+        let db_path = opts
+            .data_dir
+            .as_ref()
+            .map(|data_dir| data_dir.join("bfte.redb"));
 
         let consensus_ctrl_module_init_consensus_version = modules_inits
             .get(&bfte_module_consensus_ctrl::KIND)
             .expect("Must have ConsensusCtrlModuleInit")
             .latest_version();
         let db = match opts.command {
-            Commands::GenSecret => {
-                let root_seckey = DeriveableSecret::generate();
-                let peer_seckey = root_seckey.get_peer_seckey().expect("Just generated");
-                eprintln!("PeerId: {}", peer_seckey.pubkey());
+            Commands::GenSecret { encrypt } => {
+                secret::print_secret_file(
+                    DeriveableSecret::generate(),
+                    encrypt,
+                    opts.secret_passphrase.as_deref(),
+                )?;
                 eprintln!();
-                println!("{}", root_seckey.reveal_display());
-                eprintln!();
-                eprintln!(
-                    "This mnemonic is irrecoverable if lost. Please make a back up before using it!",
-                );
+                if encrypt {
+                    eprintln!(
+                        "This secret is irrecoverable if lost. Please back up its mnemonic (see `secret show-mnemonic`) before using it!",
+                    );
+                } else {
+                    eprintln!(
+                        "This mnemonic is irrecoverable if lost. Please make a back up before using it!",
+                    );
+                }
+                return Ok(());
+            }
+
+            Commands::Secret { command } => {
+                match command {
+                    SecretCommands::ShowMnemonic => {
+                        let secret = secret
+                            .whatever_context("Secret must be provided with `--secret-path`")?;
+                        println!("{}", secret.reveal_display());
+                    }
+                    SecretCommands::RestoreFromMnemonic { encrypt } => {
+                        secret::print_secret_file(
+                            secret::read_mnemonic()?,
+                            encrypt,
+                            opts.secret_passphrase.as_deref(),
+                        )?;
+                    }
+                }
                 return Ok(());
             }
 
+            Commands::Signer { socket } => {
+                let peer_seckey = secret
+                    .whatever_context("Secret must be provided to run a signer")?
+                    .get_peer_seckey()
+                    .whatever_context("Invalid secret")?;
+
+                let db = if let Some(data_dir) = opts.data_dir.as_ref() {
+                    Database::open(
+                        data_dir.join("signer.redb"),
+                        bfte_signer::local::DB_MIGRATIONS,
+                    )
+                    .await
+                } else {
+                    warn!(
+                        target: LOG_TARGET,
+                        "Running signer without a data dir: double-sign protection will not survive a restart"
+                    );
+                    Database::new_in_memory().await
+                }
+                .whatever_context("Failed to open signer database")?;
+
+                match bfte_signer::server::run(&socket, LocalSigner::new(peer_seckey, Arc::new(db)))
+                    .await? {}
+            }
+
             Commands::Join { invite, run } => {
                 let db = Arc::new(
                     Node::open_db(db_path)
@@ -118,8 +185,9 @@ impl Bfte {
 
                 bfte_node::Node::consensus_init_static(
                     db.clone(),
-                    secret
-                        .whatever_context("Secret must be provided to create a new federation")?,
+                    peer_pubkey.whatever_context(
+                        "Secret or remote signer must be provided to create a new federation",
+                    )?,
                     extra_peers,
                     consensus_ctrl_module_init_consensus_version,
                 )
@@ -142,7 +210,17 @@ impl Bfte {
 
         bfte_node::Node::builder()
             .maybe_root_secret(secret)
+            .maybe_signer(signer)
+            .maybe_next_root_secret(next_secret)
             .maybe_force_ui_password(opts.force_ui_password)
+            .pruning_config(PruningConfig {
+                retention: opts.prune_retention,
+                mode: if opts.pruned {
+                    StorageMode::Pruned
+                } else {
+                    StorageMode::Archival
+                },
+            })
             .db(db)
             .consensus_ctrl_module_init_consensus_version(
                 consensus_ctrl_module_init_consensus_version,
@@ -165,7 +243,7 @@ impl Bfte {
                 }
             }))
             .app(Box::new(
-                move |db, api, shared_modules, pending_transactions_tx| {
+                move |db, api, shared_modules, pending_transactions_tx, simulate_citem_rx| {
                     Box::pin({
                         let modules_inits = modules_inits.clone();
                         async move {
@@ -175,6 +253,7 @@ impl Bfte {
                                 modules_inits,
                                 shared_modules,
                                 pending_transactions_tx,
+                                simulate_citem_rx,
                             )
                             .await
                             .run()
@@ -3,6 +3,7 @@ use std::path::PathBuf;
 
 use bfte_consensus_core::peer::PeerPubkey;
 use bfte_invite::Invite;
+use bfte_node::DEFAULT_PRUNING_RETENTION;
 use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
@@ -28,13 +29,58 @@ pub(crate) struct Opts {
     #[arg(long, env = "BFTE_SECRET_PATH", global = true)]
     pub secret_path: Option<PathBuf>,
 
+    /// Path to a file containing the secret to rotate the peer key to
+    ///
+    /// Required to rotate the peer key. Once the rotation takes effect, the
+    /// node has to be restarted with it as `--secret-path`.
+    #[arg(long, env = "BFTE_NEXT_SECRET_PATH", global = true)]
+    pub next_secret_path: Option<PathBuf>,
+
+    /// Passphrase of an encrypted secret file (prompted for if not set)
+    #[arg(
+        long,
+        env = "BFTE_SECRET_PASSPHRASE",
+        hide_env_values = true,
+        global = true
+    )]
+    pub secret_passphrase: Option<String>,
+
+    /// Sign with a remote signer (see `signer` command) listening on a Unix
+    /// socket, instead of the peer secret key
+    #[arg(long, env = "BFTE_REMOTE_SIGNER_SOCKET", global = true)]
+    pub remote_signer_socket: Option<PathBuf>,
+
+    /// Drop payloads of finalized blocks past the retention, instead of
+    /// keeping the full history for peers syncing from scratch
+    #[arg(long, env = "BFTE_PRUNED", global = true)]
+    pub pruned: bool,
+
+    /// Number of rounds behind the finality consensus to keep all the data
+    /// of
+    #[arg(
+        long,
+        env = "BFTE_PRUNE_RETENTION",
+        default_value_t = DEFAULT_PRUNING_RETENTION,
+        global = true
+    )]
+    pub prune_retention: u64,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
 #[derive(Debug, Subcommand)]
 pub(crate) enum Commands {
-    GenSecret,
+    GenSecret {
+        /// Encrypt the generated secret with a passphrase
+        #[arg(long)]
+        encrypt: bool,
+    },
+    /// Secret backup and restore
+    Secret {
+        #[command(subcommand)]
+        command: SecretCommands,
+    },
     Init {
         #[arg(long)]
         run: bool,
@@ -50,4 +96,22 @@ pub(crate) enum Commands {
         invite: Invite,
     },
     Run,
+    /// Run a signer process holding the peer secret key
+    Signer {
+        /// Unix socket to listen on
+        #[arg(long)]
+        socket: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum SecretCommands {
+    /// Show the mnemonic of the secret at `--secret-path`
+    ShowMnemonic,
+    /// Read a mnemonic from stdin and print a secret file for it
+    RestoreFromMnemonic {
+        /// Encrypt the restored secret with a passphrase
+        #[arg(long)]
+        encrypt: bool,
+    },
 }
@@ -0,0 +1,104 @@
+use std::io::{self, BufRead as _, IsTerminal as _, Write as _};
+use std::path::Path;
+use std::str::FromStr as _;
+
+use bfte_derive_secret::DeriveableSecret;
+use bfte_derive_secret::encrypted::EncryptedSecret;
+use bfte_node::derive_secret_ext::DeriveSecretExt as _;
+use bfte_util_error::WhateverResult;
+use snafu::{ResultExt as _, whatever};
+
+/// Read a secret file, either a plaintext mnemonic or an
+/// [`EncryptedSecret`]
+///
+/// Encrypted files are decrypted using `passphrase`, or one prompted for
+/// interactively.
+pub(crate) async fn read_secret_file(
+    path: &Path,
+    passphrase: Option<&str>,
+) -> WhateverResult<DeriveableSecret> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .whatever_context("Failed to read secret file")?;
+
+    if !EncryptedSecret::is_encrypted(&content) {
+        return DeriveableSecret::from_str(content.trim())
+            .whatever_context("Failed to parse secret");
+    }
+
+    let encrypted =
+        EncryptedSecret::from_str(&content).whatever_context("Failed to parse encrypted secret")?;
+    let passphrase = get_passphrase(passphrase, false)?;
+
+    encrypted
+        .decrypt(&passphrase)
+        .whatever_context("Failed to decrypt secret")
+}
+
+/// Get the passphrase to use for secret encryption, prompting for it if
+/// not provided
+pub(crate) fn get_passphrase(passphrase: Option<&str>, confirm: bool) -> WhateverResult<String> {
+    if let Some(passphrase) = passphrase {
+        return Ok(passphrase.to_owned());
+    }
+
+    let passphrase = rpassword::prompt_password("Secret passphrase: ")
+        .whatever_context("Failed to read passphrase")?;
+    if confirm {
+        let confirmation = rpassword::prompt_password("Confirm secret passphrase: ")
+            .whatever_context("Failed to read passphrase")?;
+        if passphrase != confirmation {
+            whatever!("Passphrases do not match");
+        }
+    }
+    if passphrase.is_empty() {
+        whatever!("Passphrase must not be empty");
+    }
+    Ok(passphrase)
+}
+
+/// Read a mnemonic from stdin
+pub(crate) fn read_mnemonic() -> WhateverResult<DeriveableSecret> {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        eprint!("Mnemonic: ");
+        io::stderr()
+            .flush()
+            .whatever_context("Failed to flush stderr")?;
+    }
+
+    let mut line = String::new();
+    stdin
+        .lock()
+        .read_line(&mut line)
+        .whatever_context("Failed to read mnemonic")?;
+
+    DeriveableSecret::from_str(line.trim()).whatever_context("Failed to parse mnemonic")
+}
+
+/// Print the content of a secret file for `root_secret` to stdout,
+/// encrypted if `encrypt` is set
+pub(crate) fn print_secret_file(
+    root_secret: DeriveableSecret,
+    encrypt: bool,
+    passphrase: Option<&str>,
+) -> WhateverResult<()> {
+    let peer_seckey = root_secret
+        .get_peer_seckey()
+        .whatever_context("Secret must be root")?;
+    eprintln!("PeerId: {}", peer_seckey.pubkey());
+    eprintln!();
+
+    if encrypt {
+        let passphrase = get_passphrase(passphrase, true)?;
+        println!(
+            "{}",
+            root_secret
+                .encrypt(&passphrase)
+                .whatever_context("Secret must be root")?
+        );
+    } else {
+        println!("{}", root_secret.reveal_display());
+    }
+    Ok(())
+}
@@ -1,3 +1,5 @@
+pub mod proposal;
+
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
@@ -8,19 +10,24 @@ use bfte_util_array_type::{
 };
 use bfte_util_bincode::decode_whole;
 use bfte_util_error::WhateverResult;
-use bincode::{Decode, Encode};
+use bincode::de::{BorrowDecoder, Decoder};
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{BorrowDecode, Decode, Encode};
 use num_bigint::BigUint;
-use serde::Deserialize;
-use snafu::{ResultExt as _, Snafu};
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt as _, ResultExt as _, Snafu};
 
 use crate::bincode::CONSENSUS_BINCODE_CONFIG;
 use crate::citem::CItem;
 use crate::consensus_params::{ConsensusParams, ConsensusParamsHash, ConsensusParamsLen};
 use crate::framed_payload_define;
+use crate::merkle::merkle_root;
 use crate::num_peers::NumPeers;
 use crate::peer::PeerIdx;
 use crate::signed::{Hashable, Signable};
 use crate::timestamp::Timestamp;
+use crate::weight::{PeerWeight, TotalWeight};
 
 array_type_fixed_size_define! {
     /// Non-dumy block sequence number
@@ -31,6 +38,7 @@ array_type_fixed_size_define! {
     #[derive(Encode, Decode, Clone, Copy)]
     pub struct BlockSeq(u32);
 }
+array_type_fixed_size_impl_serde!(BlockSeq);
 
 array_type_fixed_size_define! {
     /// Round the block was produced in
@@ -39,6 +47,15 @@ array_type_fixed_size_define! {
 }
 array_type_fixed_size_impl_serde!(BlockRound);
 
+array_type_fixed_size_define! {
+    /// Position of a citem in a block
+    ///
+    /// It's fixed sized, big-endian encoded, so that (together with
+    /// [`BlockRound`]) it sorts in block order in database keys.
+    #[derive(Encode, Decode, Clone, Copy)]
+    pub struct BlockCItemIdx(u32);
+}
+
 impl BlockRound {
     pub fn leader_idx(self, n: NumPeers) -> PeerIdx {
         let bytes = (BigUint::from_bytes_be(self.hash().as_bytes()) % BigUint::from(n.total()))
@@ -52,6 +69,29 @@ impl BlockRound {
         idx.into()
     }
 
+    /// Like [`Self::leader_idx`], but picks peers proportionally to their
+    /// `weights`
+    ///
+    /// With all weights equal, the result is the same as [`Self::leader_idx`].
+    pub fn weighted_leader_idx(self, weights: &[PeerWeight]) -> PeerIdx {
+        let total = TotalWeight::from_weights(weights.iter().copied());
+
+        let mut point = u64::try_from(
+            BigUint::from_bytes_be(self.hash().as_bytes()) % BigUint::from(total.total()),
+        )
+        .expect("Can't overflow");
+
+        for (idx, weight) in weights.iter().enumerate() {
+            let weight = u64::from(*weight);
+            if point < weight {
+                return u8::try_from(idx).expect("Can't overflow").into();
+            }
+            point -= weight;
+        }
+
+        unreachable!("Point must be within total weight")
+    }
+
     pub fn half(self) -> Self {
         Self::from(self.to_number() / 2)
     }
@@ -77,8 +117,24 @@ fn block_round_leader_test() {
             leader_now,
             "{n} {r} {leader_fixture} -> {n} {r} {leader_now}"
         );
+        assert_eq!(
+            BlockRound::from(r).weighted_leader_idx(&vec![1; usize::from(n)]),
+            leader_now
+        );
     }
 }
+
+#[test]
+fn block_round_weighted_leader_test() {
+    let weights = [3, 0, 1];
+    let mut leaders = [0; 3];
+    for r in 0..400 {
+        leaders[BlockRound::from(r).weighted_leader_idx(&weights).as_usize()] += 1;
+    }
+
+    assert_eq!(leaders[1], 0);
+    assert!(leaders[2] * 2 < leaders[0], "{leaders:?}");
+}
 array_type_define! {
     #[derive(Encode, Decode, Copy, Clone)]
     pub struct BlockHash[32];
@@ -100,12 +156,51 @@ impl From<BlockHash> for blake3::Hash {
     }
 }
 
-#[derive(Debug, Encode, Decode, Copy, Clone, PartialEq, Eq)]
+array_type_define! {
+    /// Merkle root of [`CItem`]s in the block payload
+    ///
+    /// See [`crate::merkle`].
+    #[derive(Encode, Decode, Copy, Clone)]
+    pub struct BlockCItemsRoot[32];
+}
+array_type_impl_zero_default!(BlockCItemsRoot);
+array_type_impl_base32_str!(BlockCItemsRoot);
+array_type_impl_serde!(BlockCItemsRoot);
+array_type_impl_debug_as_display!(BlockCItemsRoot);
+
+impl From<blake3::Hash> for BlockCItemsRoot {
+    fn from(value: blake3::Hash) -> Self {
+        Self(*value.as_bytes())
+    }
+}
+
+impl From<BlockCItemsRoot> for blake3::Hash {
+    fn from(value: BlockCItemsRoot) -> Self {
+        blake3::Hash::from_bytes(value.0)
+    }
+}
+
+impl BlockCItemsRoot {
+    pub fn from_citems(citems: &[CItem]) -> Self {
+        let leaves = encode_citems_leaves(citems);
+        merkle_root(leaves.iter().map(Vec::as_slice)).into()
+    }
+}
+
+/// Encode every [`CItem`] separately, as committed to in [`BlockCItemsRoot`]
+pub fn encode_citems_leaves(citems: &[CItem]) -> Vec<Vec<u8>> {
+    citems
+        .iter()
+        .map(|citem| bincode::encode_to_vec(citem, CONSENSUS_BINCODE_CONFIG).expect("Can't fail"))
+        .collect()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BlockHeader {
     /// Version of this header format
     ///
-    /// Should be `0`, and not expected to need to change, but reserved just in
-    /// case.
+    /// * `0` - original format, 128B
+    /// * `1` - adds [`Self::citems_root`] and [`Self::num_citems`], 164B
     pub header_version: u8, // 1B
     /// Just to align things, could be used for non-consensus flags
     /// in the future (e.g. round leader signaling certain networking
@@ -136,6 +231,83 @@ pub struct BlockHeader {
 
     /// Commits to [`BlockPayload`]
     pub payload_hash: BlockPayloadHash, // 32B
+
+    /// Commits to individual [`CItem`]s in [`BlockPayload`]
+    ///
+    /// Allows compact proofs of a [`CItem`] being included in a block,
+    /// without the whole payload. Only encoded since `header_version` `1`,
+    /// and always zero in older versions.
+    pub citems_root: BlockCItemsRoot, // 32B (v1)
+
+    /// Number of [`CItem`]s committed to in [`Self::citems_root`]
+    ///
+    /// The shape of the Merkle tree depends on it, so inclusion proofs
+    /// need it to also prove the position of a [`CItem`]. Only encoded since
+    /// `header_version` `1`, and always zero in older versions.
+    pub num_citems: u32, // 4B (v1)
+}
+
+impl Encode for BlockHeader {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.header_version.encode(encoder)?;
+        self.padding.encode(encoder)?;
+        self.seq.encode(encoder)?;
+        self.round.encode(encoder)?;
+        self.timestamp.encode(encoder)?;
+        self.payload_len.encode(encoder)?;
+        self.consensus_params_len.encode(encoder)?;
+        self.prev_block_hash.encode(encoder)?;
+        self.consensus_params_hash.encode(encoder)?;
+        self.payload_hash.encode(encoder)?;
+        if 1 <= self.header_version {
+            self.citems_root.encode(encoder)?;
+            self.num_citems.encode(encoder)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C> Decode<C> for BlockHeader {
+    fn decode<D: Decoder<Context = C>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let header_version: u8 = Decode::decode(decoder)?;
+        let padding = Decode::decode(decoder)?;
+        let seq = Decode::decode(decoder)?;
+        let round = Decode::decode(decoder)?;
+        let timestamp = Decode::decode(decoder)?;
+        let payload_len = Decode::decode(decoder)?;
+        let consensus_params_len = Decode::decode(decoder)?;
+        let prev_block_hash = Decode::decode(decoder)?;
+        let consensus_params_hash = Decode::decode(decoder)?;
+        let payload_hash = Decode::decode(decoder)?;
+        let (citems_root, num_citems) = if 1 <= header_version {
+            (Decode::decode(decoder)?, Decode::decode(decoder)?)
+        } else {
+            (BlockCItemsRoot::ZERO, 0)
+        };
+
+        Ok(Self {
+            header_version,
+            padding,
+            seq,
+            round,
+            timestamp,
+            payload_len,
+            consensus_params_len,
+            prev_block_hash,
+            consensus_params_hash,
+            payload_hash,
+            citems_root,
+            num_citems,
+        })
+    }
+}
+
+impl<'de, C> BorrowDecode<'de, C> for BlockHeader {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = C>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Decode::decode(decoder)
+    }
 }
 
 #[derive(Debug, Snafu)]
@@ -146,6 +318,8 @@ pub enum VerifyWithContentError {
     UnknownVersion,
     ConsensusLenMismatch,
     ConsensusVersionMismatch,
+    CItemsRootMismatch,
+    NumCItemsMismatch,
 }
 
 pub type VerifyWithContentMismatchResult<T> = std::result::Result<T, VerifyWithContentError>;
@@ -157,6 +331,10 @@ impl Signable for BlockHeader {
 
 #[bon::bon]
 impl BlockHeader {
+    /// Create a new block header
+    ///
+    /// If `citems` (encoded in the `payload`) are passed, the header will
+    /// be a version `1` header committing to them individually.
     #[builder]
     pub fn new(
         prev: Option<BlockHeader>,
@@ -164,9 +342,13 @@ impl BlockHeader {
         round: BlockRound,
         consensus_params: &ConsensusParams,
         payload: &BlockPayloadRaw,
+        citems: Option<&[CItem]>,
     ) -> Self {
+        debug_assert!(
+            citems.is_none_or(|citems| BlockPayloadRaw::encode_citems(citems) == *payload)
+        );
         Self {
-            header_version: 0,
+            header_version: if citems.is_some() { 1 } else { 0 },
             padding: [0u8; 3],
             timestamp,
             seq: prev.map(|p| p.seq.next_wrapping()).unwrap_or_default(),
@@ -176,6 +358,10 @@ impl BlockHeader {
             consensus_params_hash: consensus_params.hash(),
             consensus_params_len: consensus_params.len(),
             payload_hash: payload.hash(),
+            citems_root: citems.map(BlockCItemsRoot::from_citems).unwrap_or_default(),
+            num_citems: citems
+                .map(|citems| u32::try_from(citems.len()).expect("Can't have that many citems"))
+                .unwrap_or_default(),
         }
     }
 }
@@ -204,8 +390,21 @@ impl BlockHeader {
             }
         }
 
-        if self.header_version != 0 {
-            UnknownVersionSnafu.fail()?;
+        match self.header_version {
+            0 => {}
+            1 => {
+                let citems = payload
+                    .decode_citems()
+                    .ok()
+                    .context(CItemsRootMismatchSnafu)?;
+                if BlockCItemsRoot::from_citems(&citems) != self.citems_root {
+                    CItemsRootMismatchSnafu.fail()?;
+                }
+                if u32::try_from(citems.len()).ok() != Some(self.num_citems) {
+                    NumCItemsMismatchSnafu.fail()?;
+                }
+            }
+            _ => UnknownVersionSnafu.fail()?,
         }
 
         if self.consensus_params_hash != block_round_consensus_params_hash {
@@ -230,6 +429,8 @@ impl BlockHeader {
             payload_len: BlockPayloadLen::ZERO,
             consensus_params_hash,
             payload_hash: BlockPayloadHash::ZERO,
+            citems_root: BlockCItemsRoot::ZERO,
+            num_citems: 0,
         }
     }
 
@@ -267,6 +468,7 @@ array_type_fixed_size_define! {
     #[derive(Encode, Decode, Clone, Copy)]
     pub struct BlockPayloadLen(u32);
 }
+array_type_fixed_size_impl_serde!(BlockPayloadLen);
 
 array_type_define! {
     #[derive(Encode, Decode, Copy, Clone)]
@@ -0,0 +1,72 @@
+//! Selecting [`CItem`]s for a block proposal, within
+//! [`ConsensusParams::max_block_payload_len`]
+
+use std::collections::BTreeMap;
+
+use crate::bincode::CONSENSUS_BINCODE_CONFIG;
+use crate::citem::CItem;
+use crate::consensus_params::ConsensusParams;
+use crate::module::ModuleId;
+
+/// Share of the payload (`1/N`) citems of a single non-priority module can
+/// take
+pub const MODULE_PAYLOAD_QUOTA_DIVISOR: usize = 4;
+
+/// Upper bound on the length prefix of the encoded citems list
+const CITEMS_LEN_PREFIX_MAX: usize = 9;
+
+/// Select and order `citems` to propose in a block
+///
+/// Citems are ordered by priority: citems of `priority_modules`, citems of
+/// other modules, and finally transactions, otherwise keeping their order.
+/// Each non-priority module can take at most `1/`[`MODULE_PAYLOAD_QUOTA_DIVISOR`]
+/// of the payload, so a chatty module can't starve the others, and citems
+/// that don't fit are skipped.
+pub fn select_proposal_citems(
+    params: &ConsensusParams,
+    priority_modules: &[ModuleId],
+    mut citems: Vec<CItem>,
+) -> Vec<CItem> {
+    let max_len = usize::try_from(params.max_block_payload_len).expect("Can't fail");
+    let module_quota = max_len / MODULE_PAYLOAD_QUOTA_DIVISOR;
+
+    let module_id_of = |citem: &CItem| match citem {
+        CItem::PeerCItem(module_citem) => Some(module_citem.module_id()),
+        CItem::ForwardedPeerCItem(forwarded) => Some(forwarded.citem.module_id()),
+        CItem::Transaction(_) => None,
+    };
+    citems.sort_by_key(|citem| match module_id_of(citem) {
+        Some(module_id) if priority_modules.contains(&module_id) => 0,
+        Some(_) => 1,
+        None => 2,
+    });
+
+    let mut len = CITEMS_LEN_PREFIX_MAX;
+    let mut module_lens: BTreeMap<ModuleId, usize> = BTreeMap::new();
+    let mut selected = Vec::with_capacity(citems.len());
+
+    for citem in citems {
+        let citem_len = bincode::encode_to_vec(&citem, CONSENSUS_BINCODE_CONFIG)
+            .expect("Can't fail")
+            .len();
+        if max_len < len + citem_len {
+            continue;
+        }
+
+        if let Some(module_id) = module_id_of(&citem).filter(|id| !priority_modules.contains(id)) {
+            let module_len = module_lens.entry(module_id).or_default();
+            if module_quota < *module_len + citem_len {
+                continue;
+            }
+            *module_len += citem_len;
+        }
+
+        len += citem_len;
+        selected.push(citem);
+    }
+
+    selected
+}
+
+#[cfg(test)]
+mod tests;
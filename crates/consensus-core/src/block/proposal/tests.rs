@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use super::select_proposal_citems;
+use crate::block::BlockPayloadRaw;
+use crate::citem::{CItem, CItemRaw, ModuleDyn};
+use crate::consensus_params::ConsensusParams;
+use crate::module::ModuleId;
+
+fn citem(module_id: u32, len: usize) -> CItem {
+    CItem::PeerCItem(ModuleDyn::new(
+        ModuleId::new(module_id),
+        CItemRaw(Arc::from(vec![0; len])),
+    ))
+}
+
+fn params(max_block_payload_len: u32) -> ConsensusParams {
+    ConsensusParams {
+        max_block_payload_len,
+        ..ConsensusParams::new_test_dummy()
+    }
+}
+
+fn module_ids(citems: &[CItem]) -> Vec<u32> {
+    citems
+        .iter()
+        .map(|citem| match citem {
+            CItem::PeerCItem(module_citem) => u32::from(module_citem.module_id()),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+#[test]
+fn priority_modules_go_first() {
+    let selected = select_proposal_citems(
+        &params(ConsensusParams::DEFAULT_MAX_BLOCK_PAYLOAD_LEN),
+        &[ModuleId::new(0)],
+        vec![citem(2, 10), citem(0, 10), citem(1, 10), citem(0, 10)],
+    );
+
+    assert_eq!(module_ids(&selected), [0, 0, 2, 1]);
+}
+
+#[test]
+fn chatty_module_limited_to_quota() {
+    let selected = select_proposal_citems(
+        &params(1000),
+        &[ModuleId::new(0)],
+        [
+            vec![citem(1, 100); 10],
+            vec![citem(2, 100)],
+            vec![citem(0, 100); 4],
+        ]
+        .concat(),
+    );
+
+    let ids = module_ids(&selected);
+    assert_eq!(ids.iter().filter(|id| **id == 0).count(), 4);
+    assert_eq!(ids.iter().filter(|id| **id == 1).count(), 2);
+    assert_eq!(ids.iter().filter(|id| **id == 2).count(), 1);
+}
+
+#[test]
+fn payload_within_limit() {
+    let max_len = 1000;
+    let selected = select_proposal_citems(
+        &params(max_len),
+        &[ModuleId::new(0)],
+        vec![citem(0, 300); 10],
+    );
+
+    assert_eq!(selected.len(), 3);
+    let payload = BlockPayloadRaw::encode_citems(&selected);
+    assert!(u32::from(payload.len()) <= max_len);
+}
+
+#[test]
+fn payload_limit_encoding() {
+    let max_len = 1024 * 1024;
+    let params = ConsensusParams::new_test_dummy().make_change(
+        1.into(),
+        crate::timestamp::Timestamp::ZERO,
+        ConsensusParams::new_test_dummy().peers,
+        vec![],
+        vec![],
+        max_len,
+        None,
+    );
+    assert_eq!(
+        params.consensus_params_format_version,
+        ConsensusParams::FORMAT_VERSION_PAYLOAD_LIMIT
+    );
+
+    let decoded = ConsensusParams::from_raw(&params.to_raw()).expect("Valid params");
+    assert_eq!(decoded, params);
+    assert_eq!(decoded.max_block_payload_len, max_len);
+
+    let default_params = ConsensusParams::new_test_dummy();
+    let decoded = ConsensusParams::from_raw(&default_params.to_raw()).expect("Valid params");
+    assert_eq!(
+        decoded.max_block_payload_len,
+        ConsensusParams::DEFAULT_MAX_BLOCK_PAYLOAD_LEN
+    );
+
+    // A limit too low to carry any consensus items is rejected
+    let too_low = ConsensusParams {
+        max_block_payload_len: ConsensusParams::MIN_MAX_BLOCK_PAYLOAD_LEN - 1,
+        ..params
+    };
+    assert!(ConsensusParams::from_raw(&too_low.to_raw()).is_err());
+}
@@ -1,7 +1,13 @@
 use hex_literal::hex;
 
-use crate::block::BlockHeader;
+use std::sync::Arc;
+
+use crate::bincode::CONSENSUS_BINCODE_CONFIG;
+use crate::block::{BlockCItemsRoot, BlockHeader, BlockPayloadRaw, VerifyWithContentError};
+use crate::citem::{CItem, CItemRaw, ModuleDyn};
 use crate::consensus_params::ConsensusParams;
+use crate::module::ModuleId;
+use crate::timestamp::Timestamp;
 
 #[test]
 fn block_header_size_sanity() {
@@ -41,3 +47,49 @@ fn block_header_fixture() {
         );
     }
 }
+
+#[test]
+fn block_header_v1_sanity() {
+    let params = ConsensusParams::new_test_dummy();
+    let citems = [CItem::PeerCItem(ModuleDyn::new(
+        ModuleId::new(0),
+        CItemRaw(Arc::from(vec![1, 2, 3])),
+    ))];
+    let payload = BlockPayloadRaw::encode_citems(&citems);
+    let block = BlockHeader::builder()
+        .round(1.into())
+        .timestamp(Timestamp::ZERO)
+        .consensus_params(&params)
+        .payload(&payload)
+        .citems(&citems)
+        .build();
+
+    assert_eq!(block.header_version, 1);
+    assert_ne!(block.citems_root, BlockCItemsRoot::ZERO);
+    assert_eq!(block.num_citems, 1);
+
+    let encoded = bincode::encode_to_vec(block, CONSENSUS_BINCODE_CONFIG).expect("Can't fail");
+    assert_eq!(encoded.len(), 164);
+    let decoded: BlockHeader =
+        bfte_util_bincode::decode_whole(&encoded, CONSENSUS_BINCODE_CONFIG).expect("Can't fail");
+    assert_eq!(decoded, block);
+
+    let (params_hash, params_len) = params.hash_and_len();
+    block
+        .verify_with_content(params_hash, params_len, &payload)
+        .expect("Valid");
+
+    let mut tampered = block;
+    tampered.citems_root = BlockCItemsRoot::ZERO;
+    assert!(matches!(
+        tampered.verify_with_content(params_hash, params_len, &payload),
+        Err(VerifyWithContentError::CItemsRootMismatch)
+    ));
+
+    let mut tampered = block;
+    tampered.num_citems = 2;
+    assert!(matches!(
+        tampered.verify_with_content(params_hash, params_len, &payload),
+        Err(VerifyWithContentError::NumCItemsMismatch)
+    ));
+}
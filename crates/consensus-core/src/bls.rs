@@ -0,0 +1,329 @@
+//! BLS signatures, allowing notarizations to be aggregated
+//!
+//! Peers sign the same [`Signable::sign_hash`] as they do with their ed25519
+//! [`PeerSeckey`], which allows a [`Notarized`] block to carry a single
+//! [`BlsAggregateSignature`] instead of one signature per peer.
+//!
+//! [`Signable::sign_hash`]: crate::signed::Signable::sign_hash
+//! [`Notarized`]: crate::signed::Notarized
+
+use std::fmt;
+use std::io::Write as _;
+use std::str::FromStr;
+
+use bfte_util_array_type::data_encoding::{
+    BASE32_DNSCURVE, DecodeError as Base32DecodeError, DecodeKind,
+};
+use bfte_util_array_type::{
+    array_type_define, array_type_impl_base32_str, array_type_impl_bytes_conv,
+    array_type_impl_debug_as_display, array_type_impl_serde,
+};
+use bincode::de::{BorrowDecoder, Decoder};
+use bincode::error::DecodeError;
+use bincode::{BorrowDecode, Decode, Encode};
+use blst::BLST_ERROR;
+use blst::min_pk;
+use serde::{Deserialize, Serialize};
+
+use crate::peer::{PeerIdx, PeerPubkey, PeerSeckey};
+use crate::signed::{InvalidSignatureError, InvalidSignatureResult};
+
+/// Domain separation tag for regular signatures
+const DST_SIG: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+/// Domain separation tag for proofs of possession
+const DST_POP: &[u8] = b"BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+array_type_define! {
+    #[derive(Encode, Decode, Clone, Copy)]
+    pub struct BlsPubkey[48];
+}
+array_type_impl_base32_str!(BlsPubkey);
+array_type_impl_serde!(BlsPubkey);
+array_type_impl_debug_as_display!(BlsPubkey);
+
+array_type_define! {
+    #[derive(Encode, Decode, Clone, Copy)]
+    pub struct BlsSignature[96];
+}
+array_type_impl_base32_str!(BlsSignature);
+array_type_impl_serde!(BlsSignature);
+array_type_impl_debug_as_display!(BlsSignature);
+
+array_type_define! {
+    #[derive(Encode, Decode, Clone, Copy)]
+    pub struct BlsSeckey[32];
+}
+array_type_impl_bytes_conv!(BlsSeckey);
+
+impl BlsSeckey {
+    /// Derive the BLS secret key from the peer's ed25519 secret key
+    ///
+    /// This way peers don't need to backup any additional secrets.
+    pub fn derive_from(peer_seckey: PeerSeckey) -> Self {
+        let ikm = blake3::derive_key("bfte bls seckey", &peer_seckey.to_bytes());
+        Self(
+            min_pk::SecretKey::key_gen(&ikm, &[])
+                .expect("Can't fail with 32 bytes of ikm")
+                .to_bytes(),
+        )
+    }
+
+    fn to_blst(self) -> min_pk::SecretKey {
+        min_pk::SecretKey::from_bytes(&self.0).expect("Must be a valid secret key")
+    }
+
+    pub fn pubkey(self) -> BlsPubkey {
+        BlsPubkey(self.to_blst().sk_to_pk().to_bytes())
+    }
+
+    pub fn sign_hash(self, hash: blake3::Hash) -> BlsSignature {
+        BlsSignature(
+            self.to_blst()
+                .sign(hash.as_bytes(), DST_SIG, &[])
+                .to_bytes(),
+        )
+    }
+
+    /// Create a [`BlsKeyRegistration`] of this key for `peer_pubkey`
+    pub fn registration(self, peer_pubkey: PeerPubkey) -> BlsKeyRegistration {
+        let pubkey = self.pubkey();
+        BlsKeyRegistration {
+            pubkey,
+            pop: BlsSignature(
+                self.to_blst()
+                    .sign(
+                        &BlsKeyRegistration::pop_msg(peer_pubkey, pubkey),
+                        DST_POP,
+                        &[],
+                    )
+                    .to_bytes(),
+            ),
+        }
+    }
+}
+
+impl BlsPubkey {
+    /// Returns `None` if this is not a valid public key
+    fn to_blst(self) -> Option<min_pk::PublicKey> {
+        min_pk::PublicKey::key_validate(&self.0).ok()
+    }
+
+    pub fn verify_hash(self, hash: blake3::Hash, sig: BlsSignature) -> InvalidSignatureResult<()> {
+        let pubkey = self.to_blst().ok_or(InvalidSignatureError)?;
+        let sig = sig.to_blst().ok_or(InvalidSignatureError)?;
+
+        if sig.verify(true, hash.as_bytes(), DST_SIG, &[], &pubkey, false)
+            != BLST_ERROR::BLST_SUCCESS
+        {
+            return Err(InvalidSignatureError);
+        }
+        Ok(())
+    }
+}
+
+impl BlsSignature {
+    fn to_blst(self) -> Option<min_pk::Signature> {
+        min_pk::Signature::from_bytes(&self.0).ok()
+    }
+}
+
+/// A [`BlsPubkey`] with a proof of possession of its secret key, bound to
+/// a [`PeerPubkey`] registering it
+///
+/// Proof of possession prevents rogue key attacks, where a peer registers a
+/// key crafted to cancel out keys of other peers in an aggregate.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct BlsKeyRegistration {
+    pub pubkey: BlsPubkey,
+    pub pop: BlsSignature,
+}
+
+impl BlsKeyRegistration {
+    fn pop_msg(peer_pubkey: PeerPubkey, pubkey: BlsPubkey) -> Vec<u8> {
+        let mut msg = vec![];
+        msg.write_all(b"bfte").expect("Can't fail");
+        msg.write_all(peer_pubkey.as_slice()).expect("Can't fail");
+        msg.write_all(pubkey.as_slice()).expect("Can't fail");
+        msg
+    }
+
+    pub fn verify(&self, peer_pubkey: PeerPubkey) -> InvalidSignatureResult<()> {
+        let pubkey = self.pubkey.to_blst().ok_or(InvalidSignatureError)?;
+        let pop = self.pop.to_blst().ok_or(InvalidSignatureError)?;
+
+        if pop.verify(
+            true,
+            &Self::pop_msg(peer_pubkey, self.pubkey),
+            DST_POP,
+            &[],
+            &pubkey,
+            false,
+        ) != BLST_ERROR::BLST_SUCCESS
+        {
+            return Err(InvalidSignatureError);
+        }
+        Ok(())
+    }
+}
+
+/// Base32 of the public key followed by the proof of possession, so it can
+/// be copied between nodes, e.g. when rotating keys
+impl fmt::Display for BlsKeyRegistration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bytes = self.pubkey.as_slice().to_vec();
+        bytes.extend_from_slice(self.pop.as_slice());
+        BASE32_DNSCURVE.encode_write(&bytes, f)
+    }
+}
+
+impl FromStr for BlsKeyRegistration {
+    type Err = Base32DecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = BASE32_DNSCURVE.decode(s.as_bytes())?;
+        if bytes.len() != BlsPubkey::LEN + BlsSignature::LEN {
+            return Err(Base32DecodeError {
+                position: 0,
+                kind: DecodeKind::Length,
+            });
+        }
+        let (pubkey, pop) = bytes.split_at(BlsPubkey::LEN);
+        Ok(Self {
+            pubkey: BlsPubkey(pubkey.try_into().expect("Can't fail")),
+            pop: BlsSignature(pop.try_into().expect("Can't fail")),
+        })
+    }
+}
+
+/// Set of [`PeerIdx`]s encoded as a bitmap
+///
+/// Encoding is canonical: trailing zero bytes are never produced and are
+/// rejected on decoding, so each set of signers has exactly one encoding.
+#[derive(Encode, Clone, Default, PartialEq, Eq, Debug)]
+pub struct PeerBitmap(Vec<u8>);
+
+impl<C> Decode<C> for PeerBitmap {
+    fn decode<D: Decoder<Context = C>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let bytes: Vec<u8> = Decode::decode(decoder)?;
+        if Self::MAX_LEN < bytes.len() {
+            return Err(DecodeError::Other("peer bitmap too long"));
+        }
+        if bytes.last() == Some(&0) {
+            return Err(DecodeError::Other("peer bitmap not minimal"));
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl<'de, C> BorrowDecode<'de, C> for PeerBitmap {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = C>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Decode::decode(decoder)
+    }
+}
+
+impl PeerBitmap {
+    /// Enough bytes to fit every possible [`PeerIdx`]
+    const MAX_LEN: usize = 256 / 8;
+
+    pub fn insert(&mut self, peer_idx: PeerIdx) {
+        let (byte, bit) = (peer_idx.as_usize() / 8, peer_idx.as_usize() % 8);
+        if self.0.len() <= byte {
+            self.0.resize(byte + 1, 0);
+        }
+        self.0[byte] |= 1 << bit;
+    }
+
+    pub fn contains(&self, peer_idx: PeerIdx) -> bool {
+        let (byte, bit) = (peer_idx.as_usize() / 8, peer_idx.as_usize() % 8);
+        self.0.get(byte).is_some_and(|byte| byte & (1 << bit) != 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = PeerIdx> + '_ {
+        self.0.iter().enumerate().flat_map(|(byte_idx, byte)| {
+            (0..8)
+                .filter(move |bit| byte & (1 << bit) != 0)
+                .map(move |bit| PeerIdx::new(u8::try_from(byte_idx * 8 + bit).expect("Can't fail")))
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+impl FromIterator<PeerIdx> for PeerBitmap {
+    fn from_iter<I: IntoIterator<Item = PeerIdx>>(iter: I) -> Self {
+        let mut bitmap = Self::default();
+        for peer_idx in iter {
+            bitmap.insert(peer_idx);
+        }
+        bitmap
+    }
+}
+
+/// Signatures of the same message by `signers`, aggregated into one
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct BlsAggregateSignature {
+    pub signers: PeerBitmap,
+    pub sig: BlsSignature,
+}
+
+impl BlsAggregateSignature {
+    /// Aggregate `sigs` of distinct peers
+    ///
+    /// Returns `None` if `sigs` is empty or any of them is malformed.
+    pub fn aggregate(sigs: impl IntoIterator<Item = (PeerIdx, BlsSignature)>) -> Option<Self> {
+        let mut signers = PeerBitmap::default();
+        let mut blst_sigs = vec![];
+
+        for (peer_idx, sig) in sigs {
+            signers.insert(peer_idx);
+            blst_sigs.push(sig.to_blst()?);
+        }
+
+        let sig =
+            min_pk::AggregateSignature::aggregate(&blst_sigs.iter().collect::<Vec<_>>(), false)
+                .ok()?
+                .to_signature();
+
+        Some(Self {
+            signers,
+            sig: BlsSignature(sig.to_bytes()),
+        })
+    }
+
+    /// Verify against `bls_pubkeys` of all peers, indexed by [`PeerIdx`]
+    pub fn verify_hash(
+        &self,
+        hash: blake3::Hash,
+        bls_pubkeys: &[BlsPubkey],
+    ) -> InvalidSignatureResult<()> {
+        let pubkeys = self
+            .signers
+            .iter()
+            .map(|peer_idx| bls_pubkeys.get(peer_idx.as_usize())?.to_blst())
+            .collect::<Option<Vec<_>>>()
+            .ok_or(InvalidSignatureError)?;
+        let sig = self.sig.to_blst().ok_or(InvalidSignatureError)?;
+
+        if sig.fast_aggregate_verify(
+            true,
+            hash.as_bytes(),
+            DST_SIG,
+            &pubkeys.iter().collect::<Vec<_>>(),
+        ) != BLST_ERROR::BLST_SUCCESS
+        {
+            return Err(InvalidSignatureError);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,183 @@
+use super::{BlsAggregateSignature, BlsKeyRegistration, BlsSeckey, PeerBitmap};
+use crate::bincode::CONSENSUS_BINCODE_CONFIG;
+use crate::block::BlockHeader;
+use crate::consensus_params::ConsensusParams;
+use crate::peer::{PeerIdx, PeerSeckey};
+use crate::signed::{InvalidNotarizationError, Notarized, Signable as _};
+
+#[test]
+fn peer_bitmap_sanity() {
+    let bitmap: PeerBitmap = [0, 3, 8, 255].into_iter().map(PeerIdx::new).collect();
+
+    assert_eq!(bitmap.len(), 4);
+    assert!(bitmap.contains(PeerIdx::new(8)));
+    assert!(!bitmap.contains(PeerIdx::new(7)));
+    assert_eq!(
+        bitmap.iter().collect::<Vec<_>>(),
+        [0, 3, 8, 255].map(PeerIdx::new)
+    );
+    assert!(PeerBitmap::default().is_empty());
+}
+
+#[test]
+fn peer_bitmap_encoding_is_canonical() {
+    let bitmap: PeerBitmap = [1, 9].into_iter().map(PeerIdx::new).collect();
+    let encoded = bincode::encode_to_vec(&bitmap, CONSENSUS_BINCODE_CONFIG).expect("Can't fail");
+    let decoded: PeerBitmap =
+        bfte_util_bincode::decode_whole(&encoded, CONSENSUS_BINCODE_CONFIG).expect("Valid");
+    assert_eq!(decoded, bitmap);
+
+    for bytes in [vec![0x02, 0x02, 0x00], vec![0x00], vec![0xff; 33]] {
+        let encoded = bincode::encode_to_vec(&bytes, CONSENSUS_BINCODE_CONFIG).expect("Can't fail");
+        assert!(
+            bfte_util_bincode::decode_whole::<PeerBitmap>(&encoded, CONSENSUS_BINCODE_CONFIG)
+                .is_err()
+        );
+    }
+
+    let encoded = bincode::encode_to_vec(PeerBitmap::default(), CONSENSUS_BINCODE_CONFIG)
+        .expect("Can't fail");
+    assert!(
+        bfte_util_bincode::decode_whole::<PeerBitmap>(&encoded, CONSENSUS_BINCODE_CONFIG)
+            .expect("Valid")
+            .is_empty()
+    );
+}
+
+#[test]
+fn bls_key_registration_is_bound_to_peer() {
+    let peer_seckey = PeerSeckey::generate();
+    let seckey = BlsSeckey::derive_from(peer_seckey);
+    assert_eq!(
+        seckey.pubkey(),
+        BlsSeckey::derive_from(peer_seckey).pubkey()
+    );
+
+    let registration = seckey.registration(peer_seckey.pubkey());
+    registration
+        .verify(peer_seckey.pubkey())
+        .expect("Valid registration");
+    assert!(
+        registration
+            .verify(PeerSeckey::generate().pubkey())
+            .is_err()
+    );
+}
+
+#[test]
+fn bls_key_registration_str_roundtrip() {
+    let peer_seckey = PeerSeckey::generate();
+    let registration = BlsSeckey::derive_from(peer_seckey).registration(peer_seckey.pubkey());
+
+    let decoded: BlsKeyRegistration = registration.to_string().parse().expect("Valid");
+    assert_eq!(decoded, registration);
+    decoded.verify(peer_seckey.pubkey()).expect("Valid");
+
+    assert!(
+        registration
+            .pubkey
+            .to_string()
+            .parse::<BlsKeyRegistration>()
+            .is_err()
+    );
+}
+
+#[test]
+fn notarized_aggregate_sanity() {
+    let peer_seckeys: Vec<_> = (0..4).map(|_| PeerSeckey::generate()).collect();
+    let mut params = ConsensusParams {
+        peers: peer_seckeys.iter().map(|seckey| seckey.pubkey()).collect(),
+        ..ConsensusParams::new_test_dummy()
+    };
+    // Peers got sorted, so derive keys in the same order
+    let bls_seckeys: Vec<_> = params
+        .peers
+        .iter()
+        .map(|peer_pubkey| {
+            let peer_seckey = peer_seckeys
+                .iter()
+                .find(|seckey| seckey.pubkey() == *peer_pubkey)
+                .expect("Must be there");
+            BlsSeckey::derive_from(*peer_seckey)
+        })
+        .collect();
+    params.bls_pubkeys = bls_seckeys.iter().map(|seckey| seckey.pubkey()).collect();
+
+    let block = BlockHeader::new_dummy(3.into(), &params);
+
+    let aggregate = BlsAggregateSignature::aggregate([0, 1, 3].map(|i| {
+        (
+            PeerIdx::new(i),
+            block.sign_bls_with(bls_seckeys[usize::from(i)]),
+        )
+    }))
+    .expect("Valid sigs");
+    assert_eq!(
+        Notarized::new_aggregate(block, aggregate.clone()).verify_sigs(&params),
+        Ok(())
+    );
+
+    // Signers not matching the signatures
+    let mut wrong_signers = aggregate.clone();
+    wrong_signers.signers = [0, 1, 2].into_iter().map(PeerIdx::new).collect();
+    assert_eq!(
+        Notarized::new_aggregate(block, wrong_signers).verify_sigs(&params),
+        Err(InvalidNotarizationError::InvalidAggregateSignature)
+    );
+
+    // Not enough signers
+    let aggregate = BlsAggregateSignature::aggregate([0, 1].map(|i| {
+        (
+            PeerIdx::new(i),
+            block.sign_bls_with(bls_seckeys[usize::from(i)]),
+        )
+    }))
+    .expect("Valid sigs");
+    assert_eq!(
+        Notarized::new_aggregate(block, aggregate).verify_sigs(&params),
+        Err(InvalidNotarizationError::NotEnoughSignatures)
+    );
+}
+
+#[test]
+fn consensus_params_bls_pubkeys_encoding() {
+    let params = ConsensusParams::new_test_dummy();
+    let peer_seckey = PeerSeckey::generate();
+    let changed = params.clone().make_change(
+        1.into(),
+        params.timestamp,
+        [peer_seckey.pubkey()].into(),
+        vec![BlsSeckey::derive_from(peer_seckey).pubkey()],
+        vec![],
+        params.max_block_payload_len,
+        None,
+    );
+
+    assert_eq!(
+        changed.consensus_params_format_version,
+        ConsensusParams::FORMAT_VERSION_BLS
+    );
+    assert_eq!(
+        ConsensusParams::from_raw(&changed.to_raw()).expect("Valid"),
+        changed
+    );
+
+    // Params without BLS keys keep the original encoding
+    assert_eq!(
+        ConsensusParams::from_raw(&params.to_raw()).expect("Valid"),
+        params
+    );
+    let changed_no_bls = params.clone().make_change(
+        1.into(),
+        params.timestamp,
+        [peer_seckey.pubkey()].into(),
+        vec![],
+        vec![],
+        params.max_block_payload_len,
+        None,
+    );
+    assert_eq!(
+        changed_no_bls.consensus_params_format_version,
+        ConsensusParams::FORMAT_VERSION
+    );
+}
@@ -4,12 +4,18 @@ pub mod transaction;
 pub mod transaction_nonce;
 use std::sync::Arc;
 
+use bfte_util_array_type::{
+    array_type_define, array_type_impl_base32_str, array_type_impl_debug_as_display,
+};
 use bincode::{BorrowDecode, Decode, Encode};
 use derive_more::Deref;
 use transaction::Transaction;
 
+use crate::bincode::CONSENSUS_BINCODE_CONFIG;
+use crate::block::BlockRound;
 use crate::module::ModuleId;
 use crate::peer::PeerPubkey;
+use crate::signed::{Hashable, Signable, Signed};
 
 /// Consensus item
 ///
@@ -22,6 +28,55 @@ pub enum CItem {
     PeerCItem(ModuleDyn<CItemRaw>),
     /// A signed transaction aggregating inputs and outputs.
     Transaction(Transaction),
+    /// Consensus item of a non-leader peer, forwarded to and included by the
+    /// round leader
+    ForwardedPeerCItem(Signed<ForwardedCItem>),
+}
+
+/// A module consensus item of `peer_pubkey`, signed by it, so any round
+/// leader can include it in a block on its behalf
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct ForwardedCItem {
+    pub peer_pubkey: PeerPubkey,
+    /// Must be included in a block before this round, which limits replaying
+    /// of the citem
+    pub valid_until: BlockRound,
+    pub citem: ModuleDyn<CItemRaw>,
+}
+
+impl ForwardedCItem {
+    /// Maximum number of rounds, after the one it is included in, a
+    /// forwarded citem can be valid for
+    ///
+    /// Bounds how long included citems need to be remembered to reject
+    /// replays of them.
+    pub const MAX_VALID_ROUNDS: u64 = 64;
+}
+
+impl Hashable for ForwardedCItem {}
+
+impl Signable for ForwardedCItem {
+    const TAG: [u8; 4] = *b"fcit";
+}
+
+array_type_define! {
+    /// Hash of a module citem of a peer
+    ///
+    /// The same whether the citem was included by the peer itself, or
+    /// forwarded (any number of times) by it.
+    #[derive(Encode, Decode, Copy, Clone)]
+    pub struct PeerCItemHash[32];
+}
+array_type_impl_base32_str!(PeerCItemHash);
+array_type_impl_debug_as_display!(PeerCItemHash);
+
+impl PeerCItemHash {
+    pub fn new(peer_pubkey: PeerPubkey, citem: &ModuleDyn<CItemRaw>) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        bincode::encode_into_std_write((peer_pubkey, citem), &mut hasher, CONSENSUS_BINCODE_CONFIG)
+            .expect("Can't fail");
+        Self(*hasher.finalize().as_bytes())
+    }
 }
 
 #[derive(Encode, Decode)]
@@ -1,8 +1,12 @@
-use bfte_util_array_type::array_type_define;
+use bfte_util_array_type::{
+    array_type_define, array_type_impl_base32_str, array_type_impl_debug_as_display,
+    array_type_impl_serde,
+};
 use bincode::{Decode, Encode};
 
 use super::transaction_nonce::TransactionNonce;
 use super::{InputRaw, ModuleDyn, OutputRaw};
+use crate::signed::Hashable;
 
 #[derive(Encode, Decode, Clone, Debug)]
 pub struct TransactionUnsigned {
@@ -11,12 +15,38 @@ pub struct TransactionUnsigned {
     pub outputs: Vec<ModuleDyn<OutputRaw>>,
 }
 
+impl Hashable for TransactionUnsigned {}
+
 #[derive(Encode, Decode, Clone, Debug)]
 pub struct Transaction {
     pub inner: TransactionUnsigned,
     pub signature: TransactionSignature,
 }
 
+impl Transaction {
+    /// Hash identifying the transaction
+    ///
+    /// Commits to the unsigned part only, so it can't be changed by
+    /// re-encoding the signature.
+    pub fn tx_hash(&self) -> TransactionHash {
+        self.inner.hash().into()
+    }
+}
+
+array_type_define! {
+    #[derive(Encode, Decode, Copy, Clone)]
+    pub struct TransactionHash[32];
+}
+array_type_impl_base32_str!(TransactionHash);
+array_type_impl_serde!(TransactionHash);
+array_type_impl_debug_as_display!(TransactionHash);
+
+impl From<blake3::Hash> for TransactionHash {
+    fn from(value: blake3::Hash) -> Self {
+        Self(*value.as_bytes())
+    }
+}
+
 array_type_define! {
     #[derive(Encode, Decode, Copy, Clone, Debug)]
     pub struct TransactionSignature[32];
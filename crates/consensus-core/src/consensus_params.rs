@@ -5,12 +5,16 @@ use bfte_util_array_type::{
     array_type_impl_debug_as_display, array_type_impl_serde, array_type_impl_zero_default,
 };
 use bfte_util_bincode::decode_whole;
-use bincode::{Decode, Encode};
+use bincode::de::{BorrowDecoder, Decoder};
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{BorrowDecode, Decode, Encode};
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt as _, Snafu};
 
 use crate::bincode::CONSENSUS_BINCODE_CONFIG;
 use crate::block::{BlockHash, BlockRound};
+use crate::bls::BlsPubkey;
 use crate::framed_payload_define;
 use crate::num_peers::{NumPeers, ToNumPeers as _};
 use crate::peer::{PeerIdx, PeerPubkey};
@@ -18,6 +22,7 @@ use crate::peer_set::PeerSet;
 use crate::signed::Hashable;
 use crate::timestamp::Timestamp;
 use crate::ver::ConsensusVersion;
+use crate::weight::{DEFAULT_PEER_WEIGHT, PeerWeight, TotalWeight};
 
 array_type_fixed_size_define! {
     /// Length of block payload
@@ -43,7 +48,7 @@ framed_payload_define! {
 /// and that information is being committed to in every block
 /// to allow other nodes to easily verify it even when they
 /// don't (yet, or at all) track the consensus state themselves.
-#[derive(Decode, Encode, Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct ConsensusParams {
     /// Version of this [`ConsensusParams`] format
     pub consensus_params_format_version: u8,
@@ -97,11 +102,125 @@ pub struct ConsensusParams {
 
     /// Set of voting peers
     pub peers: PeerSet,
+
+    /// BLS public keys of the `peers`, in the same order
+    ///
+    /// Empty, unless every peer registered its BLS key, in which case
+    /// votes carry BLS signatures too and notarized blocks can be proven
+    /// with a single aggregate signature.
+    ///
+    /// Only encoded since [`Self::FORMAT_VERSION_BLS`].
+    pub bls_pubkeys: Vec<BlsPubkey>,
+
+    /// Voting weights of the `peers`, in the same order
+    ///
+    /// Empty if every peer has the [`DEFAULT_PEER_WEIGHT`].
+    ///
+    /// Only encoded since [`Self::FORMAT_VERSION_WEIGHTS`].
+    pub peer_weights: Vec<PeerWeight>,
+
+    /// Maximum length of a block payload a leader can propose
+    ///
+    /// Only encoded since [`Self::FORMAT_VERSION_PAYLOAD_LIMIT`], and
+    /// [`Self::DEFAULT_MAX_BLOCK_PAYLOAD_LEN`] in older versions.
+    pub max_block_payload_len: u32,
+}
+
+impl Encode for ConsensusParams {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.consensus_params_format_version.encode(encoder)?;
+        self.init_core_module_cons_version.encode(encoder)?;
+        self.timestamp.encode(encoder)?;
+        self.schedule_round.encode(encoder)?;
+        self.apply_round.encode(encoder)?;
+        self.prev_mid_block.encode(encoder)?;
+        self.peers.encode(encoder)?;
+        if Self::FORMAT_VERSION_BLS <= self.consensus_params_format_version {
+            self.bls_pubkeys.encode(encoder)?;
+        }
+        if Self::FORMAT_VERSION_WEIGHTS <= self.consensus_params_format_version {
+            self.peer_weights.encode(encoder)?;
+        }
+        if Self::FORMAT_VERSION_PAYLOAD_LIMIT <= self.consensus_params_format_version {
+            self.max_block_payload_len.encode(encoder)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C> Decode<C> for ConsensusParams {
+    fn decode<D: Decoder<Context = C>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let consensus_params_format_version: u8 = Decode::decode(decoder)?;
+        let init_core_module_cons_version = Decode::decode(decoder)?;
+        let timestamp = Decode::decode(decoder)?;
+        let schedule_round = Decode::decode(decoder)?;
+        let apply_round = Decode::decode(decoder)?;
+        let prev_mid_block = Decode::decode(decoder)?;
+        let peers = Decode::decode(decoder)?;
+        let bls_pubkeys = if Self::FORMAT_VERSION_BLS <= consensus_params_format_version {
+            Decode::decode(decoder)?
+        } else {
+            vec![]
+        };
+        let peer_weights = if Self::FORMAT_VERSION_WEIGHTS <= consensus_params_format_version {
+            Decode::decode(decoder)?
+        } else {
+            vec![]
+        };
+        let max_block_payload_len =
+            if Self::FORMAT_VERSION_PAYLOAD_LIMIT <= consensus_params_format_version {
+                Decode::decode(decoder)?
+            } else {
+                Self::DEFAULT_MAX_BLOCK_PAYLOAD_LEN
+            };
+
+        Ok(Self {
+            consensus_params_format_version,
+            init_core_module_cons_version,
+            timestamp,
+            schedule_round,
+            apply_round,
+            prev_mid_block,
+            peers,
+            bls_pubkeys,
+            peer_weights,
+            max_block_payload_len,
+        })
+    }
+}
+
+impl<'de, C> BorrowDecode<'de, C> for ConsensusParams {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = C>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Decode::decode(decoder)
+    }
 }
 
 impl ConsensusParams {
     pub const FORMAT_VERSION: u8 = 0;
 
+    /// Format version adding [`Self::bls_pubkeys`]
+    pub const FORMAT_VERSION_BLS: u8 = 1;
+
+    /// Format version adding [`Self::peer_weights`]
+    pub const FORMAT_VERSION_WEIGHTS: u8 = 2;
+
+    /// Format version adding [`Self::max_block_payload_len`]
+    pub const FORMAT_VERSION_PAYLOAD_LIMIT: u8 = 3;
+
+    /// Latest format version this code can encode and decode
+    pub const FORMAT_VERSION_LATEST: u8 = Self::FORMAT_VERSION_PAYLOAD_LIMIT;
+
+    /// [`Self::max_block_payload_len`] of formats not encoding it
+    pub const DEFAULT_MAX_BLOCK_PAYLOAD_LEN: u32 = 4 * 1024 * 1024;
+
+    /// Lowest [`Self::max_block_payload_len`] allowed
+    ///
+    /// Keeps blocks able to carry at least some consensus items, including
+    /// the ones needed to raise the limit again.
+    pub const MIN_MAX_BLOCK_PAYLOAD_LEN: u32 = 64 * 1024;
+
     /// Minimum base delay (in rounds) before application level
     /// consensus changes are applied on the core consensus.
     pub const CONSENSUS_PARAMS_CORE_APPLY_DELAY_BASE: u64 = 32;
@@ -118,6 +237,9 @@ impl ConsensusParams {
             timestamp: Timestamp::ZERO,
             schedule_round: 0.into(),
             apply_round: 0.into(),
+            bls_pubkeys: vec![],
+            peer_weights: vec![],
+            max_block_payload_len: Self::DEFAULT_MAX_BLOCK_PAYLOAD_LEN,
         }
     }
 
@@ -126,13 +248,46 @@ impl ConsensusParams {
         schedule_round: BlockRound,
         block_timestamp: Timestamp,
         peer_set: PeerSet,
+        bls_pubkeys: Vec<BlsPubkey>,
+        peer_weights: Vec<PeerWeight>,
+        max_block_payload_len: u32,
         prev_mid_block: Option<(BlockRound, BlockHash)>,
     ) -> Self {
         let apply_round = schedule_round
             .checked_add(self.consensus_params_schedulign_delay())
             .expect("Can't ran out of u64 of rounds");
+        assert!(bls_pubkeys.is_empty() || bls_pubkeys.len() == peer_set.len());
+        assert!(peer_weights.is_empty() || peer_weights.len() == peer_set.len());
+        let peer_weights = if peer_weights
+            .iter()
+            .all(|weight| *weight == DEFAULT_PEER_WEIGHT)
+        {
+            vec![]
+        } else {
+            peer_weights
+        };
+
+        // Stay on the old format, unless it's needed
+        let mut consensus_params_format_version = self.consensus_params_format_version;
+        if !bls_pubkeys.is_empty() {
+            consensus_params_format_version =
+                consensus_params_format_version.max(Self::FORMAT_VERSION_BLS);
+        }
+        if !peer_weights.is_empty() {
+            consensus_params_format_version =
+                consensus_params_format_version.max(Self::FORMAT_VERSION_WEIGHTS);
+        }
+        if max_block_payload_len != Self::DEFAULT_MAX_BLOCK_PAYLOAD_LEN {
+            consensus_params_format_version =
+                consensus_params_format_version.max(Self::FORMAT_VERSION_PAYLOAD_LIMIT);
+        }
+
         Self {
+            consensus_params_format_version,
             peers: peer_set,
+            bls_pubkeys,
+            peer_weights,
+            max_block_payload_len,
             timestamp: block_timestamp,
             prev_mid_block,
             schedule_round,
@@ -146,12 +301,67 @@ impl ConsensusParams {
             + Self::CONSENSUS_PARAMS_CORE_APPLY_DELAY_BASE
     }
 
+    /// Are BLS signatures of votes required and aggregated
+    pub fn uses_bls(&self) -> bool {
+        !self.bls_pubkeys.is_empty()
+    }
+
     pub fn num_peers(&self) -> NumPeers {
         self.peers.to_num_peers()
     }
 
+    /// Are peers using non-default voting weights
+    pub fn uses_weights(&self) -> bool {
+        !self.peer_weights.is_empty()
+    }
+
+    /// Voting weight of `peer_idx`, `0` if it's not a peer
+    pub fn peer_weight(&self, peer_idx: PeerIdx) -> u64 {
+        let idx = peer_idx.as_usize();
+        if self.peers.len() <= idx {
+            return 0;
+        }
+        u64::from(
+            self.peer_weights
+                .get(idx)
+                .copied()
+                .unwrap_or(DEFAULT_PEER_WEIGHT),
+        )
+    }
+
+    pub fn total_weight(&self) -> TotalWeight {
+        TotalWeight::from(
+            self.num_peers()
+                .peer_idx_iter()
+                .map(|peer_idx| self.peer_weight(peer_idx))
+                .sum::<u64>(),
+        )
+    }
+
+    /// Combined voting weight of (distinct) `peers`
+    pub fn weight_of(&self, peers: impl IntoIterator<Item = PeerIdx>) -> u64 {
+        peers
+            .into_iter()
+            .map(|peer_idx| self.peer_weight(peer_idx))
+            .sum()
+    }
+
+    /// Do (distinct) `peers` have enough weight to reach consensus
+    pub fn is_threshold(&self, peers: impl IntoIterator<Item = PeerIdx>) -> bool {
+        self.total_weight().threshold() <= self.weight_of(peers)
+    }
+
+    /// Do (distinct) `peers` have more weight than faulty peers could have
+    pub fn exceeds_max_faulty(&self, peers: impl IntoIterator<Item = PeerIdx>) -> bool {
+        self.total_weight().max_faulty() < self.weight_of(peers)
+    }
+
     pub fn leader_idx(&self, round: BlockRound) -> PeerIdx {
-        round.leader_idx(self.num_peers())
+        if self.uses_weights() {
+            round.weighted_leader_idx(&self.peer_weights)
+        } else {
+            round.leader_idx(self.num_peers())
+        }
     }
 
     pub fn hash(&self) -> ConsensusParamsHash {
@@ -190,13 +400,31 @@ impl ConsensusParams {
         let decoded: ConsensusParams =
             decode_whole(&raw.0, CONSENSUS_BINCODE_CONFIG).context(BincodeSnafu)?;
 
-        if decoded.consensus_params_format_version != 0 {
+        if Self::FORMAT_VERSION_LATEST < decoded.consensus_params_format_version {
             return MismatchedFormatVersionSnafu {
                 version: decoded.consensus_params_format_version,
             }
             .fail();
         }
 
+        if decoded.uses_bls() && decoded.bls_pubkeys.len() != decoded.peers.len() {
+            return MismatchedBlsPubkeysSnafu.fail();
+        }
+
+        if decoded.uses_weights()
+            && (decoded.peer_weights.len() != decoded.peers.len()
+                || decoded.peer_weights.contains(&0))
+        {
+            return InvalidPeerWeightsSnafu.fail();
+        }
+
+        if decoded.max_block_payload_len < Self::MIN_MAX_BLOCK_PAYLOAD_LEN {
+            return InvalidMaxBlockPayloadLenSnafu {
+                len: decoded.max_block_payload_len,
+            }
+            .fail();
+        }
+
         Ok(decoded)
     }
 
@@ -216,6 +444,9 @@ impl Hashable for ConsensusParams {}
 pub enum ConsensusParamsDecodeError {
     Bincode { source: bincode::error::DecodeError },
     MismatchedFormatVersion { version: u8 },
+    MismatchedBlsPubkeys,
+    InvalidPeerWeights,
+    InvalidMaxBlockPayloadLen { len: u32 },
     UnknownVersion { version: ConsensusVersion },
 }
 
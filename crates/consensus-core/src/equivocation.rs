@@ -0,0 +1,55 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt as _, Snafu};
+
+use crate::block::{BlockHeader, BlockRound};
+use crate::peer::PeerPubkey;
+use crate::signed::Signed;
+
+#[derive(Debug, Snafu)]
+pub enum InvalidEquivocationError {
+    RoundMismatch,
+    NotConflicting,
+    #[snafu(display("Dummy block votes are not equivocations"))]
+    Dummy,
+    InvalidSignature,
+}
+
+pub type InvalidEquivocationResult<T> = Result<T, InvalidEquivocationError>;
+
+/// Proof that a peer signed two different [`BlockHeader`]s in the same round
+///
+/// Honest peers never sign (vote for or propose) more than one non-dummy
+/// block per round, so anyone holding a valid evidence can convince everyone
+/// else that `peer_pubkey` misbehaved.
+#[derive(Debug, Clone, Encode, Decode, Serialize, Deserialize)]
+pub struct EquivocationEvidence {
+    pub peer_pubkey: PeerPubkey,
+    pub first: Signed<BlockHeader>,
+    pub second: Signed<BlockHeader>,
+}
+
+impl EquivocationEvidence {
+    pub fn round(&self) -> BlockRound {
+        self.first.round
+    }
+
+    pub fn verify(&self) -> InvalidEquivocationResult<()> {
+        if self.first.round != self.second.round {
+            return RoundMismatchSnafu.fail();
+        }
+        if self.first.inner == self.second.inner {
+            return NotConflictingSnafu.fail();
+        }
+        if self.first.is_dummy() || self.second.is_dummy() {
+            return DummySnafu.fail();
+        }
+        for signed in [&self.first, &self.second] {
+            signed
+                .verify_sig_peer_pubkey(self.peer_pubkey)
+                .ok()
+                .context(InvalidSignatureSnafu)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt as _, Snafu};
+
+use crate::peer::{PeerPubkey, PeerSeckey};
+use crate::signed::{Hashable, Signable, Signed};
+
+#[derive(Debug, Snafu)]
+pub enum InvalidKeyRotationError {
+    #[snafu(display("Can't rotate to the same key"))]
+    SameKey,
+    InvalidSignature,
+}
+
+pub type InvalidKeyRotationResult<T> = Result<T, InvalidKeyRotationError>;
+
+/// Handover of a peer's place in the consensus from `old_pubkey` to
+/// `new_pubkey`
+///
+/// Must be signed by `old_pubkey` (see [`SignedKeyRotation`]), which allows
+/// peers to rotate their keys without going through removing and adding a
+/// peer, temporarily changing the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct KeyRotation {
+    pub old_pubkey: PeerPubkey,
+    pub new_pubkey: PeerPubkey,
+}
+
+impl Hashable for KeyRotation {}
+
+impl Signable for KeyRotation {
+    const TAG: [u8; 4] = *b"krot";
+}
+
+pub type SignedKeyRotation = Signed<KeyRotation>;
+
+impl KeyRotation {
+    /// Create a [`KeyRotation`] from `old_seckey` to `new_pubkey`, signed
+    /// by `old_seckey`
+    pub fn new_signed(old_seckey: PeerSeckey, new_pubkey: PeerPubkey) -> SignedKeyRotation {
+        Signed::new_sign(
+            Self {
+                old_pubkey: old_seckey.pubkey(),
+                new_pubkey,
+            },
+            old_seckey,
+        )
+    }
+}
+
+impl Signed<KeyRotation> {
+    pub fn verify(&self) -> InvalidKeyRotationResult<()> {
+        if self.old_pubkey == self.new_pubkey {
+            return SameKeySnafu.fail();
+        }
+        self.verify_sig_peer_pubkey(self.old_pubkey)
+            .ok()
+            .context(InvalidSignatureSnafu)
+    }
+}
+
+#[cfg(test)]
+mod tests;
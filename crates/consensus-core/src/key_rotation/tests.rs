@@ -0,0 +1,25 @@
+use crate::key_rotation::{InvalidKeyRotationError, KeyRotation};
+use crate::peer::PeerSeckey;
+use crate::signed::Signable as _;
+
+#[test]
+fn key_rotation_sanity() {
+    let old_seckey = PeerSeckey::generate();
+    let new_pubkey = PeerSeckey::generate().pubkey();
+
+    let rotation = KeyRotation::new_signed(old_seckey, new_pubkey);
+    rotation.verify().expect("Valid rotation");
+
+    // Not signed by the old key
+    let mut forged = rotation;
+    forged.sig = rotation.inner.sign_with(PeerSeckey::generate());
+    assert!(matches!(
+        forged.verify(),
+        Err(InvalidKeyRotationError::InvalidSignature)
+    ));
+
+    assert!(matches!(
+        KeyRotation::new_signed(old_seckey, old_seckey.pubkey()).verify(),
+        Err(InvalidKeyRotationError::SameKey)
+    ));
+}
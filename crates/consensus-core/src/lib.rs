@@ -14,18 +14,26 @@ use bfte_util_array_type::{
 
 pub mod bincode;
 pub mod block;
+pub mod bls;
 pub mod citem;
 pub mod consensus_params;
+pub mod equivocation;
 pub mod federation_id;
+pub mod key_rotation;
+pub mod merkle;
 pub mod module;
 pub mod msg;
 pub mod num_peers;
 pub mod peer;
+pub mod peer_encrypted;
 pub mod peer_set;
+pub mod proof;
 pub mod signed;
+pub mod snapshot;
 pub mod timestamp;
 pub mod ver;
 pub mod vote;
+pub mod weight;
 
 array_type_define! {
     #[derive(Encode, Decode, Copy, Clone)]
@@ -0,0 +1,119 @@
+//! Simple binary Merkle tree used to commit to block's [`CItem`]s
+//!
+//! Leaves and inner nodes are domain separated, and an odd node at the end
+//! of a level is promoted to the next level as is (not duplicated), so every
+//! tree shape has exactly one root.
+//!
+//! [`CItem`]: crate::citem::CItem
+
+use std::io::Write as _;
+
+fn leaf_hash(data: &[u8]) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.write_all(&[0]).expect("Can't fail");
+    hasher.write_all(data).expect("Can't fail");
+    hasher.finalize()
+}
+
+fn node_hash(left: &blake3::Hash, right: &blake3::Hash) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.write_all(&[1]).expect("Can't fail");
+    hasher.write_all(left.as_bytes()).expect("Can't fail");
+    hasher.write_all(right.as_bytes()).expect("Can't fail");
+    hasher.finalize()
+}
+
+fn next_level(level: &[blake3::Hash]) -> Vec<blake3::Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Root of the tree with `leaves`
+///
+/// Root of an empty tree is all zeroes.
+pub fn merkle_root<'a>(leaves: impl IntoIterator<Item = &'a [u8]>) -> blake3::Hash {
+    let mut level: Vec<_> = leaves.into_iter().map(leaf_hash).collect();
+
+    if level.is_empty() {
+        return blake3::Hash::from_bytes([0; 32]);
+    }
+
+    while 1 < level.len() {
+        level = next_level(&level);
+    }
+
+    level[0]
+}
+
+/// Sibling hashes needed to prove inclusion of a leaf at `idx`
+///
+/// Returns `None` if `idx` is out of bounds.
+pub fn merkle_path<'a>(
+    leaves: impl IntoIterator<Item = &'a [u8]>,
+    mut idx: usize,
+) -> Option<Vec<blake3::Hash>> {
+    let mut level: Vec<_> = leaves.into_iter().map(leaf_hash).collect();
+
+    if level.len() <= idx {
+        return None;
+    }
+
+    let mut path = vec![];
+    while 1 < level.len() {
+        let sibling_idx = idx ^ 1;
+        if sibling_idx < level.len() {
+            path.push(level[sibling_idx]);
+        }
+        level = next_level(&level);
+        idx /= 2;
+    }
+
+    Some(path)
+}
+
+/// Root of a tree with `num_leaves` leaves, with `leaf` at `idx` and a given
+/// inclusion `path`
+///
+/// Returns `None` if the `path` doesn't match the shape of the tree.
+pub fn merkle_root_from_path(
+    leaf: &[u8],
+    mut idx: usize,
+    mut num_leaves: usize,
+    path: &[blake3::Hash],
+) -> Option<blake3::Hash> {
+    if num_leaves <= idx {
+        return None;
+    }
+
+    let mut path = path.iter();
+    let mut hash = leaf_hash(leaf);
+
+    while 1 < num_leaves {
+        let sibling_idx = idx ^ 1;
+        if sibling_idx < num_leaves {
+            let sibling = path.next()?;
+            hash = if idx % 2 == 0 {
+                node_hash(&hash, sibling)
+            } else {
+                node_hash(sibling, &hash)
+            };
+        }
+        idx /= 2;
+        num_leaves = num_leaves.div_ceil(2);
+    }
+
+    if path.next().is_some() {
+        return None;
+    }
+
+    Some(hash)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,48 @@
+use super::{merkle_path, merkle_root, merkle_root_from_path};
+
+#[test]
+fn merkle_path_roundtrip() {
+    for num_leaves in 1..=9usize {
+        let leaves: Vec<Vec<u8>> = (0..num_leaves).map(|i| vec![i as u8; i + 1]).collect();
+        let root = merkle_root(leaves.iter().map(Vec::as_slice));
+
+        for idx in 0..num_leaves {
+            let path = merkle_path(leaves.iter().map(Vec::as_slice), idx).expect("In bounds");
+
+            assert_eq!(
+                merkle_root_from_path(&leaves[idx], idx, num_leaves, &path),
+                Some(root),
+                "{num_leaves} {idx}"
+            );
+
+            // Wrong leaf, position or shape must not verify
+            assert_ne!(
+                merkle_root_from_path(b"other", idx, num_leaves, &path),
+                Some(root)
+            );
+            assert_ne!(
+                merkle_root_from_path(&leaves[idx], idx, num_leaves + 1, &path),
+                Some(root)
+            );
+            if 1 < num_leaves {
+                assert_ne!(
+                    merkle_root_from_path(&leaves[idx], (idx + 1) % num_leaves, num_leaves, &path),
+                    Some(root)
+                );
+            }
+        }
+
+        assert_eq!(
+            merkle_path(leaves.iter().map(Vec::as_slice), num_leaves),
+            None
+        );
+    }
+}
+
+#[test]
+fn merkle_root_empty() {
+    assert_eq!(
+        merkle_root(std::iter::empty()),
+        blake3::Hash::from_bytes([0; 32])
+    );
+}
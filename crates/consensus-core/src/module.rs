@@ -28,6 +28,12 @@ impl ModuleId {
     }
 }
 
+impl From<ModuleId> for u32 {
+    fn from(value: ModuleId) -> Self {
+        value.0
+    }
+}
+
 #[derive(
     Copy,
     Clone,
@@ -51,3 +57,9 @@ impl ModuleKind {
         Self(id)
     }
 }
+
+impl From<ModuleKind> for u32 {
+    fn from(value: ModuleKind) -> Self {
+        value.0
+    }
+}
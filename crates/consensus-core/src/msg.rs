@@ -2,7 +2,8 @@ use bincode::{Decode, Encode};
 use derive_more::From;
 
 use crate::block::{BlockHeader, BlockPayloadRaw, BlockRound};
-use crate::signed::{Hashable, Notarized, Signable, Signed};
+use crate::bls::BlsSignature;
+use crate::signed::{Hashable, Notarized, NotarizedNoBls, Signable, Signed};
 
 /// Wait for the peer vote on the block in the round
 #[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
@@ -18,23 +19,84 @@ pub enum WaitVoteResponse {
     /// If the peer is a leader it should respond with its proposal
     Proposal {
         block: Signed<BlockHeader>,
+        /// Required if [`ConsensusParams::uses_bls`]
+        ///
+        /// [`ConsensusParams::uses_bls`]: crate::consensus_params::ConsensusParams::uses_bls
+        bls_sig: Option<BlsSignature>,
         payload: BlockPayloadRaw,
     },
     /// If not, it should vote on the proposal it received or dummy block
-    Vote { block: Signed<BlockHeader> },
+    Vote {
+        block: Signed<BlockHeader>,
+        /// Required for non-dummy blocks, if [`ConsensusParams::uses_bls`]
+        ///
+        /// [`ConsensusParams::uses_bls`]: crate::consensus_params::ConsensusParams::uses_bls
+        bls_sig: Option<BlsSignature>,
+    },
 }
 
 impl WaitVoteResponse {
     pub fn block(&self) -> &Signed<BlockHeader> {
         match self {
-            WaitVoteResponse::Proposal { block, payload: _ } => block,
-            WaitVoteResponse::Vote { block } => block,
+            WaitVoteResponse::Proposal { block, .. } => block,
+            WaitVoteResponse::Vote { block, .. } => block,
+        }
+    }
+
+    pub fn bls_sig(&self) -> Option<BlsSignature> {
+        match self {
+            WaitVoteResponse::Proposal { bls_sig, .. } => *bls_sig,
+            WaitVoteResponse::Vote { bls_sig, .. } => *bls_sig,
         }
     }
 
     pub fn is_proposal(&self) -> bool {
         matches!(self, WaitVoteResponse::Proposal { .. })
     }
+
+    /// Drop the BLS signature, to respond with a [`WaitVoteResponseNoBls`]
+    pub fn into_no_bls(self) -> WaitVoteResponseNoBls {
+        match self {
+            WaitVoteResponse::Proposal { block, payload, .. } => {
+                WaitVoteResponseNoBls::Proposal { block, payload }
+            }
+            WaitVoteResponse::Vote { block, .. } => WaitVoteResponseNoBls::Vote { block },
+        }
+    }
+}
+
+/// [`WaitVoteResponse`] without the BLS signature, encoded like before it was
+/// added
+///
+/// Used when [`ConsensusParams::uses_bls`] is false, so peers not upgraded yet
+/// can still decode it.
+///
+/// [`ConsensusParams::uses_bls`]: crate::consensus_params::ConsensusParams::uses_bls
+#[derive(Decode, Encode, Clone, Debug)]
+pub enum WaitVoteResponseNoBls {
+    Proposal {
+        block: Signed<BlockHeader>,
+        payload: BlockPayloadRaw,
+    },
+    Vote {
+        block: Signed<BlockHeader>,
+    },
+}
+
+impl From<WaitVoteResponseNoBls> for WaitVoteResponse {
+    fn from(value: WaitVoteResponseNoBls) -> Self {
+        match value {
+            WaitVoteResponseNoBls::Proposal { block, payload } => WaitVoteResponse::Proposal {
+                block,
+                bls_sig: None,
+                payload,
+            },
+            WaitVoteResponseNoBls::Vote { block } => WaitVoteResponse::Vote {
+                block,
+                bls_sig: None,
+            },
+        }
+    }
 }
 
 /// Wait for the first non-dummy notarized block in range
@@ -68,6 +130,38 @@ pub struct WaitNotarizedBlockResponse {
     pub payload: BlockPayloadRaw,
 }
 
+impl WaitNotarizedBlockResponse {
+    /// Convert to [`WaitNotarizedBlockResponseNoBls`], unless the block was
+    /// notarized by the BLS aggregate
+    pub fn into_no_bls(self) -> Option<WaitNotarizedBlockResponseNoBls> {
+        Some(WaitNotarizedBlockResponseNoBls {
+            block: self.block.into_no_bls()?,
+            payload: self.payload,
+        })
+    }
+}
+
+/// [`WaitNotarizedBlockResponse`] with a [`NotarizedNoBls`] block
+///
+/// Used when [`ConsensusParams::uses_bls`] is false, so peers not upgraded yet
+/// can still decode it.
+///
+/// [`ConsensusParams::uses_bls`]: crate::consensus_params::ConsensusParams::uses_bls
+#[derive(Decode, Encode, Clone, Debug)]
+pub struct WaitNotarizedBlockResponseNoBls {
+    pub block: NotarizedNoBls<BlockHeader>,
+    pub payload: BlockPayloadRaw,
+}
+
+impl From<WaitNotarizedBlockResponseNoBls> for WaitNotarizedBlockResponse {
+    fn from(value: WaitNotarizedBlockResponseNoBls) -> Self {
+        Self {
+            block: value.block.into(),
+            payload: value.payload,
+        }
+    }
+}
+
 /// Wait for the first unnotarized non-dummy block round on the peer to change
 ///
 /// `prev` contains previous known value
@@ -97,3 +191,6 @@ impl Hashable for FinalityVoteUpdate {}
 impl Signable for FinalityVoteUpdate {
     const TAG: [u8; 4] = *b"furu";
 }
+
+#[cfg(test)]
+mod tests;
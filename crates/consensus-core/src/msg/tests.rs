@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use bincode::{Decode, Encode};
+
+use crate::Signature;
+use crate::bincode::CONSENSUS_BINCODE_CONFIG;
+use crate::block::{BlockHeader, BlockPayloadRaw};
+use crate::consensus_params::ConsensusParams;
+use crate::msg::{
+    WaitNotarizedBlockResponse, WaitNotarizedBlockResponseNoBls, WaitVoteResponse,
+    WaitVoteResponseNoBls,
+};
+use crate::peer::PeerIdx;
+use crate::signed::{Notarized, Signed};
+
+/// [`WaitVoteResponse`] as encoded before BLS signatures were added
+#[derive(Encode, Decode)]
+enum WaitVoteResponseV0 {
+    Proposal {
+        block: Signed<BlockHeader>,
+        payload: BlockPayloadRaw,
+    },
+    Vote {
+        block: Signed<BlockHeader>,
+    },
+}
+
+/// [`WaitNotarizedBlockResponse`] as encoded before BLS aggregates were added
+#[derive(Encode, Decode)]
+struct WaitNotarizedBlockResponseV0 {
+    block: NotarizedV0,
+    payload: BlockPayloadRaw,
+}
+
+#[derive(Encode, Decode)]
+struct NotarizedV0 {
+    inner: BlockHeader,
+    sigs: BTreeMap<PeerIdx, Signature>,
+}
+
+fn encode(v: &impl Encode) -> Vec<u8> {
+    bincode::encode_to_vec(v, CONSENSUS_BINCODE_CONFIG).expect("Can't fail")
+}
+
+fn decode<T: Decode<()>>(bytes: &[u8]) -> T {
+    let (v, len) = bincode::decode_from_slice(bytes, CONSENSUS_BINCODE_CONFIG).expect("Can't fail");
+    assert_eq!(len, bytes.len());
+    v
+}
+
+#[test]
+fn wait_vote_response_no_bls_compat() {
+    let block = Signed::new(
+        BlockHeader::new_dummy(1.into(), &ConsensusParams::new_test_dummy()),
+        Signature::ZERO,
+    );
+    let payload = BlockPayloadRaw::encode_citems(&[]);
+
+    let resp = WaitVoteResponse::Proposal {
+        block: block.clone(),
+        bls_sig: None,
+        payload: payload.clone(),
+    };
+    let v0 = WaitVoteResponseV0::Proposal {
+        block: block.clone(),
+        payload,
+    };
+    assert_eq!(encode(&resp.into_no_bls()), encode(&v0));
+
+    let v0 = WaitVoteResponseV0::Vote { block };
+    let resp: WaitVoteResponse = decode::<WaitVoteResponseNoBls>(&encode(&v0)).into();
+    assert!(!resp.is_proposal());
+    assert!(resp.bls_sig().is_none());
+}
+
+#[test]
+fn wait_notarized_block_response_no_bls_compat() {
+    let block = BlockHeader::new_dummy(1.into(), &ConsensusParams::new_test_dummy());
+    let sigs = BTreeMap::from([(PeerIdx::new(0), Signature::ZERO)]);
+    let payload = BlockPayloadRaw::encode_citems(&[]);
+
+    let resp = WaitNotarizedBlockResponse {
+        block: Notarized::new(block, sigs.clone()),
+        payload: payload.clone(),
+    };
+    let v0 = WaitNotarizedBlockResponseV0 {
+        block: NotarizedV0 {
+            inner: block,
+            sigs: sigs.clone(),
+        },
+        payload,
+    };
+    let v0_encoded = encode(&v0);
+    assert_eq!(
+        encode(&resp.into_no_bls().expect("Not aggregated")),
+        v0_encoded
+    );
+
+    let resp: WaitNotarizedBlockResponse =
+        decode::<WaitNotarizedBlockResponseNoBls>(&v0_encoded).into();
+    assert_eq!(resp.block.sigs, sigs);
+    assert!(resp.block.aggregate.is_none());
+}
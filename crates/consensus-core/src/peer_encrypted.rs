@@ -0,0 +1,417 @@
+//! Payloads encrypted to each peer of a [`PeerSet`], e.g. secret shares
+//! published in consensus items for auditability
+//!
+//! Peers encrypt to dedicated [`PeerEncryptionPubkey`]s, never to their
+//! signing keys. Modules derive the [`PeerEncryptionSeckey`] from their
+//! module secret and register the public key on-chain with a
+//! [`PeerEncryptionKeyRegistration`].
+//!
+//! Encryption is ECIES: the sender picks an ephemeral key `r`, publishes
+//! `R = r·B`, and each peer with key `a` (and `A = a·B`) shares the secret
+//! `S = r·A = a·R` with the sender, used to derive a ChaCha20-Poly1305 key for
+//! its ciphertext.
+//!
+//! If a ciphertext turns out to be invalid, its recipient can publish a
+//! [`PeerKeyDisclosure`]: `S` along with a proof that it was computed
+//! correctly, without revealing `a`. Anyone can then decrypt the ciphertext
+//! and check the plaintext with a module-specific [`VerifyPlaintext`], proving
+//! the sender misbehaved.
+//!
+//! All points are required to be in the prime order subgroup, as the proofs
+//! say nothing about their small order components.
+
+use std::collections::BTreeMap;
+
+use bfte_util_array_type::{
+    array_type_define, array_type_impl_base32_str, array_type_impl_debug_as_display,
+    array_type_impl_serde,
+};
+use bincode::de::{BorrowDecoder, Decoder};
+use bincode::error::DecodeError;
+use bincode::{BorrowDecode, Decode, Encode};
+use chacha20poly1305::aead::Aead as _;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit as _, Nonce};
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use rand::RngCore as _;
+use snafu::{OptionExt as _, Snafu};
+
+use crate::peer::PeerPubkey;
+use crate::peer_set::PeerSet;
+use crate::signed::{InvalidSignatureError, InvalidSignatureResult};
+
+#[derive(Debug, Snafu)]
+pub enum PeerDecryptError {
+    #[snafu(display("Not a recipient of the payload"))]
+    NotRecipient,
+    #[snafu(display("Invalid ephemeral key"))]
+    InvalidEphemeralKey,
+    #[snafu(display("Failed to decrypt the payload"))]
+    Decrypt,
+}
+
+pub type PeerDecryptResult<T> = Result<T, PeerDecryptError>;
+
+#[derive(Debug, Snafu)]
+pub enum InvalidKeyDisclosureError {
+    #[snafu(display("Not a recipient of the payload"))]
+    DisclosureNotRecipient,
+    #[snafu(display("Invalid key"))]
+    InvalidKey,
+    #[snafu(display("Invalid proof"))]
+    InvalidProof,
+}
+
+pub type InvalidKeyDisclosureResult<T> = Result<T, InvalidKeyDisclosureError>;
+
+array_type_define! {
+    /// Public key [`PeerEncrypted`] payloads are encrypted to
+    #[derive(Encode, Decode, Clone, Copy)]
+    pub struct PeerEncryptionPubkey[32];
+}
+array_type_impl_base32_str!(PeerEncryptionPubkey);
+array_type_impl_serde!(PeerEncryptionPubkey);
+array_type_impl_debug_as_display!(PeerEncryptionPubkey);
+
+/// Secret key for decrypting [`PeerEncrypted`] payloads
+#[derive(Clone, Copy)]
+pub struct PeerEncryptionSeckey(Scalar);
+
+impl PeerEncryptionSeckey {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self::from_secret_bytes(bytes)
+    }
+
+    /// Derive from secret key material, e.g. a secret derived from the module
+    /// secret
+    pub fn from_secret_bytes(bytes: [u8; 32]) -> Self {
+        Self(hash_to_scalar(&[b"bfte-peer-encryption-seckey", &bytes]))
+    }
+
+    pub fn pubkey(self) -> PeerEncryptionPubkey {
+        PeerEncryptionPubkey((ED25519_BASEPOINT_POINT * self.0).compress().to_bytes())
+    }
+
+    /// Create a [`PeerEncryptionKeyRegistration`] of this key for
+    /// `peer_pubkey`
+    pub fn registration(self, peer_pubkey: PeerPubkey) -> PeerEncryptionKeyRegistration {
+        let pubkey = self.pubkey();
+        let k = hash_to_scalar(&[
+            b"bfte-peer-encryption-pop-nonce",
+            self.0.as_bytes(),
+            peer_pubkey.as_slice(),
+        ]);
+        let commitment = (ED25519_BASEPOINT_POINT * k).compress().to_bytes();
+        let c = PeerEncryptionKeyRegistration::challenge(peer_pubkey, pubkey, &commitment);
+
+        PeerEncryptionKeyRegistration {
+            pubkey,
+            commitment,
+            response: (k + c * self.0).to_bytes(),
+        }
+    }
+}
+
+/// A [`PeerEncryptionPubkey`] along with a proof of possession of its
+/// secret key, bound to the peer registering it
+///
+/// Modules publish it in a consensus item of the peer, and keep the verified
+/// keys in a consensus table to encrypt to.
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeerEncryptionKeyRegistration {
+    pub pubkey: PeerEncryptionPubkey,
+    /// Compressed `k·B`
+    commitment: [u8; 32],
+    /// `k + c·a`
+    response: [u8; 32],
+}
+
+impl PeerEncryptionKeyRegistration {
+    fn challenge(
+        peer_pubkey: PeerPubkey,
+        pubkey: PeerEncryptionPubkey,
+        commitment: &[u8; 32],
+    ) -> Scalar {
+        hash_to_scalar(&[
+            b"bfte-peer-encryption-pop",
+            peer_pubkey.as_slice(),
+            pubkey.as_slice(),
+            commitment,
+        ])
+    }
+
+    pub fn verify(&self, peer_pubkey: PeerPubkey) -> InvalidSignatureResult<()> {
+        let pubkey_point = decompress(&self.pubkey.to_bytes()).ok_or(InvalidSignatureError)?;
+        let commitment = decompress(&self.commitment).ok_or(InvalidSignatureError)?;
+        let z = Option::<Scalar>::from(Scalar::from_canonical_bytes(self.response))
+            .ok_or(InvalidSignatureError)?;
+
+        let c = Self::challenge(peer_pubkey, self.pubkey, &self.commitment);
+        if ED25519_BASEPOINT_POINT * z != commitment + pubkey_point * c {
+            return Err(InvalidSignatureError);
+        }
+        Ok(())
+    }
+}
+
+/// Module-specific check of a decrypted plaintext
+///
+/// E.g. a DKG share checked against the sender's public commitments.
+pub trait VerifyPlaintext {
+    fn verify_plaintext(&self, recipient: PeerPubkey, plaintext: &[u8]) -> bool;
+}
+
+/// Outcome of checking a [`PeerKeyDisclosure`] against a [`PeerEncrypted`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SenderFault {
+    /// The ciphertext for the recipient does not decrypt
+    UndecryptableCiphertext,
+    /// The ciphertext decrypts, but the plaintext failed [`VerifyPlaintext`]
+    InvalidPlaintext(Vec<u8>),
+}
+
+/// A payload encrypted separately to each peer of a [`PeerSet`]
+///
+/// Decoding rejects ciphertexts not strictly sorted by their recipients, so
+/// each recipient has at most one ciphertext, and it can be looked up.
+#[derive(Encode, Clone, Debug, PartialEq, Eq)]
+pub struct PeerEncrypted {
+    /// Compressed ephemeral point `R`
+    ephemeral: [u8; 32],
+    /// Ciphertexts, sorted by their recipients
+    ciphertexts: Vec<(PeerPubkey, Vec<u8>)>,
+}
+
+impl<C> Decode<C> for PeerEncrypted {
+    fn decode<D: Decoder<Context = C>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let ephemeral = Decode::decode(decoder)?;
+        let ciphertexts: Vec<(PeerPubkey, Vec<u8>)> = Decode::decode(decoder)?;
+        if !ciphertexts.is_sorted_by(|(a, _), (b, _)| a < b) {
+            return Err(DecodeError::Other("ciphertexts not sorted by recipient"));
+        }
+        Ok(Self {
+            ephemeral,
+            ciphertexts,
+        })
+    }
+}
+
+impl<'de, C> BorrowDecode<'de, C> for PeerEncrypted {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = C>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Decode::decode(decoder)
+    }
+}
+
+impl PeerEncrypted {
+    /// Encrypt `plaintext_for(peer)` to the registered encryption key of each
+    /// `peer` of `peers`
+    ///
+    /// Returns `None` if any of the `peers` has no valid key in `keys`.
+    pub fn encrypt(
+        peers: &PeerSet,
+        keys: &BTreeMap<PeerPubkey, PeerEncryptionPubkey>,
+        mut plaintext_for: impl FnMut(PeerPubkey) -> Vec<u8>,
+    ) -> Option<Self> {
+        let mut r_bytes = [0u8; 64];
+        rand::thread_rng().fill_bytes(&mut r_bytes);
+        let r = Scalar::from_bytes_mod_order_wide(&r_bytes);
+        let ephemeral = (ED25519_BASEPOINT_POINT * r).compress().to_bytes();
+
+        let ciphertexts = peers
+            .iter()
+            .map(|&peer_pubkey| {
+                let key = *keys.get(&peer_pubkey)?;
+                let shared = decompress(&key.to_bytes())? * r;
+                let ciphertext = cipher(&shared, &ephemeral, peer_pubkey, key)
+                    .encrypt(&Nonce::default(), plaintext_for(peer_pubkey).as_slice())
+                    .expect("Can't fail");
+                Some((peer_pubkey, ciphertext))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            ephemeral,
+            ciphertexts,
+        })
+    }
+
+    pub fn recipients(&self) -> impl Iterator<Item = PeerPubkey> + '_ {
+        self.ciphertexts.iter().map(|(peer_pubkey, _)| *peer_pubkey)
+    }
+
+    fn ciphertext_for(&self, peer_pubkey: PeerPubkey) -> Option<&[u8]> {
+        self.ciphertexts
+            .binary_search_by_key(&peer_pubkey, |(peer_pubkey, _)| *peer_pubkey)
+            .ok()
+            .map(|idx| self.ciphertexts[idx].1.as_slice())
+    }
+
+    /// Decrypt the payload for `peer_pubkey`, owning the encryption `seckey`
+    pub fn decrypt_for_self(
+        &self,
+        peer_pubkey: PeerPubkey,
+        seckey: PeerEncryptionSeckey,
+    ) -> PeerDecryptResult<Vec<u8>> {
+        let ciphertext = self
+            .ciphertext_for(peer_pubkey)
+            .context(NotRecipientSnafu)?;
+        let ephemeral = decompress(&self.ephemeral).context(InvalidEphemeralKeySnafu)?;
+
+        let shared = ephemeral * seckey.0;
+
+        cipher(&shared, &self.ephemeral, peer_pubkey, seckey.pubkey())
+            .decrypt(&Nonce::default(), ciphertext)
+            .ok()
+            .context(DecryptSnafu)
+    }
+
+    /// Disclose the shared secret of `peer_pubkey`, owning the encryption
+    /// `seckey`, to prove the sender encrypted an invalid payload to it
+    pub fn disclose_key(
+        &self,
+        peer_pubkey: PeerPubkey,
+        seckey: PeerEncryptionSeckey,
+    ) -> PeerDecryptResult<PeerKeyDisclosure> {
+        self.ciphertext_for(peer_pubkey)
+            .context(NotRecipientSnafu)?;
+        let ephemeral = decompress(&self.ephemeral).context(InvalidEphemeralKeySnafu)?;
+
+        let a = seckey.0;
+        let key_point = ED25519_BASEPOINT_POINT * a;
+        let shared = ephemeral * a;
+
+        // Chaum-Pedersen proof that log_B(A) == log_R(S), with a deterministic
+        // nonce
+        let k = hash_to_scalar(&[b"bfte-peer-encrypted-nonce", a.as_bytes(), &self.ephemeral]);
+        let t1 = ED25519_BASEPOINT_POINT * k;
+        let t2 = ephemeral * k;
+        let c = challenge(&key_point, &ephemeral, &shared, &t1, &t2);
+
+        Ok(PeerKeyDisclosure {
+            recipient: peer_pubkey,
+            shared: shared.compress().to_bytes(),
+            commitments: [t1.compress().to_bytes(), t2.compress().to_bytes()],
+            response: (k + c * a).to_bytes(),
+        })
+    }
+
+    /// Check a [`PeerKeyDisclosure`] against the registered encryption `key`
+    /// of its recipient, returning the [`SenderFault`] it proves, if any
+    pub fn check_disclosure(
+        &self,
+        disclosure: &PeerKeyDisclosure,
+        key: PeerEncryptionPubkey,
+        verifier: &impl VerifyPlaintext,
+    ) -> InvalidKeyDisclosureResult<Option<SenderFault>> {
+        let ciphertext = self
+            .ciphertext_for(disclosure.recipient)
+            .context(DisclosureNotRecipientSnafu)?;
+        let shared = disclosure.verify(&self.ephemeral, key)?;
+
+        let Ok(plaintext) = cipher(&shared, &self.ephemeral, disclosure.recipient, key)
+            .decrypt(&Nonce::default(), ciphertext)
+        else {
+            return Ok(Some(SenderFault::UndecryptableCiphertext));
+        };
+
+        if verifier.verify_plaintext(disclosure.recipient, &plaintext) {
+            return Ok(None);
+        }
+        Ok(Some(SenderFault::InvalidPlaintext(plaintext)))
+    }
+}
+
+/// Shared secret of a recipient of a [`PeerEncrypted`], with a proof of its
+/// correctness
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct PeerKeyDisclosure {
+    pub recipient: PeerPubkey,
+    /// Compressed shared secret point `S`
+    shared: [u8; 32],
+    /// Compressed `k·B` and `k·R`
+    commitments: [[u8; 32]; 2],
+    /// `k + c·a`
+    response: [u8; 32],
+}
+
+impl PeerKeyDisclosure {
+    /// Verify the proof, returning the shared secret
+    fn verify(
+        &self,
+        ephemeral: &[u8; 32],
+        key: PeerEncryptionPubkey,
+    ) -> InvalidKeyDisclosureResult<EdwardsPoint> {
+        let key_point = decompress(&key.to_bytes()).context(InvalidKeySnafu)?;
+        let ephemeral = decompress(ephemeral).context(InvalidKeySnafu)?;
+        let shared = decompress(&self.shared).context(InvalidKeySnafu)?;
+        let t1 = decompress(&self.commitments[0]).context(InvalidProofSnafu)?;
+        let t2 = decompress(&self.commitments[1]).context(InvalidProofSnafu)?;
+        let z = Option::<Scalar>::from(Scalar::from_canonical_bytes(self.response))
+            .context(InvalidProofSnafu)?;
+
+        let c = challenge(&key_point, &ephemeral, &shared, &t1, &t2);
+        if ED25519_BASEPOINT_POINT * z != t1 + key_point * c || ephemeral * z != t2 + shared * c {
+            return InvalidProofSnafu.fail();
+        }
+
+        Ok(shared)
+    }
+}
+
+/// Decompress a point, rejecting ones with a small order component
+fn decompress(bytes: &[u8; 32]) -> Option<EdwardsPoint> {
+    CompressedEdwardsY(*bytes)
+        .decompress()
+        .filter(EdwardsPoint::is_torsion_free)
+}
+
+fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut hasher = blake3::Hasher::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut bytes = [0u8; 64];
+    hasher.finalize_xof().fill(&mut bytes);
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+fn challenge(
+    key_point: &EdwardsPoint,
+    ephemeral: &EdwardsPoint,
+    shared: &EdwardsPoint,
+    t1: &EdwardsPoint,
+    t2: &EdwardsPoint,
+) -> Scalar {
+    hash_to_scalar(&[
+        b"bfte-peer-encrypted-dleq",
+        key_point.compress().as_bytes(),
+        ephemeral.compress().as_bytes(),
+        shared.compress().as_bytes(),
+        t1.compress().as_bytes(),
+        t2.compress().as_bytes(),
+    ])
+}
+
+fn cipher(
+    shared: &EdwardsPoint,
+    ephemeral: &[u8; 32],
+    recipient: PeerPubkey,
+    key: PeerEncryptionPubkey,
+) -> ChaCha20Poly1305 {
+    let mut hasher = blake3::Hasher::new_derive_key("bfte peer-encrypted key");
+    hasher.update(shared.compress().as_bytes());
+    hasher.update(ephemeral);
+    hasher.update(recipient.as_slice());
+    hasher.update(key.as_slice());
+    // Each key is used only once, as `ephemeral` is unique, so a fixed nonce
+    // is fine
+    ChaCha20Poly1305::new(Key::from_slice(hasher.finalize().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests;
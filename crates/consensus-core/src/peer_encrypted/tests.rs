@@ -0,0 +1,251 @@
+use assert_matches::assert_matches;
+use curve25519_dalek::constants::EIGHT_TORSION;
+
+use bfte_util_bincode::decode_whole;
+
+use super::*;
+use crate::bincode::CONSENSUS_BINCODE_CONFIG;
+use crate::peer::PeerSeckey;
+
+struct ExpectPlaintext;
+
+impl VerifyPlaintext for ExpectPlaintext {
+    fn verify_plaintext(&self, recipient: PeerPubkey, plaintext: &[u8]) -> bool {
+        plaintext == recipient.as_slice()
+    }
+}
+
+struct Setup {
+    peers: PeerSet,
+    seckeys: BTreeMap<PeerPubkey, PeerEncryptionSeckey>,
+    keys: BTreeMap<PeerPubkey, PeerEncryptionPubkey>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let seckeys: BTreeMap<_, _> = (0..3)
+            .map(|_| {
+                (
+                    PeerSeckey::generate().pubkey(),
+                    PeerEncryptionSeckey::generate(),
+                )
+            })
+            .collect();
+        Self {
+            peers: seckeys.keys().copied().collect(),
+            keys: seckeys
+                .iter()
+                .map(|(peer_pubkey, seckey)| (*peer_pubkey, seckey.pubkey()))
+                .collect(),
+            seckeys,
+        }
+    }
+
+    fn peer(&self, i: usize) -> (PeerPubkey, PeerEncryptionSeckey) {
+        let peer_pubkey = self.peers[i];
+        (peer_pubkey, self.seckeys[&peer_pubkey])
+    }
+}
+
+#[test]
+fn peer_encrypted_roundtrip() {
+    let setup = Setup::new();
+
+    let encrypted = PeerEncrypted::encrypt(&setup.peers, &setup.keys, |peer_pubkey| {
+        peer_pubkey.as_slice().to_vec()
+    })
+    .expect("Valid keys");
+    assert_eq!(
+        encrypted.recipients().collect::<Vec<_>>(),
+        setup.peers.as_slice()
+    );
+
+    for (peer_pubkey, seckey) in &setup.seckeys {
+        assert_eq!(
+            encrypted
+                .decrypt_for_self(*peer_pubkey, *seckey)
+                .expect("Recipient"),
+            peer_pubkey.as_slice()
+        );
+    }
+
+    assert_matches!(
+        encrypted.decrypt_for_self(
+            PeerSeckey::generate().pubkey(),
+            PeerEncryptionSeckey::generate()
+        ),
+        Err(PeerDecryptError::NotRecipient)
+    );
+    let (peer_pubkey, _) = setup.peer(0);
+    assert_matches!(
+        encrypted.decrypt_for_self(peer_pubkey, PeerEncryptionSeckey::generate()),
+        Err(PeerDecryptError::Decrypt)
+    );
+
+    // Every recipient needs a registered key
+    let mut keys = setup.keys.clone();
+    keys.remove(&peer_pubkey);
+    assert!(PeerEncrypted::encrypt(&setup.peers, &keys, |_| vec![]).is_none());
+}
+
+#[test]
+fn peer_encryption_key_registration() {
+    let peer_pubkey = PeerSeckey::generate().pubkey();
+    let seckey = PeerEncryptionSeckey::from_secret_bytes([7; 32]);
+    assert_eq!(
+        seckey.pubkey(),
+        PeerEncryptionSeckey::from_secret_bytes([7; 32]).pubkey()
+    );
+
+    let registration = seckey.registration(peer_pubkey);
+    registration.verify(peer_pubkey).expect("Valid");
+
+    // Bound to the peer registering it
+    assert!(
+        registration
+            .verify(PeerSeckey::generate().pubkey())
+            .is_err()
+    );
+
+    // Only by the owner of the secret key
+    let mut forged = registration;
+    forged.pubkey = PeerEncryptionSeckey::generate().pubkey();
+    assert!(forged.verify(peer_pubkey).is_err());
+
+    // Keys with a small order component are rejected
+    let mut torsioned = registration;
+    torsioned.pubkey = PeerEncryptionPubkey::from_bytes(
+        (decompress(&registration.pubkey.to_bytes()).expect("Valid") + EIGHT_TORSION[1])
+            .compress()
+            .to_bytes(),
+    );
+    assert!(torsioned.verify(peer_pubkey).is_err());
+}
+
+#[test]
+fn peer_encrypted_disclosure_proves_sender_fault() {
+    let setup = Setup::new();
+    let (victim, victim_seckey) = setup.peer(1);
+
+    let mut encrypted = PeerEncrypted::encrypt(&setup.peers, &setup.keys, |peer_pubkey| {
+        if peer_pubkey == victim {
+            b"garbage".to_vec()
+        } else {
+            peer_pubkey.as_slice().to_vec()
+        }
+    })
+    .expect("Valid keys");
+
+    let (honest, honest_seckey) = setup.peer(0);
+    let disclosure = encrypted
+        .disclose_key(honest, honest_seckey)
+        .expect("Recipient");
+    assert_eq!(
+        encrypted
+            .check_disclosure(&disclosure, setup.keys[&honest], &ExpectPlaintext)
+            .expect("Valid disclosure"),
+        None
+    );
+
+    let complaint = encrypted
+        .disclose_key(victim, victim_seckey)
+        .expect("Recipient");
+    assert_eq!(
+        encrypted
+            .check_disclosure(&complaint, setup.keys[&victim], &ExpectPlaintext)
+            .expect("Valid disclosure"),
+        Some(SenderFault::InvalidPlaintext(b"garbage".to_vec()))
+    );
+
+    let (other, _) = setup.peer(2);
+    let mut forged = complaint.clone();
+    forged.recipient = other;
+    assert_matches!(
+        encrypted.check_disclosure(&forged, setup.keys[&other], &ExpectPlaintext),
+        Err(InvalidKeyDisclosureError::InvalidProof)
+    );
+
+    let idx = encrypted
+        .ciphertexts
+        .iter()
+        .position(|(peer_pubkey, _)| *peer_pubkey == victim)
+        .expect("Recipient");
+    encrypted.ciphertexts[idx].1[0] ^= 1;
+    assert_eq!(
+        encrypted
+            .check_disclosure(&complaint, setup.keys[&victim], &ExpectPlaintext)
+            .expect("Valid disclosure"),
+        Some(SenderFault::UndecryptableCiphertext)
+    );
+}
+
+#[test]
+fn peer_encrypted_disclosure_rejects_torsion() {
+    let setup = Setup::new();
+    let (peer_pubkey, seckey) = setup.peer(0);
+    let encrypted =
+        PeerEncrypted::encrypt(&setup.peers, &setup.keys, |_| vec![1, 2, 3]).expect("Valid keys");
+    let disclosure = encrypted
+        .disclose_key(peer_pubkey, seckey)
+        .expect("Recipient");
+
+    let add_torsion = |bytes: &[u8; 32]| {
+        (decompress(bytes).expect("Valid") + EIGHT_TORSION[1])
+            .compress()
+            .to_bytes()
+    };
+
+    let mut torsioned = disclosure.clone();
+    torsioned.shared = add_torsion(&disclosure.shared);
+    assert_matches!(
+        encrypted.check_disclosure(&torsioned, setup.keys[&peer_pubkey], &ExpectPlaintext),
+        Err(InvalidKeyDisclosureError::InvalidKey)
+    );
+
+    for i in 0..2 {
+        let mut torsioned = disclosure.clone();
+        torsioned.commitments[i] = add_torsion(&disclosure.commitments[i]);
+        assert_matches!(
+            encrypted.check_disclosure(&torsioned, setup.keys[&peer_pubkey], &ExpectPlaintext),
+            Err(InvalidKeyDisclosureError::InvalidProof)
+        );
+    }
+
+    let mut torsioned_ephemeral = encrypted.clone();
+    torsioned_ephemeral.ephemeral = add_torsion(&encrypted.ephemeral);
+    assert_matches!(
+        torsioned_ephemeral.decrypt_for_self(peer_pubkey, seckey),
+        Err(PeerDecryptError::InvalidEphemeralKey)
+    );
+    assert_matches!(
+        torsioned_ephemeral.check_disclosure(
+            &disclosure,
+            setup.keys[&peer_pubkey],
+            &ExpectPlaintext
+        ),
+        Err(InvalidKeyDisclosureError::InvalidKey)
+    );
+}
+
+#[test]
+fn peer_encrypted_decode_rejects_unsorted_recipients() {
+    let setup = Setup::new();
+    let encrypted =
+        PeerEncrypted::encrypt(&setup.peers, &setup.keys, |_| vec![1, 2, 3]).expect("Valid keys");
+    let decode = |encrypted: &PeerEncrypted| {
+        decode_whole::<PeerEncrypted, _>(
+            &bincode::encode_to_vec(encrypted, CONSENSUS_BINCODE_CONFIG).expect("Can't fail"),
+            CONSENSUS_BINCODE_CONFIG,
+        )
+    };
+
+    assert_eq!(decode(&encrypted).expect("Sorted"), encrypted);
+
+    let mut unsorted = encrypted.clone();
+    unsorted.ciphertexts.swap(0, 1);
+    decode(&unsorted).expect_err("Unsorted recipients must be rejected");
+
+    let mut duplicated = encrypted.clone();
+    duplicated.ciphertexts[1].0 = duplicated.ciphertexts[0].0;
+    decode(&duplicated).expect_err("Duplicate recipients must be rejected");
+}
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::num_peers::{NumPeers, ToNumPeers};
 use crate::peer::PeerPubkey;
+use crate::weight::{DEFAULT_PEER_WEIGHT, PeerWeight, TotalWeight};
 
 #[derive(Debug, Clone, Encode, Decode, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PeerSet(Vec<PeerPubkey>);
@@ -94,5 +95,69 @@ impl From<Vec<PeerPubkey>> for PeerSet {
     }
 }
 
+/// [`PeerSet`] along with voting weights of its peers
+///
+/// Dereferences to the [`PeerSet`], so it can be used in its place where
+/// weights don't matter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WeightedPeerSet {
+    peers: PeerSet,
+    weights: Vec<PeerWeight>,
+}
+
+impl ops::Deref for WeightedPeerSet {
+    type Target = PeerSet;
+
+    fn deref(&self) -> &Self::Target {
+        &self.peers
+    }
+}
+
+impl WeightedPeerSet {
+    /// Create from `weights` in the same order as `peers`
+    ///
+    /// Peers without a corresponding weight have the default one.
+    pub fn new(peers: PeerSet, weights: Vec<PeerWeight>) -> Self {
+        Self { peers, weights }
+    }
+
+    /// Voting weight of `peer`, `0` if it's not a peer
+    pub fn peer_weight(&self, peer: &PeerPubkey) -> u64 {
+        let Ok(idx) = self.peers.binary_search(peer) else {
+            return 0;
+        };
+        u64::from(
+            self.weights
+                .get(idx)
+                .copied()
+                .unwrap_or(DEFAULT_PEER_WEIGHT),
+        )
+    }
+
+    pub fn total_weight(&self) -> TotalWeight {
+        TotalWeight::from_weights((0..self.peers.len()).map(|idx| {
+            self.weights
+                .get(idx)
+                .copied()
+                .unwrap_or(DEFAULT_PEER_WEIGHT)
+        }))
+    }
+
+    /// Do (distinct) `peers` have enough weight to reach consensus
+    pub fn is_threshold<'a>(&self, peers: impl IntoIterator<Item = &'a PeerPubkey>) -> bool {
+        self.total_weight().threshold()
+            <= peers
+                .into_iter()
+                .map(|peer| self.peer_weight(peer))
+                .sum::<u64>()
+    }
+}
+
+impl From<PeerSet> for WeightedPeerSet {
+    fn from(peers: PeerSet) -> Self {
+        Self::new(peers, vec![])
+    }
+}
+
 #[cfg(test)]
 mod tests;
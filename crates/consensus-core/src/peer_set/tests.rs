@@ -1,5 +1,6 @@
-use super::PeerSet;
+use super::{PeerSet, WeightedPeerSet};
 use crate::peer::PeerSeckey;
+use crate::weight::TotalWeight;
 
 #[test]
 fn peer_set_sanity() {
@@ -22,3 +23,23 @@ fn peer_set_sanity() {
     assert!(set.insert(pk3));
     assert!(!set.insert(pk3));
 }
+
+#[test]
+fn weighted_peer_set_sanity() {
+    let peers: PeerSet = (0..3).map(|_| PeerSeckey::generate().pubkey()).collect();
+    let non_peer = PeerSeckey::generate().pubkey();
+
+    let unweighted = WeightedPeerSet::from(peers.clone());
+    assert_eq!(unweighted.total_weight(), TotalWeight::from(3));
+    assert!(unweighted.is_threshold(&peers[..]));
+    assert!(!unweighted.is_threshold(&peers[1..]));
+    assert_eq!(unweighted.peer_weight(&non_peer), 0);
+
+    let weighted = WeightedPeerSet::new(peers.clone(), vec![4, 1, 1]);
+    assert_eq!(*weighted, peers);
+    assert_eq!(weighted.total_weight(), TotalWeight::from(6));
+    assert!(weighted.is_threshold(&peers[..2]));
+    assert!(!weighted.is_threshold(&peers[1..]));
+    assert_eq!(weighted.peer_weight(&peers[0]), 4);
+    assert_eq!(weighted.peer_weight(&non_peer), 0);
+}
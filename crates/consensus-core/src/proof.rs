@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use bfte_util_bincode::decode_whole;
+use bincode::{Decode, Encode};
+use snafu::{OptionExt as _, ResultExt as _, Snafu};
+
+use crate::Signature;
+use crate::bincode::CONSENSUS_BINCODE_CONFIG;
+use crate::block::{BlockCItemsRoot, BlockHeader, encode_citems_leaves};
+use crate::citem::CItem;
+use crate::consensus_params::{ConsensusParams, ConsensusParamsHash};
+use crate::merkle::{merkle_path, merkle_root_from_path};
+use crate::msg::FinalityVoteUpdate;
+use crate::peer::PeerIdx;
+use crate::signed::{InvalidNotarizationError, Notarized, Signable as _};
+
+#[derive(Debug, Snafu)]
+pub enum InvalidProofError {
+    ParamsHashMismatch,
+    ParamsNotNewer,
+    InvalidNotarization {
+        source: InvalidNotarizationError,
+    },
+    /// New params were not endorsed by a threshold of previous params' peers
+    NotEndorsed,
+    #[snafu(display("Block header version does not commit to citems"))]
+    UnsupportedHeaderVersion,
+    InvalidMerklePath,
+    CItemsRootMismatch,
+    InvalidCItem,
+    /// A descendant does not extend the previous block
+    BrokenChain,
+    #[snafu(display("Invalid finality vote of peer {peer_idx}"))]
+    InvalidFinalityVote {
+        peer_idx: PeerIdx,
+    },
+    /// Finality votes were not cast by a threshold of peers
+    NotFinalized,
+}
+
+pub type InvalidProofResult<T> = Result<T, InvalidProofError>;
+
+/// A switch to new [`ConsensusParams`], proven by a block using them
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct ConsensusParamsTransition {
+    pub params: ConsensusParams,
+
+    /// A block using `params`, also signed by a threshold of peers of the
+    /// previous [`ConsensusParams`]
+    pub block: Notarized<BlockHeader>,
+}
+
+/// Proof that a notarized block is final
+///
+/// A notarized block alone is not enough, as a dummy of the same round
+/// might have been notarized too, and the chain continued without the block.
+///
+/// A finality vote for a round is a peer's claim that its highest notarized
+/// block is in the round right before it. There can't be two different
+/// notarized non-dummy blocks in the same round, so a threshold of votes for
+/// the round right after the last of a chain of notarized blocks means a
+/// threshold of peers adopted that chain, making it (and any block it extends)
+/// final. Votes for any higher round don't prove anything about the chain.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct FinalityCertificate {
+    /// Notarized blocks extending the proven block, each extending the
+    /// previous one, possibly empty
+    pub descendants: Vec<Notarized<BlockHeader>>,
+
+    /// Signatures of [`FinalityVoteUpdate`]s for the round right after the
+    /// last block, by peers of the [`ConsensusParams`] it uses
+    pub votes: BTreeMap<PeerIdx, Signature>,
+}
+
+impl FinalityCertificate {
+    /// Verify that `block` is final, looking up params used by hash with
+    /// `find_params`
+    fn verify<'p>(
+        &self,
+        block: &BlockHeader,
+        find_params: impl Fn(ConsensusParamsHash) -> InvalidProofResult<&'p ConsensusParams>,
+    ) -> InvalidProofResult<()> {
+        let mut tip = block;
+        for descendant in &self.descendants {
+            if descendant.prev_block_hash != tip.hash() || descendant.round <= tip.round {
+                return BrokenChainSnafu.fail();
+            }
+            descendant
+                .verify_sigs(find_params(descendant.consensus_params_hash)?)
+                .context(InvalidNotarizationSnafu)?;
+            tip = &descendant.inner;
+        }
+
+        let params = find_params(tip.consensus_params_hash)?;
+        let update = FinalityVoteUpdate::new(tip.round.next().context(NotFinalizedSnafu)?);
+        for (peer_idx, sig) in &self.votes {
+            let peer_pubkey = params.peers.as_slice().get(peer_idx.as_usize()).context(
+                InvalidFinalityVoteSnafu {
+                    peer_idx: *peer_idx,
+                },
+            )?;
+            update
+                .verify_signature(*peer_pubkey, *sig)
+                .ok()
+                .context(InvalidFinalityVoteSnafu {
+                    peer_idx: *peer_idx,
+                })?;
+        }
+        if !params.is_threshold(self.votes.keys().copied()) {
+            return NotFinalizedSnafu.fail();
+        }
+
+        Ok(())
+    }
+}
+
+/// Compact proof that a [`CItem`] was included in a finalized block
+///
+/// Verifiable without a database, starting from a trusted checkpoint
+/// [`ConsensusParams`] (e.g. the init params of the federation), by
+/// following the chain of [`ConsensusParamsTransition`]s to the params used
+/// in the block and in its [`FinalityCertificate`].
+///
+/// Only citems are covered. Effects of processing them are not committed to
+/// in blocks, so can't be proven this way.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct CItemProof {
+    pub params_transitions: Vec<ConsensusParamsTransition>,
+
+    /// Block including the [`CItem`]; must be at least `header_version` `1`
+    pub block: Notarized<BlockHeader>,
+
+    pub finality: FinalityCertificate,
+
+    pub citem_idx: u32,
+
+    /// Encoded [`CItem`]
+    pub citem: Vec<u8>,
+
+    /// Merkle inclusion path of `citem` in [`BlockHeader::citems_root`]
+    pub merkle_path: Vec<[u8; 32]>,
+}
+
+impl CItemProof {
+    /// Create a proof for `citem_idx`-th item of `citems` of the `block`
+    ///
+    /// Returns `None` if `citem_idx` is out of bounds, or `block` does not
+    /// commit to individual citems.
+    pub fn new(
+        params_transitions: Vec<ConsensusParamsTransition>,
+        block: Notarized<BlockHeader>,
+        finality: FinalityCertificate,
+        citems: &[CItem],
+        citem_idx: u32,
+    ) -> Option<Self> {
+        if block.header_version < 1 {
+            return None;
+        }
+
+        let leaves = encode_citems_leaves(citems);
+        let idx = usize::try_from(citem_idx).ok()?;
+        let merkle_path = merkle_path(leaves.iter().map(Vec::as_slice), idx)?
+            .into_iter()
+            .map(|hash| *hash.as_bytes())
+            .collect();
+
+        Some(Self {
+            params_transitions,
+            block,
+            finality,
+            citem_idx,
+            citem: leaves[idx].clone(),
+            merkle_path,
+        })
+    }
+
+    /// Verify the proof starting from `trusted_params`, and return the
+    /// proven [`CItem`]
+    pub fn verify(&self, trusted_params: &ConsensusParams) -> InvalidProofResult<CItem> {
+        let mut params = trusted_params;
+
+        for transition in &self.params_transitions {
+            if transition.params.apply_round <= params.apply_round {
+                return ParamsNotNewerSnafu.fail();
+            }
+            if transition.block.consensus_params_hash != transition.params.hash()
+                || transition.block.round < transition.params.apply_round
+            {
+                return ParamsHashMismatchSnafu.fail();
+            }
+            transition
+                .block
+                .verify_sigs(&transition.params)
+                .context(InvalidNotarizationSnafu)?;
+
+            // Sigs were verified above, so only need to count the ones
+            // from the peers we already trust, with the weight we trust them
+            let endorsing = transition
+                .block
+                .signers()
+                .into_iter()
+                .filter_map(|peer_idx| {
+                    params.find_peer_idx(transition.params.peers.as_slice()[peer_idx.as_usize()])
+                });
+            if !params.is_threshold(endorsing) {
+                return NotEndorsedSnafu.fail();
+            }
+
+            params = &transition.params;
+        }
+
+        // All the params are trusted now, and the block and the finality
+        // certificate might use any of them
+        let find_params = |hash: ConsensusParamsHash| {
+            std::iter::once(trusted_params)
+                .chain(self.params_transitions.iter().map(|t| &t.params))
+                .find(|params| params.hash() == hash)
+                .context(ParamsHashMismatchSnafu)
+        };
+        self.block
+            .verify_sigs(find_params(self.block.consensus_params_hash)?)
+            .context(InvalidNotarizationSnafu)?;
+        self.finality.verify(&self.block, find_params)?;
+
+        if self.block.header_version < 1 {
+            return UnsupportedHeaderVersionSnafu.fail();
+        }
+
+        let merkle_path: Vec<_> = self
+            .merkle_path
+            .iter()
+            .map(|hash| blake3::Hash::from_bytes(*hash))
+            .collect();
+        let root = merkle_root_from_path(
+            &self.citem,
+            usize::try_from(self.citem_idx)
+                .ok()
+                .context(InvalidMerklePathSnafu)?,
+            usize::try_from(self.block.num_citems)
+                .ok()
+                .context(InvalidMerklePathSnafu)?,
+            &merkle_path,
+        )
+        .context(InvalidMerklePathSnafu)?;
+
+        if BlockCItemsRoot::from(root) != self.block.citems_root {
+            return CItemsRootMismatchSnafu.fail();
+        }
+
+        decode_whole(&self.citem, CONSENSUS_BINCODE_CONFIG)
+            .ok()
+            .context(InvalidCItemSnafu)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+
+use super::{CItemProof, ConsensusParamsTransition, FinalityCertificate, InvalidProofError};
+use crate::Signature;
+use crate::bincode::CONSENSUS_BINCODE_CONFIG;
+use crate::block::{BlockHeader, BlockPayloadRaw, BlockRound};
+use crate::citem::{CItem, CItemRaw, ModuleDyn};
+use crate::consensus_params::ConsensusParams;
+use crate::module::ModuleId;
+use crate::msg::FinalityVoteUpdate;
+use crate::peer::{PeerIdx, PeerSeckey};
+use crate::peer_set::PeerSet;
+use crate::signed::{Notarized, Signable as _};
+use crate::timestamp::Timestamp;
+
+fn citems() -> Vec<CItem> {
+    (0..5u8)
+        .map(|i| {
+            CItem::PeerCItem(ModuleDyn::new(
+                ModuleId::new(0),
+                CItemRaw(Arc::from(vec![i; usize::from(i) + 1])),
+            ))
+        })
+        .collect()
+}
+
+fn params_with(seckeys: &[PeerSeckey], apply_round: BlockRound) -> ConsensusParams {
+    ConsensusParams {
+        peers: seckeys
+            .iter()
+            .map(|seckey| seckey.pubkey())
+            .collect::<PeerSet>(),
+        apply_round,
+        ..ConsensusParams::new_test_dummy()
+    }
+}
+
+fn notarize(
+    block: BlockHeader,
+    params: &ConsensusParams,
+    seckeys: &[PeerSeckey],
+) -> Notarized<BlockHeader> {
+    Notarized::new(
+        block,
+        seckeys.iter().map(|seckey| {
+            let peer_idx = params
+                .peers
+                .as_slice()
+                .iter()
+                .position(|peer_pubkey| *peer_pubkey == seckey.pubkey())
+                .expect("Must be a peer");
+            (
+                PeerIdx::new(u8::try_from(peer_idx).expect("Can't fail")),
+                block.sign_with(*seckey),
+            )
+        }),
+    )
+}
+
+/// Finality votes of all `seckeys` for `round`
+fn finality_votes(
+    round: BlockRound,
+    params: &ConsensusParams,
+    seckeys: &[PeerSeckey],
+) -> BTreeMap<PeerIdx, Signature> {
+    let update = FinalityVoteUpdate::new(round);
+    seckeys
+        .iter()
+        .map(|seckey| {
+            (
+                params
+                    .find_peer_idx(seckey.pubkey())
+                    .expect("Must be a peer"),
+                update.sign_with(*seckey),
+            )
+        })
+        .collect()
+}
+
+/// Certificate finalizing `block` without any descendants
+fn finality(
+    block: &BlockHeader,
+    params: &ConsensusParams,
+    seckeys: &[PeerSeckey],
+) -> FinalityCertificate {
+    FinalityCertificate {
+        descendants: vec![],
+        votes: finality_votes(block.round.next_expect(), params, seckeys),
+    }
+}
+
+fn block(round: BlockRound, params: &ConsensusParams, citems: &[CItem]) -> BlockHeader {
+    BlockHeader::builder()
+        .round(round)
+        .timestamp(Timestamp::ZERO)
+        .consensus_params(params)
+        .payload(&BlockPayloadRaw::encode_citems(citems))
+        .citems(citems)
+        .build()
+}
+
+fn encode(citem: &CItem) -> Vec<u8> {
+    bincode::encode_to_vec(citem, CONSENSUS_BINCODE_CONFIG).expect("Can't fail")
+}
+
+#[test]
+fn citem_proof_sanity() {
+    let seckeys = [PeerSeckey::generate()];
+    let params = params_with(&seckeys, 0.into());
+    let citems = citems();
+    let block = notarize(block(3.into(), &params, &citems), &params, &seckeys);
+
+    for idx in 0..5 {
+        let proof = CItemProof::new(
+            vec![],
+            block.clone(),
+            finality(&block, &params, &seckeys),
+            &citems,
+            idx,
+        )
+        .expect("Valid");
+        let citem = proof.verify(&params).expect("Valid proof");
+        assert_eq!(encode(&citem), encode(&citems[idx as usize]));
+    }
+    assert!(
+        CItemProof::new(
+            vec![],
+            block.clone(),
+            finality(&block, &params, &seckeys),
+            &citems,
+            5
+        )
+        .is_none()
+    );
+
+    let mut proof = CItemProof::new(
+        vec![],
+        block.clone(),
+        finality(&block, &params, &seckeys),
+        &citems,
+        1,
+    )
+    .expect("Valid");
+    proof.citem = encode(&citems[2]);
+    assert_matches!(
+        proof.verify(&params),
+        Err(InvalidProofError::CItemsRootMismatch)
+    );
+
+    // Leaf `2` of `3` has the same path as leaf `1` of `2` would, but the
+    // number of citems is committed to in the block
+    let citems3 = &citems[..3];
+    let block3 = notarize(block(4.into(), &params, citems3), &params, &seckeys);
+    let mut proof = CItemProof::new(
+        vec![],
+        block3.clone(),
+        finality(&block3, &params, &seckeys),
+        citems3,
+        2,
+    )
+    .expect("Valid");
+    proof.verify(&params).expect("Valid proof");
+    proof.citem_idx = 1;
+    assert_matches!(
+        proof.verify(&params),
+        Err(InvalidProofError::InvalidMerklePath)
+    );
+
+    let other_params = params_with(&[PeerSeckey::generate()], 0.into());
+    let proof = CItemProof::new(
+        vec![],
+        block.clone(),
+        finality(&block, &params, &seckeys),
+        &citems,
+        1,
+    )
+    .expect("Valid");
+    assert_matches!(
+        proof.verify(&other_params),
+        Err(InvalidProofError::ParamsHashMismatch)
+    );
+}
+
+#[test]
+fn citem_proof_params_transitions() {
+    let seckeys0 = [PeerSeckey::generate()];
+    let params0 = params_with(&seckeys0, 0.into());
+    let citems = citems();
+
+    // Adding a peer is endorsed by the existing one
+    let seckeys1 = [seckeys0[0], PeerSeckey::generate()];
+    let params1 = params_with(&seckeys1, 10.into());
+    let transition = ConsensusParamsTransition {
+        params: params1.clone(),
+        block: notarize(block(10.into(), &params1, &[]), &params1, &seckeys1),
+    };
+    let target = notarize(block(12.into(), &params1, &citems), &params1, &seckeys1);
+
+    let target_finality = finality(&target, &params1, &seckeys1);
+    let proof = CItemProof::new(
+        vec![transition.clone()],
+        target.clone(),
+        target_finality.clone(),
+        &citems,
+        4,
+    )
+    .expect("Valid");
+    proof.verify(&params0).expect("Valid proof");
+
+    let proof = CItemProof::new(vec![], target, target_finality, &citems, 4).expect("Valid");
+    assert_matches!(
+        proof.verify(&params0),
+        Err(InvalidProofError::ParamsHashMismatch)
+    );
+
+    // Complete takeover is not endorsed by anyone we trust
+    let seckeys2 = [PeerSeckey::generate(), PeerSeckey::generate()];
+    let params2 = params_with(&seckeys2, 10.into());
+    let transition = ConsensusParamsTransition {
+        params: params2.clone(),
+        block: notarize(block(10.into(), &params2, &[]), &params2, &seckeys2),
+    };
+    let target = notarize(block(12.into(), &params2, &citems), &params2, &seckeys2);
+    let target_finality = finality(&target, &params2, &seckeys2);
+    let proof =
+        CItemProof::new(vec![transition], target, target_finality, &citems, 0).expect("Valid");
+    assert_matches!(proof.verify(&params0), Err(InvalidProofError::NotEndorsed));
+}
+
+#[test]
+fn citem_proof_finality() {
+    let seckeys = [PeerSeckey::generate(), PeerSeckey::generate()];
+    let params = params_with(&seckeys, 0.into());
+    let citems = citems();
+    let target = notarize(block(3.into(), &params, &citems), &params, &seckeys);
+    let descendant = notarize(
+        BlockHeader::builder()
+            .prev(*target)
+            .round(5.into())
+            .timestamp(Timestamp::ZERO)
+            .consensus_params(&params)
+            .payload(&BlockPayloadRaw::empty())
+            .build(),
+        &params,
+        &seckeys,
+    );
+    let proof = |descendants: Vec<Notarized<BlockHeader>>, votes| {
+        CItemProof::new(
+            vec![],
+            target.clone(),
+            FinalityCertificate { descendants, votes },
+            &citems,
+            0,
+        )
+        .expect("Valid")
+    };
+
+    proof(vec![], finality_votes(4.into(), &params, &seckeys))
+        .verify(&params)
+        .expect("Valid proof");
+    proof(
+        vec![descendant.clone()],
+        finality_votes(6.into(), &params, &seckeys),
+    )
+    .verify(&params)
+    .expect("Valid proof");
+
+    // Not enough votes
+    assert_matches!(
+        proof(vec![], finality_votes(4.into(), &params, &seckeys[..1])).verify(&params),
+        Err(InvalidProofError::NotFinalized)
+    );
+
+    // Votes for a later round don't prove the block was not skipped
+    assert_matches!(
+        proof(vec![], finality_votes(6.into(), &params, &seckeys)).verify(&params),
+        Err(InvalidProofError::InvalidFinalityVote { .. })
+    );
+    assert_matches!(
+        proof(
+            vec![descendant],
+            finality_votes(4.into(), &params, &seckeys)
+        )
+        .verify(&params),
+        Err(InvalidProofError::InvalidFinalityVote { .. })
+    );
+
+    // Descendants must extend the block
+    let unrelated = notarize(block(5.into(), &params, &citems[..1]), &params, &seckeys);
+    assert_matches!(
+        proof(vec![unrelated], finality_votes(6.into(), &params, &seckeys)).verify(&params),
+        Err(InvalidProofError::BrokenChain)
+    );
+}
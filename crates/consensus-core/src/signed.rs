@@ -1,13 +1,15 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::Write as _;
 use std::{ops, result};
 
 use bincode::{Decode, Encode};
 use ed25519_dalek::ed25519::signature::SignerMut as _;
+use serde::{Deserialize, Serialize};
 use snafu::{OptionExt as _, Snafu};
 
 use crate::Signature;
 use crate::bincode::CONSENSUS_BINCODE_CONFIG;
+use crate::bls::{BlsAggregateSignature, BlsPubkey, BlsSeckey, BlsSignature};
 use crate::consensus_params::ConsensusParams;
 use crate::peer::{PeerIdx, PeerPubkey, PeerSeckey};
 
@@ -34,25 +36,47 @@ pub trait Signable: Hashable {
     const TAG: [u8; 4];
 
     fn sign_hash(&self) -> blake3::Hash {
-        let mut hasher = blake3::Hasher::new();
-
-        hasher.write_all(b"bfte").expect("Can't fail");
-        hasher.write_all(&Self::TAG).expect("Can't fail");
-        hasher
-            .write_all(self.hash().as_bytes())
-            .expect("Can't fail");
-
-        hasher.finalize()
+        signable_sign_hash(Self::TAG, self.hash())
     }
 
     fn sign_with(&self, seckey: PeerSeckey) -> Signature {
-        let v = ed25519_dalek::SigningKey::from(seckey).sign(self.sign_hash().as_bytes());
-        v.into()
+        sign_hash_with(self.sign_hash(), seckey)
     }
 
     fn verify_signature(&self, pubkey: PeerPubkey, sig: Signature) -> InvalidSignatureResult<()> {
         verify_hash_signature(self.sign_hash(), pubkey, sig)
     }
+
+    fn sign_bls_with(&self, seckey: BlsSeckey) -> BlsSignature {
+        seckey.sign_hash(self.sign_hash())
+    }
+
+    fn verify_bls_signature(
+        &self,
+        pubkey: BlsPubkey,
+        sig: BlsSignature,
+    ) -> InvalidSignatureResult<()> {
+        pubkey.verify_hash(self.sign_hash(), sig)
+    }
+}
+
+/// The hash actually signed for a [`Signable`] with a given `tag` and `hash`
+///
+/// Allows signing a message knowing only its type tag and [`Hashable::hash`],
+/// e.g. in a remote signer.
+pub fn signable_sign_hash(tag: [u8; 4], hash: blake3::Hash) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+
+    hasher.write_all(b"bfte").expect("Can't fail");
+    hasher.write_all(&tag).expect("Can't fail");
+    hasher.write_all(hash.as_bytes()).expect("Can't fail");
+
+    hasher.finalize()
+}
+
+pub fn sign_hash_with(hash: blake3::Hash, seckey: PeerSeckey) -> Signature {
+    let v = ed25519_dalek::SigningKey::from(seckey).sign(hash.as_bytes());
+    v.into()
 }
 
 fn verify_hash_signature(
@@ -68,7 +92,7 @@ fn verify_hash_signature(
     Ok(())
 }
 
-#[derive(Decode, Encode, Clone, Copy, Debug)]
+#[derive(Decode, Encode, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Signed<T> {
     pub inner: T,
     pub sig: Signature,
@@ -115,6 +139,9 @@ impl<T> ops::Deref for Signed<T> {
 pub struct Notarized<T> {
     pub inner: T,
     pub sigs: BTreeMap<PeerIdx, Signature>,
+    /// Signatures of peers aggregated into one, used instead of `sigs` when
+    /// [`ConsensusParams`] have BLS keys of all the peers
+    pub aggregate: Option<BlsAggregateSignature>,
 }
 
 impl<T> Notarized<T> {
@@ -122,9 +149,60 @@ impl<T> Notarized<T> {
         Self {
             inner,
             sigs: sigs.into_iter().collect(),
+            aggregate: None,
+        }
+    }
+
+    pub fn new_aggregate(inner: T, aggregate: BlsAggregateSignature) -> Self {
+        Self {
+            inner,
+            sigs: BTreeMap::new(),
+            aggregate: Some(aggregate),
         }
     }
+
+    /// All peers that signed, individually or in the aggregate
+    pub fn signers(&self) -> BTreeSet<PeerIdx> {
+        self.sigs
+            .keys()
+            .copied()
+            .chain(
+                self.aggregate
+                    .iter()
+                    .flat_map(|aggregate| aggregate.signers.iter()),
+            )
+            .collect()
+    }
+}
+/// [`Notarized`] without the BLS aggregate, encoded like before it was added
+///
+/// Sent to peers in place of [`Notarized`] when [`ConsensusParams::uses_bls`]
+/// is false, so ones not upgraded yet can still decode it.
+#[derive(Decode, Encode, Clone, Debug)]
+pub struct NotarizedNoBls<T> {
+    pub inner: T,
+    pub sigs: BTreeMap<PeerIdx, Signature>,
 }
+
+impl<T> From<NotarizedNoBls<T>> for Notarized<T> {
+    fn from(value: NotarizedNoBls<T>) -> Self {
+        Self::new(value.inner, value.sigs)
+    }
+}
+
+impl<T> Notarized<T> {
+    /// Convert to [`NotarizedNoBls`], unless notarized by the BLS aggregate
+    pub fn into_no_bls(self) -> Option<NotarizedNoBls<T>> {
+        if self.aggregate.is_some() {
+            return None;
+        }
+        Some(NotarizedNoBls {
+            inner: self.inner,
+            sigs: self.sigs,
+        })
+    }
+}
+
 impl<T> ops::Deref for Notarized<T> {
     type Target = T;
 
@@ -137,6 +215,7 @@ impl<T> ops::Deref for Notarized<T> {
 pub enum InvalidNotarizationError {
     NotEnoughSignatures,
     InvalidPeerSignature { peer_idx: PeerIdx },
+    InvalidAggregateSignature,
 }
 
 impl<T> Notarized<T>
@@ -147,19 +226,33 @@ where
         &self,
         consensus_params: &ConsensusParams,
     ) -> result::Result<(), InvalidNotarizationError> {
-        if self.sigs.len() < consensus_params.num_peers().threshold() {
+        if !consensus_params.is_threshold(self.signers()) {
             NotEnoughSignaturesSnafu.fail()?;
         }
 
         let hash = self.sign_hash();
 
         for (peer_idx, sig) in &self.sigs {
-            verify_hash_signature(hash, consensus_params.peers[peer_idx.as_usize()], *sig)
+            let peer_pubkey = consensus_params
+                .peers
+                .as_slice()
+                .get(peer_idx.as_usize())
+                .context(InvalidPeerSignatureSnafu {
+                    peer_idx: *peer_idx,
+                })?;
+            verify_hash_signature(hash, *peer_pubkey, *sig)
                 .ok()
                 .context(InvalidPeerSignatureSnafu {
                     peer_idx: *peer_idx,
                 })?;
         }
+
+        if let Some(aggregate) = &self.aggregate {
+            aggregate
+                .verify_hash(hash, &consensus_params.bls_pubkeys)
+                .ok()
+                .context(InvalidAggregateSignatureSnafu)?;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use bfte_util_array_type::{
+    array_type_define, array_type_fixed_size_define, array_type_impl_base32_str,
+    array_type_impl_debug_as_display, array_type_impl_serde, array_type_impl_zero_default,
+};
+use bfte_util_bincode::decode_whole;
+use bincode::{Decode, Encode};
+use snafu::{ResultExt as _, Snafu};
+
+use crate::bincode::CONSENSUS_BINCODE_CONFIG;
+use crate::block::BlockRound;
+use crate::consensus_params::ConsensusParams;
+use crate::framed_payload_define;
+use crate::signed::{Hashable, Signable};
+
+array_type_fixed_size_define! {
+    /// Length of encoded [`StateSnapshot`]
+    ///
+    /// Committed to in [`StateSnapshotHeader`], so it's possible to propagate
+    /// snapshots via BAO incremental verification.
+    #[derive(Encode, Decode, Clone, Copy)]
+    pub struct StateSnapshotLen(u32);
+}
+
+array_type_define! {
+    #[derive(Encode, Decode, Copy, Clone)]
+    pub struct StateSnapshotHash[32];
+}
+array_type_impl_zero_default!(StateSnapshotHash);
+array_type_impl_base32_str!(StateSnapshotHash);
+array_type_impl_serde!(StateSnapshotHash);
+array_type_impl_debug_as_display!(StateSnapshotHash);
+
+impl From<blake3::Hash> for StateSnapshotHash {
+    fn from(value: blake3::Hash) -> Self {
+        Self(*value.as_bytes())
+    }
+}
+
+framed_payload_define! {
+    pub struct StateSnapshotRaw;
+
+    StateSnapshotHash;
+    StateSnapshotLen;
+
+    pub struct StateSnapshotSlice;
+}
+
+/// Raw content of a table: encoded keys and values, in order
+pub type StateSnapshotTable = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Snapshot of the application level (modules) state
+///
+/// Taken after processing all the finalized blocks before `round`, and
+/// nothing else. A node joining the consensus can restore it and start
+/// processing blocks from `round`, instead of replaying the whole history.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct StateSnapshot {
+    pub round: BlockRound,
+
+    /// All [`ConsensusParams`] scheduled before `round`, including ones still
+    /// pending application
+    ///
+    /// Since these are scheduled by the application level processing, a node
+    /// that does not replay the history would not know about them otherwise.
+    pub consensus_params: Vec<ConsensusParams>,
+
+    /// Content of all (non-local) module tables, by their full table name
+    pub tables: BTreeMap<String, StateSnapshotTable>,
+}
+
+impl StateSnapshot {
+    /// Every how many rounds peers take [`StateSnapshot`]s
+    pub const INTERVAL_ROUNDS: u64 = 10_000;
+
+    /// Round of the snapshot that should be taken before processing a block
+    /// at `block_round`, when all the blocks before `cur_round` were already
+    /// processed
+    pub fn round_to_take(cur_round: BlockRound, block_round: BlockRound) -> Option<BlockRound> {
+        let snapshot_round = BlockRound::from(
+            block_round.to_number() / Self::INTERVAL_ROUNDS * Self::INTERVAL_ROUNDS,
+        );
+
+        (snapshot_round != BlockRound::ZERO && cur_round <= snapshot_round)
+            .then_some(snapshot_round)
+    }
+
+    pub fn to_raw(&self) -> StateSnapshotRaw {
+        StateSnapshotRaw(
+            bincode::encode_to_vec(self, CONSENSUS_BINCODE_CONFIG)
+                .expect("Can't fail")
+                .into(),
+        )
+    }
+
+    pub fn from_raw(raw: &StateSnapshotRaw) -> StateSnapshotDecodeResult<Self> {
+        decode_whole(&raw.0, CONSENSUS_BINCODE_CONFIG).context(BincodeSnafu)
+    }
+}
+
+#[derive(Snafu, Debug)]
+pub enum StateSnapshotDecodeError {
+    Bincode { source: bincode::error::DecodeError },
+}
+
+pub type StateSnapshotDecodeResult<T> = Result<T, StateSnapshotDecodeError>;
+
+impl StateSnapshotRaw {
+    pub fn header(&self, round: BlockRound) -> StateSnapshotHeader {
+        StateSnapshotHeader {
+            round,
+            hash: self.hash(),
+            len: self.len(),
+        }
+    }
+}
+
+/// What peers sign to attest they've got the same [`StateSnapshot`]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StateSnapshotHeader {
+    pub round: BlockRound,
+    pub hash: StateSnapshotHash,
+    pub len: StateSnapshotLen,
+}
+
+impl Hashable for StateSnapshotHeader {}
+impl Signable for StateSnapshotHeader {
+    const TAG: [u8; 4] = *b"snap";
+}
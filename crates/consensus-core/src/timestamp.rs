@@ -1,5 +1,6 @@
-use bfte_util_array_type::array_type_fixed_size_define;
+use bfte_util_array_type::{array_type_fixed_size_define, array_type_fixed_size_impl_serde};
 use bincode::{Decode, Encode};
+use serde::Deserialize;
 use time::UtcDateTime;
 
 array_type_fixed_size_define! {
@@ -7,6 +8,7 @@ array_type_fixed_size_define! {
     #[derive(Encode, Decode, Clone, Copy)]
     pub struct Timestamp(u64);
 }
+array_type_fixed_size_impl_serde!(Timestamp);
 
 impl Timestamp {
     pub fn now() -> Self {
@@ -0,0 +1,47 @@
+use core::fmt;
+
+use derive_more::From;
+
+/// Voting weight of a single peer
+///
+/// Peers have a weight of `1` each, unless configured otherwise.
+pub type PeerWeight = u32;
+
+/// Default [`PeerWeight`]
+pub const DEFAULT_PEER_WEIGHT: PeerWeight = 1;
+
+/// Sum of [`PeerWeight`]s of all peers
+///
+/// Weighted counterpart of [`crate::num_peers::NumPeers`].
+#[derive(Debug, Clone, Copy, From, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TotalWeight(u64);
+
+impl fmt::Display for TotalWeight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl TotalWeight {
+    pub fn from_weights(weights: impl IntoIterator<Item = PeerWeight>) -> Self {
+        Self(weights.into_iter().map(u64::from).sum())
+    }
+
+    /// Total weight of all peers
+    pub fn total(self) -> u64 {
+        self.0
+    }
+
+    /// Max weight of faulty nodes
+    pub fn max_faulty(self) -> u64 {
+        self.total().saturating_sub(1) / 3
+    }
+
+    /// Weight required to reach consensus
+    pub fn threshold(self) -> u64 {
+        self.total() - self.max_faulty()
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,79 @@
+use crate::consensus_params::ConsensusParams;
+use crate::num_peers::NumPeers;
+use crate::peer::{PeerIdx, PeerSeckey};
+use crate::peer_set::PeerSet;
+use crate::weight::{DEFAULT_PEER_WEIGHT, TotalWeight};
+
+#[test]
+fn total_weight_sanity() {
+    // With default weights it must match `NumPeers`
+    for n in 1..=10u8 {
+        let num = NumPeers::from(n);
+        let weight = TotalWeight::from_weights((0..n).map(|_| DEFAULT_PEER_WEIGHT));
+        assert_eq!(weight.total(), n.into());
+        assert_eq!(
+            weight.max_faulty(),
+            u64::try_from(num.max_faulty()).expect("Can't fail")
+        );
+        assert_eq!(
+            weight.threshold(),
+            u64::try_from(num.threshold()).expect("Can't fail")
+        );
+    }
+
+    // One peer with more than third of the weight can stall the consensus
+    let weight = TotalWeight::from_weights([2, 1, 1]);
+    assert_eq!(weight.max_faulty(), 1);
+    assert_eq!(weight.threshold(), 3);
+}
+
+#[test]
+fn consensus_params_weights_sanity() {
+    let peers: PeerSet = (0..3).map(|_| PeerSeckey::generate().pubkey()).collect();
+    let params = ConsensusParams {
+        peers: peers.clone(),
+        ..ConsensusParams::new_test_dummy()
+    };
+    assert!(!params.uses_weights());
+    assert!(params.is_threshold([0, 1, 2].map(PeerIdx::new)));
+    assert!(!params.is_threshold([0, 1].map(PeerIdx::new)));
+
+    // Default weights keep the original format
+    let unchanged = params.clone().make_change(
+        1.into(),
+        params.timestamp,
+        peers.clone(),
+        vec![],
+        vec![DEFAULT_PEER_WEIGHT; 3],
+        params.max_block_payload_len,
+        None,
+    );
+    assert_eq!(
+        unchanged.consensus_params_format_version,
+        ConsensusParams::FORMAT_VERSION
+    );
+
+    let weighted = params.clone().make_change(
+        1.into(),
+        params.timestamp,
+        peers,
+        vec![],
+        vec![4, 1, 1],
+        params.max_block_payload_len,
+        None,
+    );
+    assert_eq!(
+        weighted.consensus_params_format_version,
+        ConsensusParams::FORMAT_VERSION_WEIGHTS
+    );
+    assert_eq!(
+        ConsensusParams::from_raw(&weighted.to_raw()).expect("Valid"),
+        weighted
+    );
+    assert_eq!(weighted.total_weight(), TotalWeight::from(6));
+    assert!(weighted.is_threshold([0, 1].map(PeerIdx::new)));
+    assert!(!weighted.is_threshold([1, 2].map(PeerIdx::new)));
+    assert!(weighted.exceeds_max_faulty([0].map(PeerIdx::new)));
+    assert!(!weighted.exceeds_max_faulty([1].map(PeerIdx::new)));
+    assert_eq!(weighted.peer_weight(PeerIdx::new(3)), 0);
+}
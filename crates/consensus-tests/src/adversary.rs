@@ -0,0 +1,173 @@
+use bfte_consensus_core::block::{BlockHeader, BlockPayloadRaw, BlockRound};
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::msg::{FinalityVoteUpdate, WaitFinalityVoteResponse, WaitVoteResponse};
+use bfte_consensus_core::peer::{PeerIdx, PeerPubkey, PeerSeckey};
+use bfte_consensus_core::signed::{Signable, Signed};
+use bfte_consensus_core::timestamp::Timestamp;
+
+/// The way an [`AdversaryPeer`] misbehaves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdversaryBehavior {
+    /// When a leader, propose two different blocks for the same round
+    EquivocateProposal,
+    /// Vote for the proposal and for a conflicting block in the same round
+    DoubleVote,
+    /// When a leader, propose a block with a payload not matching the header
+    MismatchedPayload,
+    /// Never send any finality votes
+    WithholdFinalityVote,
+    /// Send address updates for other peers, or with forged signatures
+    InvalidAddressUpdate,
+}
+
+/// A peer producing actively malicious consensus messages
+///
+/// Plugged in a [`crate::network::Network`] in place of an honest peer. Every
+/// message it produces is signed with its own (valid) [`PeerSeckey`], unless
+/// forging signatures is the point of the misbehavior. Messages unrelated to
+/// its [`AdversaryBehavior`] are produced honestly.
+pub struct AdversaryPeer {
+    seckey: PeerSeckey,
+    peer_idx: PeerIdx,
+    cons_params: ConsensusParams,
+    behavior: AdversaryBehavior,
+}
+
+impl AdversaryPeer {
+    pub fn new(
+        seckey: PeerSeckey,
+        cons_params: ConsensusParams,
+        behavior: AdversaryBehavior,
+    ) -> Self {
+        let peer_idx = cons_params
+            .find_peer_idx(seckey.pubkey())
+            .expect("Adversary must be a part of the peer set");
+        Self {
+            seckey,
+            peer_idx,
+            cons_params,
+            behavior,
+        }
+    }
+
+    pub fn peer_idx(&self) -> PeerIdx {
+        self.peer_idx
+    }
+
+    pub fn pubkey(&self) -> PeerPubkey {
+        self.seckey.pubkey()
+    }
+
+    pub fn behavior(&self) -> AdversaryBehavior {
+        self.behavior
+    }
+
+    /// Block extending `prev` in `round`, with a payload unique to `variant`
+    pub fn block(
+        &self,
+        round: BlockRound,
+        prev: Option<BlockHeader>,
+        variant: u8,
+    ) -> (BlockHeader, BlockPayloadRaw) {
+        let payload = BlockPayloadRaw::from(vec![variant]);
+        let block = BlockHeader::builder()
+            .maybe_prev(prev)
+            .timestamp(Timestamp::now())
+            .round(round)
+            .consensus_params(&self.cons_params)
+            .payload(&payload)
+            .build();
+        (block, payload)
+    }
+
+    /// Responses to a vote request in `round` in which the adversary is a
+    /// leader
+    pub fn propose(&self, round: BlockRound, prev: Option<BlockHeader>) -> Vec<WaitVoteResponse> {
+        let (block, payload) = self.block(round, prev, 0);
+
+        match self.behavior {
+            AdversaryBehavior::EquivocateProposal => {
+                let (other_block, other_payload) = self.block(round, prev, 1);
+                vec![
+                    WaitVoteResponse::Proposal {
+                        block: Signed::new_sign(block, self.seckey),
+                        bls_sig: None,
+                        payload,
+                    },
+                    WaitVoteResponse::Proposal {
+                        block: Signed::new_sign(other_block, self.seckey),
+                        bls_sig: None,
+                        payload: other_payload,
+                    },
+                ]
+            }
+            AdversaryBehavior::MismatchedPayload => {
+                let (_, other_payload) = self.block(round, prev, 1);
+                vec![WaitVoteResponse::Proposal {
+                    block: Signed::new_sign(block, self.seckey),
+                    bls_sig: None,
+                    payload: other_payload,
+                }]
+            }
+            _ => vec![WaitVoteResponse::Proposal {
+                block: Signed::new_sign(block, self.seckey),
+                bls_sig: None,
+                payload,
+            }],
+        }
+    }
+
+    /// Responses to a vote request for a round with a known `proposal`
+    pub fn vote(&self, proposal: BlockHeader, prev: Option<BlockHeader>) -> Vec<WaitVoteResponse> {
+        let vote = WaitVoteResponse::Vote {
+            block: Signed::new_sign(proposal, self.seckey),
+            bls_sig: None,
+        };
+
+        match self.behavior {
+            AdversaryBehavior::DoubleVote => {
+                let (other_block, _) = self.block(proposal.round, prev, 1);
+                vec![
+                    vote,
+                    WaitVoteResponse::Vote {
+                        block: Signed::new_sign(other_block, self.seckey),
+                        bls_sig: None,
+                    },
+                ]
+            }
+            _ => vec![vote],
+        }
+    }
+
+    /// Finality vote update for `round`, `None` if the adversary withholds it
+    pub fn finality_vote(&self, round: BlockRound) -> Option<WaitFinalityVoteResponse> {
+        if self.behavior == AdversaryBehavior::WithholdFinalityVote {
+            return None;
+        }
+
+        Some(WaitFinalityVoteResponse {
+            update: Signed::new_sign(FinalityVoteUpdate::new(round), self.seckey),
+        })
+    }
+
+    /// Address updates the adversary gossips
+    ///
+    /// `own` is an update of the adversary's own address, and `hijacked` the
+    /// same claiming to be about another peer. Generic over the update type,
+    /// which belongs to the node, not the consensus.
+    pub fn address_updates<T>(&self, own: T, hijacked: T) -> Vec<Signed<T>>
+    where
+        T: Signable + Clone,
+    {
+        if self.behavior != AdversaryBehavior::InvalidAddressUpdate {
+            return vec![Signed::new_sign(own, self.seckey)];
+        }
+
+        vec![
+            // Claim to be the victim, but sign with own key
+            Signed::new_sign(hijacked.clone(), self.seckey),
+            // Reuse own (valid) signature for a different message
+            Signed::new(hijacked, own.sign_with(self.seckey)),
+        ]
+    }
+}
@@ -1 +1,5 @@
+#![doc = include_str!("../README.md")]
 
+pub mod adversary;
+pub mod network;
+pub mod setup;
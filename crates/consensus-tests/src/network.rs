@@ -0,0 +1,227 @@
+use std::sync::Arc;
+
+use bfte_consensus::consensus::{Consensus, ProcessVoteError};
+use bfte_consensus_core::block::{BlockHeader, BlockPayloadRaw, BlockRound};
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::equivocation::EquivocationEvidence;
+use bfte_consensus_core::msg::{FinalityVoteUpdate, WaitFinalityVoteResponse, WaitVoteResponse};
+use bfte_consensus_core::num_peers::NumPeers;
+use bfte_consensus_core::peer::{PeerIdx, PeerPubkey, PeerSeckey};
+use bfte_consensus_core::signed::Signed;
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_consensus_core::ver::ConsensusVersion;
+use bfte_db::Database;
+use bfte_util_error::WhateverResult;
+use snafu::{ResultExt as _, whatever};
+
+use crate::adversary::{AdversaryBehavior, AdversaryPeer};
+use crate::setup::gen_peers;
+
+/// An honest peer, running its own consensus instance
+pub struct HonestNode {
+    pub peer_idx: PeerIdx,
+    pub seckey: PeerSeckey,
+    pub consensus: Consensus,
+    /// Database of the consensus, shared with anything else the node runs
+    pub db: Arc<Database>,
+}
+
+/// Honest nodes of a peer set exchanging messages with each other
+///
+/// Any peer can be [replaced](Self::replace_with_adversary) by an
+/// [`AdversaryPeer`], with its messages delivered to all the honest nodes
+/// like any other.
+pub struct Network {
+    pub cons_params: ConsensusParams,
+    pub seckeys: Vec<PeerSeckey>,
+    pub nodes: Vec<HonestNode>,
+}
+
+impl Network {
+    pub async fn bootstrap(num_peers: NumPeers) -> WhateverResult<Self> {
+        let (seckeys, cons_params) = gen_peers(num_peers, ConsensusVersion::new(0, 0));
+
+        let mut nodes = vec![];
+        for (peer_idx, seckey) in num_peers.peer_idx_iter().zip(seckeys.iter().copied()) {
+            let db = Arc::new(
+                Database::new_in_memory()
+                    .await
+                    .whatever_context("Failed to create database")?,
+            );
+            let consensus = Consensus::init(&cons_params, db.clone(), Some(seckey.pubkey()), None)
+                .await
+                .whatever_context("Failed to create consensus")?;
+            nodes.push(HonestNode {
+                peer_idx,
+                seckey,
+                consensus,
+                db,
+            });
+        }
+
+        Ok(Self {
+            cons_params,
+            seckeys,
+            nodes,
+        })
+    }
+
+    /// Stop running an honest node for `peer_idx`, and return an adversary to
+    /// play it instead
+    pub fn replace_with_adversary(
+        &mut self,
+        peer_idx: PeerIdx,
+        behavior: AdversaryBehavior,
+    ) -> AdversaryPeer {
+        self.nodes.retain(|node| node.peer_idx != peer_idx);
+        AdversaryPeer::new(
+            self.seckeys[peer_idx.as_usize()],
+            self.cons_params.clone(),
+            behavior,
+        )
+    }
+
+    pub fn leader_idx(&self, round: BlockRound) -> PeerIdx {
+        self.cons_params.leader_idx(round)
+    }
+
+    /// Non-dummy block extending `prev` in `round`, with an empty payload
+    pub fn block(&self, round: BlockRound, prev: Option<BlockHeader>) -> BlockHeader {
+        BlockHeader::builder()
+            .maybe_prev(prev)
+            .timestamp(Timestamp::now())
+            .round(round)
+            .consensus_params(&self.cons_params)
+            .payload(&BlockPayloadRaw::empty())
+            .build()
+    }
+
+    fn node(&self, peer_idx: PeerIdx) -> WhateverResult<&HonestNode> {
+        let Some(node) = self.nodes.iter().find(|node| node.peer_idx == peer_idx) else {
+            whatever!("Peer {peer_idx} is not honest");
+        };
+        Ok(node)
+    }
+
+    /// Deliver a vote response of peer `from` to all the honest nodes
+    ///
+    /// Returns the outcome of processing it by each of them.
+    pub async fn deliver_vote(
+        &self,
+        from: PeerIdx,
+        resp: WaitVoteResponse,
+    ) -> Vec<Result<(), ProcessVoteError>> {
+        let mut results = vec![];
+        for node in &self.nodes {
+            results.push(
+                node.consensus
+                    .process_vote_response(from, resp.clone())
+                    .await,
+            );
+        }
+        results
+    }
+
+    /// Have the honest leader of `block`'s round propose it to everyone
+    pub async fn propose(&self, block: BlockHeader) -> WhateverResult<()> {
+        let leader = self.node(self.leader_idx(block.round))?;
+        let resp = WaitVoteResponse::Proposal {
+            block: Signed::new_sign(block, leader.seckey),
+            bls_sig: None,
+            payload: BlockPayloadRaw::empty(),
+        };
+        for res in self.deliver_vote(leader.peer_idx, resp).await {
+            res.whatever_context("Failed to process honest proposal")?;
+        }
+        Ok(())
+    }
+
+    /// Have honest `voters` vote for `block`, and everyone process their votes
+    pub async fn vote(&self, voters: &[PeerIdx], block: BlockHeader) -> WhateverResult<()> {
+        for voter in voters {
+            let voter = self.node(*voter)?;
+            let resp = WaitVoteResponse::Vote {
+                block: Signed::new_sign(block, voter.seckey),
+                bls_sig: None,
+            };
+            for res in self.deliver_vote(voter.peer_idx, resp).await {
+                res.whatever_context("Failed to process honest vote")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// All the honest peers, except the ones in `except`
+    pub fn honest_peers_except(&self, except: &[PeerIdx]) -> Vec<PeerIdx> {
+        self.nodes
+            .iter()
+            .map(|node| node.peer_idx)
+            .filter(|peer_idx| !except.contains(peer_idx))
+            .collect()
+    }
+
+    /// Deliver a finality vote of peer `from` to all the honest nodes
+    pub async fn deliver_finality_vote(
+        &self,
+        from: PeerPubkey,
+        resp: WaitFinalityVoteResponse,
+    ) -> WhateverResult<()> {
+        for node in &self.nodes {
+            if node.seckey.pubkey() == from {
+                continue;
+            }
+            node.consensus
+                .process_finality_vote_update_response(from, resp.clone())
+                .await
+                .whatever_context("Failed to process finality vote")?;
+        }
+        Ok(())
+    }
+
+    /// Have honest `voters` share their current finality votes with everyone
+    pub async fn share_finality_votes(&self, voters: &[PeerIdx]) -> WhateverResult<()> {
+        for voter in voters {
+            let voter = self.node(*voter)?;
+            let round = *voter.consensus.finality_self_vote_rx().borrow();
+            self.deliver_finality_vote(
+                voter.seckey.pubkey(),
+                WaitFinalityVoteResponse {
+                    update: Signed::new_sign(FinalityVoteUpdate::new(round), voter.seckey),
+                },
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn current_rounds(&self) -> Vec<BlockRound> {
+        let mut rounds = vec![];
+        for node in &self.nodes {
+            rounds.push(node.consensus.get_current_round().await);
+        }
+        rounds
+    }
+
+    pub fn finality_consensus(&self) -> Vec<BlockRound> {
+        self.nodes
+            .iter()
+            .map(|node| *node.consensus.finality_consensus_rx().borrow())
+            .collect()
+    }
+
+    pub async fn notarized_blocks_before(&self, round: BlockRound) -> Vec<Option<BlockHeader>> {
+        let mut blocks = vec![];
+        for node in &self.nodes {
+            blocks.push(node.consensus.get_prev_notarized_block(round).await);
+        }
+        blocks
+    }
+
+    pub async fn equivocations(&self) -> Vec<Vec<EquivocationEvidence>> {
+        let mut equivocations = vec![];
+        for node in &self.nodes {
+            equivocations.push(node.consensus.get_equivocations().await);
+        }
+        equivocations
+    }
+}
@@ -0,0 +1,81 @@
+use bfte_consensus::consensus::Consensus;
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::num_peers::NumPeers;
+use bfte_consensus_core::peer::{PeerPubkey, PeerSeckey};
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_consensus_core::ver::ConsensusVersion;
+use bfte_db::Database;
+use bfte_util_error::{BoxedErrorResult, WhateverResult};
+use snafu::ResultExt as _;
+
+/// A consensus instance following a freshly generated peer set
+pub struct Setup {
+    pub consensus: Consensus,
+    pub cons_params: ConsensusParams,
+    pub seckeys: Vec<PeerSeckey>,
+}
+
+impl Setup {
+    /// Bootstrap a consensus instance running as peer `0`
+    pub async fn bootstrap(
+        num_peers: NumPeers,
+        init_core_module_cons_version: ConsensusVersion,
+    ) -> WhateverResult<Self> {
+        let (seckeys, cons_params) = gen_peers(num_peers, init_core_module_cons_version);
+        let consensus = temp_consensus(&cons_params, Some(seckeys[0].pubkey()))
+            .await
+            .whatever_context("Failed to create temporary consensus")?;
+
+        Ok(Self {
+            consensus,
+            cons_params,
+            seckeys,
+        })
+    }
+
+    pub fn seckey(&self) -> PeerSeckey {
+        self.seckeys[0]
+    }
+}
+
+/// Generate keys of a new peer set, and its initial consensus params
+///
+/// The seckeys are sorted, so their positions match the
+/// [`PeerIdx`](bfte_consensus_core::peer::PeerIdx)s.
+pub fn gen_peers(
+    num_peers: NumPeers,
+    init_core_module_cons_version: ConsensusVersion,
+) -> (Vec<PeerSeckey>, ConsensusParams) {
+    let mut seckeys: Vec<_> = (0..num_peers.total())
+        .map(|_| PeerSeckey::generate())
+        .collect();
+
+    // PeerIdx's are assigned based on the pubkey, so sort the seckeys identifying
+    // the peers for our convenience.
+    seckeys.sort_unstable_by_key(|seckey1| seckey1.pubkey());
+
+    let cons_params = ConsensusParams {
+        prev_mid_block: None,
+        peers: seckeys.iter().map(|s| s.pubkey()).collect(),
+        consensus_params_format_version: ConsensusParams::FORMAT_VERSION,
+        init_core_module_cons_version,
+        timestamp: Timestamp::now(),
+        schedule_round: 0.into(),
+        apply_round: 0.into(),
+        bls_pubkeys: vec![],
+        peer_weights: vec![],
+        max_block_payload_len: ConsensusParams::DEFAULT_MAX_BLOCK_PAYLOAD_LEN,
+    };
+
+    (seckeys, cons_params)
+}
+
+pub async fn temp_consensus(
+    params: &ConsensusParams,
+    our_peer_pubkey: Option<PeerPubkey>,
+) -> BoxedErrorResult<Consensus> {
+    let db = Database::new_in_memory().await?;
+    let consensus = Consensus::init(params, db.into(), our_peer_pubkey, None).await?;
+
+    Ok(consensus)
+}
@@ -0,0 +1,313 @@
+use bfte_consensus::consensus::ProcessVoteError;
+use bfte_consensus_core::block::{BlockHeader, BlockRound};
+use bfte_consensus_core::num_peers::NumPeers;
+use bfte_consensus_core::peer::{PeerIdx, PeerPubkey};
+use bfte_consensus_core::signed::Signed;
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_consensus_tests::adversary::{AdversaryBehavior, AdversaryPeer};
+use bfte_consensus_tests::network::Network;
+use bfte_node::{AddressUpdate, Node};
+use bfte_node_core::address::{IrohAddress, PeerAddress};
+use bfte_util_error::WhateverResult;
+
+const NUM_PEERS: u8 = 4;
+
+/// Some non-leader peer of `round`
+fn non_leader(network: &Network, round: BlockRound) -> PeerIdx {
+    let leader_idx = network.leader_idx(round);
+    network
+        .cons_params
+        .num_peers()
+        .peer_idx_iter()
+        .find(|peer_idx| *peer_idx != leader_idx)
+        .expect("More than one peer")
+}
+
+/// A network with the leader of round `0` as an adversary
+async fn bootstrap_with_adversary_leader(
+    behavior: AdversaryBehavior,
+) -> WhateverResult<(Network, AdversaryPeer)> {
+    let mut network = Network::bootstrap(NumPeers::from(NUM_PEERS)).await?;
+    let leader_idx = network.leader_idx(BlockRound::ZERO);
+    let adversary = network.replace_with_adversary(leader_idx, behavior);
+    Ok((network, adversary))
+}
+
+/// A network with some non-leader peer of round `0` as an adversary
+async fn bootstrap_with_adversary_voter(
+    behavior: AdversaryBehavior,
+) -> WhateverResult<(Network, AdversaryPeer)> {
+    let mut network = Network::bootstrap(NumPeers::from(NUM_PEERS)).await?;
+    let adversary_idx = non_leader(&network, BlockRound::ZERO);
+    let adversary = network.replace_with_adversary(adversary_idx, behavior);
+    Ok((network, adversary))
+}
+
+fn assert_all_eq<T: PartialEq + std::fmt::Debug>(values: Vec<T>, expected: T) {
+    assert!(!values.is_empty());
+    for value in values {
+        assert_eq!(value, expected);
+    }
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn equivocating_leader_is_detected_by_all_honest_peers() -> WhateverResult<()> {
+    let (network, adversary) =
+        bootstrap_with_adversary_leader(AdversaryBehavior::EquivocateProposal).await?;
+    let round = BlockRound::ZERO;
+    let leader_idx = adversary.peer_idx();
+
+    let mut proposals = adversary.propose(round, None).into_iter();
+    let first = proposals.next().expect("At least one proposal");
+    for res in network.deliver_vote(leader_idx, first.clone()).await {
+        assert!(res.is_ok(), "{res:?}");
+    }
+
+    for proposal in proposals {
+        for res in network.deliver_vote(leader_idx, proposal).await {
+            assert!(
+                matches!(res, Err(ProcessVoteError::ForkedProposal { .. })),
+                "{res:?}"
+            );
+        }
+    }
+
+    for equivocations in network.equivocations().await {
+        assert_eq!(equivocations.len(), 1);
+        assert_eq!(equivocations[0].peer_pubkey, adversary.pubkey());
+        assert!(equivocations[0].verify().is_ok());
+    }
+
+    let honest = network.honest_peers_except(&[]);
+    network.vote(&honest[..2], first.block().inner).await?;
+
+    assert_all_eq(network.current_rounds().await, BlockRound::from(1));
+    assert_all_eq(
+        network.notarized_blocks_before(1.into()).await,
+        Some(first.block().inner),
+    );
+
+    network.share_finality_votes(&honest).await?;
+    assert_all_eq(network.finality_consensus(), BlockRound::from(1));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn double_vote_is_detected_by_all_honest_peers() -> WhateverResult<()> {
+    let (network, adversary) =
+        bootstrap_with_adversary_voter(AdversaryBehavior::DoubleVote).await?;
+    let round = BlockRound::ZERO;
+    let leader_idx = network.leader_idx(round);
+    let adversary_idx = adversary.peer_idx();
+    let proposal = network.block(round, None);
+
+    // Before the proposal is known, the second vote conflicts with the first one
+    let mut votes = adversary.vote(proposal, None).into_iter();
+    for res in network
+        .deliver_vote(adversary_idx, votes.next().expect("At least one vote"))
+        .await
+    {
+        assert!(res.is_ok(), "{res:?}");
+    }
+    for vote in votes {
+        for res in network.deliver_vote(adversary_idx, vote).await {
+            assert!(
+                matches!(res, Err(ProcessVoteError::ForkedSignature { .. })),
+                "{res:?}"
+            );
+        }
+    }
+
+    for equivocations in network.equivocations().await {
+        assert_eq!(equivocations.len(), 1);
+        assert_eq!(equivocations[0].peer_pubkey, adversary.pubkey());
+    }
+
+    network.propose(proposal).await?;
+
+    // Once the proposal is known, votes for anything else are rejected outright
+    for vote in adversary.vote(proposal, None).into_iter().skip(1) {
+        for res in network.deliver_vote(adversary_idx, vote).await {
+            assert!(
+                matches!(res, Err(ProcessVoteError::VoteForADifferentProposal { .. })),
+                "{res:?}"
+            );
+        }
+    }
+
+    assert_all_eq(network.current_rounds().await, BlockRound::ZERO);
+
+    let honest = network.honest_peers_except(&[leader_idx]);
+    network.vote(&honest[..1], proposal).await?;
+
+    assert_all_eq(network.current_rounds().await, BlockRound::from(1));
+    assert_all_eq(
+        network.notarized_blocks_before(1.into()).await,
+        Some(proposal),
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn double_vote_after_proposal_is_detected_by_all_honest_peers() -> WhateverResult<()> {
+    let (network, adversary) =
+        bootstrap_with_adversary_voter(AdversaryBehavior::DoubleVote).await?;
+    let round = BlockRound::ZERO;
+    let adversary_idx = adversary.peer_idx();
+    let proposal = network.block(round, None);
+
+    network.propose(proposal).await?;
+
+    let mut votes = adversary.vote(proposal, None).into_iter();
+    for res in network
+        .deliver_vote(adversary_idx, votes.next().expect("At least one vote"))
+        .await
+    {
+        assert!(res.is_ok(), "{res:?}");
+    }
+    for vote in votes {
+        for res in network.deliver_vote(adversary_idx, vote).await {
+            assert!(
+                matches!(
+                    res,
+                    Err(ProcessVoteError::VoteForADifferentProposal { evidence: Some(_) })
+                ),
+                "{res:?}"
+            );
+        }
+    }
+
+    for equivocations in network.equivocations().await {
+        assert_eq!(equivocations.len(), 1);
+        assert_eq!(equivocations[0].peer_pubkey, adversary.pubkey());
+        assert!(equivocations[0].verify().is_ok());
+    }
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn mismatched_payload_is_rejected_by_all_honest_peers() -> WhateverResult<()> {
+    let (network, adversary) =
+        bootstrap_with_adversary_leader(AdversaryBehavior::MismatchedPayload).await?;
+    let round = BlockRound::ZERO;
+    let leader_idx = adversary.peer_idx();
+
+    for proposal in adversary.propose(round, None) {
+        for res in network.deliver_vote(leader_idx, proposal).await {
+            assert!(
+                matches!(res, Err(ProcessVoteError::InvalidContent { .. })),
+                "{res:?}"
+            );
+        }
+    }
+
+    for node in &network.nodes {
+        assert_eq!(node.consensus.get_proposal(round).await, None);
+    }
+
+    // Honest peers time out on the leader and move on with a dummy block
+    let dummy = BlockHeader::new_dummy(round, &network.cons_params);
+    network
+        .vote(&network.honest_peers_except(&[]), dummy)
+        .await?;
+
+    assert_all_eq(network.current_rounds().await, BlockRound::from(1));
+    assert_all_eq(network.notarized_blocks_before(1.into()).await, None);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn withheld_finality_votes_do_not_stall_finality() -> WhateverResult<()> {
+    let (network, adversary) =
+        bootstrap_with_adversary_voter(AdversaryBehavior::WithholdFinalityVote).await?;
+    let round = BlockRound::ZERO;
+    let leader_idx = network.leader_idx(round);
+    let proposal = network.block(round, None);
+
+    network.propose(proposal).await?;
+    network
+        .vote(&network.honest_peers_except(&[leader_idx]), proposal)
+        .await?;
+    assert_all_eq(network.current_rounds().await, BlockRound::from(1));
+
+    if let Some(vote) = adversary.finality_vote(1.into()) {
+        network
+            .deliver_finality_vote(adversary.pubkey(), vote)
+            .await?;
+    }
+
+    // Without the adversary's vote, a single honest vote on top of the own one
+    // is not enough
+    let honest = network.honest_peers_except(&[]);
+    network.share_finality_votes(&honest[..1]).await?;
+    assert_all_eq(network.finality_consensus(), BlockRound::ZERO);
+
+    // All the honest peers together finalize the round without the adversary
+    network.share_finality_votes(&honest).await?;
+    assert_all_eq(network.finality_consensus(), BlockRound::from(1));
+
+    Ok(())
+}
+
+fn address_update(peer_pubkey: PeerPubkey) -> AddressUpdate {
+    AddressUpdate {
+        timestamp: Timestamp::now(),
+        peer_pubkey,
+        addr: PeerAddress::Iroh(IrohAddress::default()),
+    }
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn invalid_address_updates_are_rejected_by_all_honest_peers() -> WhateverResult<()> {
+    let (network, adversary) =
+        bootstrap_with_adversary_voter(AdversaryBehavior::InvalidAddressUpdate).await?;
+    let round = BlockRound::ZERO;
+    let leader_idx = network.leader_idx(round);
+    let victim = &network.nodes[0];
+    let victim_update = Signed::new_sign(address_update(victim.seckey.pubkey()), victim.seckey);
+
+    for node in &network.nodes {
+        Node::handle_address_update(&node.db, victim_update.clone()).await?;
+    }
+
+    let updates = adversary.address_updates(
+        address_update(adversary.pubkey()),
+        address_update(victim.seckey.pubkey()),
+    );
+    assert!(!updates.is_empty());
+    for update in updates {
+        for node in &network.nodes {
+            assert!(
+                Node::handle_address_update(&node.db, update.clone())
+                    .await
+                    .is_err()
+            );
+        }
+    }
+
+    for node in &network.nodes {
+        let stored = Node::get_peer_addr(&node.db, victim.seckey.pubkey())
+            .await?
+            .expect("Honest update must be stored");
+        assert_eq!(stored.sig, victim_update.sig);
+    }
+
+    // Honest peers keep finalizing
+    let proposal = network.block(round, None);
+    network.propose(proposal).await?;
+    network
+        .vote(&network.honest_peers_except(&[leader_idx]), proposal)
+        .await?;
+    assert_all_eq(network.current_rounds().await, BlockRound::from(1));
+
+    network
+        .share_finality_votes(&network.honest_peers_except(&[]))
+        .await?;
+    assert_all_eq(network.finality_consensus(), BlockRound::from(1));
+
+    Ok(())
+}
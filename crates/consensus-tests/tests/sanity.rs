@@ -1,72 +1,16 @@
-use bfte_consensus::consensus::Consensus;
 use bfte_consensus_core::block::{BlockHeader, BlockPayloadRaw, BlockRound};
-use bfte_consensus_core::consensus_params::ConsensusParams;
 use bfte_consensus_core::msg::{
     FinalityVoteUpdate, WaitFinalityVoteResponse, WaitNotarizedBlockResponse, WaitVoteResponse,
 };
 use bfte_consensus_core::num_peers::NumPeers;
-use bfte_consensus_core::peer::{PeerIdx, PeerPubkey, PeerSeckey};
+use bfte_consensus_core::peer::PeerIdx;
 use bfte_consensus_core::signed::{Notarized, Signable as _, Signed};
 use bfte_consensus_core::timestamp::Timestamp;
 use bfte_consensus_core::ver::ConsensusVersion;
-use bfte_db::Database;
+use bfte_consensus_tests::setup::Setup;
 use bfte_util_error::{BoxedErrorResult, WhateverResult};
 use snafu::ResultExt as _;
 
-struct Setup {
-    pub consensus: Consensus,
-    pub cons_params: ConsensusParams,
-    pub seckeys: Vec<PeerSeckey>,
-}
-
-impl Setup {
-    async fn bootstrap(
-        num_peers: NumPeers,
-        init_core_module_cons_version: ConsensusVersion,
-    ) -> WhateverResult<Self> {
-        let mut seckeys: Vec<_> = (0..num_peers.total())
-            .map(|_| PeerSeckey::generate())
-            .collect();
-
-        // PeerIdx's are assigned based on the pubkey, so sort the seckeys identifying
-        // the peers for our convenience.
-        seckeys.sort_unstable_by_key(|seckey1| seckey1.pubkey());
-
-        let cons_params = ConsensusParams {
-            prev_mid_block: None,
-            peers: seckeys.iter().map(|s| s.pubkey()).collect(),
-            consensus_params_format_version: ConsensusParams::FORMAT_VERSION,
-            init_core_module_cons_version,
-            timestamp: Timestamp::now(),
-            schedule_round: 0.into(),
-            apply_round: 0.into(),
-        };
-        let consensus = temp_consensus(&cons_params, Some(seckeys[0].pubkey()))
-            .await
-            .whatever_context("Failed to create temporary consensus")?;
-
-        Ok(Self {
-            consensus,
-            cons_params,
-            seckeys,
-        })
-    }
-
-    fn seckey(&self) -> PeerSeckey {
-        self.seckeys[0]
-    }
-}
-
-pub(crate) async fn temp_consensus(
-    params: &ConsensusParams,
-    our_peer_pubkey: Option<PeerPubkey>,
-) -> BoxedErrorResult<Consensus> {
-    let db = Database::new_in_memory().await?;
-    let consensus = Consensus::init(params, db.into(), our_peer_pubkey, None).await?;
-
-    Ok(consensus)
-}
-
 #[test_log::test(tokio::test(flavor = "multi_thread"))]
 async fn starting_consensus_and_generating_first_block_via_vote() -> BoxedErrorResult<()> {
     let setup = Setup::bootstrap(1.into(), ConsensusVersion::new(0, 0)).await?;
@@ -90,6 +34,7 @@ async fn starting_consensus_and_generating_first_block_via_vote() -> BoxedErrorR
             PeerIdx::from(0),
             WaitVoteResponse::Proposal {
                 block: Signed::new_sign(block, setup.seckey()),
+                bls_sig: None,
                 payload,
             },
         )
@@ -170,6 +115,7 @@ async fn two_peers_first_round() -> WhateverResult<()> {
             PeerIdx::from(1),
             WaitVoteResponse::Proposal {
                 block: Signed::new_sign(block, setup.seckeys[1]),
+                bls_sig: None,
                 payload,
             },
         )
@@ -192,6 +138,7 @@ async fn two_peers_first_round() -> WhateverResult<()> {
             PeerIdx::from(0),
             WaitVoteResponse::Vote {
                 block: Signed::new_sign(block, setup.seckeys[0]),
+                bls_sig: None,
             },
         )
         .await
@@ -5,6 +5,8 @@ mod handle_finality_vote;
 mod handle_notarized_block;
 mod handle_vote;
 mod init;
+mod prune;
+mod record_equivocation;
 mod version;
 
 use std::sync::Arc;
@@ -14,7 +16,13 @@ use bfte_consensus_core::peer::PeerPubkey;
 use bfte_db::Database;
 use tokio::sync::watch;
 
+pub use self::getters::GetNotarizedBlockError;
+pub use self::handle_finality_vote::ProcessFirstUnnotarizedUpdateError;
+pub use self::handle_notarized_block::ProcessNotarizedBlockError;
+pub use self::handle_vote::ProcessVoteError;
 pub use self::init::*;
+pub use self::prune::{DEFAULT_PRUNING_RETENTION, PruningConfig, StorageMode};
+pub use self::record_equivocation::RecordEquivocationError;
 
 const LOG_TARGET: &str = "bfte::consensus";
 
@@ -46,4 +54,8 @@ pub struct Consensus {
     /// Notifications every new proposal
     new_proposal_tx: watch::Sender<()>,
     new_proposal_rx: watch::Receiver<()>,
+
+    /// Notifications every new equivocation evidence recorded
+    new_equivocation_tx: watch::Sender<()>,
+    new_equivocation_rx: watch::Receiver<()>,
 }
@@ -2,9 +2,11 @@ use bfte_consensus_core::Signature;
 use bfte_consensus_core::block::{
     BlockHash, BlockHeader, BlockPayloadHash, BlockPayloadRaw, BlockRound,
 };
+use bfte_consensus_core::bls::{BlsAggregateSignature, BlsSignature};
 use bfte_consensus_core::consensus_params::{ConsensusParams, ConsensusParamsHash};
+use bfte_consensus_core::equivocation::EquivocationEvidence;
 use bfte_consensus_core::peer::{PeerIdx, PeerPubkey};
-use bfte_consensus_core::signed::Signed;
+use bfte_consensus_core::signed::{Notarized, Signed};
 use bfte_consensus_core::vote::SignedVote;
 use bfte_db::ctx::WriteTransactionCtx;
 use bfte_db::error::DbResult;
@@ -31,24 +33,44 @@ pub(crate) trait ConsensusReadDbOps {
         round: BlockRound,
         peer_idx: PeerIdx,
     ) -> DbResult<Option<Signed<BlockHeader>>>;
+    fn get_vote_block_bls(
+        &self,
+        round: BlockRound,
+        peer_idx: PeerIdx,
+    ) -> DbResult<Option<BlsSignature>>;
 
     fn get_block_payload(
         &self,
         payload_hash: BlockPayloadHash,
     ) -> DbResult<Option<BlockPayloadRaw>>;
     fn get_finality_vote(&self, peer_pubkey: PeerPubkey) -> DbResult<Option<BlockRound>>;
+    fn get_finality_vote_sigs(&self, round: BlockRound) -> DbResult<Vec<(PeerPubkey, Signature)>>;
     fn get_peers_with_proposal_votes(&self, round: BlockRound) -> DbResult<VoteSet>;
     fn get_peers_with_dummy_votes(&self, round: BlockRound) -> DbResult<VoteSet>;
     fn get_proposal(&self, round: BlockRound) -> DbResult<Option<BlockHeader>>;
     fn has_notarized_non_dummy_block(&self, round: BlockRound) -> DbResult<bool>;
-    fn get_num_votes_dummy(&self, round: BlockRound) -> DbResult<usize>;
-    fn get_num_votes_proposal(&self, round: BlockRound) -> DbResult<usize>;
     fn get_votes_dummy(&self, round: BlockRound) -> DbResult<Vec<(PeerIdx, Signature)>>;
     fn get_votes_proposal(&self, round: BlockRound) -> DbResult<Vec<(PeerIdx, Signature)>>;
+    /// BLS signatures of (non-stale) proposal votes
+    fn get_votes_proposal_bls(&self, round: BlockRound) -> DbResult<Vec<(PeerIdx, BlsSignature)>>;
+    fn get_notarized_block_aggregate(
+        &self,
+        round: BlockRound,
+    ) -> DbResult<Option<BlsAggregateSignature>>;
+    /// Signatures proving notarization of a notarized `block`
+    ///
+    /// Uses the aggregate signature, if available.
+    fn get_notarization(&self, block: BlockHeader) -> DbResult<Notarized<BlockHeader>> {
+        if let Some(aggregate) = self.get_notarized_block_aggregate(block.round)? {
+            return Ok(Notarized::new_aggregate(block, aggregate));
+        }
+        Ok(Notarized::new(block, self.get_votes_proposal(block.round)?))
+    }
     fn get_prev_notarized_block(&self, round: BlockRound) -> DbResult<Option<BlockHeader>>;
     fn get_next_notarized_block(&self, round: BlockRound) -> DbResult<Option<BlockHeader>>;
     fn get_notarized_block(&self, round: BlockRound) -> DbResult<Option<BlockHeader>>;
     fn get_pinned_block(&self, round: BlockRound) -> DbResult<Option<BlockHash>>;
+    fn get_equivocations(&self) -> DbResult<Vec<EquivocationEvidence>>;
 }
 
 pub(crate) trait ConsensusWriteDbOps {
@@ -75,6 +97,12 @@ pub(crate) trait ConsensusWriteDbOps {
         payload: Option<&BlockPayloadRaw>,
     ) -> DbResult<InsertOutcome<BlockHeader>>;
 
+    fn insert_notarized_block_aggregate(
+        &self,
+        round: BlockRound,
+        aggregate: &BlsAggregateSignature,
+    ) -> DbResult<()>;
+
     fn insert_pinned_block(
         &self,
         round: BlockRound,
@@ -87,6 +115,13 @@ pub(crate) trait ConsensusWriteDbOps {
         round: BlockRound,
     ) -> DbResult<Option<BlockRound>>;
 
+    fn insert_finality_vote_sig(
+        &self,
+        round: BlockRound,
+        peer_pubkey: PeerPubkey,
+        sig: Signature,
+    ) -> DbResult<()>;
+
     fn update_finality_consensus(&self, round: BlockRound) -> DbResult<Option<BlockRound>>;
     fn prune_dummy_votes(&self, round: BlockRound) -> DbResult<()>;
 
@@ -105,7 +140,18 @@ pub(crate) trait ConsensusWriteDbOps {
         peerd_idx: PeerIdx,
         vote: SignedVote,
     ) -> DbResult<InsertOutcome<SignedVote>>;
+    fn insert_block_vote_bls(
+        &self,
+        round: BlockRound,
+        peerd_idx: PeerIdx,
+        sig: BlsSignature,
+    ) -> DbResult<()>;
     fn set_current_round(&self, round: BlockRound) -> DbResult<()>;
+
+    fn insert_equivocation(
+        &self,
+        evidence: &EquivocationEvidence,
+    ) -> DbResult<InsertOutcome<EquivocationEvidence>>;
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -157,6 +203,15 @@ macro_rules! impl_consensus_read_db_ops {
 
                 Ok(tbl.get(&(round, peer_idx))?.map(|g| g.value()))
             }
+            fn get_vote_block_bls(
+                &self,
+                round: BlockRound,
+                peer_idx: PeerIdx,
+            ) -> DbResult<Option<BlsSignature>> {
+                let tbl = self.open_table(&tables::cons_votes_block_bls::TABLE)?;
+
+                Ok(tbl.get(&(round, peer_idx))?.map(|g| g.value()))
+            }
 
             fn get_peers_with_proposal_votes(&self, round: BlockRound) -> DbResult<VoteSet> {
                 let mut vote_set = VoteSet::ZERO;
@@ -227,6 +282,20 @@ macro_rules! impl_consensus_read_db_ops {
                 Ok(tbl.get(&peer_pubkey)?.map(|g| g.value()))
             }
 
+            fn get_finality_vote_sigs(
+                &self,
+                round: BlockRound,
+            ) -> DbResult<Vec<(PeerPubkey, Signature)>> {
+                let tbl = self.open_table(&tables::cons_finality_vote_sigs::TABLE)?;
+
+                tbl.range(&(round, PeerPubkey::ZERO)..=&(round, PeerPubkey::MAX))?
+                    .map(|kv| {
+                        let (k, v) = kv?;
+                        Ok((k.value().1, v.value()))
+                    })
+                    .collect()
+            }
+
             fn get_block_payload(
                 &self,
                 payload_hash: BlockPayloadHash,
@@ -288,22 +357,6 @@ macro_rules! impl_consensus_read_db_ops {
                     .map(|(_k, v)| v.value()))
             }
 
-            fn get_num_votes_dummy(&self, round: BlockRound) -> DbResult<usize> {
-                let tbl = self.open_table(&tables::cons_votes_dummy::TABLE)?;
-
-                Ok(tbl
-                    .range(&(round, PeerIdx::MIN)..=&(round, PeerIdx::MAX))?
-                    .count())
-            }
-
-            fn get_num_votes_proposal(&self, round: BlockRound) -> DbResult<usize> {
-                let tbl = self.open_table(&tables::cons_votes_block::TABLE)?;
-
-                Ok(tbl
-                    .range(&(round, PeerIdx::MIN)..=&(round, PeerIdx::MAX))?
-                    .count())
-            }
-
             fn get_votes_dummy(&self, round: BlockRound) -> DbResult<Vec<(PeerIdx, Signature)>> {
                 let mut sigs = vec![];
                 let tbl = self.open_table(&tables::cons_votes_dummy::TABLE)?;
@@ -329,6 +382,45 @@ macro_rules! impl_consensus_read_db_ops {
 
                 Ok(sigs)
             }
+
+            fn get_votes_proposal_bls(
+                &self,
+                round: BlockRound,
+            ) -> DbResult<Vec<(PeerIdx, BlsSignature)>> {
+                let mut sigs = vec![];
+                let tbl_votes = self.open_table(&tables::cons_votes_block::TABLE)?;
+                let tbl = self.open_table(&tables::cons_votes_block_bls::TABLE)?;
+
+                for kv in tbl.range(&(round, PeerIdx::MIN)..=&(round, PeerIdx::MAX))? {
+                    let (k, v) = kv?;
+
+                    if tbl_votes.get(&k.value())?.is_some() {
+                        sigs.push((k.value().1, v.value()));
+                    }
+                }
+
+                Ok(sigs)
+            }
+
+            fn get_notarized_block_aggregate(
+                &self,
+                round: BlockRound,
+            ) -> DbResult<Option<BlsAggregateSignature>> {
+                let tbl = self.open_table(&tables::cons_blocks_notarized_aggregate::TABLE)?;
+
+                Ok(tbl.get(&round)?.map(|g| g.value()))
+            }
+
+            fn get_equivocations(&self) -> DbResult<Vec<EquivocationEvidence>> {
+                let tbl = self.open_table(&tables::cons_equivocations::TABLE)?;
+
+                tbl.range(..)?
+                    .map(|kv| {
+                        let (_, v) = kv?;
+                        Ok(v.value())
+                    })
+                    .collect()
+            }
         }
     };
 }
@@ -420,6 +512,18 @@ impl ConsensusWriteDbOps for WriteTransactionCtx {
         Ok(prev)
     }
 
+    fn insert_finality_vote_sig(
+        &self,
+        round: BlockRound,
+        peer_pubkey: PeerPubkey,
+        sig: Signature,
+    ) -> DbResult<()> {
+        let mut tbl = self.open_table(&tables::cons_finality_vote_sigs::TABLE)?;
+        tbl.insert(&(round, peer_pubkey), &sig)?;
+
+        Ok(())
+    }
+
     fn insert_notarized_block(
         &self,
         round: BlockRound,
@@ -459,6 +563,16 @@ impl ConsensusWriteDbOps for WriteTransactionCtx {
         Ok(InsertOutcome::Inserted)
     }
 
+    fn insert_notarized_block_aggregate(
+        &self,
+        round: BlockRound,
+        aggregate: &BlsAggregateSignature,
+    ) -> DbResult<()> {
+        let mut tbl = self.open_table(&tables::cons_blocks_notarized_aggregate::TABLE)?;
+        tbl.insert(&round, aggregate)?;
+        Ok(())
+    }
+
     fn insert_pinned_block(
         &self,
         round: BlockRound,
@@ -476,6 +590,8 @@ impl ConsensusWriteDbOps for WriteTransactionCtx {
         let mut tbl_blocks = self.open_table(&tables::cons_blocks_notarized::TABLE)?;
 
         tbl_blocks.remove(&round)?;
+        self.open_table(&tables::cons_blocks_notarized_aggregate::TABLE)?
+            .remove(&round)?;
         // Note: we never remove payloads, they can just hang around, it's fine.
 
         Ok(())
@@ -509,9 +625,34 @@ impl ConsensusWriteDbOps for WriteTransactionCtx {
         Ok(InsertOutcome::Inserted)
     }
 
+    fn insert_block_vote_bls(
+        &self,
+        round: BlockRound,
+        peerd_idx: PeerIdx,
+        sig: BlsSignature,
+    ) -> DbResult<()> {
+        let mut tbl = self.open_table(&tables::cons_votes_block_bls::TABLE)?;
+        tbl.insert(&(round, peerd_idx), &sig)?;
+        Ok(())
+    }
+
     fn set_current_round(&self, round: BlockRound) -> DbResult<()> {
         let mut tbl = self.open_table(&tables::cons_current_round::TABLE)?;
         tbl.insert(&(), &round)?;
         Ok(())
     }
+
+    fn insert_equivocation(
+        &self,
+        evidence: &EquivocationEvidence,
+    ) -> DbResult<InsertOutcome<EquivocationEvidence>> {
+        let mut tbl = self.open_table(&tables::cons_equivocations::TABLE)?;
+        let key = (evidence.peer_pubkey, evidence.round());
+
+        if let Some(existing) = tbl.get(&key)?.map(|v| v.value()) {
+            return Ok(InsertOutcome::AlreadyPresent(existing));
+        }
+        tbl.insert(&key, evidence)?;
+        Ok(InsertOutcome::Inserted)
+    }
 }
@@ -1,6 +1,8 @@
 use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::bls::BlsAggregateSignature;
+use bfte_consensus_core::consensus_params::ConsensusParams;
 use bfte_db::ctx::WriteTransactionCtx;
-use bfte_db::error::{DbTxResult, TxSnafu};
+use bfte_db::error::{DbResult, DbTxResult, TxSnafu};
 use snafu::{ResultExt as _, Snafu};
 use tracing::{debug, info, warn};
 
@@ -50,7 +52,6 @@ impl Consensus {
 
             let mut needs_a_timeout = false;
             let consensus_param = ctx.get_consensus_params(cur_round)?;
-            let threshold = consensus_param.num_peers().threshold();
 
             // If we're here, we have no notarized vote higher or equal
             // so we check if the current proposal reached the treashold
@@ -58,16 +59,19 @@ impl Consensus {
             if let Some(proposal) = ctx.get_proposal(cur_round)? {
                 // If we have a block we always want to have a timeout on
                 needs_a_timeout |= true;
-                let num_votes_proposal = ctx.get_num_votes_proposal(cur_round)?;
-                if threshold <= num_votes_proposal {
+                let votes_proposal = ctx.get_peers_with_proposal_votes(cur_round)?;
+                if consensus_param.is_threshold(votes_proposal.iter()) {
                     info!(
                         target: LOG_TARGET,
                         round = %cur_round,
-                        %num_votes_proposal,
+                        num_votes_proposal = %votes_proposal.len(),
                         "Round produced a block"
                     );
                     new_last_notarized_round = Some(cur_round);
                     ctx.insert_notarized_block(cur_round, proposal, None)?;
+                    if consensus_param.uses_bls() {
+                        Self::aggregate_notarized_block(ctx, cur_round, &consensus_param)?;
+                    }
                     cur_round = cur_round.next_expect();
                     continue;
                 }
@@ -76,19 +80,19 @@ impl Consensus {
             // We don't have any notarized blocks, the proposal
             // didn't reach a `threshold` so maybe dummy votes did.
 
-            let num_votes_dummy = ctx.get_num_votes_dummy(cur_round)?;
-            if threshold <= num_votes_dummy {
+            let votes_dummy = ctx.get_peers_with_dummy_votes(cur_round)?;
+            if consensus_param.is_threshold(votes_dummy.iter()) {
                 warn!(
                     target: LOG_TARGET,
                     round = %cur_round,
-                    %num_votes_dummy,
+                    num_votes_dummy = %votes_dummy.len(),
                     "Round produced a dummy"
                 );
                 cur_round = cur_round.next_expect();
                 continue;
             }
 
-            if consensus_param.num_peers().max_faulty() < num_votes_dummy {
+            if consensus_param.exceeds_max_faulty(votes_dummy.iter()) {
                 // Seems that enough peers wants the round to go on
                 // so we should have a timeout on too
                 needs_a_timeout |= true;
@@ -160,4 +164,25 @@ impl Consensus {
 
         Ok(())
     }
+
+    /// Store BLS signatures of votes of a just notarized block as an aggregate
+    fn aggregate_notarized_block(
+        ctx: &WriteTransactionCtx,
+        round: BlockRound,
+        consensus_params: &ConsensusParams,
+    ) -> DbResult<()> {
+        let sigs = ctx.get_votes_proposal_bls(round)?;
+
+        // Might happen for votes collected before BLS was enabled
+        if !consensus_params.is_threshold(sigs.iter().map(|(peer_idx, _)| *peer_idx)) {
+            return Ok(());
+        }
+
+        let Some(aggregate) = BlsAggregateSignature::aggregate(sigs) else {
+            return Ok(());
+        };
+        ctx.insert_notarized_block_aggregate(round, &aggregate)?;
+
+        Ok(())
+    }
 }
@@ -1,16 +1,21 @@
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 use bfte_consensus_core::block::{BlockHeader, BlockPayloadHash, BlockPayloadRaw, BlockRound};
+use bfte_consensus_core::bls::BlsPubkey;
 use bfte_consensus_core::consensus_params::ConsensusParams;
 use bfte_consensus_core::msg::{
     WaitNotarizedBlockRequest, WaitNotarizedBlockResponse, WaitVoteResponse,
 };
 use bfte_consensus_core::peer::{PeerIdx, PeerPubkey};
 use bfte_consensus_core::peer_set::PeerSet;
+use bfte_consensus_core::proof::{CItemProof, ConsensusParamsTransition, FinalityCertificate};
 use bfte_consensus_core::signed::{Notarized, Signed};
 use bfte_consensus_core::timestamp::Timestamp;
+use bfte_consensus_core::weight::PeerWeight;
 use bfte_db::ctx::WriteTransactionCtx;
 use bfte_db::error::DbResult;
+use snafu::Snafu;
 use tokio::sync::watch;
 use tracing::{info, warn};
 
@@ -18,9 +23,24 @@ use super::Consensus;
 use super::ctx::ConsensusReadDbOps as _;
 use crate::consensus::LOG_TARGET;
 use crate::consensus::ctx::ConsensusWriteDbOps as _;
-use crate::tables::{cons_blocks_notarized, cons_blocks_payloads};
+use crate::tables::{
+    cons_blocks_notarized, cons_blocks_payloads, cons_params, cons_params_schedule,
+};
 use crate::vote_set::VoteSet;
 
+/// How many blocks after the proven one to look through for finality votes
+///
+/// Finality votes of peers are recorded only as they are exchanged, so some
+/// rounds never get a threshold of them, but one of the next few should.
+const FINALITY_CERTIFICATE_MAX_DESCENDANTS: usize = 16;
+
+#[derive(Debug, Snafu)]
+pub enum GetNotarizedBlockError {
+    /// The requester needs to sync the block from an archival peer
+    #[snafu(display("Payload of the notarized block in round {round} was pruned"))]
+    PayloadPruned { round: BlockRound },
+}
+
 impl Consensus {
     pub fn current_round_with_timeout_rx(&self) -> watch::Receiver<(BlockRound, bool)> {
         self.current_round_with_timeout_rx.clone()
@@ -41,6 +61,10 @@ impl Consensus {
         self.new_proposal_rx.clone()
     }
 
+    pub fn new_equivocation_rx(&self) -> watch::Receiver<()> {
+        self.new_equivocation_rx.clone()
+    }
+
     pub async fn get_current_round(&self) -> BlockRound {
         self.db
             .read_with_expect(|ctx| ctx.get_current_round())
@@ -103,10 +127,12 @@ impl Consensus {
     }
 
     /// This is very much tied to semantics of [`WaitNotarizedBlockRequest`]
+    ///
+    /// Returns `Ok(None)` if there's nothing to respond with yet.
     pub async fn get_notarized_block_resp(
         &self,
         req: WaitNotarizedBlockRequest,
-    ) -> Option<WaitNotarizedBlockResponse> {
+    ) -> Result<Option<WaitNotarizedBlockResponse>, GetNotarizedBlockError> {
         self.db
             .read_with_expect(|ctx| {
                 let tbl_notarized_blocks = ctx.open_table(&cons_blocks_notarized::TABLE)?;
@@ -118,38 +144,38 @@ impl Consensus {
                     .map(|(_k, v)| v.value())
                 {
                     let tbl_payloads = ctx.open_table(&cons_blocks_payloads::TABLE)?;
-                    let payload = tbl_payloads
-                        .get(&block.payload_hash)?
-                        .map(|g| g.value())
-                        .expect("Must have payload for every notarized block");
-
-                    let votes_block = ctx.get_votes_proposal(block.round)?;
+                    let Some(payload) = tbl_payloads.get(&block.payload_hash)?.map(|g| g.value())
+                    else {
+                        return Ok(PayloadPrunedSnafu { round: block.round }.fail());
+                    };
 
-                    let block = Notarized::new(block, votes_block);
+                    let block = ctx.get_notarization(block)?;
                     debug_assert_eq!(
                         block.verify_sigs(&ctx.get_consensus_params(block.round)?),
                         Ok(())
                     );
-                    return Ok(Some(WaitNotarizedBlockResponse { block, payload }));
+                    return Ok(Ok(Some(WaitNotarizedBlockResponse { block, payload })));
                 }
 
                 let consensus_params = ctx.get_consensus_params(req.cur_round)?;
                 let votes_dummy = ctx.get_votes_dummy(req.cur_round)?;
 
-                if consensus_params.num_peers().threshold() <= votes_dummy.len() {
+                if consensus_params
+                    .is_threshold(votes_dummy.iter().map(|(peer_idx, _)| *peer_idx))
+                {
                     let block = BlockHeader::new_dummy(req.cur_round, &consensus_params);
 
                     let block = Notarized::new(block, votes_dummy);
 
                     debug_assert_eq!(block.verify_sigs(&consensus_params), Ok(()));
 
-                    return Ok(Some(WaitNotarizedBlockResponse {
+                    return Ok(Ok(Some(WaitNotarizedBlockResponse {
                         block,
                         payload: BlockPayloadRaw::empty(),
-                    }));
+                    })));
                 }
 
-                Ok(None)
+                Ok(Ok(None))
             })
             .await
     }
@@ -214,10 +240,12 @@ impl Consensus {
                             BlockHeader::new_dummy(round, round_consensus_params),
                             sig,
                         ),
+                        bls_sig: None,
                     }));
                 }
                 if let Some(block) = ctx.get_vote_block(round, peer_idx)? {
-                    if peer_idx == round.leader_idx(round_consensus_params.num_peers()) {
+                    let bls_sig = ctx.get_vote_block_bls(round, peer_idx)?;
+                    if peer_idx == round_consensus_params.leader_idx(round) {
                         let Some(payload) = ctx.get_block_payload(block.inner.payload_hash)? else {
                             warn!(
                                 target: LOG_TARGET,
@@ -227,9 +255,13 @@ impl Consensus {
                             );
                             return Ok(None);
                         };
-                        return Ok(Some(WaitVoteResponse::Proposal { block, payload }));
+                        return Ok(Some(WaitVoteResponse::Proposal {
+                            block,
+                            bls_sig,
+                            payload,
+                        }));
                     } else {
-                        return Ok(Some(WaitVoteResponse::Vote { block }));
+                        return Ok(Some(WaitVoteResponse::Vote { block, bls_sig }));
                     }
                 }
                 Ok(None)
@@ -255,8 +287,7 @@ impl Consensus {
                     return Ok(None);
                 };
 
-                let votes_block = ctx.get_votes_proposal(block.round)?;
-                let block = Notarized::new(block, votes_block);
+                let block = ctx.get_notarization(block)?;
                 Ok(Some(block))
             })
             .await?;
@@ -264,12 +295,196 @@ impl Consensus {
         Some(block)
     }
 
+    /// Finalized (non-dummy) blocks with their payloads in
+    /// `round_start..round_end`, at most `limit` of them
+    ///
+    /// Returns the blocks, along with the round up to which (exclusive)
+    /// they cover the requested range, which can be lower than `round_end`
+    /// if the `limit` was hit, the range is not finalized yet, or payloads
+    /// were pruned.
+    pub async fn get_finalized_blocks_range(
+        &self,
+        round_start: BlockRound,
+        round_end: BlockRound,
+        limit: usize,
+    ) -> (Vec<(Notarized<BlockHeader>, BlockPayloadRaw)>, BlockRound) {
+        let round_end = round_end.min(*self.finality_consensus_tx.borrow());
+        if round_end <= round_start {
+            return (vec![], round_start);
+        }
+
+        self.db
+            .read_with_expect(|ctx| {
+                // If we're lagging ourselves, we can only vouch for rounds we went through
+                let round_end = round_end.min(ctx.get_current_round()?);
+                if round_end <= round_start {
+                    return Ok((vec![], round_start));
+                }
+
+                let tbl_notarized_blocks = ctx.open_table(&cons_blocks_notarized::TABLE)?;
+                let tbl_payloads = ctx.open_table(&cons_blocks_payloads::TABLE)?;
+
+                let mut blocks = vec![];
+                for kv in tbl_notarized_blocks.range(round_start..round_end)? {
+                    let (_k, v) = kv?;
+                    let block = v.value();
+                    if limit <= blocks.len() {
+                        // Everything before the first block we're not returning is covered
+                        return Ok((blocks, block.round));
+                    }
+                    let Some(payload) = tbl_payloads.get(&block.payload_hash)?.map(|g| g.value())
+                    else {
+                        // Pruned, everything before it is covered
+                        return Ok((blocks, block.round));
+                    };
+
+                    blocks.push((ctx.get_notarization(block)?, payload));
+                }
+
+                Ok((blocks, round_end))
+            })
+            .await
+    }
+
+    /// All scheduled [`ConsensusParams`], ordered by the round they apply at
+    pub async fn get_consensus_params_schedule(&self) -> Vec<ConsensusParams> {
+        self.db
+            .read_with_expect(|ctx| {
+                let tbl_schedule = ctx.open_table(&cons_params_schedule::TABLE)?;
+                let tbl_params = ctx.open_table(&cons_params::TABLE)?;
+
+                tbl_schedule
+                    .range(..)?
+                    .map(|kv| {
+                        let (_k, v) = kv?;
+                        Ok(tbl_params
+                            .get(&v.value())?
+                            .map(|g| g.value())
+                            .expect("Must always have params for a given hash"))
+                    })
+                    .collect()
+            })
+            .await
+    }
+
+    /// Build a [`CItemProof`] of `citem_idx`-th citem in a finalized block at
+    /// `round`, verifiable starting from params used at `trusted_round`
+    ///
+    /// Returns `None` if there's no such citem, or the block does not commit
+    /// to individual citems.
+    pub async fn get_citem_proof(
+        &self,
+        trusted_round: BlockRound,
+        round: BlockRound,
+        citem_idx: u32,
+    ) -> Option<CItemProof> {
+        let (blocks, _) = self
+            .get_finalized_blocks_range(round, round.next()?, 1)
+            .await;
+        let (block, payload) = blocks.into_iter().next()?;
+        let citems = payload.decode_citems().ok()?;
+
+        let finality = self.get_finality_certificate(block.inner).await?;
+        let tip = finality.descendants.last().map_or(block.inner, |b| b.inner);
+        let round_end = tip.round.next()?;
+
+        let schedule = self.get_consensus_params_schedule().await;
+        let mut params = self.get_consensus_params(trusted_round).await;
+        let mut params_transitions = vec![];
+
+        // Every transition is proven with the first finalized block after new
+        // params were applied, until we reach the params of the last block of
+        // the finality certificate, which are the newest needed
+        while tip.consensus_params_hash != params.hash() {
+            let next_apply_round = schedule
+                .iter()
+                .map(|p| p.apply_round)
+                .find(|apply_round| params.apply_round < *apply_round)?;
+
+            let (blocks, _) = self
+                .get_finalized_blocks_range(next_apply_round, round_end, 1)
+                .await;
+            let (transition_block, _) = blocks.into_iter().next()?;
+
+            params = self.get_consensus_params(transition_block.round).await;
+            params_transitions.push(ConsensusParamsTransition {
+                params: params.clone(),
+                block: transition_block,
+            });
+        }
+
+        CItemProof::new(params_transitions, block, finality, &citems, citem_idx)
+    }
+
+    /// Build a [`FinalityCertificate`] of a notarized `block`
+    ///
+    /// Follows the notarized blocks extending it until one that a threshold
+    /// of peers voted final right after, as recorded finality vote signatures.
+    async fn get_finality_certificate(&self, block: BlockHeader) -> Option<FinalityCertificate> {
+        self.db
+            .read_with_expect(|ctx| {
+                let tbl_notarized_blocks = ctx.open_table(&cons_blocks_notarized::TABLE)?;
+                let mut descendants = vec![];
+                let mut tip = block;
+
+                loop {
+                    let Some(vote_round) = tip.round.next() else {
+                        return Ok(None);
+                    };
+                    let params = ctx.get_consensus_params(tip.round)?;
+                    let votes: BTreeMap<_, _> = ctx
+                        .get_finality_vote_sigs(vote_round)?
+                        .into_iter()
+                        .filter_map(|(peer_pubkey, sig)| {
+                            Some((params.find_peer_idx(peer_pubkey)?, sig))
+                        })
+                        .collect();
+                    if params.is_threshold(votes.keys().copied()) {
+                        return Ok(Some(FinalityCertificate { descendants, votes }));
+                    }
+
+                    if FINALITY_CERTIFICATE_MAX_DESCENDANTS <= descendants.len() {
+                        return Ok(None);
+                    }
+                    let Some(next) = tbl_notarized_blocks
+                        .range(vote_round..)?
+                        .next()
+                        .transpose()?
+                        .map(|(_, v)| v.value())
+                    else {
+                        return Ok(None);
+                    };
+                    if next.prev_block_hash != tip.hash() {
+                        return Ok(None);
+                    }
+                    descendants.push(ctx.get_notarization(next)?);
+                    tip = next;
+                }
+            })
+            .await
+    }
+
+    /// Insert [`ConsensusParams`] schedule obtained from a state snapshot
+    pub fn restore_consensus_params_schedule_tx(
+        &self,
+        ctx: &WriteTransactionCtx,
+        consensus_params: &[ConsensusParams],
+    ) -> DbResult<()> {
+        for params in consensus_params {
+            ctx.insert_consensus_params(params.apply_round, params)?;
+        }
+        Ok(())
+    }
+
     pub fn consensus_params_change_tx(
         &self,
         ctx: &WriteTransactionCtx,
         round: BlockRound,
         block_timestamp: Timestamp,
         new_peer_set: PeerSet,
+        new_bls_pubkeys: Vec<BlsPubkey>,
+        new_peer_weights: Vec<PeerWeight>,
+        new_max_block_payload_len: u32,
     ) -> DbResult<()> {
         let current_params = ctx.get_consensus_params(round)?;
 
@@ -279,6 +494,9 @@ impl Consensus {
             round,
             block_timestamp,
             new_peer_set,
+            new_bls_pubkeys,
+            new_peer_weights,
+            new_max_block_payload_len,
             prev_mid_block.map(|b| (b.round, b.hash())),
         );
 
@@ -316,11 +534,11 @@ impl Consensus {
                     
                     // Check for notarized block
                     if let Some(block_header) = ctx.get_notarized_block(round)? {
-                        // Get block votes
-                        let votes = ctx.get_votes_proposal(round)?;
+                        // Get block signers
+                        let signers = ctx.get_notarization(block_header)?.signers();
                         let consensus_params = ctx.get_consensus_params(round)?;
-                        let signatories: Vec<PeerPubkey> = votes.iter()
-                            .filter_map(|(peer_idx, _sig)| {
+                        let signatories: Vec<PeerPubkey> = signers.iter()
+                            .filter_map(|peer_idx| {
                                 let idx = peer_idx.as_usize();
                                 consensus_params.peers.as_slice().get(idx).copied()
                             })
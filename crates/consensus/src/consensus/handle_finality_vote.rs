@@ -1,8 +1,7 @@
 use bfte_consensus_core::block::BlockRound;
-use bfte_consensus_core::msg::WaitFinalityVoteResponse;
-use bfte_consensus_core::num_peers::ToNumPeers as _;
+use bfte_consensus_core::msg::{FinalityVoteUpdate, WaitFinalityVoteResponse};
 use bfte_consensus_core::peer::PeerPubkey;
-use bfte_consensus_core::signed::InvalidSignatureError;
+use bfte_consensus_core::signed::{InvalidSignatureError, Signed};
 use bfte_db::ctx::WriteTransactionCtx;
 use bfte_db::error::{DbResult, DbTxError, TxSnafu};
 use bfte_util_fmt_opt::AsFmtOption as _;
@@ -48,10 +47,36 @@ impl Consensus {
             .verify_sig_peer_pubkey(peer_pubkey)
             .context(InvalidSignaturesSnafu)
             .context(TxSnafu)?;
+        ctx.insert_finality_vote_sig(update.inner.0, peer_pubkey, update.sig)?;
         self.update_peer_finality_vote_round(ctx, cur_round, peer_pubkey, update.inner.0)?;
         Ok(())
     }
 
+    /// Record the signature of our own finality vote, as sent to peers
+    ///
+    /// Needed to prove blocks final with our vote, see
+    /// [`bfte_consensus_core::proof::FinalityCertificate`].
+    pub async fn record_own_finality_vote(&self, update: Signed<FinalityVoteUpdate>) {
+        let Some(our_peer_pubkey) = self.our_peer_pubkey else {
+            return;
+        };
+        // Every peer asks for the same vote, so avoid needless writes
+        if self
+            .db
+            .read_with_expect(|ctx| ctx.get_finality_vote_sigs(update.inner.0))
+            .await
+            .iter()
+            .any(|(peer_pubkey, _)| *peer_pubkey == our_peer_pubkey)
+        {
+            return;
+        }
+        self.db
+            .write_with_expect(|ctx| {
+                ctx.insert_finality_vote_sig(update.inner.0, our_peer_pubkey, update.sig)
+            })
+            .await;
+    }
+
     /// Track finalization updates as peers confirm their notarizations
     pub(crate) fn update_peer_finality_vote_round(
         &self,
@@ -99,15 +124,27 @@ impl Consensus {
     ) -> Result<(), bfte_db::error::DbError> {
         let cur_round_consensus_params = ctx.get_consensus_params(cur_round)?;
         let mut votes = vec![];
-        for peer_pubkey in cur_round_consensus_params.peers.as_slice() {
-            votes.push(ctx.get_finality_vote(*peer_pubkey)?.unwrap_or_default());
+        for (peer_idx, peer_pubkey) in cur_round_consensus_params.iter_peers() {
+            votes.push((
+                ctx.get_finality_vote(peer_pubkey)?.unwrap_or_default(),
+                cur_round_consensus_params.peer_weight(peer_idx),
+            ));
         }
         debug!(target: LOG_TARGET, ?votes, "Current finality votes");
         votes.sort();
 
-        let num_peers = cur_round_consensus_params.peers.to_num_peers();
-
-        let finality_cons = votes[num_peers.max_faulty()];
+        // The highest round voted for by peers with at least a threshold
+        // of the total weight, i.e. skip the lowest votes as long as they
+        // could all come from faulty peers
+        let max_faulty = cur_round_consensus_params.total_weight().max_faulty();
+        let mut skipped_weight = 0;
+        let finality_cons = votes
+            .iter()
+            .find_map(|(vote, weight)| {
+                skipped_weight += weight;
+                (max_faulty < skipped_weight).then_some(*vote)
+            })
+            .expect("Total weight must exceed max faulty weight");
 
         let prev_finality_cons = ctx
             .update_finality_consensus(finality_cons)?
@@ -127,9 +164,6 @@ impl Consensus {
         };
 
         if prev_finality_cons != finality_cons {
-            // Prune dummy votes, purely to save space
-            ctx.prune_dummy_votes(finality_cons)?;
-
             let tx = self.finality_consensus_tx.clone();
 
             ctx.on_commit(move || {
@@ -21,6 +21,8 @@ pub enum ProcessNotarizedBlockError {
         source: InvalidNotarizationError,
     },
     WrongRoundDummy,
+    /// Dummy blocks are always notarized with individual signatures
+    AggregatedDummy,
     WrongRoundBlock {
         required: BlockRound,
         received: BlockRound,
@@ -95,6 +97,9 @@ impl Consensus {
             if block.round != cur_round {
                 WrongRoundDummySnafu.fail().context(TxSnafu)?;
             }
+            if block.aggregate.is_some() {
+                AggregatedDummySnafu.fail().context(TxSnafu)?;
+            }
             debug!(
                 target: LOG_TARGET,
                 "New notarized dummy block our previous notarized block"
@@ -203,6 +208,9 @@ impl Consensus {
         let outcome = ctx.insert_notarized_block(block.round, block.inner, Some(&payload))?;
         debug_assert_eq!(outcome, InsertOutcome::Inserted);
         let block_round = block.round;
+        if let Some(aggregate) = &block.aggregate {
+            ctx.insert_notarized_block_aggregate(block_round, aggregate)?;
+        }
         for (peer_idx, sig) in block.sigs {
             ctx.insert_block_vote(
                 block_round,
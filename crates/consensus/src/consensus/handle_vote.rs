@@ -2,8 +2,10 @@ use bfte_consensus_core::Signature;
 use bfte_consensus_core::block::{
     BlockHeader, BlockPayloadRaw, BlockRound, VerifyWithContentError,
 };
+use bfte_consensus_core::equivocation::EquivocationEvidence;
 use bfte_consensus_core::msg::WaitVoteResponse;
-use bfte_consensus_core::peer::PeerIdx;
+use bfte_consensus_core::peer::{PeerIdx, PeerPubkey};
+use bfte_consensus_core::signed::{Signable as _, Signed};
 use bfte_db::ctx::WriteTransactionCtx;
 use bfte_db::error::{DbTxError, TxSnafu};
 use snafu::{OptionExt as _, ResultExt as _, Snafu};
@@ -22,18 +24,28 @@ pub enum ProcessVoteError {
         received: BlockRound,
     },
     InvalidSignature,
+    MissingBlsSignature,
     ForkedProposal {
         peer_idx: PeerIdx,
         existing: BlockHeader,
+        evidence: Option<Box<EquivocationEvidence>>,
     },
     ForkedSignature {
         peer_idx: PeerIdx,
         existing: Signature,
+        evidence: Option<Box<EquivocationEvidence>>,
+    },
+    VoteForADifferentProposal {
+        evidence: Option<Box<EquivocationEvidence>>,
     },
-    VoteForADifferentProposal,
     NotALeader,
     #[snafu(display("Proposed a dummy block"))]
     Dummy,
+    #[snafu(display("Payload too large: {len}, max: {max}"))]
+    PayloadTooLarge {
+        len: u32,
+        max: u32,
+    },
     InvalidContent {
         source: VerifyWithContentError,
     },
@@ -48,16 +60,36 @@ pub enum ProcessVoteError {
 
 type ProcessVoteResult<T> = Result<T, ProcessVoteError>;
 
+impl ProcessVoteError {
+    /// Proof of misbehavior, if the error was caused by an equivocating peer
+    pub fn equivocation_evidence(&self) -> Option<&EquivocationEvidence> {
+        match self {
+            ProcessVoteError::ForkedProposal { evidence, .. }
+            | ProcessVoteError::ForkedSignature { evidence, .. }
+            | ProcessVoteError::VoteForADifferentProposal { evidence } => evidence.as_deref(),
+            _ => None,
+        }
+    }
+}
+
 impl Consensus {
     pub async fn process_vote_response(
         &self,
         peer_idx: PeerIdx,
         resp: WaitVoteResponse,
     ) -> ProcessVoteResult<()> {
-        self.db
+        let res = self
+            .db
             .write_with_expect_falliable(|ctx| self.process_vote_response_tx(ctx, peer_idx, resp))
-            .await?;
-        Ok(())
+            .await;
+
+        // The transaction rejecting the vote was rolled back, so persist
+        // the evidence separately
+        if let Some(evidence) = res.as_ref().err().and_then(|e| e.equivocation_evidence()) {
+            self.record_equivocation_verified(evidence).await;
+        }
+
+        res
     }
 
     #[instrument(skip_all,
@@ -95,10 +127,16 @@ impl Consensus {
         let consensus_params = ctx.get_consensus_params(cur_round)?;
         let consensus_params_hash = consensus_params.hash();
         let consensus_params_len = consensus_params.len();
+        let peer_pubkey = consensus_params
+            .peers
+            .as_slice()
+            .get(peer_idx.as_usize())
+            .copied();
 
+        let bls_sig = resp.bls_sig();
         let vote = match resp {
-            WaitVoteResponse::Vote { block } => block,
-            WaitVoteResponse::Proposal { block, payload } => {
+            WaitVoteResponse::Vote { block, .. } => block,
+            WaitVoteResponse::Proposal { block, payload, .. } => {
                 // Only leader can propose a block
                 if consensus_params.leader_idx(cur_round) != peer_idx {
                     return Err(ProcessVoteError::NotALeader).context(TxSnafu)?;
@@ -109,6 +147,15 @@ impl Consensus {
                     return Err(ProcessVoteError::Dummy).context(TxSnafu)?;
                 }
 
+                let payload_len = u32::from(payload.len());
+                if consensus_params.max_block_payload_len < payload_len {
+                    return Err(ProcessVoteError::PayloadTooLarge {
+                        len: payload_len,
+                        max: consensus_params.max_block_payload_len,
+                    })
+                    .context(TxSnafu)?;
+                }
+
                 block
                     .verify_with_content(consensus_params_hash, consensus_params_len, &payload)
                     .context(InvalidContentSnafu)
@@ -124,8 +171,17 @@ impl Consensus {
                 match ctx.insert_block_proposal(cur_round, block.inner, &payload)? {
                     InsertOutcome::AlreadyPresent(existing) => {
                         if existing != block.inner {
-                            return Err(ProcessVoteError::ForkedProposal { existing, peer_idx })
-                                .context(TxSnafu)?;
+                            // The existing proposal was counted as a leader's vote
+                            let evidence =
+                                ctx.get_vote_block(cur_round, peer_idx)?.and_then(|first| {
+                                    Self::make_equivocation_evidence(peer_pubkey?, first, block)
+                                });
+                            return Err(ProcessVoteError::ForkedProposal {
+                                existing,
+                                peer_idx,
+                                evidence,
+                            })
+                            .context(TxSnafu)?;
                         } else {
                             return Ok(());
                         }
@@ -154,6 +210,25 @@ impl Consensus {
             .context(InvalidSignatureSnafu)
             .context(TxSnafu)?;
 
+        // With BLS enabled, votes on non-dummy blocks must be BLS-signed too, so
+        // the block can be notarized with an aggregate signature
+        let bls_sig = if consensus_params.uses_bls() && !vote.inner.is_dummy() {
+            let bls_sig = bls_sig.context(MissingBlsSignatureSnafu).context(TxSnafu)?;
+            let bls_pubkey = consensus_params
+                .bls_pubkeys
+                .get(peer_idx.as_usize())
+                .copied()
+                .context(InvalidSignatureSnafu)
+                .context(TxSnafu)?;
+            vote.verify_bls_signature(bls_pubkey, bls_sig)
+                .ok()
+                .context(InvalidSignatureSnafu)
+                .context(TxSnafu)?;
+            Some(bls_sig)
+        } else {
+            None
+        };
+
         let vote_insert_outcome = if vote.inner.is_dummy() {
             vote.inner
                 .verify_with_content(
@@ -168,24 +243,43 @@ impl Consensus {
                 BlockHeader::new_dummy(vote.inner.round, &consensus_params)
             );
             ctx.insert_dummy_vote(cur_round, peer_idx, vote.sig)?
+                .map(|sig| (sig, None))
         } else {
             if let Some(proposal) = ctx.get_proposal(cur_round)? {
                 if proposal != vote.inner {
-                    VoteForADifferentProposalSnafu.fail().context(TxSnafu)?;
+                    // If the peer already voted for the proposal, this vote
+                    // conflicts with it
+                    let evidence = ctx.get_vote_block(cur_round, peer_idx)?.and_then(|first| {
+                        Self::make_equivocation_evidence(peer_pubkey?, first, vote)
+                    });
+                    return Err(ProcessVoteError::VoteForADifferentProposal { evidence })
+                        .context(TxSnafu)?;
+                }
+            }
+            let outcome = ctx.insert_block_vote(cur_round, peer_idx, vote)?;
+            if matches!(outcome, InsertOutcome::Inserted) {
+                if let Some(bls_sig) = bls_sig {
+                    ctx.insert_block_vote_bls(cur_round, peer_idx, bls_sig)?;
                 }
             }
-            ctx.insert_block_vote(cur_round, peer_idx, vote)?
-                .map(|x| x.sig)
+            outcome.map(|existing| (existing.sig, Some(existing)))
         };
 
         self.notify_new_votes(ctx);
 
         match vote_insert_outcome {
             InsertOutcome::Inserted => {}
-            InsertOutcome::AlreadyPresent(existing) => {
+            InsertOutcome::AlreadyPresent((existing, existing_vote)) => {
                 if existing != vote.sig {
-                    return Err(ProcessVoteError::ForkedSignature { peer_idx, existing })
-                        .context(TxSnafu)?;
+                    let evidence = existing_vote.and_then(|first| {
+                        Self::make_equivocation_evidence(peer_pubkey?, first, vote)
+                    });
+                    return Err(ProcessVoteError::ForkedSignature {
+                        peer_idx,
+                        existing,
+                        evidence,
+                    })
+                    .context(TxSnafu)?;
                 } else {
                     return Ok(());
                 }
@@ -197,6 +291,24 @@ impl Consensus {
         Ok(())
     }
 
+    /// Build an [`EquivocationEvidence`], if `first` and `second` really prove
+    /// `peer_pubkey` equivocated
+    fn make_equivocation_evidence(
+        peer_pubkey: PeerPubkey,
+        first: Signed<BlockHeader>,
+        second: Signed<BlockHeader>,
+    ) -> Option<Box<EquivocationEvidence>> {
+        let evidence = EquivocationEvidence {
+            peer_pubkey,
+            first,
+            second,
+        };
+
+        evidence.verify().ok()?;
+
+        Some(Box::new(evidence))
+    }
+
     pub(crate) fn notify_new_votes(&self, ctx: &WriteTransactionCtx) {
         ctx.on_commit({
             let new_votes_tx = self.new_votes_tx.clone();
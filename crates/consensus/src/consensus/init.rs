@@ -11,8 +11,10 @@ use snafu::{ResultExt as _, Snafu};
 use super::Consensus;
 use super::ctx::{ConsensusReadDbOps as _, ConsensusWriteDbOps as _, InsertOutcome};
 use crate::tables::{
-    cons_blocks_notarized, cons_blocks_pinned, cons_blocks_proposals, cons_finality_consensus,
-    cons_finality_votes, cons_votes_block, cons_votes_dummy,
+    cons_blocks_notarized, cons_blocks_notarized_aggregate, cons_blocks_pinned,
+    cons_blocks_proposals, cons_equivocations, cons_finality_consensus, cons_finality_vote_sigs,
+    cons_finality_votes, cons_payloads_pruned_before, cons_votes_block, cons_votes_block_bls,
+    cons_votes_dummy,
 };
 
 #[derive(Debug, Snafu)]
@@ -59,6 +61,7 @@ impl Consensus {
         let (round_timeout_tx, round_timeout_rx) = tokio::sync::watch::channel((cur_round, false));
         let (new_votes_tx, new_votes_rx) = tokio::sync::watch::channel(());
         let (new_proposal_tx, new_proposal_rx) = tokio::sync::watch::channel(());
+        let (new_equivocation_tx, new_equivocation_rx) = tokio::sync::watch::channel(());
         let (finality_consensus, finality_self) = db
             .read_with_expect(|ctx| {
                 Ok((
@@ -86,6 +89,8 @@ impl Consensus {
             new_votes_rx,
             new_proposal_tx,
             new_proposal_rx,
+            new_equivocation_tx,
+            new_equivocation_rx,
         };
 
         // This will mostly calculate a correct timeout again, based on the state
@@ -142,10 +147,15 @@ impl Consensus {
     fn init_tables_tx(tx: &WriteTransactionCtx) -> DbResult<()> {
         tx.open_table(&cons_blocks_proposals::TABLE)?;
         tx.open_table(&cons_blocks_notarized::TABLE)?;
+        tx.open_table(&cons_blocks_notarized_aggregate::TABLE)?;
         tx.open_table(&cons_votes_dummy::TABLE)?;
         tx.open_table(&cons_votes_block::TABLE)?;
+        tx.open_table(&cons_votes_block_bls::TABLE)?;
         tx.open_table(&cons_finality_consensus::TABLE)?;
         tx.open_table(&cons_finality_votes::TABLE)?;
+        tx.open_table(&cons_finality_vote_sigs::TABLE)?;
+        tx.open_table(&cons_equivocations::TABLE)?;
+        tx.open_table(&cons_payloads_pruned_before::TABLE)?;
         Ok(())
     }
 }
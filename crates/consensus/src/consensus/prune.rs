@@ -0,0 +1,234 @@
+//! Pruning of finalized consensus data no one needs anymore
+
+use std::collections::BTreeSet;
+
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_db::ctx::WriteTransactionCtx;
+use bfte_db::error::DbResult;
+use bincode::{Decode, Encode};
+use tokio::select;
+use tokio::sync::watch;
+use tracing::debug;
+
+use super::Consensus;
+use super::ctx::{ConsensusReadDbOps as _, ConsensusWriteDbOps as _};
+use crate::consensus::LOG_TARGET;
+use crate::tables::{
+    cons_blocks_notarized, cons_blocks_payloads, cons_blocks_proposals, cons_finality_vote_sigs,
+    cons_payloads_pruned_before,
+};
+
+/// Default number of finalized rounds to keep all the data of
+pub const DEFAULT_PRUNING_RETENTION: u64 = 1024;
+
+/// Minimum number of rounds to prune at once
+///
+/// Pruning payloads requires scanning all the retained blocks, so it's
+/// better not done every round.
+const PRUNING_MIN_ROUNDS: u64 = 64;
+
+/// How much of the finalized history the node keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+pub enum StorageMode {
+    /// Keep payloads of all finalized blocks, so peers syncing from scratch
+    /// can fetch them
+    #[default]
+    Archival,
+    /// Drop payloads of finalized blocks past the retention
+    Pruned,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruningConfig {
+    /// Number of rounds behind the finality consensus to keep all the data of
+    pub retention: u64,
+    pub mode: StorageMode,
+}
+
+impl Default for PruningConfig {
+    fn default() -> Self {
+        Self {
+            retention: DEFAULT_PRUNING_RETENTION,
+            mode: StorageMode::default(),
+        }
+    }
+}
+
+impl Consensus {
+    /// Keep pruning data of rounds `config.retention` behind the finality
+    /// consensus, as it advances
+    ///
+    /// Block proposals and dummy votes are always pruned. Payloads only in
+    /// [`StorageMode::Pruned`], and never of rounds `processed_rx` did not
+    /// pass yet, as the application might still need them.
+    ///
+    /// Returns only when shutting down.
+    pub async fn run_pruning(
+        &self,
+        config: PruningConfig,
+        mut processed_rx: watch::Receiver<BlockRound>,
+    ) {
+        let mut finality_consensus_rx = self.finality_consensus_rx();
+        let mut pruned_before = BlockRound::ZERO;
+
+        loop {
+            let prune_before = BlockRound::from(
+                finality_consensus_rx
+                    .borrow_and_update()
+                    .to_number()
+                    .saturating_sub(config.retention),
+            );
+
+            if pruned_before.to_number().saturating_add(PRUNING_MIN_ROUNDS)
+                <= prune_before.to_number()
+            {
+                let payloads_before = match config.mode {
+                    StorageMode::Archival => None,
+                    StorageMode::Pruned => {
+                        Some(prune_before.min(*processed_rx.borrow_and_update()))
+                    }
+                };
+
+                self.db
+                    .write_with_expect(|ctx| Self::prune_tx(ctx, prune_before, payloads_before))
+                    .await;
+                debug!(
+                    target: LOG_TARGET,
+                    %prune_before,
+                    "Pruned finalized consensus data"
+                );
+                pruned_before = prune_before;
+            }
+
+            select! {
+                res = finality_consensus_rx.changed() => {
+                    if res.is_err() {
+                        return;
+                    }
+                }
+                res = processed_rx.changed() => {
+                    if res.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Prune proposals, dummy votes and useless finality vote signatures of
+    /// rounds before `before`, and payloads (with all finality vote
+    /// signatures) of blocks before `payloads_before` (if set)
+    fn prune_tx(
+        ctx: &WriteTransactionCtx,
+        before: BlockRound,
+        payloads_before: Option<BlockRound>,
+    ) -> DbResult<()> {
+        // Even if the round was finalized, we might be lagging behind and still
+        // in it, and we must not lose track of what we signed.
+        let before = before.min(ctx.get_current_round()?);
+
+        ctx.open_table(&cons_blocks_proposals::TABLE)?
+            .retain_in(&BlockRound::MIN..&before, |_, _| false)?;
+        ctx.prune_dummy_votes(before)?;
+        Self::prune_finality_vote_sigs_tx(ctx, before)?;
+
+        if let Some(payloads_before) = payloads_before {
+            Self::prune_payloads_tx(ctx, payloads_before.min(before))?;
+        }
+
+        Ok(())
+    }
+
+    /// Prune finality vote signatures of rounds before `before` that can't
+    /// prove any block final, as they don't follow a notarized block
+    fn prune_finality_vote_sigs_tx(ctx: &WriteTransactionCtx, before: BlockRound) -> DbResult<()> {
+        let tbl_notarized_blocks = ctx.open_table(&cons_blocks_notarized::TABLE)?;
+        let mut tbl_sigs = ctx.open_table(&cons_finality_vote_sigs::TABLE)?;
+
+        let mut useless = vec![];
+        for kv in
+            tbl_sigs.range(&(BlockRound::MIN, PeerPubkey::ZERO)..&(before, PeerPubkey::ZERO))?
+        {
+            let (k, _) = kv?;
+            let key = k.value();
+            let follows_block = match key.0.prev() {
+                Some(prev) => tbl_notarized_blocks.get(&prev)?.is_some(),
+                None => false,
+            };
+            if !follows_block {
+                useless.push(key);
+            }
+        }
+        for key in &useless {
+            tbl_sigs.remove(key)?;
+        }
+
+        Ok(())
+    }
+
+    fn prune_payloads_tx(ctx: &WriteTransactionCtx, before: BlockRound) -> DbResult<()> {
+        let mut tbl_pruned_before = ctx.open_table(&cons_payloads_pruned_before::TABLE)?;
+        let pruned_before = tbl_pruned_before
+            .get(&())?
+            .map(|v| v.value())
+            .unwrap_or_default();
+        if before <= pruned_before {
+            return Ok(());
+        }
+
+        let tbl_notarized_blocks = ctx.open_table(&cons_blocks_notarized::TABLE)?;
+        let mut payload_hashes = BTreeSet::new();
+        for kv in tbl_notarized_blocks.range(pruned_before..before)? {
+            let (_, v) = kv?;
+            payload_hashes.insert(v.value().payload_hash);
+        }
+
+        // Identical payloads are stored once, so keep ones still used by
+        // retained blocks
+        if !payload_hashes.is_empty() {
+            for kv in tbl_notarized_blocks.range(before..)? {
+                let (_, v) = kv?;
+                payload_hashes.remove(&v.value().payload_hash);
+            }
+            for kv in ctx
+                .open_table(&cons_blocks_proposals::TABLE)?
+                .range(before..)?
+            {
+                let (_, v) = kv?;
+                payload_hashes.remove(&v.value().payload_hash);
+            }
+        }
+
+        let mut tbl_payloads = ctx.open_table(&cons_blocks_payloads::TABLE)?;
+        for payload_hash in &payload_hashes {
+            tbl_payloads.remove(payload_hash)?;
+        }
+
+        // Without payloads, blocks can't be proven to contain anything anyway
+        ctx.open_table(&cons_finality_vote_sigs::TABLE)?.retain_in(
+            &(BlockRound::MIN, PeerPubkey::ZERO)..&(before, PeerPubkey::ZERO),
+            |_, _| false,
+        )?;
+
+        tbl_pruned_before.insert(&(), &before)?;
+
+        Ok(())
+    }
+
+    /// Payloads of blocks in rounds before the returned one were pruned
+    pub async fn get_payloads_pruned_before(&self) -> BlockRound {
+        self.db
+            .read_with_expect(|ctx| {
+                Ok(ctx
+                    .open_table(&cons_payloads_pruned_before::TABLE)?
+                    .get(&())?
+                    .map(|v| v.value())
+                    .unwrap_or_default())
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests;
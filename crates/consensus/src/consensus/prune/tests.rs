@@ -0,0 +1,125 @@
+use bfte_consensus_core::Signature;
+use bfte_consensus_core::block::{BlockHeader, BlockPayloadRaw, BlockRound};
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::peer::{PeerIdx, PeerPubkey};
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_consensus_core::ver::ConsensusVersion;
+use bfte_db::Database;
+use bfte_db::ctx::WriteTransactionCtx;
+use bfte_db::error::DbResult;
+
+use super::Consensus;
+use crate::consensus::ctx::{ConsensusReadDbOps as _, ConsensusWriteDbOps as _};
+use crate::tables::cons_current_round;
+
+fn params() -> ConsensusParams {
+    ConsensusParams {
+        prev_mid_block: None,
+        peers: vec![PeerPubkey::ZERO].into(),
+        consensus_params_format_version: ConsensusParams::FORMAT_VERSION,
+        init_core_module_cons_version: ConsensusVersion::new(0, 0),
+        timestamp: Timestamp::ZERO,
+        schedule_round: 0.into(),
+        apply_round: 0.into(),
+        bls_pubkeys: vec![],
+        peer_weights: vec![],
+        max_block_payload_len: ConsensusParams::DEFAULT_MAX_BLOCK_PAYLOAD_LEN,
+    }
+}
+
+fn payload(byte: u8) -> BlockPayloadRaw {
+    BlockPayloadRaw::from(vec![byte])
+}
+
+fn block(round: u64, payload: &BlockPayloadRaw) -> BlockHeader {
+    BlockHeader::builder()
+        .round(BlockRound::from(round))
+        .timestamp(Timestamp::ZERO)
+        .consensus_params(&params())
+        .payload(payload)
+        .build()
+}
+
+/// Proposals and dummy votes in rounds 0 to 9, blocks notarized in rounds 1
+/// and 2 with distinct payloads, and in round 6 with the payload of round 2
+fn populate_tx(ctx: &WriteTransactionCtx, cur_round: u64) -> DbResult<()> {
+    ctx.open_table(&cons_current_round::TABLE)?
+        .insert(&(), &BlockRound::from(cur_round))?;
+
+    for round in 0..10 {
+        let payload = payload(u8::try_from(round).expect("Can't fail"));
+        ctx.insert_block_proposal(BlockRound::from(round), block(round, &payload), &payload)?;
+        ctx.insert_dummy_vote(BlockRound::from(round), PeerIdx::from(0), Signature::ZERO)?;
+    }
+    for (round, payload_byte) in [(1, 0xa1), (2, 0xa2), (6, 0xa2)] {
+        let payload = payload(payload_byte);
+        ctx.insert_notarized_block(
+            BlockRound::from(round),
+            block(round, &payload),
+            Some(&payload),
+        )?;
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prunes_proposals_dummy_votes_and_payloads() -> DbResult<()> {
+    let db = Database::new_in_memory().await?;
+
+    db.write_with(|ctx| {
+        populate_tx(ctx, 10)?;
+        Consensus::prune_tx(ctx, BlockRound::from(5), None)?;
+
+        for round in 0..10 {
+            let round = BlockRound::from(round);
+            let kept = 5 <= round.to_number();
+            assert_eq!(ctx.get_proposal(round)?.is_some(), kept);
+            assert_eq!(ctx.get_vote_dummy(round, PeerIdx::from(0))?.is_some(), kept);
+        }
+        assert!(ctx.get_block_payload(payload(0xa1).hash())?.is_some());
+
+        Consensus::prune_tx(ctx, BlockRound::from(5), Some(BlockRound::from(5)))?;
+
+        assert!(ctx.get_block_payload(payload(0xa1).hash())?.is_none());
+        assert!(ctx.get_block_payload(payload(0xa2).hash())?.is_some());
+        assert!(ctx.get_block_payload(payload(5).hash())?.is_some());
+        Ok(())
+    })
+    .await?;
+
+    assert_eq!(db_pruned_before(&db).await, BlockRound::from(5));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn does_not_prune_past_current_round() -> DbResult<()> {
+    let db = Database::new_in_memory().await?;
+
+    db.write_with(|ctx| {
+        populate_tx(ctx, 2)?;
+        Consensus::prune_tx(ctx, BlockRound::from(5), Some(BlockRound::from(5)))?;
+
+        assert!(ctx.get_proposal(BlockRound::from(1))?.is_none());
+        assert!(ctx.get_proposal(BlockRound::from(2))?.is_some());
+        assert!(ctx.get_block_payload(payload(0xa1).hash())?.is_none());
+        assert!(ctx.get_block_payload(payload(0xa2).hash())?.is_some());
+        Ok(())
+    })
+    .await?;
+
+    assert_eq!(db_pruned_before(&db).await, BlockRound::from(2));
+
+    Ok(())
+}
+
+async fn db_pruned_before(db: &Database) -> BlockRound {
+    db.read_with_expect(|ctx| {
+        Ok(ctx
+            .open_table(&crate::tables::cons_payloads_pruned_before::TABLE)?
+            .get(&())?
+            .map(|v| v.value())
+            .unwrap_or_default())
+    })
+    .await
+}
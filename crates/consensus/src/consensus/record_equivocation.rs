@@ -0,0 +1,85 @@
+use bfte_consensus_core::equivocation::{EquivocationEvidence, InvalidEquivocationError};
+use bfte_db::ctx::WriteTransactionCtx;
+use bfte_db::error::{DbResult, TxSnafu};
+use snafu::{ResultExt as _, Snafu};
+use tracing::warn;
+
+use super::Consensus;
+use crate::consensus::LOG_TARGET;
+use crate::consensus::ctx::{ConsensusReadDbOps as _, ConsensusWriteDbOps as _, InsertOutcome};
+
+#[derive(Debug, Snafu)]
+pub enum RecordEquivocationError {
+    Invalid { source: InvalidEquivocationError },
+    NotAPeer,
+}
+
+type RecordEquivocationResult<T> = Result<T, RecordEquivocationError>;
+
+impl Consensus {
+    /// Record equivocation evidence obtained from outside (e.g. other peers)
+    ///
+    /// Returns `true` if the evidence was not known before.
+    pub async fn record_equivocation(
+        &self,
+        evidence: &EquivocationEvidence,
+    ) -> RecordEquivocationResult<bool> {
+        evidence.verify().context(InvalidSnafu)?;
+
+        self.db
+            .write_with_expect_falliable(|ctx| {
+                let consensus_params = ctx.get_consensus_params(evidence.round())?;
+                if consensus_params
+                    .find_peer_idx(evidence.peer_pubkey)
+                    .is_none()
+                {
+                    return NotAPeerSnafu.fail().context(TxSnafu);
+                }
+
+                Ok(self.record_equivocation_tx(ctx, evidence)?)
+            })
+            .await
+    }
+
+    /// Record equivocation evidence that was already verified
+    pub(crate) async fn record_equivocation_verified(&self, evidence: &EquivocationEvidence) {
+        self.db
+            .write_with_expect(|ctx| self.record_equivocation_tx(ctx, evidence))
+            .await;
+    }
+
+    fn record_equivocation_tx(
+        &self,
+        ctx: &WriteTransactionCtx,
+        evidence: &EquivocationEvidence,
+    ) -> DbResult<bool> {
+        debug_assert!(evidence.verify().is_ok());
+
+        if let InsertOutcome::AlreadyPresent(_) = ctx.insert_equivocation(evidence)? {
+            return Ok(false);
+        }
+
+        warn!(
+            target: LOG_TARGET,
+            peer_pubkey = %evidence.peer_pubkey,
+            round = %evidence.round(),
+            "Recorded equivocation evidence"
+        );
+
+        ctx.on_commit({
+            let new_equivocation_tx = self.new_equivocation_tx.clone();
+            move || {
+                new_equivocation_tx.send_replace(());
+            }
+        });
+
+        Ok(true)
+    }
+
+    /// All the recorded equivocation evidence
+    pub async fn get_equivocations(&self) -> Vec<EquivocationEvidence> {
+        self.db
+            .read_with_expect(|ctx| ctx.get_equivocations())
+            .await
+    }
+}
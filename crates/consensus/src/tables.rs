@@ -1,15 +1,12 @@
 use bfte_consensus_core::Signature;
 use bfte_consensus_core::block::{BlockHash, BlockHeader, BlockPayloadRaw, BlockRound};
+use bfte_consensus_core::bls::{BlsAggregateSignature, BlsSignature};
 use bfte_consensus_core::consensus_params::{ConsensusParams, ConsensusParamsHash};
+use bfte_consensus_core::equivocation::EquivocationEvidence;
 use bfte_consensus_core::peer::{PeerIdx, PeerPubkey};
 use bfte_consensus_core::vote::SignedVote;
 use bfte_util_db::def_table;
 
-def_table! {
-    /// Tracks consensus database/schema version
-    db_version: () => u64
-}
-
 def_table! {
     /// Current consensus round we're in
     ///
@@ -40,6 +37,20 @@ def_table! {
     cons_finality_votes: PeerPubkey => BlockRound
 }
 
+def_table! {
+    /// Signatures of finality votes of peers, by the round voted for
+    ///
+    /// Used to prove blocks final to light clients, see
+    /// [`FinalityCertificate`]. Only votes for rounds right after notarized
+    /// blocks are any use.
+    ///
+    /// Cleanup: votes not following a notarized block are pruned with block
+    /// proposals, and all of them with the payloads, as proofs need both.
+    ///
+    /// [`FinalityCertificate`]: bfte_consensus_core::proof::FinalityCertificate
+    cons_finality_vote_sigs: (BlockRound, PeerPubkey) => Signature
+}
+
 def_table! {
     /// [`ConsensusParams`] schedule
     ///
@@ -69,7 +80,11 @@ def_table! {
     /// the proposal if something is here already. This guarantees we never sign
     /// two different proposals for the same round.
     ///
-    /// Cleanup: table can be pruned after round is finalized.
+    /// Cleanup: pruned once the finality consensus is the pruning retention
+    /// past the round, but never for the round we're still in, see
+    /// [`Consensus::run_pruning`].
+    ///
+    /// [`Consensus::run_pruning`]: crate::consensus::Consensus::run_pruning
     cons_blocks_proposals: BlockRound => BlockHeader
 }
 
@@ -83,6 +98,15 @@ def_table! {
     cons_blocks_notarized: BlockRound => BlockHeader
 }
 
+def_table! {
+    /// Aggregate signatures of blocks in `cons_blocks_notarized`
+    ///
+    /// Only available if [`ConsensusParams::uses_bls`]. If present, it
+    /// proves the notarization, and `cons_votes_blocks` might not have any
+    /// votes for the block.
+    cons_blocks_notarized_aggregate: BlockRound => BlsAggregateSignature
+}
+
 def_table! {
     /// Block pins
     ///
@@ -102,8 +126,11 @@ def_table! {
     /// payload when only checking stuff about the header.
     ///
     /// Another one is being able to re-use it for both `cons_blocks_notarized`
-    /// and `cons_blocks_proposals`. It's unnecessary to every garbage collect this
-    /// table.
+    /// and `cons_blocks_proposals`.
+    ///
+    /// Cleanup: kept forever by archival nodes, as peers syncing from scratch
+    /// need them. Pruned nodes drop payloads of old finalized blocks, see
+    /// `cons_payloads_pruned_before`.
     cons_blocks_payloads: bfte_consensus_core::block::BlockPayloadHash => BlockPayloadRaw
 }
 
@@ -121,6 +148,15 @@ def_table! {
     cons_votes_block: (BlockRound, PeerIdx)  => SignedVote
 }
 
+def_table! {
+    /// BLS signatures of votes in `cons_votes_block`
+    ///
+    /// Only collected if [`ConsensusParams::uses_bls`]. Entries without
+    /// a corresponding vote in `cons_votes_block` are stale and should be
+    /// ignored.
+    cons_votes_block_bls: (BlockRound, PeerIdx)  => BlsSignature
+}
+
 def_table! {
     /// Votes for a dummy block in a [`BlockRound`] by a [`PeerIdx`]
     ///
@@ -133,5 +169,25 @@ def_table! {
     ///
     /// Votes for dummy blocks can be deleted when the round or
     /// any round above is finalized. No one cares at that point.
+    ///
+    /// Cleanup: pruned together with block proposals of the same rounds.
     cons_votes_dummy: (BlockRound, PeerIdx)  => Signature
 }
+
+def_table! {
+    /// Payloads of notarized blocks in all rounds before this one were
+    /// pruned
+    ///
+    /// Only ever set by nodes running in [`StorageMode::Pruned`].
+    ///
+    /// [`StorageMode::Pruned`]: crate::consensus::StorageMode::Pruned
+    cons_payloads_pruned_before: () => BlockRound
+}
+
+def_table! {
+    /// Evidence of peers signing two different blocks in the same round
+    ///
+    /// Only the first evidence for a given peer and round is kept, one is
+    /// enough. Never pruned, as it is small, rare, and useful to have around.
+    cons_equivocations: (PeerPubkey, BlockRound) => EquivocationEvidence
+}
@@ -63,6 +63,12 @@ impl VoteSet {
     pub fn is_empty(self) -> bool {
         self.len() == 0
     }
+
+    pub fn iter(self) -> impl Iterator<Item = PeerIdx> {
+        (0..=u8::MAX)
+            .map(PeerIdx::from)
+            .filter(move |peer_idx| self.contains(*peer_idx))
+    }
 }
 
 #[cfg(test)]
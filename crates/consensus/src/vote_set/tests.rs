@@ -1,4 +1,5 @@
 use bfte_consensus_core::bincode::CONSENSUS_BINCODE_CONFIG;
+use bfte_consensus_core::peer::PeerIdx;
 use bfte_util_bincode::decode_whole;
 
 use crate::vote_set::VoteSet;
@@ -13,6 +14,10 @@ pub(crate) fn vote_set_sanity() {
     assert!(!set.contains(2.into()));
     set.insert(100.into());
     assert!(set.contains(100.into()));
+    assert_eq!(
+        set.iter().collect::<Vec<_>>(),
+        [PeerIdx::from(3), PeerIdx::from(100)]
+    );
 
     let rr_set = decode_whole(
         &bincode::encode_to_vec(set, CONSENSUS_BINCODE_CONFIG).expect("Can't fail"),
@@ -6,7 +6,7 @@ use redb_bincode::{WriteTransaction, redb};
 pub struct WriteTransactionCtx {
     commit_hook_order_lock: Arc<std::sync::Mutex<()>>,
     dbtx: WriteTransaction,
-    on_commit: std::sync::Mutex<Vec<Box<dyn FnOnce() + 'static>>>,
+    on_commit: std::sync::Mutex<Vec<Box<dyn FnOnce() + Send + 'static>>>,
 }
 
 impl WriteTransactionCtx {
@@ -33,7 +33,12 @@ impl ops::DerefMut for WriteTransactionCtx {
 }
 
 impl WriteTransactionCtx {
-    pub fn on_commit(&self, f: impl FnOnce() + 'static) {
+    /// Register a hook to run after the transaction is committed
+    ///
+    /// Hooks must be `Send`, as the transaction can be shared between threads
+    /// processing independent consensus items in parallel, in which case the
+    /// relative order of hooks registered by different threads is unspecified.
+    pub fn on_commit(&self, f: impl FnOnce() + Send + 'static) {
         self.on_commit
             .lock()
             .expect("Locking failed")
@@ -45,6 +45,9 @@ pub enum DbError {
         #[snafu(implicit)]
         location: Location,
     },
+    #[snafu(display(
+        "Database version {db_ver} is higher than the supported {code_ver}, it was used by a newer version"
+    ))]
     DbVersionTooHigh {
         db_ver: u64,
         code_ver: u64,
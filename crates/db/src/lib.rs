@@ -2,6 +2,7 @@
 
 pub mod ctx;
 pub mod error;
+pub mod migration;
 
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -12,6 +13,7 @@ use error::{
     CommitSnafu, DatabaseSnafu, DbResult, DbTxError, DbTxResult, InvalidPathSnafu, JoinSnafu,
     TransactionSnafu,
 };
+use migration::Migration;
 use redb_bincode::{ReadTransaction, redb};
 use snafu::{OptionExt as _, ResultExt as _};
 use tracing::{debug, instrument, warn};
@@ -34,7 +36,10 @@ impl Database {
         Self::open_inner(inner, true).await
     }
 
-    pub async fn open(path: impl Into<PathBuf>) -> DbResult<Database> {
+    /// Open (or create) an on-disk database, and apply `migrations` to it
+    ///
+    /// See [`migration`].
+    pub async fn open(path: impl Into<PathBuf>, migrations: &[Migration]) -> DbResult<Database> {
         let path = path.into();
         tokio::fs::create_dir_all(path.parent().context(InvalidPathSnafu)?).await?;
         debug!(target: LOG_TARGET, path = %path.display(), "Opening database…");
@@ -50,7 +55,11 @@ impl Database {
         .context(JoinSnafu)?
         .context(DatabaseSnafu)?;
 
-        Self::open_inner(inner, false).await
+        let db = Self::open_inner(inner, false).await?;
+        db.write_with(|ctx| migration::migrate_tx(ctx, migrations))
+            .await?;
+
+        Ok(db)
     }
 
     #[instrument(skip_all)]
@@ -102,6 +111,23 @@ impl Database {
         })
     }
 
+    async fn dry_write_with_inner_falliable<T, E>(
+        inner: &redb_bincode::Database,
+        commit_hook_order_lock: Arc<std::sync::Mutex<()>>,
+        f: impl FnOnce(&'_ WriteTransactionCtx) -> DbTxResult<T, E>,
+    ) -> DbTxResult<T, E>
+    where
+        E: snafu::Error + 'static,
+    {
+        tokio::task::block_in_place(|| {
+            let dbtx = WriteTransactionCtx::new(
+                inner.begin_write().context(TransactionSnafu)?,
+                commit_hook_order_lock,
+            );
+            f(&dbtx)
+        })
+    }
+
     async fn write_with_inner<T>(
         inner: &redb_bincode::Database,
         commit_hook_order_lock: Arc<std::sync::Mutex<()>>,
@@ -159,6 +185,36 @@ impl Database {
         }
     }
 
+    /// Like [`Self::write_with_expect_falliable`], but the transaction is
+    /// always rolled back
+    ///
+    /// Useful to simulate changes against the current state. The transaction
+    /// is dropped without committing, so commit hooks never run.
+    pub async fn dry_write_with_expect_falliable<T, E>(
+        &self,
+        f: impl FnOnce(&'_ WriteTransactionCtx) -> DbTxResult<T, E>,
+    ) -> Result<T, E>
+    where
+        E: snafu::Error + 'static,
+    {
+        match Self::dry_write_with_inner_falliable(
+            &self.inner,
+            self.commit_hook_order_lock.clone(),
+            f,
+        )
+        .await
+        {
+            Ok(o) => Ok(o),
+            Err(DbTxError::DbError { source, location }) => {
+                panic!("Database error: {source:#} at {location}")
+            }
+            Err(DbTxError::TxError {
+                source,
+                location: _,
+            }) => Err(source),
+        }
+    }
+
     /// Do a writeable database transaction and panic on internal db errors
     ///
     /// If the handler `f` can fail for logical reasons, use
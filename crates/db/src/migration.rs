@@ -0,0 +1,82 @@
+//! Database schema migrations
+//!
+//! Every database tracks its schema version in [`DB_VERSION_TABLE`]. The
+//! version is simply the number of [`Migration`] steps applied to it, so a
+//! registry of migrations is just a list of steps, in order, that must only
+//! ever be appended to.
+//!
+//! All steps missing in a database are applied in a single write transaction
+//! when it is opened with [`crate::Database::open`], so a failing migration
+//! leaves the database untouched.
+
+use redb_bincode::TableDefinition;
+use snafu::ensure;
+use tracing::info;
+
+use crate::LOG_TARGET;
+use crate::ctx::WriteTransactionCtx;
+use crate::error::{DbResult, DbVersionTooHighSnafu};
+
+/// Schema version of the database
+///
+/// Missing value means version `0`, which is also what any database created
+/// before migrations were introduced is at.
+pub const DB_VERSION_TABLE: TableDefinition<'static, (), u64> = TableDefinition::new("db_version");
+
+/// A single step migrating the database from one schema version to the next
+///
+/// Steps run on every on-disk database that is not yet at their version,
+/// including empty, freshly created ones, so they must handle missing tables.
+pub struct Migration {
+    /// Short description, for logging
+    pub name: &'static str,
+    pub migrate: fn(&WriteTransactionCtx) -> DbResult<()>,
+}
+
+/// Schema version of a database with all the `migrations` applied
+pub fn code_version(migrations: &[Migration]) -> u64 {
+    u64::try_from(migrations.len()).expect("Can't fail")
+}
+
+fn get_db_version_tx(ctx: &WriteTransactionCtx) -> DbResult<u64> {
+    Ok(ctx
+        .open_table(&DB_VERSION_TABLE)?
+        .get(&())?
+        .map(|v| v.value())
+        .unwrap_or_default())
+}
+
+/// Apply all `migrations` the database is missing
+///
+/// Fails if the database is at a higher version than `migrations` cover,
+/// as it was used by a newer version of the code, which might have changed
+/// the data in ways this one can't understand.
+pub(crate) fn migrate_tx(ctx: &WriteTransactionCtx, migrations: &[Migration]) -> DbResult<()> {
+    let db_ver = get_db_version_tx(ctx)?;
+    let code_ver = code_version(migrations);
+
+    ensure!(
+        db_ver <= code_ver,
+        DbVersionTooHighSnafu { db_ver, code_ver }
+    );
+
+    for (ver, migration) in (0..).zip(migrations).skip_while(|(ver, _)| *ver < db_ver) {
+        info!(
+            target: LOG_TARGET,
+            from = ver,
+            to = ver + 1,
+            name = migration.name,
+            "Migrating database…"
+        );
+        (migration.migrate)(ctx)?;
+    }
+
+    if db_ver < code_ver {
+        ctx.open_table(&DB_VERSION_TABLE)?.insert(&(), &code_ver)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use redb_bincode::{TableDefinition, redb};
+
+use super::{DB_VERSION_TABLE, Migration, code_version};
+use crate::Database;
+use crate::ctx::WriteTransactionCtx;
+use crate::error::{DbError, DbResult};
+
+/// Schema at version `0`: a single counter
+const COUNTER_V0: TableDefinition<'static, (), u32> = TableDefinition::new("counter");
+/// Schema at version `1`: counters by name, the old one becoming `"default"`
+const COUNTERS_V1: TableDefinition<'static, String, u32> = TableDefinition::new("counters");
+/// Schema at version `2`: counters widened to `u64`
+const COUNTERS_V2: TableDefinition<'static, String, u64> = TableDefinition::new("counters_v2");
+
+fn delete_table_tx(ctx: &WriteTransactionCtx, name: &str) -> DbResult<()> {
+    ctx.as_raw()
+        .delete_table(redb::TableDefinition::<&[u8], &[u8]>::new(name))?;
+    Ok(())
+}
+
+fn counter_to_counters_tx(ctx: &WriteTransactionCtx) -> DbResult<()> {
+    let Some(counter) = ctx.open_table(&COUNTER_V0)?.get(&())?.map(|v| v.value()) else {
+        return Ok(());
+    };
+    ctx.open_table(&COUNTERS_V1)?
+        .insert(&"default".to_owned(), &counter)?;
+    delete_table_tx(ctx, "counter")?;
+    Ok(())
+}
+
+fn widen_counters_tx(ctx: &WriteTransactionCtx) -> DbResult<()> {
+    let mut counters = vec![];
+    for kv in ctx.open_table(&COUNTERS_V1)?.range(..)? {
+        let (k, v) = kv?;
+        counters.push((k.value(), u64::from(v.value())));
+    }
+
+    let mut tbl = ctx.open_table(&COUNTERS_V2)?;
+    for (name, counter) in counters {
+        tbl.insert(&name, &counter)?;
+    }
+    delete_table_tx(ctx, "counters")?;
+    Ok(())
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        name: "counter_to_counters",
+        migrate: counter_to_counters_tx,
+    },
+    Migration {
+        name: "widen_counters",
+        migrate: widen_counters_tx,
+    },
+];
+
+/// Path to a fresh database file, unique for the `test`
+fn db_path(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bfte-db-migration-{}-{test}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("db.redb")
+}
+
+/// Database laid out as code from before migrations were introduced would
+///
+/// The schema is made up for testing the mechanism; migrations of the real
+/// node schema are tested against its old layout in `bfte-node`.
+async fn fixture_v0(path: &Path) -> DbResult<()> {
+    let db = Database::open(path, &[]).await?;
+    db.write_with(|ctx| {
+        ctx.open_table(&COUNTER_V0)?.insert(&(), &3)?;
+        Ok(())
+    })
+    .await
+}
+
+/// Database as created by code knowing only the first migration
+async fn fixture_v1(path: &Path) -> DbResult<()> {
+    let db = Database::open(path, &MIGRATIONS[..1]).await?;
+    db.write_with(|ctx| {
+        let mut tbl = ctx.open_table(&COUNTERS_V1)?;
+        tbl.insert(&"a".to_owned(), &u32::MAX)?;
+        tbl.insert(&"b".to_owned(), &5)?;
+        Ok(())
+    })
+    .await
+}
+
+async fn read_db_version(db: &Database) -> u64 {
+    db.read_with_expect(|ctx| {
+        Ok(ctx
+            .open_table(&DB_VERSION_TABLE)?
+            .get(&())?
+            .map(|v| v.value())
+            .unwrap_or_default())
+    })
+    .await
+}
+
+async fn read_counters(db: &Database) -> Vec<(String, u64)> {
+    db.read_with_expect(|ctx| {
+        let mut counters = vec![];
+        for kv in ctx.open_table(&COUNTERS_V2)?.range(..)? {
+            let (k, v) = kv?;
+            counters.push((k.value(), v.value()));
+        }
+        Ok(counters)
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn migrates_fresh_database() -> DbResult<()> {
+    let path = db_path("fresh");
+
+    let db = Database::open(&path, MIGRATIONS).await?;
+    assert_eq!(read_db_version(&db).await, code_version(MIGRATIONS));
+    assert_eq!(read_counters(&db).await, vec![]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn migrates_v0_database() -> DbResult<()> {
+    let path = db_path("v0");
+    fixture_v0(&path).await?;
+
+    let db = Database::open(&path, MIGRATIONS).await?;
+    assert_eq!(read_db_version(&db).await, 2);
+    assert_eq!(read_counters(&db).await, vec![("default".to_owned(), 3)]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn migrates_v1_database() -> DbResult<()> {
+    let path = db_path("v1");
+    fixture_v1(&path).await?;
+
+    let db = Database::open(&path, MIGRATIONS).await?;
+    assert_eq!(read_db_version(&db).await, 2);
+    assert_eq!(
+        read_counters(&db).await,
+        vec![("a".to_owned(), u64::from(u32::MAX)), ("b".to_owned(), 5)]
+    );
+    drop(db);
+
+    // Reopening with all the migrations already applied doesn't change anything
+    let db = Database::open(&path, MIGRATIONS).await?;
+    assert_eq!(read_db_version(&db).await, 2);
+    assert_eq!(read_counters(&db).await.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn refuses_newer_database() -> DbResult<()> {
+    let path = db_path("newer");
+    drop(Database::open(&path, MIGRATIONS).await?);
+
+    let res = Database::open(&path, &MIGRATIONS[..1]).await;
+    assert!(matches!(
+        res,
+        Err(DbError::DbVersionTooHigh {
+            db_ver: 2,
+            code_ver: 1,
+            ..
+        })
+    ));
+
+    Ok(())
+}
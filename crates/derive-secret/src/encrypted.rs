@@ -0,0 +1,226 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chacha20poly1305::aead::Aead as _;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit as _, Nonce};
+use rand::Rng as _;
+use snafu::{OptionExt as _, Snafu};
+
+use crate::{DeriveableSecret, LevelResult};
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const CIPHERTEXT_LEN: usize = 32 + 16;
+const KDF_PARAMS_LEN: usize = 1 + 1 + 3 * 4;
+
+#[derive(Debug, Snafu)]
+pub enum DecryptSecretError {
+    #[snafu(display("Invalid passphrase or corrupted secret"))]
+    InvalidPassphrase,
+}
+
+#[derive(Debug, Snafu)]
+pub enum ParseEncryptedSecretError {
+    #[snafu(display("Missing `{}` prefix", EncryptedSecret::PREFIX))]
+    MissingPrefix,
+    #[snafu(display("Invalid encoding"))]
+    InvalidEncoding,
+}
+
+/// Argon2 variant, version and costs used to derive the key
+///
+/// Stored in the encoded secret, so decrypting keeps working if the
+/// parameters used for new secrets (or `argon2` defaults) ever change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KdfParams {
+    algorithm: argon2::Algorithm,
+    version: argon2::Version,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+impl KdfParams {
+    /// Parameters used to encrypt new secrets
+    fn current() -> Self {
+        Self {
+            algorithm: argon2::Algorithm::Argon2id,
+            version: argon2::Version::V0x13,
+            m_cost: argon2::Params::DEFAULT_M_COST,
+            t_cost: argon2::Params::DEFAULT_T_COST,
+            p_cost: argon2::Params::DEFAULT_P_COST,
+        }
+    }
+
+    fn params(&self) -> Option<argon2::Params> {
+        argon2::Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32)).ok()
+    }
+
+    fn derive_key(&self, passphrase: &str, salt: &[u8; SALT_LEN]) -> [u8; 32] {
+        let params = self.params().expect("Validated on construction");
+        let mut key = [0u8; 32];
+        argon2::Argon2::new(self.algorithm, self.version, params)
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .expect("Fixed lengths, can't fail");
+        key
+    }
+
+    fn to_bytes(self) -> [u8; KDF_PARAMS_LEN] {
+        let algorithm = match self.algorithm {
+            argon2::Algorithm::Argon2d => 0,
+            argon2::Algorithm::Argon2i => 1,
+            argon2::Algorithm::Argon2id => 2,
+        };
+        let version = u8::try_from(u32::from(self.version)).expect("Versions fit in a byte");
+
+        let mut bytes = [0u8; KDF_PARAMS_LEN];
+        bytes[0] = algorithm;
+        bytes[1] = version;
+        bytes[2..6].copy_from_slice(&self.m_cost.to_be_bytes());
+        bytes[6..10].copy_from_slice(&self.t_cost.to_be_bytes());
+        bytes[10..14].copy_from_slice(&self.p_cost.to_be_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; KDF_PARAMS_LEN]) -> Option<Self> {
+        let algorithm = match bytes[0] {
+            0 => argon2::Algorithm::Argon2d,
+            1 => argon2::Algorithm::Argon2i,
+            2 => argon2::Algorithm::Argon2id,
+            _ => return None,
+        };
+        let version = argon2::Version::try_from(u32::from(bytes[1])).ok()?;
+        let cost = |range: std::ops::Range<usize>| {
+            u32::from_be_bytes(bytes[range].try_into().expect("Fixed length, can't fail"))
+        };
+
+        let s = Self {
+            algorithm,
+            version,
+            m_cost: cost(2..6),
+            t_cost: cost(6..10),
+            p_cost: cost(10..14),
+        };
+        s.params()?;
+        Some(s)
+    }
+}
+
+/// A root [`DeriveableSecret`] encrypted with a passphrase
+///
+/// The key is derived from the passphrase with Argon2 (Argon2id for new
+/// secrets), and the secret is encrypted with ChaCha20-Poly1305.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct EncryptedSecret {
+    kdf: KdfParams,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    ciphertext: [u8; CIPHERTEXT_LEN],
+}
+
+impl fmt::Debug for EncryptedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedSecret").finish_non_exhaustive()
+    }
+}
+
+impl EncryptedSecret {
+    pub const PREFIX: &str = "bfte-encrypted-secret-v1:";
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<DeriveableSecret, DecryptSecretError> {
+        let key = self.kdf.derive_key(passphrase, &self.salt);
+        let bytes = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(Nonce::from_slice(&self.nonce), self.ciphertext.as_slice())
+            .ok()
+            .context(InvalidPassphraseSnafu)?;
+
+        Ok(DeriveableSecret {
+            bytes: bytes.try_into().ok().context(InvalidPassphraseSnafu)?,
+            level: 0,
+        })
+    }
+
+    /// Check if `s` looks like an encrypted secret (as opposed to a
+    /// plaintext mnemonic)
+    pub fn is_encrypted(s: &str) -> bool {
+        s.trim().starts_with(Self::PREFIX)
+    }
+}
+
+impl DeriveableSecret {
+    /// Encrypt the (root) secret with a `passphrase`
+    pub fn encrypt(self, passphrase: &str) -> LevelResult<EncryptedSecret> {
+        self.encrypt_with_kdf(passphrase, KdfParams::current())
+    }
+
+    fn encrypt_with_kdf(self, passphrase: &str, kdf: KdfParams) -> LevelResult<EncryptedSecret> {
+        self.ensure_level(0)?;
+
+        let mut rng = rand::thread_rng();
+        let salt: [u8; SALT_LEN] = rng.r#gen();
+        let nonce: [u8; NONCE_LEN] = rng.r#gen();
+
+        let key = kdf.derive_key(passphrase, &salt);
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(Nonce::from_slice(&nonce), self.bytes.as_slice())
+            .expect("Can't fail");
+
+        Ok(EncryptedSecret {
+            kdf,
+            salt,
+            nonce,
+            ciphertext: ciphertext.try_into().expect("Fixed length, can't fail"),
+        })
+    }
+}
+
+impl fmt::Display for EncryptedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(Self::PREFIX)?;
+        f.write_str(
+            &data_encoding::HEXLOWER.encode(
+                &[
+                    self.kdf.to_bytes().as_slice(),
+                    self.salt.as_slice(),
+                    self.nonce.as_slice(),
+                    self.ciphertext.as_slice(),
+                ]
+                .concat(),
+            ),
+        )
+    }
+}
+
+impl FromStr for EncryptedSecret {
+    type Err = ParseEncryptedSecretError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = data_encoding::HEXLOWER_PERMISSIVE
+            .decode(
+                s.trim()
+                    .strip_prefix(Self::PREFIX)
+                    .context(MissingPrefixSnafu)?
+                    .as_bytes(),
+            )
+            .ok()
+            .context(InvalidEncodingSnafu)?;
+
+        if bytes.len() != KDF_PARAMS_LEN + SALT_LEN + NONCE_LEN + CIPHERTEXT_LEN {
+            return InvalidEncodingSnafu.fail();
+        }
+        let (kdf, rest) = bytes.split_at(KDF_PARAMS_LEN);
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        Ok(Self {
+            kdf: KdfParams::from_bytes(kdf.try_into().expect("Just checked length"))
+                .context(InvalidEncodingSnafu)?,
+            salt: salt.try_into().expect("Just checked length"),
+            nonce: nonce.try_into().expect("Just checked length"),
+            ciphertext: ciphertext.try_into().expect("Just checked length"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
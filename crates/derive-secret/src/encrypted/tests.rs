@@ -0,0 +1,75 @@
+use super::*;
+
+#[test]
+fn encrypted_secret_roundtrip() {
+    let secret = DeriveableSecret::generate();
+
+    let encrypted = secret.encrypt("correct horse").expect("Root secret");
+    let encoded = encrypted.to_string();
+    assert!(EncryptedSecret::is_encrypted(&encoded));
+
+    let decoded = EncryptedSecret::from_str(&encoded).expect("Valid encoding");
+    assert_eq!(decoded, encrypted);
+
+    let decrypted = decoded.decrypt("correct horse").expect("Valid passphrase");
+    assert_eq!(decrypted.reveal_bytes(), secret.reveal_bytes());
+    assert!(decrypted.is_root());
+
+    decoded
+        .decrypt("wrong horse")
+        .expect_err("Invalid passphrase must fail");
+}
+
+#[test]
+fn encrypt_requires_root_secret() {
+    DeriveableSecret::generate()
+        .derive(0.into())
+        .encrypt("passphrase")
+        .expect_err("Non-root secret must not be encryptable");
+}
+
+#[test]
+fn encrypted_secret_uses_stored_kdf_params() {
+    let secret = DeriveableSecret::generate();
+    let kdf = KdfParams {
+        algorithm: argon2::Algorithm::Argon2i,
+        version: argon2::Version::V0x10,
+        m_cost: 1024,
+        t_cost: 1,
+        p_cost: 1,
+    };
+    assert_ne!(kdf, KdfParams::current());
+
+    let encoded = secret
+        .encrypt_with_kdf("passphrase", kdf)
+        .expect("Root secret")
+        .to_string();
+
+    let decoded = EncryptedSecret::from_str(&encoded).expect("Valid encoding");
+    assert_eq!(decoded.kdf, kdf);
+    let decrypted = decoded.decrypt("passphrase").expect("Valid passphrase");
+    assert_eq!(decrypted.reveal_bytes(), secret.reveal_bytes());
+}
+
+#[test]
+fn encrypted_secret_rejects_invalid_kdf_params() {
+    let encoded = DeriveableSecret::generate()
+        .encrypt("passphrase")
+        .expect("Root secret")
+        .to_string();
+    let hex = encoded
+        .strip_prefix(EncryptedSecret::PREFIX)
+        .expect("Has prefix");
+
+    // Unknown algorithm
+    EncryptedSecret::from_str(&format!("{}ff{}", EncryptedSecret::PREFIX, &hex[2..]))
+        .expect_err("Unknown algorithm must be rejected");
+    // Zero time cost
+    EncryptedSecret::from_str(&format!(
+        "{}{}00000000{}",
+        EncryptedSecret::PREFIX,
+        &hex[..12],
+        &hex[20..]
+    ))
+    .expect_err("Invalid costs must be rejected");
+}
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MIT
 
+pub mod encrypted;
+
 use std::fmt;
 use std::str::FromStr;
 
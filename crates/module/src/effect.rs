@@ -3,9 +3,9 @@ use std::sync::Arc;
 use bfte_consensus_core::module::ModuleKind;
 use bfte_util_bincode::decode_whole;
 use bincode::{Decode, Encode};
-use derive_more::Deref;
+use derive_more::{Deref, Display};
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode, Display)]
 pub struct EffectId(u32);
 
 impl EffectId {
@@ -39,14 +39,14 @@ pub trait EffectKindExt: EffectKind {
 
 impl<T: EffectKind> EffectKindExt for T {}
 
-#[derive(Deref, Encode, Decode)]
+#[derive(Debug, Clone, Deref, Encode, Decode)]
 pub struct CItemEffect {
     pub effect_id: EffectId,
     #[deref]
     pub raw: Arc<[u8]>,
 }
 
-#[derive(Encode, Decode)]
+#[derive(Debug, Clone, Encode, Decode)]
 pub struct ModuleCItemEffect {
     module_kind: ModuleKind,
     inner: CItemEffect,
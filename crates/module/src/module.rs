@@ -1,23 +1,30 @@
+pub mod access;
 pub mod config;
 pub mod db;
+pub mod net;
+pub mod secret;
 
 use std::any::Any;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
+use access::AccessSet;
 use async_trait::async_trait;
 use bfte_consensus_core::block::BlockRound;
 use bfte_consensus_core::citem::{CItemRaw, InputRaw, OutputRaw};
 use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_consensus_core::peer::PeerPubkey;
-use bfte_consensus_core::peer_set::PeerSet;
+use bfte_consensus_core::peer_set::WeightedPeerSet;
 use bfte_consensus_core::ver::{ConsensusVersion, ConsensusVersionMajor, ConsensusVersionMinor};
 use bfte_db::Database;
 use bfte_db::error::DbTxResult;
 use bfte_util_error::Whatever;
+use bincode::{Decode, Encode};
 use config::ModuleConfig;
-use db::{ModuleDatabase, ModuleWriteTransactionCtx};
+use db::{ConsensusTables, ModuleDatabase, ModuleWriteTransactionCtx};
 use derive_more::Deref;
+use net::ModuleNet;
+use secret::ModuleSecret;
 use snafu::Snafu;
 use tokio::sync::watch;
 
@@ -35,6 +42,11 @@ pub struct ModuleInitArgs {
     pub db: ModuleDatabase,
     pub module_consensus_version: ConsensusVersion,
     pub peer_pubkey: Option<PeerPubkey>,
+    /// Secret of this module instance, if the node has a root secret
+    pub secret: Option<ModuleSecret>,
+    /// Private messaging with other peers' instances of the module, if we are
+    /// a peer
+    pub net: Option<ModuleNet>,
     /// Only ConsensusCtrl module should use this
     #[doc(hidden)]
     pub modules_inits: BTreeMap<ModuleKind, DynModuleInit>,
@@ -49,11 +61,15 @@ impl ModuleInitArgs {
         module_consensus_version: ConsensusVersion,
         modules_inits: BTreeMap<ModuleKind, DynModuleInit>,
         peer_pubkey: Option<PeerPubkey>,
+        secret: Option<ModuleSecret>,
+        net: Option<ModuleNet>,
     ) -> Self {
         Self {
             db: ModuleDatabase::new(module_id, db),
             module_consensus_version,
             peer_pubkey,
+            secret,
+            net,
             modules_inits,
         }
     }
@@ -106,10 +122,47 @@ pub trait IModuleInit: Any {
         &self,
         args: ModuleInitArgs,
     ) -> ModuleInitResult<Arc<dyn IModule + Send + Sync + 'static>>;
+
+    /// Decode a citem of this module kind into a human readable form, e.g.
+    /// for the explorer
+    ///
+    /// Returns `None` if the module doesn't support it, or `citem` is
+    /// malformed.
+    fn decode_citem(&self, _citem: &CItemRaw) -> Option<serde_json::Value> {
+        None
+    }
 }
 
 pub type DynModule = Arc<dyn IModule + Send + Sync>;
 
+/// Outcome of processing a consensus item proposed by the module itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnCItemOutcome {
+    /// The citem was processed successfully
+    Accepted,
+    /// Processing the citem failed, and it was discarded
+    Rejected {
+        /// Formatted processing error
+        err: String,
+    },
+}
+
+/// Outcome of simulating processing of a consensus item against the current
+/// state, without committing anything
+#[derive(Debug, Clone, Encode, Decode)]
+pub enum CItemSimulation {
+    /// The citem would be processed successfully
+    Accepted {
+        /// Effects the citem would produce
+        effects: Vec<ModuleCItemEffect>,
+    },
+    /// The citem would be rejected
+    Rejected {
+        /// Formatted processing error
+        err: String,
+    },
+}
+
 #[async_trait]
 pub trait IModule: Any {
     /// Get receiver of consensus item proposals
@@ -118,18 +171,36 @@ pub trait IModule: Any {
     /// items it wishes to be published.
     async fn propose_citems_rx(&self) -> watch::Receiver<Vec<CItemRaw>>;
 
+    /// Called after a consensus item this peer's instance of the module
+    /// proposed was finalized and processed in `round`
+    ///
+    /// Called after the outcome was committed, outside of any database
+    /// transaction, so modules can e.g. retry, back off or report errors.
+    /// Delivery is best-effort: it can be missed if the node stops right
+    /// after committing, so the module's tables remain the source of truth.
+    async fn on_own_citem_processed(
+        &self,
+        _round: BlockRound,
+        _citem: &CItemRaw,
+        _outcome: OwnCItemOutcome,
+    ) {
+    }
+
     /// Process some consensus item
     ///
     /// If this function returns an error, it means that the transaction/citem
     /// is invalid and is ignored / should be discarded.
     ///
+    /// Any thresholds of peer votes should use the voting weights of
+    /// `peer_set`, which are the ones consensus itself uses.
+    ///
     /// Returns a list of effects caused by this citem
     fn process_citem(
         &self,
         dbtx: &ModuleWriteTransactionCtx,
         round: BlockRound,
         peer_pubkey: PeerPubkey,
-        peer_set: &PeerSet,
+        peer_set: &WeightedPeerSet,
         citem: &CItemRaw,
     ) -> DbTxResult<Vec<CItemEffect>, Whatever>;
 
@@ -151,6 +222,29 @@ pub trait IModule: Any {
         output: &OutputRaw,
     ) -> DbTxResult<Vec<CItemEffect>, Whatever>;
 
+    /// Tables, or parts of them, [`Self::process_citem`] can access when
+    /// processing `citem`
+    ///
+    /// Returning `None` (the default) means unknown, and such citems are
+    /// processed strictly sequentially. Citems with non-conflicting access
+    /// sets might be processed in parallel, so the declaration must be
+    /// complete, and opening a table outside of it panics.
+    /// It also implies that [`Self::process_effects`] does not access any of
+    /// these tables when there are no effects.
+    fn citem_access(&self, _citem: &CItemRaw) -> Option<AccessSet> {
+        None
+    }
+
+    /// Like [`Self::citem_access`], but for [`Self::process_input`]
+    fn input_access(&self, _input: &InputRaw) -> Option<AccessSet> {
+        None
+    }
+
+    /// Like [`Self::citem_access`], but for [`Self::process_output`]
+    fn output_access(&self, _output: &OutputRaw) -> Option<AccessSet> {
+        None
+    }
+
     /// Process all the effects generated by a processing of consensus items
     ///
     /// If this function returns an error, the whole transaction will be
@@ -159,7 +253,16 @@ pub trait IModule: Any {
     fn process_effects(
         &self,
         dbtx: &ModuleWriteTransactionCtx,
-        peer_set: &PeerSet,
+        round: BlockRound,
+        peer_set: &WeightedPeerSet,
         effects: &[ModuleCItemEffect],
     ) -> DbTxResult<(), Whatever>;
+
+    /// Tables holding the consensus state of the module
+    ///
+    /// Only these are included in state snapshots, so their content must be
+    /// identical on all peers that processed the same blocks. Tables with
+    /// node-local data, like own pending votes, settings or own software
+    /// version, must not be listed.
+    fn consensus_tables(&self) -> ConsensusTables;
 }
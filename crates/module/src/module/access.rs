@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bfte_consensus_core::bincode::CONSENSUS_BINCODE_CONFIG;
+use redb_bincode::TableDefinition;
+use redb_bincode::redb::TableHandle as _;
+
+/// Set of module tables, or parts of them, processing of a consensus item can
+/// access
+///
+/// Used by the node to process consensus items with non-conflicting access in
+/// parallel, with a result identical to processing them sequentially.
+///
+/// A table (as named by the module, without a prefix) is either accessed
+/// whole, or only in parts identified by keys. Keys are opaque to the node,
+/// and don't have to be the keys of the table: any partition of the table
+/// works, as long as processing accessing different parts of it gives the
+/// same result in any order. Tables both read and written must be included,
+/// and a declaration must cover everything processing could possibly touch,
+/// including e.g. refreshing own proposals.
+///
+/// The database allows only one handle to a table to be open at a time within
+/// a transaction, so consensus items accessing different parts of the same
+/// table are still processed one after another, though without waiting for
+/// the ones accessing other tables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessSet {
+    tables: BTreeMap<String, TableAccess>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TableAccess {
+    Whole,
+    Keys(BTreeSet<Vec<u8>>),
+}
+
+impl TableAccess {
+    fn conflicts_with(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Keys(keys), Self::Keys(other_keys)) => !keys.is_disjoint(other_keys),
+            _ => true,
+        }
+    }
+
+    fn extend(&mut self, other: &Self) {
+        match (&mut *self, other) {
+            (Self::Keys(keys), Self::Keys(other_keys)) => keys.extend(other_keys.iter().cloned()),
+            _ => *self = Self::Whole,
+        }
+    }
+}
+
+impl AccessSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_table(mut self, table_name: impl Into<String>) -> Self {
+        self.insert_table(table_name);
+        self
+    }
+
+    pub fn insert_table(&mut self, table_name: impl Into<String>) {
+        self.tables.insert(table_name.into(), TableAccess::Whole);
+    }
+
+    /// Add the whole `table_def`
+    pub fn with<K, V>(self, table_def: &TableDefinition<'_, K, V>) -> Self
+    where
+        K: bincode::Encode + bincode::Decode<()>,
+        V: bincode::Encode + bincode::Decode<()>,
+    {
+        self.with_table(table_def.as_raw().name())
+    }
+
+    /// Add the part of `table_def` identified by `key`
+    pub fn with_key<K, V>(
+        mut self,
+        table_def: &TableDefinition<'_, K, V>,
+        key: &impl bincode::Encode,
+    ) -> Self
+    where
+        K: bincode::Encode + bincode::Decode<()>,
+        V: bincode::Encode + bincode::Decode<()>,
+    {
+        let key = bincode::encode_to_vec(key, CONSENSUS_BINCODE_CONFIG).expect("Can't fail");
+        self.extend_table(
+            table_def.as_raw().name(),
+            &TableAccess::Keys(BTreeSet::from([key])),
+        );
+        self
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = &str> {
+        self.tables.keys().map(String::as_str)
+    }
+
+    /// Could processing with `self` and `other` access affect each other
+    pub fn conflicts_with(&self, other: &Self) -> bool {
+        self.tables.iter().any(|(table_name, access)| {
+            other
+                .tables
+                .get(table_name)
+                .is_some_and(|other_access| access.conflicts_with(other_access))
+        })
+    }
+
+    /// Add everything `other` accesses
+    pub fn extend(&mut self, other: &Self) {
+        for (table_name, access) in &other.tables {
+            self.extend_table(table_name, access);
+        }
+    }
+
+    fn extend_table(&mut self, table_name: &str, access: &TableAccess) {
+        match self.tables.get_mut(table_name) {
+            Some(existing) => existing.extend(access),
+            None => {
+                self.tables.insert(table_name.to_owned(), access.clone());
+            }
+        }
+    }
+}
+
+impl<S> FromIterator<S> for AccessSet
+where
+    S: Into<String>,
+{
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        Self {
+            tables: iter
+                .into_iter()
+                .map(|table_name| (table_name.into(), TableAccess::Whole))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,37 @@
+use bfte_util_db::def_table;
+
+use super::AccessSet;
+
+def_table! {
+    votes: (u8, u32) => u64
+}
+
+def_table! {
+    values: u8 => u64
+}
+
+#[test]
+fn access_set_conflicts() {
+    let votes_1 = AccessSet::new().with_key(&votes::TABLE, &1u8);
+    let votes_2 = AccessSet::new().with_key(&votes::TABLE, &2u8);
+    let values_1 = AccessSet::new().with_key(&values::TABLE, &1u8);
+
+    assert!(votes_1.conflicts_with(&votes_1));
+    assert!(!votes_1.conflicts_with(&votes_2));
+    assert!(!votes_1.conflicts_with(&values_1));
+
+    // Whole table conflicts with any part of it
+    let votes = AccessSet::new().with(&votes::TABLE);
+    assert!(votes.conflicts_with(&votes_2));
+    assert!(votes_2.conflicts_with(&votes));
+    assert!(!votes.conflicts_with(&values_1));
+
+    let mut combined = votes_1.clone();
+    combined.extend(&values_1);
+    assert!(combined.conflicts_with(&values_1));
+    assert!(!combined.conflicts_with(&votes_2));
+    assert_eq!(combined.tables().collect::<Vec<_>>(), ["values", "votes"]);
+
+    combined.extend(&votes);
+    assert!(combined.conflicts_with(&votes_2));
+}
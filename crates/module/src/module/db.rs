@@ -1,11 +1,121 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 
+use bfte_consensus_core::block::BlockRound;
 use bfte_consensus_core::module::ModuleId;
+use bfte_consensus_core::snapshot::StateSnapshotTable;
 use bfte_db::Database;
 use bfte_db::ctx::WriteTransactionCtx;
 pub use bfte_db::error::{DbError, DbResult, DbTxResult};
-use redb_bincode::redb::{TableError, TableHandle as _};
-use redb_bincode::{ReadOnlyTable, ReadTransaction, ReadableTable, Table, TableDefinition};
+use bfte_util_db::versioned::{self, HistoryRound};
+use redb_bincode::redb::{self, ReadableTable as _, TableError, TableHandle as _};
+use redb_bincode::{
+    ReadOnlyTable, ReadTransaction, ReadableTable, StorageError, Table, TableDefinition,
+};
+
+use super::access::AccessSet;
+
+/// Prefix of all module table names
+const MODULE_TABLE_PREFIX: &str = "module_";
+
+/// Names of module tables holding the module's consensus state
+///
+/// See [`super::IModule::consensus_tables`].
+#[derive(Debug, Clone, Default)]
+pub struct ConsensusTables(BTreeSet<String>);
+
+impl ConsensusTables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<K, V>(mut self, table_def: &TableDefinition<'_, K, V>) -> Self
+    where
+        K: bincode::Encode + bincode::Decode<()>,
+        V: bincode::Encode + bincode::Decode<()>,
+    {
+        self.0.insert(table_def.as_raw().name().to_owned());
+        self
+    }
+
+    fn contains(&self, table_name: &str) -> bool {
+        self.0.contains(table_name)
+    }
+}
+
+/// Split `full_name` of a module table into the module id and the table name
+/// as defined by the module
+fn parse_module_table_full_name(full_name: &str) -> Option<(ModuleId, &str)> {
+    let rest = full_name.strip_prefix(MODULE_TABLE_PREFIX)?;
+    let (module_id, table_name) = rest.split_once('_')?;
+
+    if module_id.is_empty() || !module_id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    Some((ModuleId::new(module_id.parse().ok()?), table_name))
+}
+
+/// Dump raw content of all the consensus tables of all modules
+///
+/// `consensus_tables` come from [`super::IModule::consensus_tables`] of each
+/// module.
+pub fn dump_modules_tables(
+    dbtx: &ReadTransaction,
+    consensus_tables: &BTreeMap<ModuleId, ConsensusTables>,
+) -> DbResult<BTreeMap<String, StateSnapshotTable>> {
+    let dbtx = dbtx.as_raw();
+    let mut tables = BTreeMap::new();
+
+    for handle in dbtx.list_tables()? {
+        let name = handle.name().to_owned();
+        let Some((module_id, table_name)) = parse_module_table_full_name(&name) else {
+            continue;
+        };
+        if !consensus_tables
+            .get(&module_id)
+            .is_some_and(|tables| tables.contains(table_name))
+        {
+            continue;
+        }
+
+        let tbl = dbtx.open_table(redb::TableDefinition::<&[u8], &[u8]>::new(&name))?;
+        let mut entries = vec![];
+        for kv in tbl.iter()? {
+            let (k, v) = kv?;
+            entries.push((k.value().to_vec(), v.value().to_vec()));
+        }
+        tables.insert(name, entries);
+    }
+
+    Ok(tables)
+}
+
+/// Replace content of module tables with `tables` of a state snapshot
+///
+/// Module tables not in `tables`, like ones with node-local data, are left
+/// untouched.
+pub fn restore_modules_tables(
+    dbtx: &WriteTransactionCtx,
+    tables: &BTreeMap<String, StateSnapshotTable>,
+) -> DbResult<()> {
+    let dbtx = dbtx.as_raw();
+
+    for (name, entries) in tables {
+        if parse_module_table_full_name(name).is_none() {
+            // Snapshots are attested, but let's not let them mess with anything else
+            continue;
+        }
+        let table_def = redb::TableDefinition::<&[u8], &[u8]>::new(name);
+        dbtx.delete_table(table_def)?;
+        let mut tbl = dbtx.open_table(table_def)?;
+        for (k, v) in entries {
+            tbl.insert(k.as_slice(), v.as_slice())?;
+        }
+    }
+
+    Ok(())
+}
 
 /// A wrapper around [`Database`] that encapsulates module's tables
 ///
@@ -122,15 +232,63 @@ impl ModuleDatabase {
     }
 }
 
+impl ModuleDatabase {
+    /// Like [`Self::read_with`], but reading versioned tables as of the end of
+    /// `round`
+    pub async fn read_at_round_with<T>(
+        &self,
+        round: BlockRound,
+        f: impl FnOnce(&'_ ModuleHistoricalReadTransaction) -> DbResult<T>,
+    ) -> DbResult<T> {
+        self.read_with(|dbtx| f(&ModuleHistoricalReadTransaction { inner: dbtx, round }))
+            .await
+    }
+
+    /// Like [`Self::read_with_expect`], but reading versioned tables as of the
+    /// end of `round`
+    pub async fn read_at_round_with_expect<T>(
+        &self,
+        round: BlockRound,
+        f: impl FnOnce(&'_ ModuleHistoricalReadTransaction) -> DbResult<T>,
+    ) -> T {
+        self.read_with_expect(|dbtx| f(&ModuleHistoricalReadTransaction { inner: dbtx, round }))
+            .await
+    }
+}
+
 pub struct ModuleWriteTransactionCtx<'a> {
     module_id: ModuleId,
     inner: &'a WriteTransactionCtx,
+    /// Declared access the module must stay within, if any
+    access: Option<&'a AccessSet>,
 }
 
 impl<'s> ModuleWriteTransactionCtx<'s> {
     pub fn new(module_id: ModuleId, inner: &'s WriteTransactionCtx) -> Self {
-        Self { module_id, inner }
+        Self {
+            module_id,
+            inner,
+            access: None,
+        }
     }
+
+    /// Like [`Self::new`], but opening any table not in the declared `access`
+    /// panics
+    ///
+    /// Used when processing a consensus item in parallel with others, where
+    /// undeclared access would make the result depend on the timing.
+    pub fn new_with_access(
+        module_id: ModuleId,
+        inner: &'s WriteTransactionCtx,
+        access: &'s AccessSet,
+    ) -> Self {
+        Self {
+            module_id,
+            inner,
+            access: Some(access),
+        }
+    }
+
     pub fn open_table<K, V>(
         &self,
         table_def: &TableDefinition<'_, K, V>,
@@ -139,14 +297,27 @@ impl<'s> ModuleWriteTransactionCtx<'s> {
         K: bincode::Encode + bincode::Decode<()>,
         V: bincode::Encode + bincode::Decode<()>,
     {
-        self.inner.open_table(&TableDefinition::new(&format!(
-            "module_{}_{}",
-            self.module_id,
-            table_def.as_raw().name()
-        )))
+        self.inner
+            .open_table(&TableDefinition::new(&self.table_name(table_def)))
+    }
+
+    fn table_name<K, V>(&self, table_def: &TableDefinition<'_, K, V>) -> String
+    where
+        K: bincode::Encode + bincode::Decode<()>,
+        V: bincode::Encode + bincode::Decode<()>,
+    {
+        let name = table_def.as_raw().name();
+        if let Some(access) = self.access {
+            assert!(
+                access.tables().any(|table_name| table_name == name),
+                "Module {} accessed table {name} outside of its declared access",
+                self.module_id
+            );
+        }
+        format!("{MODULE_TABLE_PREFIX}{}_{name}", self.module_id)
     }
 
-    pub fn on_commit(&self, f: impl FnOnce() + 'static) {
+    pub fn on_commit(&self, f: impl FnOnce() + Send + 'static) {
         self.inner.on_commit(f);
     }
 }
@@ -169,13 +340,67 @@ impl<'s> ModuleReadTransaction<'s> {
         V: bincode::Encode + bincode::Decode<()>,
     {
         self.inner.open_table(&TableDefinition::new(&format!(
-            "module_{}_{}",
+            "{MODULE_TABLE_PREFIX}{}_{}",
             self.module_id,
             table_def.as_raw().name()
         )))
     }
 }
 
+/// A read view of module's versioned tables as of the end of a past round
+///
+/// Only tables defined as `versioned` (see [`bfte_util_db::def_table`]) keep
+/// their history, and only since they were made versioned.
+pub struct ModuleHistoricalReadTransaction<'a> {
+    inner: &'a ModuleReadTransaction<'a>,
+    round: BlockRound,
+}
+
+impl ModuleHistoricalReadTransaction<'_> {
+    pub fn round(&self) -> BlockRound {
+        self.round
+    }
+
+    /// Open a versioned table by its `HISTORY_TABLE` definition
+    pub fn open_table<K, V>(
+        &self,
+        history_table_def: &TableDefinition<'_, (K, HistoryRound), Option<V>>,
+    ) -> Result<HistoricalTable<K, V>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode<()>,
+        V: bincode::Encode + bincode::Decode<()>,
+    {
+        Ok(HistoricalTable {
+            history_tbl: self.inner.open_table(history_table_def)?,
+            round: self.round.to_number().into(),
+        })
+    }
+}
+
+/// A versioned table as of the end of a past round
+pub struct HistoricalTable<K, V>
+where
+    K: bincode::Encode + bincode::Decode<()>,
+    V: bincode::Encode + bincode::Decode<()>,
+{
+    history_tbl: ReadOnlyTable<(K, HistoryRound), Option<V>>,
+    round: HistoryRound,
+}
+
+impl<K, V> HistoricalTable<K, V>
+where
+    K: bincode::Encode + bincode::Decode<()> + Clone + Ord,
+    V: bincode::Encode + bincode::Decode<()>,
+{
+    pub fn get(&self, key: &K) -> Result<Option<V>, StorageError> {
+        versioned::get_at(&self.history_tbl, key, self.round)
+    }
+
+    pub fn entries(&self) -> Result<BTreeMap<K, V>, StorageError> {
+        versioned::entries_at(&self.history_tbl, self.round)
+    }
+}
+
 pub trait ModuleReadableTransaction<'s> {
     type Table<K, V>: ReadableTable<K, V>
     where
@@ -206,7 +431,7 @@ impl<'s> ModuleReadableTransaction<'s> for ModuleReadTransaction<'s> {
         V: bincode::Encode + bincode::Decode<()>,
     {
         self.inner.open_table(&TableDefinition::new(&format!(
-            "module_{}_{}",
+            "{MODULE_TABLE_PREFIX}{}_{}",
             self.module_id,
             table_def.as_raw().name()
         )))
@@ -228,10 +453,10 @@ impl<'s> ModuleReadableTransaction<'s> for ModuleWriteTransactionCtx<'s> {
         K: bincode::Encode + bincode::Decode<()>,
         V: bincode::Encode + bincode::Decode<()>,
     {
-        self.inner.open_table(&TableDefinition::new(&format!(
-            "module_{}_{}",
-            self.module_id,
-            table_def.as_raw().name()
-        )))
+        self.inner
+            .open_table(&TableDefinition::new(&self.table_name(table_def)))
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::module::ModuleId;
+use bfte_db::Database;
+use bfte_util_db::{def_table, versioned};
+
+use super::{DbResult, ModuleDatabase};
+
+def_table! {
+    versioned values: u8 => u64
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn versioned_table_read_at_round() -> DbResult<()> {
+    let db = ModuleDatabase::new(ModuleId::new(1), Arc::new(Database::new_in_memory().await?));
+
+    for (round, key, value) in [
+        (1u64, 3, Some(10)),
+        (3, 3, Some(11)),
+        (3, 4, Some(20)),
+        (5, 3, None),
+    ] {
+        db.write_with(|dbtx| {
+            let mut tbl = dbtx.open_table(&values::TABLE)?;
+            let mut history_tbl = dbtx.open_table(&values::HISTORY_TABLE)?;
+            match value {
+                Some(value) => {
+                    versioned::insert(&mut tbl, &mut history_tbl, round.into(), &key, &value)?
+                }
+                None => versioned::remove(&mut tbl, &mut history_tbl, round.into(), &key)?,
+            }
+            Ok(())
+        })
+        .await?;
+    }
+
+    for (round, expected) in [
+        (0u64, vec![]),
+        (1, vec![(3, 10)]),
+        (2, vec![(3, 10)]),
+        (3, vec![(3, 11), (4, 20)]),
+        (5, vec![(4, 20)]),
+        (100, vec![(4, 20)]),
+    ] {
+        let expected: BTreeMap<u8, u64> = expected.into_iter().collect();
+        let (value, entries) = db
+            .read_at_round_with_expect(BlockRound::from(round), |dbtx| {
+                let tbl = dbtx.open_table(&values::HISTORY_TABLE)?;
+                Ok((tbl.get(&3)?, tbl.entries()?))
+            })
+            .await;
+
+        assert_eq!(value, expected.get(&3).copied(), "round {round}");
+        assert_eq!(entries, expected, "round {round}");
+    }
+
+    Ok(())
+}
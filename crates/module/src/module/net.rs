@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_util_error::WhateverResult;
+
+/// Maximum size of a [`ModuleMessage::payload`]
+pub const MODULE_MESSAGE_MAX_PAYLOAD_LEN: usize = 64 * 1024;
+
+/// Id of a received [`ModuleMessage`], used to acknowledge it
+pub type ModuleMessageId = u64;
+
+/// A private message from another peer's instance of the same module
+#[derive(Debug, Clone)]
+pub struct ModuleMessage {
+    pub id: ModuleMessageId,
+    /// Peer that sent (and signed) the message
+    pub sender: PeerPubkey,
+    pub payload: Vec<u8>,
+}
+
+/// Private point-to-point messaging between instances of the same module on
+/// different peers
+///
+/// Messages are signed by the sender's peer key and delivered over the
+/// (encrypted) p2p connections, so only the recipient can read them and the
+/// sender can't be spoofed.
+///
+/// Delivery survives restarts: an outgoing message is persisted and retried
+/// until the recipient stores it, and a received message is returned by
+/// [`IModuleNet::recv`] until it is [`IModuleNet::ack`]ed. Messages from the
+/// same sender are received in the order they were sent.
+#[async_trait]
+pub trait IModuleNet {
+    /// Queue `payload` for delivery to the `recipient`'s instance of the module
+    async fn send(&self, recipient: PeerPubkey, payload: Vec<u8>) -> WhateverResult<()>;
+
+    /// Wait for the oldest received message not acknowledged yet
+    async fn recv(&self) -> ModuleMessage;
+
+    /// Acknowledge processing of a received message, so it is not returned
+    /// by [`IModuleNet::recv`] again
+    async fn ack(&self, id: ModuleMessageId);
+}
+
+pub type ModuleNet = Arc<dyn IModuleNet + Send + Sync + 'static>;
@@ -0,0 +1,55 @@
+use bfte_consensus_core::peer::{PeerPubkey, PeerSeckey};
+use bfte_consensus_core::peer_encrypted::PeerEncryptionSeckey;
+use bfte_consensus_core::signed::{Signable, Signed};
+use bfte_derive_secret::{ChildId, DeriveableSecret};
+
+const SIGNING_SECKEY_CHILD_ID: ChildId = ChildId::new(0);
+const CHILD_SECRETS_CHILD_ID: ChildId = ChildId::new(1);
+const ENCRYPTION_SECKEY_CHILD_ID: ChildId = ChildId::new(2);
+
+/// Secret of a module instance, derived by the node from its root secret
+///
+/// Derivation path is stable for a given (module kind, module id), so
+/// modules will get the same key material after a restart, or after
+/// restoring the root secret from a backup.
+#[derive(Clone, Copy, Debug)]
+pub struct ModuleSecret(DeriveableSecret);
+
+impl ModuleSecret {
+    pub fn new(secret: DeriveableSecret) -> Self {
+        Self(secret)
+    }
+
+    /// Derive a secret for a module-specific purpose (e.g. a DKG share)
+    pub fn derive(self, child_id: ChildId) -> DeriveableSecret {
+        self.0.derive(CHILD_SECRETS_CHILD_ID).derive(child_id)
+    }
+
+    /// Key for signing module-level messages
+    ///
+    /// Unlike the peer key, it is known only to this module.
+    pub fn signing_seckey(self) -> PeerSeckey {
+        self.0.derive(SIGNING_SECKEY_CHILD_ID).reveal_bytes().into()
+    }
+
+    pub fn signing_pubkey(self) -> PeerPubkey {
+        self.signing_seckey().pubkey()
+    }
+
+    /// Key for decrypting [`PeerEncrypted`] payloads addressed to this module
+    ///
+    /// Its public key must be published on-chain with
+    /// [`PeerEncryptionSeckey::registration`], for other peers to encrypt to.
+    ///
+    /// [`PeerEncrypted`]: bfte_consensus_core::peer_encrypted::PeerEncrypted
+    pub fn encryption_seckey(self) -> PeerEncryptionSeckey {
+        PeerEncryptionSeckey::from_secret_bytes(
+            self.0.derive(ENCRYPTION_SECKEY_CHILD_ID).reveal_bytes(),
+        )
+    }
+
+    /// Sign a module-level message with [`Self::signing_seckey`]
+    pub fn sign<T: Signable>(self, inner: T) -> Signed<T> {
+        Signed::new_sign(inner, self.signing_seckey())
+    }
+}
@@ -1,7 +1,9 @@
+use bfte_consensus_core::bls::BlsPubkey;
 use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_consensus_core::peer::PeerPubkey;
 use bfte_consensus_core::peer_set::PeerSet;
 use bfte_consensus_core::ver::ConsensusVersion;
+use bfte_consensus_core::weight::PeerWeight;
 use bfte_module::effect::{EffectId, EffectKind};
 use bfte_module::kinds::MODULE_KIND_CONSENSUS_CTRL;
 use bincode::{Decode, Encode};
@@ -32,6 +34,13 @@ impl EffectKind for RemovePeerEffect {
 #[derive(Debug, Clone, Encode, Decode, Serialize, Deserialize)]
 pub struct ConsensusParamsChange {
     pub peer_set: PeerSet,
+    /// BLS keys of all peers in `peer_set` (in the same order), or empty if
+    /// not all of them registered one
+    pub bls_pubkeys: Vec<BlsPubkey>,
+    /// Voting weights of all peers in `peer_set` (in the same order)
+    pub peer_weights: Vec<PeerWeight>,
+    /// Maximum length of a block payload a leader can propose
+    pub max_block_payload_len: u32,
 }
 
 impl EffectKind for ConsensusParamsChange {
@@ -62,3 +71,35 @@ impl EffectKind for AddModuleEffect {
     const MODULE_KIND: ModuleKind = KIND;
     const EFFECT_ID: EffectId = EffectId::new(4);
 }
+
+#[derive(Debug, Clone, Encode, Decode, Serialize, Deserialize)]
+pub struct SetPeerWeightEffect {
+    pub peer: PeerPubkey,
+    pub weight: PeerWeight,
+}
+
+impl EffectKind for SetPeerWeightEffect {
+    const MODULE_KIND: ModuleKind = KIND;
+    const EFFECT_ID: EffectId = EffectId::new(5);
+}
+
+#[derive(Debug, Clone, Encode, Decode, Serialize, Deserialize)]
+pub struct RotateKeyEffect {
+    pub old_pubkey: PeerPubkey,
+    pub new_pubkey: PeerPubkey,
+}
+
+impl EffectKind for RotateKeyEffect {
+    const MODULE_KIND: ModuleKind = KIND;
+    const EFFECT_ID: EffectId = EffectId::new(6);
+}
+
+#[derive(Debug, Clone, Encode, Decode, Serialize, Deserialize)]
+pub struct SetMaxBlockPayloadLenEffect {
+    pub max_block_payload_len: u32,
+}
+
+impl EffectKind for SetMaxBlockPayloadLenEffect {
+    const MODULE_KIND: ModuleKind = KIND;
+    const EFFECT_ID: EffectId = EffectId::new(7);
+}
@@ -1,8 +1,12 @@
 use bfte_consensus_core::bincode::CONSENSUS_BINCODE_CONFIG;
+use bfte_consensus_core::bls::BlsKeyRegistration;
 use bfte_consensus_core::citem::CItemRaw;
+use bfte_consensus_core::equivocation::EquivocationEvidence;
+use bfte_consensus_core::key_rotation::SignedKeyRotation;
 use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_consensus_core::peer::PeerPubkey;
 use bfte_consensus_core::ver::{ConsensusVersion, ConsensusVersionMinor};
+use bfte_consensus_core::weight::PeerWeight;
 use bfte_util_bincode::decode_whole;
 use bfte_util_error::WhateverResult;
 use bincode::{Decode, Encode};
@@ -21,6 +25,24 @@ pub enum ConsensusCtrlCitem {
         module_id: ModuleId,
         minor_consensus_version: ConsensusVersionMinor,
     },
+    /// Report a peer that signed two different blocks in the same round
+    ReportEquivocation(EquivocationEvidence),
+    /// Register a BLS key of the submitting peer
+    RegisterBlsKey(BlsKeyRegistration),
+    VoteSetPeerWeight {
+        peer: PeerPubkey,
+        weight: PeerWeight,
+    },
+    /// Hand over the place of a peer in the consensus to a new key
+    ///
+    /// If the old key had a BLS key registered, `bls_registration` of the new
+    /// key is required, so that BLS aggregation stays enabled.
+    RotateKey {
+        rotation: SignedKeyRotation,
+        bls_registration: Option<BlsKeyRegistration>,
+    },
+    /// Vote to change the maximum length of a block payload
+    VoteSetMaxBlockPayloadLen(u32),
 }
 
 impl ConsensusCtrlCitem {
@@ -2,6 +2,7 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use bfte_consensus_core::citem::CItemRaw;
 use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_consensus_core::peer_set::PeerSet;
 use bfte_consensus_core::ver::{ConsensusVersion, ConsensusVersionMajor, ConsensusVersionMinor};
@@ -15,6 +16,7 @@ use tokio::sync::watch;
 use tracing::debug;
 
 use super::ConsensusCtrlModule;
+use crate::citem::ConsensusCtrlCitem;
 use crate::tables::{self, modules_configs};
 use crate::{CURRENT_VERSION_MAJOR, CURRENT_VERSION_MINOR, KIND, LOG_TARGET};
 
@@ -134,4 +136,8 @@ impl IModuleInit for ConsensusCtrlModuleInit {
 
         Ok(Arc::new(module))
     }
+
+    fn decode_citem(&self, citem: &CItemRaw) -> Option<serde_json::Value> {
+        serde_json::to_value(ConsensusCtrlCitem::decode_from_raw(citem).ok()?).ok()
+    }
 }
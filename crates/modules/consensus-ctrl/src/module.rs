@@ -2,29 +2,35 @@ use std::collections::BTreeMap;
 
 use async_trait::async_trait;
 use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::bls::{BlsKeyRegistration, BlsPubkey};
 use bfte_consensus_core::citem::{CItemRaw, InputRaw, OutputRaw};
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::equivocation::EquivocationEvidence;
+use bfte_consensus_core::key_rotation::SignedKeyRotation;
 use bfte_consensus_core::module::{ModuleId, ModuleKind};
-use bfte_consensus_core::num_peers::ToNumPeers;
 use bfte_consensus_core::peer::PeerPubkey;
-use bfte_consensus_core::peer_set::PeerSet;
+use bfte_consensus_core::peer_set::{PeerSet, WeightedPeerSet};
 use bfte_consensus_core::ver::{ConsensusVersion, ConsensusVersionMinor};
+use bfte_consensus_core::weight::{DEFAULT_PEER_WEIGHT, PeerWeight, TotalWeight};
 use bfte_db::error::TxSnafu;
 use bfte_module::effect::{CItemEffect, EffectKindExt, ModuleCItemEffect};
+use bfte_module::module::access::AccessSet;
 use bfte_module::module::config::ModuleConfig;
 use bfte_module::module::db::{
-    DbResult, DbTxResult, ModuleDatabase, ModuleReadableTransaction, ModuleWriteTransactionCtx,
+    ConsensusTables, DbResult, DbTxResult, ModuleDatabase, ModuleReadableTransaction,
+    ModuleWriteTransactionCtx,
 };
 use bfte_module::module::{DynModuleInit, IModule, ModuleSupportedConsensusVersions};
 use bfte_util_db::redb_bincode::{AccessGuard, ReadableTable as _};
 use bfte_util_error::{Whatever, WhateverResult};
 use snafu::{OptionExt as _, ResultExt as _, whatever};
 use tokio::sync::watch;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::citem::ConsensusCtrlCitem;
 use crate::effects::{
     AddModuleEffect, AddPeerEffect, ConsensusParamsChange, ModuleVersionUpgradeEffect,
-    RemovePeerEffect,
+    RemovePeerEffect, RotateKeyEffect, SetMaxBlockPayloadLenEffect, SetPeerWeightEffect,
 };
 use crate::{LOG_TARGET, tables};
 
@@ -51,6 +57,17 @@ impl ConsensusCtrlModule {
             .await
     }
 
+    /// Current peer set along with the voting weights of its peers
+    pub async fn get_weighted_peer_set(&self) -> WeightedPeerSet {
+        self.db
+            .read_with_expect(|dbtx| {
+                let peer_set = Self::get_peer_set_dbtx(dbtx)?;
+                let peer_weights = self.get_peer_weights_tx(dbtx, &peer_set)?;
+                Ok(WeightedPeerSet::new(peer_set, peer_weights))
+            })
+            .await
+    }
+
     /// Get modules configs without creating an instance of `ConsensusCtrl`
     /// itself
     ///
@@ -155,6 +172,110 @@ impl ConsensusCtrlModule {
         Ok(())
     }
 
+    pub async fn set_pending_set_peer_weight_vote(
+        &self,
+        peer: PeerPubkey,
+        weight: PeerWeight,
+    ) -> WhateverResult<()> {
+        if self.peer_pubkey.is_none() {
+            whatever!("Cannot cast votes: not a voting peer")
+        }
+        if weight == 0 {
+            whatever!("Peer weight must be positive; vote to remove the peer instead")
+        }
+        if !self.get_peer_set().await.contains(&peer) {
+            whatever!("Not a peer: {}", peer);
+        }
+
+        self.db
+            .write_with_expect(|dbtx| {
+                let mut tbl = dbtx.open_table(&tables::pending_set_peer_weight_vote::TABLE)?;
+                tbl.insert(&(), &(peer, weight))?;
+                Ok(())
+            })
+            .await;
+        self.refresh_consensus_proposals().await;
+        Ok(())
+    }
+
+    pub async fn set_pending_set_max_block_payload_len_vote(
+        &self,
+        max_block_payload_len: u32,
+    ) -> WhateverResult<()> {
+        if self.peer_pubkey.is_none() {
+            whatever!("Cannot cast votes: not a voting peer")
+        }
+        if max_block_payload_len < ConsensusParams::MIN_MAX_BLOCK_PAYLOAD_LEN {
+            whatever!(
+                "Maximum block payload length must be at least {}",
+                ConsensusParams::MIN_MAX_BLOCK_PAYLOAD_LEN
+            );
+        }
+
+        self.db
+            .write_with_expect(|dbtx| {
+                let mut tbl =
+                    dbtx.open_table(&tables::pending_set_max_block_payload_len_vote::TABLE)?;
+                tbl.insert(&(), &max_block_payload_len)?;
+                Ok(())
+            })
+            .await;
+        self.refresh_consensus_proposals().await;
+        Ok(())
+    }
+
+    /// Queue rotation of our key, signed by our current key
+    ///
+    /// `bls_registration` of the new key is required if we have a BLS key
+    /// registered, as otherwise BLS aggregation would get disabled for all
+    /// peers.
+    pub async fn set_pending_key_rotation(
+        &self,
+        rotation: SignedKeyRotation,
+        bls_registration: Option<BlsKeyRegistration>,
+    ) -> WhateverResult<()> {
+        let Some(peer_pubkey) = self.peer_pubkey else {
+            whatever!("Cannot rotate key: not a voting peer")
+        };
+        rotation.verify().whatever_context("Invalid key rotation")?;
+        if rotation.old_pubkey != peer_pubkey {
+            whatever!("Can only rotate own key: {}", rotation.old_pubkey);
+        }
+        let peer_set = self.get_peer_set().await;
+        if peer_set.contains(&rotation.new_pubkey) {
+            whatever!("Already a peer: {}", rotation.new_pubkey);
+        }
+        if self
+            .db
+            .read_with_expect(|dbtx| {
+                Ok(dbtx
+                    .open_table(&tables::applied_key_rotations::TABLE)?
+                    .get(&(rotation.old_pubkey, rotation.new_pubkey))?
+                    .is_some())
+            })
+            .await
+        {
+            whatever!("Already rotated to: {}", rotation.new_pubkey);
+        }
+        if let Some(bls_registration) = bls_registration {
+            bls_registration
+                .verify(rotation.new_pubkey)
+                .whatever_context("Invalid BLS key registration of the new key")?;
+        } else if self.get_bls_pubkeys().await.contains_key(&peer_pubkey) {
+            whatever!("BLS key registration of the new key is required");
+        }
+
+        self.db
+            .write_with_expect(|dbtx| {
+                let mut tbl = dbtx.open_table(&tables::pending_key_rotation::TABLE)?;
+                tbl.insert(&(), &(rotation, bls_registration))?;
+                Ok(())
+            })
+            .await;
+        self.refresh_consensus_proposals().await;
+        Ok(())
+    }
+
     pub async fn set_pending_add_module_vote(
         &self,
         module_kind: ModuleKind,
@@ -249,6 +370,173 @@ impl ConsensusCtrlModule {
             .await
     }
 
+    pub async fn get_set_peer_weight_votes(
+        &self,
+    ) -> BTreeMap<PeerPubkey, (PeerPubkey, PeerWeight)> {
+        self.db
+            .read_with_expect(|dbtx| {
+                let tbl = dbtx.open_table(&tables::set_peer_weight_votes::TABLE)?;
+                tbl.range(..)?
+                    .map(|kv| {
+                        let (voter, voted_for) = kv?;
+                        Ok((voter.value(), voted_for.value()))
+                    })
+                    .collect()
+            })
+            .await
+    }
+
+    pub async fn get_set_max_block_payload_len_votes(&self) -> BTreeMap<PeerPubkey, u32> {
+        self.db
+            .read_with_expect(|dbtx| {
+                let tbl = dbtx.open_table(&tables::set_max_block_payload_len_votes::TABLE)?;
+                tbl.range(..)?
+                    .map(|kv| {
+                        let (voter, voted_for) = kv?;
+                        Ok((voter.value(), voted_for.value()))
+                    })
+                    .collect()
+            })
+            .await
+    }
+
+    pub async fn get_max_block_payload_len(&self) -> u32 {
+        self.db
+            .read_with_expect(|dbtx| self.get_max_block_payload_len_tx(dbtx))
+            .await
+    }
+
+    /// Voting weights of all the peers in the current peer set
+    pub async fn get_peer_weights(&self) -> BTreeMap<PeerPubkey, PeerWeight> {
+        self.db
+            .read_with_expect(|dbtx| {
+                let peer_set = self.get_peer_set_tx(dbtx)?;
+                let peer_weights = self.get_peer_weights_tx(dbtx, &peer_set)?;
+                Ok(peer_set.into_iter().zip(peer_weights).collect())
+            })
+            .await
+    }
+
+    /// Queue equivocation evidence to be reported in consensus items
+    ///
+    /// Invalid evidence, and evidence already reported, is ignored.
+    pub async fn report_equivocations(&self, evidence: Vec<EquivocationEvidence>) {
+        if self.peer_pubkey.is_none() {
+            // Only voting peers can submit consensus items
+            return;
+        }
+
+        self.db
+            .write_with_expect(|dbtx| {
+                let tbl_reported = dbtx.open_table(&tables::equivocations::TABLE)?;
+                let mut tbl_pending =
+                    dbtx.open_table(&tables::pending_equivocation_reports::TABLE)?;
+
+                for evidence in evidence {
+                    if evidence.verify().is_err() {
+                        continue;
+                    }
+                    let key = (evidence.peer_pubkey, evidence.round());
+                    if tbl_reported.get(&key)?.is_some() {
+                        continue;
+                    }
+                    tbl_pending.insert(&key, &evidence)?;
+                }
+                Ok(())
+            })
+            .await;
+
+        self.refresh_consensus_proposals().await;
+    }
+
+    /// Equivocation evidence reported in finalized consensus items
+    pub async fn get_equivocations(&self) -> Vec<EquivocationEvidence> {
+        self.db
+            .read_with_expect(|dbtx| {
+                let tbl = dbtx.open_table(&tables::equivocations::TABLE)?;
+                tbl.range(..)?
+                    .map(|kv| {
+                        let (_, evidence) = kv?;
+                        Ok(evidence.value())
+                    })
+                    .collect()
+            })
+            .await
+    }
+
+    /// Queue registration of our BLS key, unless it's already registered
+    pub async fn set_pending_bls_key_registration(&self, registration: BlsKeyRegistration) {
+        let Some(peer_pubkey) = self.peer_pubkey else {
+            return;
+        };
+
+        let already_registered = self
+            .db
+            .read_with_expect(|dbtx| {
+                let tbl = dbtx.open_table(&tables::bls_pubkeys::TABLE)?;
+                Ok(tbl.get(&peer_pubkey)?.map(|v| v.value()) == Some(registration.pubkey))
+            })
+            .await;
+
+        if already_registered {
+            return;
+        }
+
+        self.db
+            .write_with_expect(|dbtx| {
+                let mut tbl = dbtx.open_table(&tables::pending_bls_key_registration::TABLE)?;
+                tbl.insert(&(), &registration)?;
+                Ok(())
+            })
+            .await;
+
+        self.refresh_consensus_proposals().await;
+    }
+
+    pub async fn get_bls_pubkeys(&self) -> BTreeMap<PeerPubkey, BlsPubkey> {
+        self.db
+            .read_with_expect(|dbtx| {
+                let tbl = dbtx.open_table(&tables::bls_pubkeys::TABLE)?;
+                tbl.range(..)?
+                    .map(|kv| {
+                        let (peer, pubkey) = kv?;
+                        Ok((peer.value(), pubkey.value()))
+                    })
+                    .collect()
+            })
+            .await
+    }
+
+    /// Our own key rotation, if one is waiting to be processed
+    pub async fn get_pending_key_rotation(&self) -> Option<SignedKeyRotation> {
+        self.db
+            .read_with_expect(|dbtx| {
+                let tbl = dbtx.open_table(&tables::pending_key_rotation::TABLE)?;
+                Ok(tbl.get(&())?.map(|v| v.value().0))
+            })
+            .await
+    }
+
+    pub async fn get_auto_remove_equivocating_peers(&self) -> bool {
+        self.db
+            .read_with_expect(|dbtx| {
+                let tbl = dbtx.open_table(&tables::auto_remove_equivocating_peers::TABLE)?;
+                Ok(tbl.get(&())?.map(|v| v.value()).unwrap_or_default())
+            })
+            .await
+    }
+
+    /// Enable or disable voting to remove peers with reported equivocations
+    pub async fn set_auto_remove_equivocating_peers(&self, enabled: bool) {
+        self.db
+            .write_with_expect(|dbtx| {
+                let mut tbl = dbtx.open_table(&tables::auto_remove_equivocating_peers::TABLE)?;
+                tbl.insert(&(), &enabled)?;
+                Ok(())
+            })
+            .await;
+    }
+
     pub(crate) async fn refresh_consensus_proposals(&self) {
         let proposals = self
             .db
@@ -268,128 +556,440 @@ impl ConsensusCtrlModule {
             return Ok(proposals);
         };
 
+        proposals.extend(Self::add_peer_proposal_dbtx(dbtx, peer_pubkey)?);
+        proposals.extend(Self::remove_peer_proposal_dbtx(dbtx, peer_pubkey)?);
+        proposals.extend(Self::set_peer_weight_proposal_dbtx(dbtx, peer_pubkey)?);
+        proposals.extend(Self::set_max_block_payload_len_proposal_dbtx(
+            dbtx,
+            peer_pubkey,
+        )?);
+        proposals.extend(self.add_module_proposal_dbtx(dbtx, peer_pubkey)?);
+
+        // Handle pending module version votes
+        let pending_module_ids = dbtx
+            .open_table(&tables::pending_modules_versions_votes::TABLE)?
+            .range(..)?
+            .map(|kv| {
+                let (module_id, _) = kv?;
+                Ok(module_id.value())
+            })
+            .collect::<DbResult<Vec<_>>>()?;
+        for module_id in pending_module_ids {
+            proposals.extend(Self::module_version_proposal_dbtx(
+                dbtx,
+                peer_pubkey,
+                module_id,
+            )?);
+        }
+
         let peer_set = self.get_peer_set_tx(dbtx)?;
 
-        let pending_add_vote = {
-            let tbl = dbtx.open_table(&tables::pending_add_peer_vote::TABLE)?;
-            tbl.get(&())?.map(|v| v.value())
-        };
+        // Handle pending equivocation reports
+        {
+            let pending_tbl = dbtx.open_table(&tables::pending_equivocation_reports::TABLE)?;
 
-        if let Some(pending_peer) = pending_add_vote {
-            if !peer_set.contains(&pending_peer) {
-                let current_vote = {
-                    let tbl = dbtx.open_table(&tables::add_peer_votes::TABLE)?;
-                    tbl.get(&peer_pubkey)?.map(|v| v.value())
-                };
+            for kv in pending_tbl.range(..)? {
+                let (_, evidence) = kv?;
+                let evidence = evidence.value();
 
-                if current_vote != Some(pending_peer) {
-                    let citem = ConsensusCtrlCitem::VoteAddPeer(pending_peer);
+                if peer_set.contains(&evidence.peer_pubkey) {
+                    let citem = ConsensusCtrlCitem::ReportEquivocation(evidence);
                     proposals.push(citem.encode_to_raw());
                 }
             }
         }
 
-        let pending_remove_vote = {
-            let tbl = dbtx.open_table(&tables::pending_remove_peer_vote::TABLE)?;
+        proposals.extend(Self::bls_key_proposal_dbtx(dbtx, peer_pubkey)?);
+
+        let pending_key_rotation = {
+            let tbl = dbtx.open_table(&tables::pending_key_rotation::TABLE)?;
             tbl.get(&())?.map(|v| v.value())
         };
 
-        if let Some(pending_peer) = pending_remove_vote {
-            if peer_set.contains(&pending_peer) {
-                let current_vote = {
-                    let tbl = dbtx.open_table(&tables::remove_peer_votes::TABLE)?;
-                    tbl.get(&peer_pubkey)?.map(|v| v.value())
+        if let Some((rotation, bls_registration)) = pending_key_rotation {
+            if peer_set.contains(&rotation.old_pubkey) && !peer_set.contains(&rotation.new_pubkey) {
+                let citem = ConsensusCtrlCitem::RotateKey {
+                    rotation,
+                    bls_registration,
                 };
+                proposals.push(citem.encode_to_raw());
+            }
+        }
 
-                if current_vote != Some(pending_peer) {
-                    let citem = ConsensusCtrlCitem::VoteRemovePeer(pending_peer);
-                    proposals.push(citem.encode_to_raw());
+        Ok(proposals)
+    }
+
+    /// Refresh only the proposals that processing `citem` could have changed
+    ///
+    /// Reads only the tables and keys declared in [`Self::citem_access`] for
+    /// it. Anything affecting other proposals (e.g. a peer set change) comes
+    /// with effects, and is handled by a full refresh in `process_effects`.
+    ///
+    /// Returns a filter of the current proposals to replace, and the
+    /// replacements.
+    #[allow(clippy::type_complexity)]
+    fn refresh_citem_proposals_dbtx<'dbtx>(
+        &self,
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        citem: &ConsensusCtrlCitem,
+    ) -> DbResult<(
+        Box<dyn Fn(&ConsensusCtrlCitem) -> bool + Send>,
+        Vec<CItemRaw>,
+    )> {
+        let Some(peer_pubkey) = self.peer_pubkey else {
+            return Ok((Box::new(|_| false), vec![]));
+        };
+
+        Ok(match citem {
+            ConsensusCtrlCitem::VoteAddPeer(_) => (
+                Box::new(|c| matches!(c, ConsensusCtrlCitem::VoteAddPeer(_))),
+                Self::add_peer_proposal_dbtx(dbtx, peer_pubkey)?
+                    .into_iter()
+                    .collect(),
+            ),
+            ConsensusCtrlCitem::VoteRemovePeer(_) => (
+                Box::new(|c| matches!(c, ConsensusCtrlCitem::VoteRemovePeer(_))),
+                Self::remove_peer_proposal_dbtx(dbtx, peer_pubkey)?
+                    .into_iter()
+                    .collect(),
+            ),
+            ConsensusCtrlCitem::VoteSetPeerWeight { .. } => (
+                Box::new(|c| matches!(c, ConsensusCtrlCitem::VoteSetPeerWeight { .. })),
+                Self::set_peer_weight_proposal_dbtx(dbtx, peer_pubkey)?
+                    .into_iter()
+                    .collect(),
+            ),
+            ConsensusCtrlCitem::VoteSetMaxBlockPayloadLen(_) => (
+                Box::new(|c| matches!(c, ConsensusCtrlCitem::VoteSetMaxBlockPayloadLen(_))),
+                Self::set_max_block_payload_len_proposal_dbtx(dbtx, peer_pubkey)?
+                    .into_iter()
+                    .collect(),
+            ),
+            ConsensusCtrlCitem::VoteAddModule { .. } => (
+                Box::new(|c| matches!(c, ConsensusCtrlCitem::VoteAddModule { .. })),
+                self.add_module_proposal_dbtx(dbtx, peer_pubkey)?
+                    .into_iter()
+                    .collect(),
+            ),
+            ConsensusCtrlCitem::VoteModuleVersion { module_id, .. } => {
+                let module_id = *module_id;
+                (
+                    Box::new(
+                        move |c| matches!(c, ConsensusCtrlCitem::VoteModuleVersion { module_id: id, .. } if *id == module_id),
+                    ),
+                    Self::module_version_proposal_dbtx(dbtx, peer_pubkey, module_id)?
+                        .into_iter()
+                        .collect(),
+                )
+            }
+            ConsensusCtrlCitem::ReportEquivocation(evidence) => {
+                let offender = evidence.peer_pubkey;
+                let key = (offender, evidence.round());
+
+                // The removal vote is only affected if it targets the offender,
+                // any other one was left untouched
+                let pending_remove_vote = dbtx
+                    .open_table(&tables::pending_remove_peer_vote::TABLE)?
+                    .get(&())?
+                    .map(|v| v.value());
+                let refresh_remove_vote = pending_remove_vote == Some(offender);
+
+                (
+                    Box::new(move |c| match c {
+                        ConsensusCtrlCitem::ReportEquivocation(evidence) => {
+                            (evidence.peer_pubkey, evidence.round()) == key
+                        }
+                        ConsensusCtrlCitem::VoteRemovePeer(_) => refresh_remove_vote,
+                        _ => false,
+                    }),
+                    if refresh_remove_vote {
+                        Self::remove_peer_proposal_dbtx(dbtx, peer_pubkey)?
+                            .into_iter()
+                            .collect()
+                    } else {
+                        vec![]
+                    },
+                )
+            }
+            ConsensusCtrlCitem::RegisterBlsKey(_) => (
+                Box::new(|c| matches!(c, ConsensusCtrlCitem::RegisterBlsKey(_))),
+                Self::bls_key_proposal_dbtx(dbtx, peer_pubkey)?
+                    .into_iter()
+                    .collect(),
+            ),
+            ConsensusCtrlCitem::RotateKey { .. } => (
+                Box::new(|_| true),
+                self.refresh_consensus_proposals_dbtx(dbtx)?,
+            ),
+        })
+    }
+
+    fn is_peer_dbtx<'dbtx>(
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        peer: PeerPubkey,
+    ) -> DbResult<bool> {
+        Ok(dbtx
+            .open_table(&tables::peers::TABLE)?
+            .get(&peer)?
+            .is_some())
+    }
+
+    fn add_peer_proposal_dbtx<'dbtx>(
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        peer_pubkey: PeerPubkey,
+    ) -> DbResult<Option<CItemRaw>> {
+        let Some(pending_peer) = dbtx
+            .open_table(&tables::pending_add_peer_vote::TABLE)?
+            .get(&())?
+            .map(|v| v.value())
+        else {
+            return Ok(None);
+        };
+
+        if Self::is_peer_dbtx(dbtx, pending_peer)? {
+            return Ok(None);
+        }
+
+        let current_vote = {
+            let tbl = dbtx.open_table(&tables::add_peer_votes::TABLE)?;
+            tbl.get(&peer_pubkey)?.map(|v| v.value())
+        };
+
+        Ok((current_vote != Some(pending_peer))
+            .then(|| ConsensusCtrlCitem::VoteAddPeer(pending_peer).encode_to_raw()))
+    }
+
+    fn remove_peer_proposal_dbtx<'dbtx>(
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        peer_pubkey: PeerPubkey,
+    ) -> DbResult<Option<CItemRaw>> {
+        let Some(pending_peer) = dbtx
+            .open_table(&tables::pending_remove_peer_vote::TABLE)?
+            .get(&())?
+            .map(|v| v.value())
+        else {
+            return Ok(None);
+        };
+
+        if !Self::is_peer_dbtx(dbtx, pending_peer)? {
+            return Ok(None);
+        }
+
+        let current_vote = {
+            let tbl = dbtx.open_table(&tables::remove_peer_votes::TABLE)?;
+            tbl.get(&peer_pubkey)?.map(|v| v.value())
+        };
+
+        Ok((current_vote != Some(pending_peer))
+            .then(|| ConsensusCtrlCitem::VoteRemovePeer(pending_peer).encode_to_raw()))
+    }
+
+    fn set_peer_weight_proposal_dbtx<'dbtx>(
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        peer_pubkey: PeerPubkey,
+    ) -> DbResult<Option<CItemRaw>> {
+        let Some((pending_peer, pending_weight)) = dbtx
+            .open_table(&tables::pending_set_peer_weight_vote::TABLE)?
+            .get(&())?
+            .map(|v| v.value())
+        else {
+            return Ok(None);
+        };
+
+        if !Self::is_peer_dbtx(dbtx, pending_peer)? {
+            return Ok(None);
+        }
+
+        let current_vote = {
+            let tbl = dbtx.open_table(&tables::set_peer_weight_votes::TABLE)?;
+            tbl.get(&peer_pubkey)?.map(|v| v.value())
+        };
+
+        Ok(
+            (current_vote != Some((pending_peer, pending_weight))).then(|| {
+                ConsensusCtrlCitem::VoteSetPeerWeight {
+                    peer: pending_peer,
+                    weight: pending_weight,
                 }
+                .encode_to_raw()
+            }),
+        )
+    }
+
+    fn set_max_block_payload_len_proposal_dbtx<'dbtx>(
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        peer_pubkey: PeerPubkey,
+    ) -> DbResult<Option<CItemRaw>> {
+        let Some(pending_len) = dbtx
+            .open_table(&tables::pending_set_max_block_payload_len_vote::TABLE)?
+            .get(&())?
+            .map(|v| v.value())
+        else {
+            return Ok(None);
+        };
+
+        let current_vote = {
+            let tbl = dbtx.open_table(&tables::set_max_block_payload_len_votes::TABLE)?;
+            tbl.get(&peer_pubkey)?.map(|v| v.value())
+        };
+
+        Ok((current_vote != Some(pending_len))
+            .then(|| ConsensusCtrlCitem::VoteSetMaxBlockPayloadLen(pending_len).encode_to_raw()))
+    }
+
+    fn add_module_proposal_dbtx<'dbtx>(
+        &self,
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        peer_pubkey: PeerPubkey,
+    ) -> DbResult<Option<CItemRaw>> {
+        let Some((pending_module_kind, pending_consensus_version)) = dbtx
+            .open_table(&tables::pending_add_module_vote::TABLE)?
+            .get(&())?
+            .map(|v| v.value())
+        else {
+            return Ok(None);
+        };
+
+        // Get module init to check if it's singleton
+        let should_check_exists = self
+            .modules_inits
+            .get(&pending_module_kind)
+            .map(|init| init.singleton())
+            .unwrap_or(false);
+
+        // Check if module already exists (only for singleton modules)
+        if should_check_exists {
+            let tbl = dbtx.open_table(&tables::modules_configs::TABLE)?;
+            for kv in tbl.range(..)? {
+                let (_, config) = kv?;
+                if config.value().kind == pending_module_kind {
+                    return Ok(None);
+                }
+            }
+        }
+
+        let current_vote = {
+            let tbl = dbtx.open_table(&tables::add_module_votes::TABLE)?;
+            tbl.get(&peer_pubkey)?.map(|v| v.value())
+        };
+
+        // Check if we need to propose: either no current vote, or current vote has
+        // different module_kind or major version
+        let should_propose = match current_vote {
+            None => true,
+            Some((current_module_kind, current_version)) => {
+                current_module_kind != pending_module_kind
+                    || current_version.major() != pending_consensus_version.major()
+            }
+        };
+
+        Ok(should_propose.then(|| {
+            ConsensusCtrlCitem::VoteAddModule {
+                module_kind: pending_module_kind,
+                consensus_version: pending_consensus_version,
+            }
+            .encode_to_raw()
+        }))
+    }
+
+    fn module_version_proposal_dbtx<'dbtx>(
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        peer_pubkey: PeerPubkey,
+        module_id: ModuleId,
+    ) -> DbResult<Option<CItemRaw>> {
+        let Some(pending_minor_version) = dbtx
+            .open_table(&tables::pending_modules_versions_votes::TABLE)?
+            .get(&module_id)?
+            .map(|v| v.value())
+        else {
+            return Ok(None);
+        };
+
+        let current_vote = dbtx
+            .open_table(&tables::modules_versions_votes::TABLE)?
+            .get(&(peer_pubkey, module_id))?
+            .map(|v| v.value().minor());
+
+        Ok((current_vote != Some(pending_minor_version)).then(|| {
+            ConsensusCtrlCitem::VoteModuleVersion {
+                module_id,
+                minor_consensus_version: pending_minor_version,
             }
-        }
+            .encode_to_raw()
+        }))
+    }
 
-        // Handle pending module add votes
-        let pending_add_module_vote = {
-            let tbl = dbtx.open_table(&tables::pending_add_module_vote::TABLE)?;
-            tbl.get(&())?.map(|v| v.value())
+    fn bls_key_proposal_dbtx<'dbtx>(
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        peer_pubkey: PeerPubkey,
+    ) -> DbResult<Option<CItemRaw>> {
+        let Some(registration) = dbtx
+            .open_table(&tables::pending_bls_key_registration::TABLE)?
+            .get(&())?
+            .map(|v| v.value())
+        else {
+            return Ok(None);
         };
 
-        if let Some((pending_module_kind, pending_consensus_version)) = pending_add_module_vote {
-            // Get module init to check if it's singleton
-            let should_check_exists = self
-                .modules_inits
-                .get(&pending_module_kind)
-                .map(|init| init.singleton())
-                .unwrap_or(false);
-
-            // Check if module already exists (only for singleton modules)
-            let module_already_exists = if should_check_exists {
-                let tbl = dbtx.open_table(&tables::modules_configs::TABLE)?;
-                let mut found = false;
-                for kv in tbl.range(..)? {
-                    let (_, config) = kv?;
-                    if config.value().kind == pending_module_kind {
-                        found = true;
-                        break;
-                    }
-                }
-                found
-            } else {
-                false
-            };
-
-            if !module_already_exists {
-                let current_vote = {
-                    let tbl = dbtx.open_table(&tables::add_module_votes::TABLE)?;
-                    tbl.get(&peer_pubkey)?.map(|v| v.value())
-                };
+        let current = {
+            let tbl = dbtx.open_table(&tables::bls_pubkeys::TABLE)?;
+            tbl.get(&peer_pubkey)?.map(|v| v.value())
+        };
 
-                // Check if we need to propose: either no current vote, or current vote has
-                // different module_kind or major version
-                let should_propose = match current_vote {
-                    None => true,
-                    Some((current_module_kind, current_version)) => {
-                        current_module_kind != pending_module_kind
-                            || current_version.major() != pending_consensus_version.major()
-                    }
-                };
+        Ok((current != Some(registration.pubkey))
+            .then(|| ConsensusCtrlCitem::RegisterBlsKey(registration).encode_to_raw()))
+    }
 
-                if should_propose {
-                    let citem = ConsensusCtrlCitem::VoteAddModule {
-                        module_kind: pending_module_kind,
-                        consensus_version: pending_consensus_version,
-                    };
-                    proposals.push(citem.encode_to_raw());
-                }
-            }
+    /// Voting weights of all peers in `peer_set`, in the same order
+    fn get_peer_weights_tx<'dbtx>(
+        &self,
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        peer_set: &PeerSet,
+    ) -> DbResult<Vec<PeerWeight>> {
+        let tbl = dbtx.open_table(&tables::peer_weights::TABLE)?;
+
+        let mut peer_weights = vec![];
+        for peer in peer_set {
+            peer_weights.push(
+                tbl.get(peer)?
+                    .map(|v| v.value())
+                    .unwrap_or(DEFAULT_PEER_WEIGHT),
+            );
         }
 
-        // Handle pending module version votes
-        {
-            let pending_votes_tbl =
-                dbtx.open_table(&tables::pending_modules_versions_votes::TABLE)?;
-            let current_votes_tbl = dbtx.open_table(&tables::modules_versions_votes::TABLE)?;
+        Ok(peer_weights)
+    }
 
-            for kv in pending_votes_tbl.range(..)? {
-                let (module_id, pending_minor_version) = kv?;
-                let module_id = module_id.value();
-                let pending_minor_version = pending_minor_version.value();
+    fn get_max_block_payload_len_tx<'dbtx>(
+        &self,
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+    ) -> DbResult<u32> {
+        let tbl = dbtx.open_table(&tables::max_block_payload_len::TABLE)?;
+        Ok(tbl
+            .get(&())?
+            .map(|v| v.value())
+            .unwrap_or(ConsensusParams::DEFAULT_MAX_BLOCK_PAYLOAD_LEN))
+    }
 
-                let current_vote = current_votes_tbl
-                    .get(&(peer_pubkey, module_id))?
-                    .map(|v| v.value().minor());
+    /// BLS keys of all peers in `peer_set`, in the same order
+    ///
+    /// Empty if any of the peers did not register a key yet.
+    fn get_bls_pubkeys_tx<'dbtx>(
+        &self,
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        peer_set: &PeerSet,
+    ) -> DbResult<Vec<BlsPubkey>> {
+        let tbl = dbtx.open_table(&tables::bls_pubkeys::TABLE)?;
 
-                if current_vote != Some(pending_minor_version) {
-                    let citem = ConsensusCtrlCitem::VoteModuleVersion {
-                        module_id,
-                        minor_consensus_version: pending_minor_version,
-                    };
-                    proposals.push(citem.encode_to_raw());
-                }
-            }
+        let mut bls_pubkeys = vec![];
+        for peer in peer_set {
+            let Some(bls_pubkey) = tbl.get(peer)?.map(|v| v.value()) else {
+                return Ok(vec![]);
+            };
+            bls_pubkeys.push(bls_pubkey);
         }
 
-        Ok(proposals)
+        Ok(bls_pubkeys)
     }
 
     /// Get the current peer set within a read transaction context
@@ -508,20 +1108,20 @@ impl ConsensusCtrlModule {
                 .remove(&())?;
         }
 
-        // Count votes for the peer_to_add from all peer set members
+        // Count weighted votes for the peer_to_add from all peer set members
+        let peer_weights = self.get_peer_weights_tx(dbtx, peer_set)?;
         let mut votes_for_candidate = 0;
-        for peer in peer_set.iter() {
+        for (peer, weight) in peer_set.iter().zip(&peer_weights) {
             match add_peer_votes_tbl.get(peer)? {
                 Some(vote) if vote.value() == peer_to_add => {
-                    votes_for_candidate += 1;
+                    votes_for_candidate += u64::from(*weight);
                 }
                 _ => {} // No vote or vote for different candidate
             }
         }
 
         // Check if threshold is reached
-        let num_peers = peer_set.to_num_peers();
-        let threshold = num_peers.threshold();
+        let threshold = TotalWeight::from_weights(peer_weights).threshold();
 
         let mut effects = vec![];
         if votes_for_candidate >= threshold {
@@ -541,6 +1141,9 @@ impl ConsensusCtrlModule {
             effects.push((AddPeerEffect { peer: peer_to_add }).encode());
             effects.push(
                 (ConsensusParamsChange {
+                    bls_pubkeys: self.get_bls_pubkeys_tx(dbtx, &updated_peer_set)?,
+                    peer_weights: self.get_peer_weights_tx(dbtx, &updated_peer_set)?,
+                    max_block_payload_len: self.get_max_block_payload_len_tx(dbtx)?,
                     peer_set: updated_peer_set,
                 })
                 .encode(),
@@ -715,19 +1318,20 @@ impl ConsensusCtrlModule {
                 .remove(&())?;
         }
 
-        // Count votes for the peer_to_remove from all peer set members
+        // Count weighted votes for the peer_to_remove from all peer set members
+        let peer_weights = self.get_peer_weights_tx(dbtx, cur_effective_peer_set)?;
         let mut votes_for_removal = 0;
-        for peer in cur_effective_peer_set.iter() {
+        for (peer, weight) in cur_effective_peer_set.iter().zip(&peer_weights) {
             match remove_peer_votes_tbl.get(peer)? {
                 Some(vote) if vote.value() == peer_to_remove => {
-                    votes_for_removal += 1;
+                    votes_for_removal += u64::from(*weight);
                 }
                 _ => {} // No vote or vote for different peer
             }
         }
 
         // Check if threshold is reached
-        let threshold = cur_effective_peer_set.to_num_peers().threshold();
+        let threshold = TotalWeight::from_weights(peer_weights).threshold();
 
         let mut effects = vec![];
         if votes_for_removal >= threshold {
@@ -738,6 +1342,8 @@ impl ConsensusCtrlModule {
                 let mut peers_tbl = dbtx.open_table(&tables::peers::TABLE)?;
                 peers_tbl.remove(&peer_to_remove)?;
             }
+            dbtx.open_table(&tables::former_peers::TABLE)?
+                .insert(&peer_to_remove, &())?;
 
             // Get the updated peer set and emit PeerSetChange effect
             let updated_peer_set = self.get_peer_set_tx(dbtx)?;
@@ -749,6 +1355,17 @@ impl ConsensusCtrlModule {
             dbtx.open_table(&tables::add_peer_votes::TABLE)?
                 .retain(|k, _vote| updated_peer_set.contains(k))?;
 
+            dbtx.open_table(&tables::set_peer_weight_votes::TABLE)?
+                .retain(|k, (peer, _weight)| {
+                    updated_peer_set.contains(k) && *peer != peer_to_remove
+                })?;
+
+            dbtx.open_table(&tables::set_max_block_payload_len_votes::TABLE)?
+                .retain(|k, _vote| updated_peer_set.contains(k))?;
+
+            dbtx.open_table(&tables::peer_weights::TABLE)?
+                .remove(&peer_to_remove)?;
+
             effects.push(
                 (RemovePeerEffect {
                     peer: peer_to_remove,
@@ -759,6 +1376,9 @@ impl ConsensusCtrlModule {
             effects.push(
                 (ConsensusParamsChange {
                     peer_set: updated_peer_set.clone(),
+                    bls_pubkeys: self.get_bls_pubkeys_tx(dbtx, &updated_peer_set)?,
+                    peer_weights: self.get_peer_weights_tx(dbtx, &updated_peer_set)?,
+                    max_block_payload_len: self.get_max_block_payload_len_tx(dbtx)?,
                 })
                 .encode(),
             );
@@ -827,12 +1447,436 @@ impl ConsensusCtrlModule {
         Ok(effects)
     }
 
+    fn process_citem_report_equivocation(
+        &self,
+        dbtx: &ModuleWriteTransactionCtx,
+        evidence: EquivocationEvidence,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        evidence
+            .verify()
+            .whatever_context("Invalid equivocation evidence")
+            .context(TxSnafu)?;
+
+        let offender = evidence.peer_pubkey;
+        let key = (offender, evidence.round());
+
+        let is_or_was_peer = dbtx
+            .open_table(&tables::peers::TABLE)?
+            .get(&offender)?
+            .is_some()
+            || dbtx
+                .open_table(&tables::former_peers::TABLE)?
+                .get(&offender)?
+                .is_some();
+        if !is_or_was_peer {
+            None.whatever_context("Offender is not and never was a peer")
+                .context(TxSnafu)?;
+        }
+
+        // No matter who reported it, we don't need to report it anymore
+        dbtx.open_table(&tables::pending_equivocation_reports::TABLE)?
+            .remove(&key)?;
+
+        {
+            let mut tbl = dbtx.open_table(&tables::equivocations::TABLE)?;
+            if tbl.get(&key)?.is_some() {
+                // Already reported, no change needed
+                return Ok(vec![]);
+            }
+            tbl.insert(&key, &evidence)?;
+        }
+
+        warn!(target: LOG_TARGET, %offender, round = %evidence.round(), "Peer equivocation reported");
+
+        let auto_remove = dbtx
+            .open_table(&tables::auto_remove_equivocating_peers::TABLE)?
+            .get(&())?
+            .map(|v| v.value())
+            .unwrap_or_default();
+
+        if let Some(our) = self
+            .peer_pubkey
+            .filter(|our| auto_remove && *our != offender)
+        {
+            // Only a peer can vote, and with us in the latest peer set the
+            // offender is never the last peer in it
+            let can_vote = Self::is_peer_dbtx(dbtx, offender)? && Self::is_peer_dbtx(dbtx, our)?;
+
+            let mut pending_tbl = dbtx.open_table(&tables::pending_remove_peer_vote::TABLE)?;
+
+            // Don't override a removal vote set by the user, or for an earlier offender
+            if can_vote && pending_tbl.get(&())?.is_none() {
+                info!(target: LOG_TARGET, %offender, "Voting to remove equivocating peer");
+                pending_tbl.insert(&(), &offender)?;
+            }
+        }
+
+        Ok(vec![])
+    }
+
+    fn process_citem_register_bls_key(
+        &self,
+        dbtx: &ModuleWriteTransactionCtx,
+        peer_pubkey: PeerPubkey,
+        registration: BlsKeyRegistration,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        registration
+            .verify(peer_pubkey)
+            .whatever_context("Invalid BLS key registration")
+            .context(TxSnafu)?;
+
+        if Some(peer_pubkey) == self.peer_pubkey {
+            dbtx.open_table(&tables::pending_bls_key_registration::TABLE)?
+                .remove(&())?;
+        }
+
+        {
+            let mut tbl = dbtx.open_table(&tables::bls_pubkeys::TABLE)?;
+            if tbl
+                .insert(&peer_pubkey, &registration.pubkey)?
+                .map(|v| v.value())
+                == Some(registration.pubkey)
+            {
+                // Already registered, no change needed
+                return Ok(vec![]);
+            }
+        }
+
+        let peer_set = self.get_peer_set_tx(dbtx)?;
+        let bls_pubkeys = self.get_bls_pubkeys_tx(dbtx, &peer_set)?;
+
+        if bls_pubkeys.is_empty() {
+            // Waiting for other peers to register their keys
+            return Ok(vec![]);
+        }
+
+        info!(target: LOG_TARGET, %peer_pubkey, "All peers registered BLS keys");
+
+        Ok(vec![
+            (ConsensusParamsChange {
+                peer_weights: self.get_peer_weights_tx(dbtx, &peer_set)?,
+                max_block_payload_len: self.get_max_block_payload_len_tx(dbtx)?,
+                peer_set,
+                bls_pubkeys,
+            })
+            .encode(),
+        ])
+    }
+
+    fn process_citem_vote_set_peer_weight(
+        &self,
+        dbtx: &ModuleWriteTransactionCtx,
+        voter_pubkey: PeerPubkey,
+        cur_effective_peer_set: &PeerSet,
+        peer: PeerPubkey,
+        weight: PeerWeight,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        if weight == 0 {
+            None.whatever_context("Peer weight must be positive")
+                .context(TxSnafu)?;
+        }
+
+        // Changes being voted on, are to be made on the latest (possibly not yet
+        // effective) peer set
+        let latest_peer_set = self.get_peer_set_tx(dbtx)?;
+        if !latest_peer_set.contains(&peer) {
+            None.whatever_context("Peer to set the weight of is not in the latest peer set")
+                .context(TxSnafu)?;
+        }
+
+        let mut votes_tbl = dbtx.open_table(&tables::set_peer_weight_votes::TABLE)?;
+
+        if votes_tbl.get(&voter_pubkey)?.map(|v| v.value()) == Some((peer, weight)) {
+            // Vote already recorded, no change needed
+            return Ok(vec![]);
+        }
+
+        votes_tbl.insert(&voter_pubkey, &(peer, weight))?;
+
+        // If this vote is from ourselves, clear the pending vote to stop proposing the
+        // same citem
+        if Some(voter_pubkey) == self.peer_pubkey {
+            dbtx.open_table(&tables::pending_set_peer_weight_vote::TABLE)?
+                .remove(&())?;
+        }
+
+        // Count weighted votes for the same change from all peer set members
+        let peer_weights = self.get_peer_weights_tx(dbtx, cur_effective_peer_set)?;
+        let mut votes_for_change = 0;
+        for (voter, voter_weight) in cur_effective_peer_set.iter().zip(&peer_weights) {
+            if votes_tbl.get(voter)?.map(|v| v.value()) == Some((peer, weight)) {
+                votes_for_change += u64::from(*voter_weight);
+            }
+        }
+
+        if votes_for_change < TotalWeight::from_weights(peer_weights).threshold() {
+            return Ok(vec![]);
+        }
+
+        votes_tbl.retain(|_k, vote| *vote != (peer, weight))?;
+
+        dbtx.open_table(&tables::peer_weights::TABLE)?
+            .insert(&peer, &weight)?;
+
+        info!(target: LOG_TARGET, %peer, %weight, "Peer weight changed");
+
+        Ok(vec![
+            (SetPeerWeightEffect { peer, weight }).encode(),
+            (ConsensusParamsChange {
+                bls_pubkeys: self.get_bls_pubkeys_tx(dbtx, &latest_peer_set)?,
+                peer_weights: self.get_peer_weights_tx(dbtx, &latest_peer_set)?,
+                max_block_payload_len: self.get_max_block_payload_len_tx(dbtx)?,
+                peer_set: latest_peer_set,
+            })
+            .encode(),
+        ])
+    }
+
+    fn process_citem_vote_set_max_block_payload_len(
+        &self,
+        dbtx: &ModuleWriteTransactionCtx,
+        voter_pubkey: PeerPubkey,
+        cur_effective_peer_set: &PeerSet,
+        max_block_payload_len: u32,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        if max_block_payload_len < ConsensusParams::MIN_MAX_BLOCK_PAYLOAD_LEN {
+            None.whatever_context("Maximum block payload length too low")
+                .context(TxSnafu)?;
+        }
+
+        let mut votes_tbl = dbtx.open_table(&tables::set_max_block_payload_len_votes::TABLE)?;
+
+        if votes_tbl.get(&voter_pubkey)?.map(|v| v.value()) == Some(max_block_payload_len) {
+            // Vote already recorded, no change needed
+            return Ok(vec![]);
+        }
+
+        votes_tbl.insert(&voter_pubkey, &max_block_payload_len)?;
+
+        // If this vote is from ourselves, clear the pending vote to stop proposing the
+        // same citem
+        if Some(voter_pubkey) == self.peer_pubkey {
+            dbtx.open_table(&tables::pending_set_max_block_payload_len_vote::TABLE)?
+                .remove(&())?;
+        }
+
+        // Count weighted votes for the same change from all peer set members
+        let peer_weights = self.get_peer_weights_tx(dbtx, cur_effective_peer_set)?;
+        let mut votes_for_change = 0;
+        for (voter, voter_weight) in cur_effective_peer_set.iter().zip(&peer_weights) {
+            if votes_tbl.get(voter)?.map(|v| v.value()) == Some(max_block_payload_len) {
+                votes_for_change += u64::from(*voter_weight);
+            }
+        }
+
+        if votes_for_change < TotalWeight::from_weights(peer_weights).threshold() {
+            return Ok(vec![]);
+        }
+
+        votes_tbl.retain(|_k, vote| *vote != max_block_payload_len)?;
+
+        dbtx.open_table(&tables::max_block_payload_len::TABLE)?
+            .insert(&(), &max_block_payload_len)?;
+
+        info!(target: LOG_TARGET, %max_block_payload_len, "Maximum block payload length changed");
+
+        let latest_peer_set = self.get_peer_set_tx(dbtx)?;
+        Ok(vec![
+            (SetMaxBlockPayloadLenEffect {
+                max_block_payload_len,
+            })
+            .encode(),
+            (ConsensusParamsChange {
+                bls_pubkeys: self.get_bls_pubkeys_tx(dbtx, &latest_peer_set)?,
+                peer_weights: self.get_peer_weights_tx(dbtx, &latest_peer_set)?,
+                max_block_payload_len,
+                peer_set: latest_peer_set,
+            })
+            .encode(),
+        ])
+    }
+
+    fn process_citem_rotate_key(
+        &self,
+        dbtx: &ModuleWriteTransactionCtx,
+        rotation: SignedKeyRotation,
+        bls_registration: Option<BlsKeyRegistration>,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        rotation
+            .verify()
+            .whatever_context("Invalid key rotation")
+            .context(TxSnafu)?;
+
+        let old_pubkey = rotation.old_pubkey;
+        let new_pubkey = rotation.new_pubkey;
+
+        // Changes are to be made on the latest (possibly not yet effective) peer set
+        let latest_peer_set = self.get_peer_set_tx(dbtx)?;
+        if !latest_peer_set.contains(&old_pubkey) {
+            None.whatever_context("Rotated key is not in the latest peer set")
+                .context(TxSnafu)?;
+        }
+        if latest_peer_set.contains(&new_pubkey) {
+            None.whatever_context("New key already part of the peer set")
+                .context(TxSnafu)?;
+        }
+        if let Some(bls_registration) = bls_registration {
+            bls_registration
+                .verify(new_pubkey)
+                .whatever_context("Invalid BLS key registration of the new key")
+                .context(TxSnafu)?;
+        }
+        if dbtx
+            .open_table(&tables::applied_key_rotations::TABLE)?
+            .insert(&(old_pubkey, new_pubkey), &())?
+            .is_some()
+        {
+            None.whatever_context("Key rotation already applied before")
+                .context(TxSnafu)?;
+        }
+
+        if Some(old_pubkey) == self.peer_pubkey {
+            dbtx.open_table(&tables::pending_key_rotation::TABLE)?
+                .remove(&())?;
+        }
+
+        {
+            let mut tbl = dbtx.open_table(&tables::peers::TABLE)?;
+            tbl.remove(&old_pubkey)?;
+            tbl.insert(&new_pubkey, &())?;
+        }
+        dbtx.open_table(&tables::former_peers::TABLE)?
+            .insert(&old_pubkey, &())?;
+
+        {
+            let mut tbl = dbtx.open_table(&tables::peer_weights::TABLE)?;
+            let weight = tbl.remove(&old_pubkey)?.map(|v| v.value());
+            if let Some(weight) = weight {
+                tbl.insert(&new_pubkey, &weight)?;
+            }
+        }
+
+        // BLS key registration is bound to the peer key, so it's replaced with
+        // the one of the new key, which is required if BLS keys are in use
+        {
+            let mut tbl = dbtx.open_table(&tables::bls_pubkeys::TABLE)?;
+            let old_bls_pubkey = tbl.remove(&old_pubkey)?.map(|v| v.value());
+            match bls_registration {
+                Some(bls_registration) => {
+                    tbl.insert(&new_pubkey, &bls_registration.pubkey)?;
+                }
+                None if old_bls_pubkey.is_some() => {
+                    None.whatever_context("Missing BLS key registration of the new key")
+                        .context(TxSnafu)?;
+                }
+                None => {}
+            }
+        }
+
+        // Carry over the votes cast by the old key, and targeting it
+        {
+            let mut tbl = dbtx.open_table(&tables::add_peer_votes::TABLE)?;
+            let vote = tbl.remove(&old_pubkey)?.map(|v| v.value());
+            if let Some(vote) = vote {
+                tbl.insert(&new_pubkey, &vote)?;
+            }
+        }
+        {
+            let mut tbl = dbtx.open_table(&tables::remove_peer_votes::TABLE)?;
+            let vote = tbl.remove(&old_pubkey)?.map(|v| v.value());
+            if let Some(vote) = vote {
+                tbl.insert(&new_pubkey, &vote)?;
+            }
+            let retargeted = tbl
+                .range(..)?
+                .map(|kv| {
+                    let (voter, vote) = kv?;
+                    Ok((voter.value(), vote.value()))
+                })
+                .collect::<DbResult<Vec<_>>>()?
+                .into_iter()
+                .filter(|(_, vote)| *vote == old_pubkey);
+            for (voter, _) in retargeted {
+                tbl.insert(&voter, &new_pubkey)?;
+            }
+        }
+        {
+            let mut tbl = dbtx.open_table(&tables::set_peer_weight_votes::TABLE)?;
+            let vote = tbl.remove(&old_pubkey)?.map(|v| v.value());
+            if let Some(vote) = vote {
+                tbl.insert(&new_pubkey, &vote)?;
+            }
+            let retargeted = tbl
+                .range(..)?
+                .map(|kv| {
+                    let (voter, vote) = kv?;
+                    Ok((voter.value(), vote.value()))
+                })
+                .collect::<DbResult<Vec<_>>>()?
+                .into_iter()
+                .filter(|(_, (peer, _))| *peer == old_pubkey);
+            for (voter, (_, weight)) in retargeted {
+                tbl.insert(&voter, &(new_pubkey, weight))?;
+            }
+        }
+        {
+            let mut tbl = dbtx.open_table(&tables::set_max_block_payload_len_votes::TABLE)?;
+            let vote = tbl.remove(&old_pubkey)?.map(|v| v.value());
+            if let Some(vote) = vote {
+                tbl.insert(&new_pubkey, &vote)?;
+            }
+        }
+        {
+            let mut tbl = dbtx.open_table(&tables::add_module_votes::TABLE)?;
+            let vote = tbl.remove(&old_pubkey)?.map(|v| v.value());
+            if let Some(vote) = vote {
+                tbl.insert(&new_pubkey, &vote)?;
+            }
+        }
+        {
+            let mut tbl = dbtx.open_table(&tables::modules_versions_votes::TABLE)?;
+            let votes = tbl
+                .range(..)?
+                .map(|kv| {
+                    let (k, v) = kv?;
+                    Ok((k.value(), v.value()))
+                })
+                .collect::<DbResult<Vec<_>>>()?;
+            for ((voter, module_id), version) in votes {
+                if voter == old_pubkey {
+                    tbl.remove(&(old_pubkey, module_id))?;
+                    tbl.insert(&(new_pubkey, module_id), &version)?;
+                }
+            }
+        }
+
+        info!(target: LOG_TARGET, %old_pubkey, %new_pubkey, "Peer key rotated");
+
+        let updated_peer_set = self.get_peer_set_tx(dbtx)?;
+
+        Ok(vec![
+            (RotateKeyEffect {
+                old_pubkey,
+                new_pubkey,
+            })
+            .encode(),
+            (ConsensusParamsChange {
+                bls_pubkeys: self.get_bls_pubkeys_tx(dbtx, &updated_peer_set)?,
+                peer_weights: self.get_peer_weights_tx(dbtx, &updated_peer_set)?,
+                max_block_payload_len: self.get_max_block_payload_len_tx(dbtx)?,
+                peer_set: updated_peer_set,
+            })
+            .encode(),
+        ])
+    }
+
     pub(crate) fn init_db_tx(
         dbtx: &ModuleWriteTransactionCtx,
         new_version: ConsensusVersion,
     ) -> DbResult<()> {
         dbtx.open_table(&tables::modules_configs::TABLE)?;
         dbtx.open_table(&tables::peers::TABLE)?;
+        dbtx.open_table(&tables::former_peers::TABLE)?;
         dbtx.open_table(&tables::add_peer_votes::TABLE)?;
         dbtx.open_table(&tables::remove_peer_votes::TABLE)?;
         dbtx.open_table(&tables::pending_add_peer_vote::TABLE)?;
@@ -841,6 +1885,19 @@ impl ConsensusCtrlModule {
         dbtx.open_table(&tables::pending_add_module_vote::TABLE)?;
         dbtx.open_table(&tables::modules_versions_votes::TABLE)?;
         dbtx.open_table(&tables::pending_modules_versions_votes::TABLE)?;
+        dbtx.open_table(&tables::equivocations::TABLE)?;
+        dbtx.open_table(&tables::pending_equivocation_reports::TABLE)?;
+        dbtx.open_table(&tables::auto_remove_equivocating_peers::TABLE)?;
+        dbtx.open_table(&tables::bls_pubkeys::TABLE)?;
+        dbtx.open_table(&tables::pending_bls_key_registration::TABLE)?;
+        dbtx.open_table(&tables::peer_weights::TABLE)?;
+        dbtx.open_table(&tables::set_peer_weight_votes::TABLE)?;
+        dbtx.open_table(&tables::pending_set_peer_weight_vote::TABLE)?;
+        dbtx.open_table(&tables::pending_key_rotation::TABLE)?;
+        dbtx.open_table(&tables::applied_key_rotations::TABLE)?;
+        dbtx.open_table(&tables::max_block_payload_len::TABLE)?;
+        dbtx.open_table(&tables::set_max_block_payload_len_votes::TABLE)?;
+        dbtx.open_table(&tables::pending_set_max_block_payload_len_vote::TABLE)?;
 
         {
             let mut tbl = dbtx.open_table(&tables::self_version::TABLE)?;
@@ -908,7 +1965,7 @@ impl IModule for ConsensusCtrlModule {
         dbtx: &ModuleWriteTransactionCtx,
         _round: BlockRound,
         peer_pubkey: PeerPubkey,
-        peer_set: &PeerSet,
+        peer_set: &WeightedPeerSet,
         citem: &CItemRaw,
     ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
         assert!(peer_set.contains(&peer_pubkey));
@@ -916,7 +1973,7 @@ impl IModule for ConsensusCtrlModule {
 
         debug!(target: LOG_TARGET, ?citem, %peer_pubkey, "Processing consensus item");
 
-        let res = match citem {
+        let res = match citem.clone() {
             ConsensusCtrlCitem::VoteAddPeer(peer_to_add) => {
                 self.process_citem_vote_add_peer(dbtx, peer_pubkey, peer_set, peer_to_add)
             }
@@ -943,19 +2000,149 @@ impl IModule for ConsensusCtrlModule {
                 module_id,
                 minor_consensus_version,
             ),
+            ConsensusCtrlCitem::ReportEquivocation(evidence) => {
+                self.process_citem_report_equivocation(dbtx, evidence)
+            }
+            ConsensusCtrlCitem::RegisterBlsKey(registration) => {
+                self.process_citem_register_bls_key(dbtx, peer_pubkey, registration)
+            }
+            ConsensusCtrlCitem::VoteSetPeerWeight { peer, weight } => {
+                self.process_citem_vote_set_peer_weight(dbtx, peer_pubkey, peer_set, peer, weight)
+            }
+            ConsensusCtrlCitem::RotateKey {
+                rotation,
+                bls_registration,
+            } => self.process_citem_rotate_key(dbtx, rotation, bls_registration),
+            ConsensusCtrlCitem::VoteSetMaxBlockPayloadLen(max_block_payload_len) => self
+                .process_citem_vote_set_max_block_payload_len(
+                    dbtx,
+                    peer_pubkey,
+                    peer_set,
+                    max_block_payload_len,
+                ),
         }?;
 
-        let proposals = self.refresh_consensus_proposals_dbtx(dbtx)?;
+        let (replaced, proposals) = self.refresh_citem_proposals_dbtx(dbtx, &citem)?;
 
         let tx = self.propose_citems_tx.clone();
 
         dbtx.on_commit(move || {
-            tx.send_replace(proposals);
+            tx.send_modify(|current| {
+                current.retain(|citem| {
+                    !ConsensusCtrlCitem::decode_from_raw(citem).is_ok_and(|citem| replaced(&citem))
+                });
+                current.extend(proposals);
+            });
         });
 
         Ok(res)
     }
 
+    /// Votes counting towards a threshold declare whole tables, as they are
+    /// counted over all voters, and reaching the threshold changes the peer
+    /// set or consensus params. Pending (local) votes are declared even if
+    /// the voter is someone else, as the access does not depend on it.
+    fn citem_access(&self, citem: &CItemRaw) -> Option<AccessSet> {
+        let citem = ConsensusCtrlCitem::decode_from_raw(citem).ok()?;
+
+        Some(match citem {
+            ConsensusCtrlCitem::VoteAddPeer(_) => AccessSet::new()
+                .with(&tables::peers::TABLE)
+                .with(&tables::add_peer_votes::TABLE)
+                .with(&tables::pending_add_peer_vote::TABLE)
+                .with(&tables::peer_weights::TABLE)
+                .with(&tables::bls_pubkeys::TABLE)
+                .with(&tables::max_block_payload_len::TABLE),
+            ConsensusCtrlCitem::VoteRemovePeer(_) => AccessSet::new()
+                .with(&tables::peers::TABLE)
+                .with(&tables::former_peers::TABLE)
+                .with(&tables::remove_peer_votes::TABLE)
+                .with(&tables::pending_remove_peer_vote::TABLE)
+                .with(&tables::add_peer_votes::TABLE)
+                .with(&tables::set_peer_weight_votes::TABLE)
+                .with(&tables::set_max_block_payload_len_votes::TABLE)
+                .with(&tables::peer_weights::TABLE)
+                .with(&tables::bls_pubkeys::TABLE)
+                .with(&tables::max_block_payload_len::TABLE)
+                .with(&tables::modules_configs::TABLE)
+                .with(&tables::modules_versions_votes::TABLE),
+            ConsensusCtrlCitem::VoteSetPeerWeight { .. } => AccessSet::new()
+                .with(&tables::peers::TABLE)
+                .with(&tables::set_peer_weight_votes::TABLE)
+                .with(&tables::pending_set_peer_weight_vote::TABLE)
+                .with(&tables::peer_weights::TABLE)
+                .with(&tables::bls_pubkeys::TABLE)
+                .with(&tables::max_block_payload_len::TABLE),
+            ConsensusCtrlCitem::VoteSetMaxBlockPayloadLen(_) => AccessSet::new()
+                .with(&tables::peers::TABLE)
+                .with(&tables::set_max_block_payload_len_votes::TABLE)
+                .with(&tables::pending_set_max_block_payload_len_vote::TABLE)
+                .with(&tables::peer_weights::TABLE)
+                .with(&tables::bls_pubkeys::TABLE)
+                .with(&tables::max_block_payload_len::TABLE),
+            ConsensusCtrlCitem::VoteAddModule { .. } => AccessSet::new()
+                .with(&tables::modules_configs::TABLE)
+                .with(&tables::add_module_votes::TABLE)
+                .with(&tables::pending_add_module_vote::TABLE),
+            // Upgrades are checked for all modules on every vote
+            ConsensusCtrlCitem::VoteModuleVersion { module_id, .. } => AccessSet::new()
+                .with(&tables::modules_configs::TABLE)
+                .with(&tables::modules_versions_votes::TABLE)
+                .with_key(&tables::pending_modules_versions_votes::TABLE, &module_id),
+            // Reports of different equivocations are independent, except for
+            // the automatic removal vote, which has only one slot
+            ConsensusCtrlCitem::ReportEquivocation(evidence) => {
+                let offender = evidence.peer_pubkey;
+                let key = (offender, evidence.round());
+
+                let mut access = AccessSet::new()
+                    .with_key(&tables::peers::TABLE, &offender)
+                    .with_key(&tables::former_peers::TABLE, &offender)
+                    .with_key(&tables::pending_equivocation_reports::TABLE, &key)
+                    .with_key(&tables::equivocations::TABLE, &key)
+                    .with(&tables::auto_remove_equivocating_peers::TABLE)
+                    .with(&tables::pending_remove_peer_vote::TABLE);
+                if let Some(our) = self.peer_pubkey {
+                    access = access
+                        .with_key(&tables::peers::TABLE, &our)
+                        .with_key(&tables::remove_peer_votes::TABLE, &our);
+                }
+                access
+            }
+            // Params change once all peers registered, which needs all the keys
+            ConsensusCtrlCitem::RegisterBlsKey(_) => AccessSet::new()
+                .with(&tables::peers::TABLE)
+                .with(&tables::bls_pubkeys::TABLE)
+                .with(&tables::pending_bls_key_registration::TABLE)
+                .with(&tables::peer_weights::TABLE)
+                .with(&tables::max_block_payload_len::TABLE),
+            // Carries over everything related to the old key
+            ConsensusCtrlCitem::RotateKey { .. } => AccessSet::new()
+                .with(&tables::peers::TABLE)
+                .with(&tables::former_peers::TABLE)
+                .with(&tables::add_peer_votes::TABLE)
+                .with(&tables::remove_peer_votes::TABLE)
+                .with(&tables::pending_add_peer_vote::TABLE)
+                .with(&tables::pending_remove_peer_vote::TABLE)
+                .with(&tables::peer_weights::TABLE)
+                .with(&tables::set_peer_weight_votes::TABLE)
+                .with(&tables::pending_set_peer_weight_vote::TABLE)
+                .with(&tables::max_block_payload_len::TABLE)
+                .with(&tables::set_max_block_payload_len_votes::TABLE)
+                .with(&tables::pending_set_max_block_payload_len_vote::TABLE)
+                .with(&tables::modules_configs::TABLE)
+                .with(&tables::modules_versions_votes::TABLE)
+                .with(&tables::pending_modules_versions_votes::TABLE)
+                .with(&tables::add_module_votes::TABLE)
+                .with(&tables::pending_add_module_vote::TABLE)
+                .with(&tables::pending_equivocation_reports::TABLE)
+                .with(&tables::bls_pubkeys::TABLE)
+                .with(&tables::pending_bls_key_registration::TABLE)
+                .with(&tables::pending_key_rotation::TABLE)
+                .with(&tables::applied_key_rotations::TABLE),
+        })
+    }
+
     fn process_input(
         &self,
         _dbtx: &ModuleWriteTransactionCtx,
@@ -976,10 +2163,46 @@ impl IModule for ConsensusCtrlModule {
 
     fn process_effects(
         &self,
-        _dbtx: &ModuleWriteTransactionCtx,
-        _peer_set: &PeerSet,
-        _effects: &[ModuleCItemEffect],
+        dbtx: &ModuleWriteTransactionCtx,
+        _round: BlockRound,
+        _peer_set: &WeightedPeerSet,
+        effects: &[ModuleCItemEffect],
     ) -> DbTxResult<(), Whatever> {
+        // Our own effects (e.g. a peer set change) can affect any of the
+        // proposals, which `process_citem` refreshes only partially
+        if !effects
+            .iter()
+            .any(|effect| effect.module_kind() == crate::KIND)
+        {
+            return Ok(());
+        }
+
+        let proposals = self.refresh_consensus_proposals_dbtx(dbtx)?;
+
+        let tx = self.propose_citems_tx.clone();
+
+        dbtx.on_commit(move || {
+            tx.send_replace(proposals);
+        });
+
         Ok(())
     }
+
+    fn consensus_tables(&self) -> ConsensusTables {
+        ConsensusTables::new()
+            .with(&tables::peers::TABLE)
+            .with(&tables::former_peers::TABLE)
+            .with(&tables::add_peer_votes::TABLE)
+            .with(&tables::remove_peer_votes::TABLE)
+            .with(&tables::peer_weights::TABLE)
+            .with(&tables::set_peer_weight_votes::TABLE)
+            .with(&tables::max_block_payload_len::TABLE)
+            .with(&tables::set_max_block_payload_len_votes::TABLE)
+            .with(&tables::modules_configs::TABLE)
+            .with(&tables::modules_versions_votes::TABLE)
+            .with(&tables::add_module_votes::TABLE)
+            .with(&tables::equivocations::TABLE)
+            .with(&tables::bls_pubkeys::TABLE)
+            .with(&tables::applied_key_rotations::TABLE)
+    }
 }
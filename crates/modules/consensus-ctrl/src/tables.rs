@@ -1,6 +1,11 @@
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::bls::{BlsKeyRegistration, BlsPubkey};
+use bfte_consensus_core::equivocation::EquivocationEvidence;
+use bfte_consensus_core::key_rotation::SignedKeyRotation;
 use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_consensus_core::peer::PeerPubkey;
 use bfte_consensus_core::ver::{ConsensusVersion, ConsensusVersionMinor};
+use bfte_consensus_core::weight::PeerWeight;
 use bfte_module::module::config::ModuleConfig;
 use bfte_util_db::def_table;
 
@@ -22,6 +27,13 @@ def_table! {
     peers: PeerPubkey => ()
 }
 
+def_table! {
+    /// Peers that were removed or rotated out of `peers`
+    ///
+    /// Their equivocations can still be reported.
+    former_peers: PeerPubkey => ()
+}
+
 def_table! {
     /// Tracks which new peers existing peers would like to add to the consensus voting.
     add_peer_votes: PeerPubkey /* voter */ => PeerPubkey /* voted to be added */
@@ -46,6 +58,44 @@ def_table! {
     pending_remove_peer_vote: () => PeerPubkey
 }
 
+def_table! {
+    /// Voting weights of peers
+    ///
+    /// Peers without an entry have the default weight.
+    peer_weights: PeerPubkey => PeerWeight
+}
+
+def_table! {
+    /// Tracks which voting weights existing peers would like to set for peers.
+    set_peer_weight_votes: PeerPubkey /* voter */ => (PeerPubkey, PeerWeight) /* voted to be set */
+}
+
+def_table! {
+    /// Our own pending vote to set a peer's weight which we want to propose
+    ///
+    /// Once it is processed as a consensus item, it will update `set_peer_weight_votes` table.
+    pending_set_peer_weight_vote: () => (PeerPubkey, PeerWeight)
+}
+
+def_table! {
+    /// Maximum length of a block payload
+    ///
+    /// `ConsensusParams::DEFAULT_MAX_BLOCK_PAYLOAD_LEN` if not set.
+    max_block_payload_len: () => u32
+}
+
+def_table! {
+    /// Tracks which maximum block payload length existing peers would like to set.
+    set_max_block_payload_len_votes: PeerPubkey /* voter */ => u32 /* voted to be set */
+}
+
+def_table! {
+    /// Our own pending vote to set the maximum block payload length which we want to propose
+    ///
+    /// Once it is processed as a consensus item, it will update `set_max_block_payload_len_votes` table.
+    pending_set_max_block_payload_len_vote: () => u32
+}
+
 def_table! {
     /// Current list of all initialized modules, along with their configuration
     modules_configs: ModuleId => ModuleConfig
@@ -70,3 +120,51 @@ def_table! {
     /// Once it is processed as a consensus item, it will update `add_module_votes` table.
     pending_add_module_vote: () => (ModuleKind, ConsensusVersion)
 }
+
+def_table! {
+    /// Equivocation evidence reported in finalized consensus items
+    equivocations: (PeerPubkey, BlockRound) => EquivocationEvidence
+}
+
+def_table! {
+    /// Equivocation evidence we want to report
+    ///
+    /// Once reported by anyone as a consensus item, it will move to `equivocations` table.
+    pending_equivocation_reports: (PeerPubkey, BlockRound) => EquivocationEvidence
+}
+
+def_table! {
+    /// If set, vote to remove any peer with reported equivocation
+    auto_remove_equivocating_peers: () => bool
+}
+
+def_table! {
+    /// BLS keys registered by peers
+    ///
+    /// Once all peers in the peer set have one, they are used in the
+    /// `ConsensusParams`, enabling aggregated block signatures.
+    bls_pubkeys: PeerPubkey => BlsPubkey
+}
+
+def_table! {
+    /// Our own BLS key registration which we want to propose
+    ///
+    /// Once it is processed as a consensus item, it will update `bls_pubkeys` table.
+    pending_bls_key_registration: () => BlsKeyRegistration
+}
+
+def_table! {
+    /// Key rotations that were applied, by the old and the new key
+    ///
+    /// Rotations are signed messages that could be replayed otherwise, e.g.
+    /// after the old key was added back as a peer.
+    applied_key_rotations: (PeerPubkey, PeerPubkey) => ()
+}
+
+def_table! {
+    /// Our own key rotation which we want to propose
+    ///
+    /// Once it is processed as a consensus item, our key in the `peers` table is replaced.
+    /// Stored along with the BLS key registration of the new key, if any.
+    pending_key_rotation: () => (SignedKeyRotation, Option<BlsKeyRegistration>)
+}
@@ -1,20 +1,32 @@
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
-use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::block::{BlockHeader, BlockPayloadRaw, BlockRound};
+use bfte_consensus_core::bls::BlsSeckey;
+use bfte_consensus_core::citem::CItemRaw;
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::equivocation::EquivocationEvidence;
+use bfte_consensus_core::key_rotation::KeyRotation;
 use bfte_consensus_core::module::ModuleId;
 use bfte_consensus_core::peer::{PeerPubkey, PeerSeckey};
-use bfte_consensus_core::peer_set::PeerSet;
+use bfte_consensus_core::peer_set::{PeerSet, WeightedPeerSet};
+use bfte_consensus_core::signed::Signed;
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_consensus_core::ver::ConsensusVersion;
 use bfte_db::Database;
-use bfte_module::effect::EffectKindExt;
-use bfte_module::module::db::ModuleDatabase;
+use bfte_module::effect::{CItemEffect, EffectKindExt};
+use bfte_module::module::db::{ModuleDatabase, ModuleWriteTransactionCtx, dump_modules_tables};
 use bfte_module::module::{IModule, IModuleInit, ModuleInitArgs};
 use bfte_util_error::BoxedErrorResult;
 
 use crate::citem::ConsensusCtrlCitem;
-use crate::effects::{AddPeerEffect, ConsensusParamsChange, RemovePeerEffect};
+use crate::effects::{
+    AddPeerEffect, ConsensusParamsChange, RemovePeerEffect, RotateKeyEffect,
+    SetMaxBlockPayloadLenEffect, SetPeerWeightEffect,
+};
 use crate::init::ConsensusCtrlModuleInit;
 use crate::module::ConsensusCtrlModule;
+use crate::tables;
 
 struct TestSetup {
     pub module: Arc<dyn IModule + Send + Sync>,
@@ -22,6 +34,7 @@ struct TestSetup {
 }
 
 struct MultiPeerTestSetup {
+    pub db: Arc<Database>,
     pub module: Arc<dyn IModule + Send + Sync>,
     #[allow(dead_code)]
     pub peer_pubkeys: Vec<PeerPubkey>,
@@ -60,6 +73,8 @@ impl TestSetup {
                 module_config.version,
                 BTreeMap::new(),
                 Some(peer_pubkey),
+                None,
+                None,
             ))
             .await?;
 
@@ -107,10 +122,13 @@ impl MultiPeerTestSetup {
                 module_config.version,
                 BTreeMap::new(),
                 Some(peer_pubkeys[0]), // Use first peer as the voting peer
+                None,
+                None,
             ))
             .await?;
 
         Ok(Self {
+            db,
             module,
             peer_pubkeys,
         })
@@ -137,7 +155,7 @@ async fn test_vote_add_peer_produces_effect() -> BoxedErrorResult<()> {
     let citem_raw = vote_citem.encode_to_raw();
 
     // Get current peer set (should only contain the initial peer)
-    let peer_set: PeerSet = vec![setup.peer_pubkey].into();
+    let peer_set = WeightedPeerSet::from(PeerSet::from(vec![setup.peer_pubkey]));
 
     // Process the citem through the module using the IModule trait
     let effects = setup
@@ -224,7 +242,7 @@ async fn test_two_peers_voting_to_add_third() -> BoxedErrorResult<()> {
     let citem_raw = vote_citem.encode_to_raw();
 
     // Get current peer set (should contain both initial peers)
-    let peer_set: PeerSet = vec![peer1_pubkey, peer2_pubkey].into();
+    let peer_set = WeightedPeerSet::from(PeerSet::from(vec![peer1_pubkey, peer2_pubkey]));
 
     // First peer votes to add the new peer - should not reach threshold yet
     let effects1 = setup
@@ -351,7 +369,7 @@ async fn test_two_peers_voting_to_remove_one() -> BoxedErrorResult<()> {
     let citem_raw = vote_citem.encode_to_raw();
 
     // Get current peer set (should contain both initial peers)
-    let peer_set: PeerSet = vec![peer1_pubkey, peer2_pubkey].into();
+    let peer_set = WeightedPeerSet::from(PeerSet::from(vec![peer1_pubkey, peer2_pubkey]));
 
     // First peer votes to remove peer2 - should not reach threshold yet
     let effects1 = setup
@@ -454,3 +472,725 @@ async fn test_two_peers_voting_to_remove_one() -> BoxedErrorResult<()> {
 
     Ok(())
 }
+
+fn make_equivocation_evidence(seckey: PeerSeckey, round: BlockRound) -> EquivocationEvidence {
+    let params = ConsensusParams::new_test_dummy();
+    let sign_block = |payload: &[u8]| {
+        let block = BlockHeader::builder()
+            .consensus_params(&params)
+            .round(round)
+            .payload(&BlockPayloadRaw::from(payload.to_vec()))
+            .timestamp(Timestamp::ZERO)
+            .build();
+        Signed::new_sign(block, seckey)
+    };
+
+    EquivocationEvidence {
+        peer_pubkey: seckey.pubkey(),
+        first: sign_block(&[0]),
+        second: sign_block(&[1]),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reported_equivocation_triggers_remove_vote() -> BoxedErrorResult<()> {
+    let peer1_pubkey = PeerSeckey::generate().pubkey();
+    let peer2_seckey = PeerSeckey::generate();
+    let peer2_pubkey = peer2_seckey.pubkey();
+
+    let setup = MultiPeerTestSetup::bootstrap_with_peers(vec![peer1_pubkey, peer2_pubkey]).await?;
+    let peer_set = WeightedPeerSet::from(PeerSet::from(vec![peer1_pubkey, peer2_pubkey]));
+
+    setup
+        .core_module()
+        .set_auto_remove_equivocating_peers(true)
+        .await;
+
+    let evidence = make_equivocation_evidence(peer2_seckey, BlockRound::from(3));
+
+    // Queued evidence should be proposed as a consensus item
+    setup
+        .core_module()
+        .report_equivocations(vec![evidence.clone()])
+        .await;
+    let proposals = setup.module.propose_citems_rx().await.borrow().clone();
+    assert_eq!(proposals.len(), 1);
+    assert!(matches!(
+        ConsensusCtrlCitem::decode_from_raw(&proposals[0])?,
+        ConsensusCtrlCitem::ReportEquivocation(_)
+    ));
+
+    let effects = setup
+        .core_module()
+        .db
+        .write_with_expect_falliable(|dbtx| {
+            setup.module.process_citem(
+                dbtx,
+                BlockRound::from(4),
+                peer1_pubkey,
+                &peer_set,
+                &proposals[0],
+            )
+        })
+        .await?;
+    assert!(effects.is_empty());
+
+    let equivocations = setup.core_module().get_equivocations().await;
+    assert_eq!(equivocations.len(), 1);
+    assert_eq!(equivocations[0].peer_pubkey, peer2_pubkey);
+
+    // The report is not proposed anymore, the removal vote is instead
+    let proposals = setup.module.propose_citems_rx().await.borrow().clone();
+    assert_eq!(proposals.len(), 1);
+    assert!(matches!(
+        ConsensusCtrlCitem::decode_from_raw(&proposals[0])?,
+        ConsensusCtrlCitem::VoteRemovePeer(peer) if peer == peer2_pubkey
+    ));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_equivocation_of_non_peer_is_rejected() -> BoxedErrorResult<()> {
+    let peer1_pubkey = PeerSeckey::generate().pubkey();
+    let peer2_pubkey = PeerSeckey::generate().pubkey();
+
+    let setup = MultiPeerTestSetup::bootstrap_with_peers(vec![peer1_pubkey, peer2_pubkey]).await?;
+    let peer_set: PeerSet = vec![peer1_pubkey, peer2_pubkey].into();
+
+    let evidence = make_equivocation_evidence(PeerSeckey::generate(), BlockRound::from(3));
+    let res = process_citem_as(
+        &setup,
+        &peer_set,
+        peer1_pubkey,
+        ConsensusCtrlCitem::ReportEquivocation(evidence),
+    )
+    .await;
+    assert!(res.is_err());
+    assert!(setup.core_module().get_equivocations().await.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reported_equivocation_keeps_existing_remove_vote() -> BoxedErrorResult<()> {
+    let peer1_pubkey = PeerSeckey::generate().pubkey();
+    let peer2_seckey = PeerSeckey::generate();
+    let peer2_pubkey = peer2_seckey.pubkey();
+    let peer3_pubkey = PeerSeckey::generate().pubkey();
+
+    let setup =
+        MultiPeerTestSetup::bootstrap_with_peers(vec![peer1_pubkey, peer2_pubkey, peer3_pubkey])
+            .await?;
+    let peer_set: PeerSet = vec![peer1_pubkey, peer2_pubkey, peer3_pubkey].into();
+
+    setup
+        .core_module()
+        .set_auto_remove_equivocating_peers(true)
+        .await;
+    setup
+        .core_module()
+        .set_pending_remove_peer_vote(peer3_pubkey)
+        .await?;
+
+    let evidence = make_equivocation_evidence(peer2_seckey, BlockRound::from(3));
+    process_citem_as(
+        &setup,
+        &peer_set,
+        peer1_pubkey,
+        ConsensusCtrlCitem::ReportEquivocation(evidence),
+    )
+    .await?;
+    assert_eq!(setup.core_module().get_equivocations().await.len(), 1);
+
+    let proposals = setup.module.propose_citems_rx().await.borrow().clone();
+    assert_eq!(proposals.len(), 1);
+    assert!(matches!(
+        ConsensusCtrlCitem::decode_from_raw(&proposals[0])?,
+        ConsensusCtrlCitem::VoteRemovePeer(peer) if peer == peer3_pubkey
+    ));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_processed_vote_refreshes_only_its_own_proposals() -> BoxedErrorResult<()> {
+    let peer1_pubkey = PeerSeckey::generate().pubkey();
+    let peer2_pubkey = PeerSeckey::generate().pubkey();
+    let peer3_pubkey = PeerSeckey::generate().pubkey();
+    let new_peer_pubkey = PeerSeckey::generate().pubkey();
+
+    let setup =
+        MultiPeerTestSetup::bootstrap_with_peers(vec![peer1_pubkey, peer2_pubkey, peer3_pubkey])
+            .await?;
+    let peer_set: PeerSet = vec![peer1_pubkey, peer2_pubkey, peer3_pubkey].into();
+
+    let max_len = 2 * ConsensusParams::DEFAULT_MAX_BLOCK_PAYLOAD_LEN;
+    setup
+        .core_module()
+        .set_pending_add_peer_vote(new_peer_pubkey)
+        .await?;
+    setup
+        .core_module()
+        .set_pending_set_max_block_payload_len_vote(max_len)
+        .await?;
+    assert_eq!(setup.core_module().propose_citems_rx.borrow().len(), 2);
+
+    // Our own vote, not reaching the threshold yet
+    let effects = process_citem_as(
+        &setup,
+        &peer_set,
+        peer1_pubkey,
+        ConsensusCtrlCitem::VoteAddPeer(new_peer_pubkey),
+    )
+    .await?;
+    assert!(effects.is_empty());
+
+    // Not using `propose_citems_rx`, as it would refresh all the proposals
+    let proposals = setup.core_module().propose_citems_rx.borrow().clone();
+    assert_eq!(proposals.len(), 1);
+    assert!(matches!(
+        ConsensusCtrlCitem::decode_from_raw(&proposals[0])?,
+        ConsensusCtrlCitem::VoteSetMaxBlockPayloadLen(len) if len == max_len
+    ));
+
+    Ok(())
+}
+
+async fn process_citem_as(
+    setup: &MultiPeerTestSetup,
+    peer_set: &PeerSet,
+    peer_pubkey: PeerPubkey,
+    citem: ConsensusCtrlCitem,
+) -> BoxedErrorResult<Vec<CItemEffect>> {
+    let citem_raw = citem.encode_to_raw();
+    // Processing panics on any table outside of the declared access
+    let access = setup
+        .module
+        .citem_access(&citem_raw)
+        .expect("Must declare access");
+    Ok(setup
+        .db
+        .write_with_expect_falliable(|dbtx| {
+            let dbtx = ModuleWriteTransactionCtx::new_with_access(ModuleId::new(0), dbtx, &access);
+            let peer_set = WeightedPeerSet::from(peer_set.clone());
+            setup.module.process_citem(
+                &dbtx,
+                BlockRound::from(4),
+                peer_pubkey,
+                &peer_set,
+                &citem_raw,
+            )
+        })
+        .await?)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_bls_keys_registered_by_all_peers_change_params() -> BoxedErrorResult<()> {
+    let peer1_seckey = PeerSeckey::generate();
+    let peer1_pubkey = peer1_seckey.pubkey();
+    let peer2_seckey = PeerSeckey::generate();
+    let peer2_pubkey = peer2_seckey.pubkey();
+
+    let setup = MultiPeerTestSetup::bootstrap_with_peers(vec![peer1_pubkey, peer2_pubkey]).await?;
+    let peer_set: PeerSet = vec![peer1_pubkey, peer2_pubkey].into();
+
+    let registration1 = BlsSeckey::derive_from(peer1_seckey).registration(peer1_pubkey);
+    let registration2 = BlsSeckey::derive_from(peer2_seckey).registration(peer2_pubkey);
+
+    // Our own registration should be proposed as a consensus item
+    setup
+        .core_module()
+        .set_pending_bls_key_registration(registration1)
+        .await;
+    let proposals = setup.module.propose_citems_rx().await.borrow().clone();
+    assert_eq!(proposals.len(), 1);
+    assert!(matches!(
+        ConsensusCtrlCitem::decode_from_raw(&proposals[0])?,
+        ConsensusCtrlCitem::RegisterBlsKey(registration) if registration == registration1
+    ));
+
+    // Registration signed for a different peer must be rejected
+    assert!(
+        process_citem_as(
+            &setup,
+            &peer_set,
+            peer2_pubkey,
+            ConsensusCtrlCitem::RegisterBlsKey(registration1)
+        )
+        .await
+        .is_err()
+    );
+
+    // Not all peers registered yet
+    let effects = process_citem_as(
+        &setup,
+        &peer_set,
+        peer1_pubkey,
+        ConsensusCtrlCitem::RegisterBlsKey(registration1),
+    )
+    .await?;
+    assert!(effects.is_empty());
+    let proposals = setup.module.propose_citems_rx().await.borrow().clone();
+    assert!(proposals.is_empty());
+
+    let effects = process_citem_as(
+        &setup,
+        &peer_set,
+        peer2_pubkey,
+        ConsensusCtrlCitem::RegisterBlsKey(registration2),
+    )
+    .await?;
+    assert_eq!(effects.len(), 1);
+    let change = ConsensusParamsChange::decode(&effects[0])
+        .map_err(|e| format!("Failed to decode ConsensusParamsChange: {e}"))?;
+    assert_eq!(change.peer_set, peer_set);
+    let expected_bls_pubkeys: Vec<_> = peer_set
+        .as_slice()
+        .iter()
+        .map(|peer| {
+            if *peer == peer1_pubkey {
+                registration1.pubkey
+            } else {
+                registration2.pubkey
+            }
+        })
+        .collect();
+    assert_eq!(change.bls_pubkeys, expected_bls_pubkeys);
+
+    // Re-registering the same key changes nothing
+    let effects = process_citem_as(
+        &setup,
+        &peer_set,
+        peer2_pubkey,
+        ConsensusCtrlCitem::RegisterBlsKey(registration2),
+    )
+    .await?;
+    assert!(effects.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_peer_weight_vote_changes_thresholds() -> BoxedErrorResult<()> {
+    let peer1_pubkey = PeerSeckey::generate().pubkey();
+    let peer2_pubkey = PeerSeckey::generate().pubkey();
+
+    let setup = MultiPeerTestSetup::bootstrap_with_peers(vec![peer1_pubkey, peer2_pubkey]).await?;
+    let peer_set: PeerSet = vec![peer1_pubkey, peer2_pubkey].into();
+
+    let set_weight = ConsensusCtrlCitem::VoteSetPeerWeight {
+        peer: peer1_pubkey,
+        weight: 3,
+    };
+
+    // Zero weight is never valid
+    assert!(
+        process_citem_as(
+            &setup,
+            &peer_set,
+            peer1_pubkey,
+            ConsensusCtrlCitem::VoteSetPeerWeight {
+                peer: peer1_pubkey,
+                weight: 0,
+            },
+        )
+        .await
+        .is_err()
+    );
+
+    let effects = process_citem_as(&setup, &peer_set, peer1_pubkey, set_weight.clone()).await?;
+    assert!(effects.is_empty());
+
+    let effects = process_citem_as(&setup, &peer_set, peer2_pubkey, set_weight).await?;
+    assert_eq!(effects.len(), 2);
+    let set_weight_effect: SetPeerWeightEffect = EffectKindExt::decode(&effects[0])
+        .map_err(|e| format!("Failed to decode SetPeerWeightEffect: {e}"))?;
+    assert_eq!(set_weight_effect.peer, peer1_pubkey);
+    assert_eq!(set_weight_effect.weight, 3);
+    let change = ConsensusParamsChange::decode(&effects[1])
+        .map_err(|e| format!("Failed to decode ConsensusParamsChange: {e}"))?;
+    let expected_weights: Vec<_> = peer_set
+        .as_slice()
+        .iter()
+        .map(|peer| if *peer == peer1_pubkey { 3 } else { 1 })
+        .collect();
+    assert_eq!(change.peer_weights, expected_weights);
+    assert_eq!(
+        setup.core_module().get_peer_weights().await,
+        [(peer1_pubkey, 3), (peer2_pubkey, 1)].into()
+    );
+
+    // Now the first peer alone has a threshold of the weight
+    let new_peer_pubkey = PeerSeckey::generate().pubkey();
+    let effects = process_citem_as(
+        &setup,
+        &peer_set,
+        peer1_pubkey,
+        ConsensusCtrlCitem::VoteAddPeer(new_peer_pubkey),
+    )
+    .await?;
+    assert_eq!(effects.len(), 2);
+    let change = ConsensusParamsChange::decode(&effects[1])
+        .map_err(|e| format!("Failed to decode ConsensusParamsChange: {e}"))?;
+    assert_eq!(change.peer_set.len(), 3);
+    assert_eq!(change.peer_weights.iter().sum::<u32>(), 5);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_max_block_payload_len_vote() -> BoxedErrorResult<()> {
+    let peer1_pubkey = PeerSeckey::generate().pubkey();
+    let peer2_pubkey = PeerSeckey::generate().pubkey();
+
+    let setup = MultiPeerTestSetup::bootstrap_with_peers(vec![peer1_pubkey, peer2_pubkey]).await?;
+    let peer_set: PeerSet = vec![peer1_pubkey, peer2_pubkey].into();
+
+    // Too low to be ever valid
+    assert!(
+        process_citem_as(
+            &setup,
+            &peer_set,
+            peer1_pubkey,
+            ConsensusCtrlCitem::VoteSetMaxBlockPayloadLen(
+                ConsensusParams::MIN_MAX_BLOCK_PAYLOAD_LEN - 1
+            ),
+        )
+        .await
+        .is_err()
+    );
+
+    let max_len = 2 * ConsensusParams::DEFAULT_MAX_BLOCK_PAYLOAD_LEN;
+    let set_len = ConsensusCtrlCitem::VoteSetMaxBlockPayloadLen(max_len);
+
+    let effects = process_citem_as(&setup, &peer_set, peer1_pubkey, set_len.clone()).await?;
+    assert!(effects.is_empty());
+    assert_eq!(
+        setup.core_module().get_max_block_payload_len().await,
+        ConsensusParams::DEFAULT_MAX_BLOCK_PAYLOAD_LEN
+    );
+
+    let effects = process_citem_as(&setup, &peer_set, peer2_pubkey, set_len).await?;
+    assert_eq!(effects.len(), 2);
+    let set_len_effect: SetMaxBlockPayloadLenEffect = EffectKindExt::decode(&effects[0])
+        .map_err(|e| format!("Failed to decode SetMaxBlockPayloadLenEffect: {e}"))?;
+    assert_eq!(set_len_effect.max_block_payload_len, max_len);
+    let change = ConsensusParamsChange::decode(&effects[1])
+        .map_err(|e| format!("Failed to decode ConsensusParamsChange: {e}"))?;
+    assert_eq!(change.max_block_payload_len, max_len);
+    assert_eq!(
+        setup.core_module().get_max_block_payload_len().await,
+        max_len
+    );
+    assert!(
+        setup
+            .core_module()
+            .get_set_max_block_payload_len_votes()
+            .await
+            .is_empty()
+    );
+
+    // Other consensus params changes keep the limit
+    let add_peer = ConsensusCtrlCitem::VoteAddPeer(PeerSeckey::generate().pubkey());
+    process_citem_as(&setup, &peer_set, peer1_pubkey, add_peer.clone()).await?;
+    let effects = process_citem_as(&setup, &peer_set, peer2_pubkey, add_peer).await?;
+    assert_eq!(effects.len(), 2);
+    let change = ConsensusParamsChange::decode(&effects[1])
+        .map_err(|e| format!("Failed to decode ConsensusParamsChange: {e}"))?;
+    assert_eq!(change.peer_set.len(), 3);
+    assert_eq!(change.max_block_payload_len, max_len);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rotate_key_replaces_peer() -> BoxedErrorResult<()> {
+    let peer1_seckey = PeerSeckey::generate();
+    let peer1_pubkey = peer1_seckey.pubkey();
+    let peer2_pubkey = PeerSeckey::generate().pubkey();
+
+    let setup = MultiPeerTestSetup::bootstrap_with_peers(vec![peer1_pubkey, peer2_pubkey]).await?;
+    let peer_set: PeerSet = vec![peer1_pubkey, peer2_pubkey].into();
+
+    // Pending vote cast by the old key should carry over
+    let candidate_pubkey = PeerSeckey::generate().pubkey();
+    let effects = process_citem_as(
+        &setup,
+        &peer_set,
+        peer1_pubkey,
+        ConsensusCtrlCitem::VoteAddPeer(candidate_pubkey),
+    )
+    .await?;
+    assert!(effects.is_empty());
+
+    // Can't rotate into an existing peer
+    assert!(
+        process_citem_as(
+            &setup,
+            &peer_set,
+            peer1_pubkey,
+            ConsensusCtrlCitem::RotateKey {
+                rotation: KeyRotation::new_signed(peer1_seckey, peer2_pubkey),
+                bls_registration: None,
+            },
+        )
+        .await
+        .is_err()
+    );
+
+    // Must be signed by the old key
+    let new_pubkey = PeerSeckey::generate().pubkey();
+    let mut forged = KeyRotation::new_signed(PeerSeckey::generate(), new_pubkey);
+    forged.inner.old_pubkey = peer1_pubkey;
+    assert!(
+        process_citem_as(
+            &setup,
+            &peer_set,
+            peer2_pubkey,
+            ConsensusCtrlCitem::RotateKey {
+                rotation: forged,
+                bls_registration: None,
+            },
+        )
+        .await
+        .is_err()
+    );
+
+    let effects = process_citem_as(
+        &setup,
+        &peer_set,
+        peer1_pubkey,
+        ConsensusCtrlCitem::RotateKey {
+            rotation: KeyRotation::new_signed(peer1_seckey, new_pubkey),
+            bls_registration: None,
+        },
+    )
+    .await?;
+    assert_eq!(effects.len(), 2);
+    let rotate_effect = RotateKeyEffect::decode(&effects[0])
+        .map_err(|e| format!("Failed to decode RotateKeyEffect: {e}"))?;
+    assert_eq!(rotate_effect.old_pubkey, peer1_pubkey);
+    assert_eq!(rotate_effect.new_pubkey, new_pubkey);
+    let change = ConsensusParamsChange::decode(&effects[1])
+        .map_err(|e| format!("Failed to decode ConsensusParamsChange: {e}"))?;
+    let expected_peer_set: PeerSet = vec![new_pubkey, peer2_pubkey].into();
+    assert_eq!(change.peer_set, expected_peer_set);
+    assert_eq!(setup.core_module().get_peer_set().await, expected_peer_set);
+    assert_eq!(
+        setup.core_module().get_add_peer_votes().await,
+        [(new_pubkey, candidate_pubkey)].into()
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rotate_key_can_not_be_replayed() -> BoxedErrorResult<()> {
+    let peer1_seckey = PeerSeckey::generate();
+    let peer1_pubkey = peer1_seckey.pubkey();
+    let peer2_pubkey = PeerSeckey::generate().pubkey();
+    let new_seckey = PeerSeckey::generate();
+    let new_pubkey = new_seckey.pubkey();
+    let newer_pubkey = PeerSeckey::generate().pubkey();
+
+    let setup = MultiPeerTestSetup::bootstrap_with_peers(vec![peer1_pubkey, peer2_pubkey]).await?;
+    let rotation = KeyRotation::new_signed(peer1_seckey, new_pubkey);
+
+    process_citem_as(
+        &setup,
+        &vec![peer1_pubkey, peer2_pubkey].into(),
+        peer1_pubkey,
+        ConsensusCtrlCitem::RotateKey {
+            rotation,
+            bls_registration: None,
+        },
+    )
+    .await?;
+    let peer_set: PeerSet = vec![new_pubkey, peer2_pubkey].into();
+    process_citem_as(
+        &setup,
+        &peer_set,
+        new_pubkey,
+        ConsensusCtrlCitem::RotateKey {
+            rotation: KeyRotation::new_signed(new_seckey, newer_pubkey),
+            bls_registration: None,
+        },
+    )
+    .await?;
+
+    // Add the old key back
+    let peer_set: PeerSet = vec![newer_pubkey, peer2_pubkey].into();
+    for voter in [newer_pubkey, peer2_pubkey] {
+        process_citem_as(
+            &setup,
+            &peer_set,
+            voter,
+            ConsensusCtrlCitem::VoteAddPeer(peer1_pubkey),
+        )
+        .await?;
+    }
+    let peer_set = setup.core_module().get_peer_set().await;
+    assert!(peer_set.contains(&peer1_pubkey));
+
+    // Old key and the new one are valid for the rotation again, but it was
+    // already applied
+    assert!(
+        process_citem_as(
+            &setup,
+            &peer_set,
+            peer2_pubkey,
+            ConsensusCtrlCitem::RotateKey {
+                rotation,
+                bls_registration: None,
+            },
+        )
+        .await
+        .is_err()
+    );
+    assert_eq!(setup.core_module().get_peer_set().await, peer_set);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rotate_key_keeps_bls_aggregation() -> BoxedErrorResult<()> {
+    let peer1_seckey = PeerSeckey::generate();
+    let peer1_pubkey = peer1_seckey.pubkey();
+    let peer2_seckey = PeerSeckey::generate();
+    let peer2_pubkey = peer2_seckey.pubkey();
+
+    let setup = MultiPeerTestSetup::bootstrap_with_peers(vec![peer1_pubkey, peer2_pubkey]).await?;
+    let peer_set: PeerSet = vec![peer1_pubkey, peer2_pubkey].into();
+
+    for (seckey, pubkey) in [(peer1_seckey, peer1_pubkey), (peer2_seckey, peer2_pubkey)] {
+        process_citem_as(
+            &setup,
+            &peer_set,
+            pubkey,
+            ConsensusCtrlCitem::RegisterBlsKey(BlsSeckey::derive_from(seckey).registration(pubkey)),
+        )
+        .await?;
+    }
+    assert_eq!(setup.core_module().get_bls_pubkeys().await.len(), 2);
+
+    let new_seckey = PeerSeckey::generate();
+    let new_pubkey = new_seckey.pubkey();
+    let rotation = KeyRotation::new_signed(peer1_seckey, new_pubkey);
+    let new_registration = BlsSeckey::derive_from(new_seckey).registration(new_pubkey);
+
+    // Rotating without a BLS key would disable aggregation for everyone
+    assert!(
+        process_citem_as(
+            &setup,
+            &peer_set,
+            peer1_pubkey,
+            ConsensusCtrlCitem::RotateKey {
+                rotation,
+                bls_registration: None,
+            },
+        )
+        .await
+        .is_err()
+    );
+
+    // Registration must be bound to the new key
+    assert!(
+        process_citem_as(
+            &setup,
+            &peer_set,
+            peer1_pubkey,
+            ConsensusCtrlCitem::RotateKey {
+                rotation,
+                bls_registration: Some(
+                    BlsSeckey::derive_from(new_seckey).registration(peer1_pubkey)
+                ),
+            },
+        )
+        .await
+        .is_err()
+    );
+
+    let effects = process_citem_as(
+        &setup,
+        &peer_set,
+        peer1_pubkey,
+        ConsensusCtrlCitem::RotateKey {
+            rotation,
+            bls_registration: Some(new_registration),
+        },
+    )
+    .await?;
+    assert_eq!(effects.len(), 2);
+    let change = ConsensusParamsChange::decode(&effects[1])
+        .map_err(|e| format!("Failed to decode ConsensusParamsChange: {e}"))?;
+    let expected_peer_set: PeerSet = vec![new_pubkey, peer2_pubkey].into();
+    assert_eq!(change.peer_set, expected_peer_set);
+    let expected_bls_pubkeys: Vec<_> = expected_peer_set
+        .as_slice()
+        .iter()
+        .map(|peer| {
+            if *peer == new_pubkey {
+                new_registration.pubkey
+            } else {
+                BlsSeckey::derive_from(peer2_seckey).pubkey()
+            }
+        })
+        .collect();
+    assert_eq!(change.bls_pubkeys, expected_bls_pubkeys);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_snapshot_does_not_depend_on_self_version() -> BoxedErrorResult<()> {
+    let peer_pubkeys = vec![
+        PeerSeckey::generate().pubkey(),
+        PeerSeckey::generate().pubkey(),
+    ];
+
+    let mut snapshots = vec![];
+    for minor in [0, 1] {
+        let setup = MultiPeerTestSetup::bootstrap_with_peers(peer_pubkeys.clone()).await?;
+
+        // Peers running different software versions
+        setup
+            .core_module()
+            .db
+            .write_with_expect(|dbtx| {
+                dbtx.open_table(&tables::self_version::TABLE)?
+                    .insert(&(), &ConsensusVersion::new(0, minor))?;
+                Ok(())
+            })
+            .await;
+
+        let consensus_tables = [(ModuleId::new(0), setup.module.consensus_tables())].into();
+        snapshots.push(
+            setup
+                .db
+                .read_with_expect(|dbtx| dump_modules_tables(dbtx, &consensus_tables))
+                .await,
+        );
+    }
+
+    assert!(!snapshots[0].is_empty());
+    assert_eq!(snapshots[0], snapshots[1]);
+
+    Ok(())
+}
+
+#[test]
+fn decode_citem() {
+    let init = ConsensusCtrlModuleInit;
+    let peer_pubkey = PeerSeckey::generate().pubkey();
+
+    let decoded = init
+        .decode_citem(&ConsensusCtrlCitem::VoteAddPeer(peer_pubkey).encode_to_raw())
+        .expect("Must decode");
+    assert!(decoded.get("VoteAddPeer").is_some());
+
+    assert!(
+        init.decode_citem(&CItemRaw(Arc::from([0xffu8; 3].as_slice())))
+            .is_none()
+    );
+}
@@ -18,6 +18,13 @@ pub enum MetaCitem {
 }
 
 impl MetaCitem {
+    /// Key the citem is about
+    pub fn key(&self) -> u8 {
+        match self {
+            Self::ProposeValue { key, .. } | Self::ApproveVote { key, .. } => *key,
+        }
+    }
+
     pub fn encode_to_raw(&self) -> CItemRaw {
         let serialized = bincode::encode_to_vec(self, CONSENSUS_BINCODE_CONFIG)
             .expect("encoding should not fail");
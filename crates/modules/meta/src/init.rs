@@ -2,11 +2,13 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use bfte_consensus_core::citem::CItemRaw;
 use bfte_consensus_core::module::ModuleKind;
 use bfte_module::module::{
     IModule, IModuleInit, ModuleInitArgs, ModuleInitResult, ModuleSupportedConsensusVersions,
 };
 
+use crate::citem::MetaCitem;
 use crate::module::MetaModule;
 use crate::{CURRENT_VERSION_MAJOR, CURRENT_VERSION_MINOR, KIND};
 
@@ -68,4 +70,8 @@ impl IModuleInit for MetaModuleInit {
 
         Ok(Arc::new(module))
     }
+
+    fn decode_citem(&self, citem: &CItemRaw) -> Option<serde_json::Value> {
+        serde_json::to_value(MetaCitem::decode_from_raw(citem).ok()?).ok()
+    }
 }
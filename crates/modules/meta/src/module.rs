@@ -4,23 +4,24 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use bfte_consensus_core::block::BlockRound;
 use bfte_consensus_core::citem::{CItemRaw, InputRaw, OutputRaw};
-use bfte_consensus_core::num_peers::ToNumPeers;
 use bfte_consensus_core::peer::PeerPubkey;
-use bfte_consensus_core::peer_set::PeerSet;
+use bfte_consensus_core::peer_set::WeightedPeerSet;
 use bfte_consensus_core::ver::ConsensusVersion;
 use bfte_db::error::TxSnafu;
 use bfte_module::effect::{CItemEffect, EffectKind, EffectKindExt, ModuleCItemEffect};
-use bfte_module::module::IModule;
+use bfte_module::module::access::AccessSet;
 use bfte_module::module::db::{
-    DbResult, DbTxResult, ModuleDatabase, ModuleReadableTransaction, ModuleWriteTransactionCtx,
+    ConsensusTables, DbResult, DbTxResult, ModuleDatabase, ModuleReadableTransaction,
+    ModuleWriteTransactionCtx,
 };
+use bfte_module::module::{IModule, OwnCItemOutcome};
 use bfte_module_consensus_ctrl::effects::RemovePeerEffect;
 use bfte_util_db::redb_bincode::ReadableTable as _;
+use bfte_util_db::versioned;
 use bfte_util_error::{Whatever, WhateverResult};
-use convi::CastFrom as _;
 use snafu::{OptionExt as _, ResultExt as _};
 use tokio::sync::watch;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::citem::MetaCitem;
 use crate::effects::KeyValueConsensusEffect;
@@ -57,6 +58,7 @@ impl MetaModule {
         new_version: ConsensusVersion,
     ) -> DbResult<()> {
         dbtx.open_table(&tables::consensus_values::TABLE)?;
+        dbtx.open_table(&tables::consensus_values::HISTORY_TABLE)?;
         dbtx.open_table(&tables::key_value_votes::TABLE)?;
         dbtx.open_table(&tables::pending_proposals::TABLE)?;
 
@@ -88,6 +90,30 @@ impl MetaModule {
             .await
     }
 
+    /// Get consensus values agreed as of the end of `round`
+    ///
+    /// Values agreed before the history was being recorded are not included.
+    pub async fn get_consensus_values_at(&self, round: BlockRound) -> BTreeMap<u8, Arc<[u8]>> {
+        self.db
+            .read_at_round_with_expect(round, |dbtx| {
+                Ok(dbtx
+                    .open_table(&tables::consensus_values::HISTORY_TABLE)?
+                    .entries()?)
+            })
+            .await
+    }
+
+    /// Get consensus value of `key` agreed as of the end of `round`
+    pub async fn get_consensus_value_at(&self, key: u8, round: BlockRound) -> Option<Arc<[u8]>> {
+        self.db
+            .read_at_round_with_expect(round, |dbtx| {
+                Ok(dbtx
+                    .open_table(&tables::consensus_values::HISTORY_TABLE)?
+                    .get(&key)?)
+            })
+            .await
+    }
+
     /// Get current votes for a specific key
     pub async fn get_votes_for_key(&self, key: u8) -> BTreeMap<PeerPubkey, Arc<[u8]>> {
         self.db
@@ -130,17 +156,39 @@ impl MetaModule {
         &self,
         dbtx: &impl ModuleReadableTransaction<'dbtx>,
     ) -> DbResult<Vec<CItemRaw>> {
+        let keys = dbtx
+            .open_table(&tables::pending_proposals::TABLE)?
+            .range(..)?
+            .map(|kv| {
+                let (key, _) = kv?;
+                Ok(key.value())
+            })
+            .collect::<DbResult<Vec<_>>>()?;
+
         let mut proposals = vec![];
+        for key in keys {
+            proposals.extend(self.key_proposal_dbtx(dbtx, key)?);
+        }
 
-        // Get pending proposals
-        let pending_tbl = dbtx.open_table(&tables::pending_proposals::TABLE)?;
-        let votes_tbl = dbtx.open_table(&tables::key_value_votes::TABLE)?;
+        Ok(proposals)
+    }
 
-        for kv in pending_tbl.range(..)? {
-            let (key, value) = kv?;
-            let key = key.value();
-            let value = value.value();
+    /// Our proposal for `key`, if we have a pending one not recorded yet
+    fn key_proposal_dbtx<'dbtx>(
+        &self,
+        dbtx: &impl ModuleReadableTransaction<'dbtx>,
+        key: u8,
+    ) -> DbResult<Option<CItemRaw>> {
+        let Some(value) = dbtx
+            .open_table(&tables::pending_proposals::TABLE)?
+            .get(&key)?
+            .map(|v| v.value())
+        else {
+            return Ok(None);
+        };
+        let votes_tbl = dbtx.open_table(&tables::key_value_votes::TABLE)?;
 
+        {
             // Check if we already have the same vote for this key
             let should_propose = if let Some(peer_pubkey) = &self.peer_pubkey {
                 match votes_tbl.get(&(key, *peer_pubkey))? {
@@ -176,18 +224,19 @@ impl MetaModule {
                     MetaCitem::ProposeValue { key, value }
                 };
 
-                proposals.push(citem.encode_to_raw());
+                return Ok(Some(citem.encode_to_raw()));
             }
         }
 
-        Ok(proposals)
+        Ok(None)
     }
 
     fn process_citem_vote_key_value(
         &self,
         dbtx: &ModuleWriteTransactionCtx,
+        round: BlockRound,
         voter_pubkey: PeerPubkey,
-        peer_set: &PeerSet,
+        peer_set: &WeightedPeerSet,
         key: u8,
         value: Arc<[u8]>,
     ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
@@ -207,20 +256,19 @@ impl MetaModule {
         // Record the vote
         votes_tbl.insert(&(key, voter_pubkey), &value)?;
 
-        // Count votes for this specific value from peers in the current peer set
-        let mut vote_count = 0;
+        // Collect votes for this specific value from peers in the current peer set
+        let mut voters = vec![];
         for kv in votes_tbl.range((key, PeerPubkey::ZERO)..=(key, PeerPubkey::MAX))? {
             let (key_and_peer, vote_value) = kv?;
 
             let (_, voter) = key_and_peer.value();
             if vote_value.value() == value && peer_set.contains(&voter) {
-                vote_count += 1;
+                voters.push(voter);
             }
         }
 
         // Check if we've reached threshold
-        let threshold = peer_set.to_num_peers().threshold();
-        if vote_count >= threshold {
+        if peer_set.is_threshold(&voters) {
             // Threshold reached - set consensus value and emit effect
 
             // Remove pending proposal for this key if it exists
@@ -232,7 +280,14 @@ impl MetaModule {
             // Set the consensus value
             {
                 let mut consensus_tbl = dbtx.open_table(&tables::consensus_values::TABLE)?;
-                consensus_tbl.insert(&key, &value)?;
+                let mut history_tbl = dbtx.open_table(&tables::consensus_values::HISTORY_TABLE)?;
+                versioned::insert(
+                    &mut consensus_tbl,
+                    &mut history_tbl,
+                    round.to_number().into(),
+                    &key,
+                    &value,
+                )?;
             }
 
             // Clear votes for this key from current peer set since consensus is reached
@@ -249,8 +304,9 @@ impl MetaModule {
     fn process_citem_approve_vote(
         &self,
         dbtx: &ModuleWriteTransactionCtx,
+        round: BlockRound,
         voter_pubkey: PeerPubkey,
-        peer_set: &PeerSet,
+        peer_set: &WeightedPeerSet,
         key: u8,
         approved_peer: PeerPubkey,
     ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
@@ -262,7 +318,7 @@ impl MetaModule {
             Some(approved_value) => {
                 // Cast our vote for the same value as the approved peer
                 let value = approved_value.value();
-                self.process_citem_vote_key_value(dbtx, voter_pubkey, peer_set, key, value)
+                self.process_citem_vote_key_value(dbtx, round, voter_pubkey, peer_set, key, value)
             }
             None => {
                 // The approved peer hasn't voted for this key, which is invalid
@@ -278,10 +334,12 @@ impl MetaModule {
     fn recheck_consensus_after_peer_removal(
         &self,
         dbtx: &ModuleWriteTransactionCtx,
-        peer_set: &PeerSet,
+        round: BlockRound,
+        peer_set: &WeightedPeerSet,
     ) -> DbTxResult<(), Whatever> {
-        let votes_tbl = dbtx.open_table(&tables::key_value_votes::TABLE)?;
-        let consensus_tbl = dbtx.open_table(&tables::consensus_values::TABLE)?;
+        let mut votes_tbl = dbtx.open_table(&tables::key_value_votes::TABLE)?;
+        let mut consensus_tbl = dbtx.open_table(&tables::consensus_values::TABLE)?;
+        let mut history_tbl = dbtx.open_table(&tables::consensus_values::HISTORY_TABLE)?;
 
         // Collect all unique keys that have votes
         let mut keys_with_votes = std::collections::BTreeSet::new();
@@ -298,32 +356,35 @@ impl MetaModule {
                 continue;
             }
 
-            // Count votes for each value from current peer set members
-            let mut value_counts: BTreeMap<Arc<[u8]>, u32> = BTreeMap::new();
+            // Collect votes for each value from current peer set members
+            let mut value_voters: BTreeMap<Arc<[u8]>, Vec<PeerPubkey>> = BTreeMap::new();
             for kv in votes_tbl.range((key, PeerPubkey::ZERO)..=(key, PeerPubkey::MAX))? {
                 let (key_and_peer, vote_value) = kv?;
                 let (_, voter) = key_and_peer.value();
 
                 if peer_set.contains(&voter) {
                     let value = vote_value.value();
-                    *value_counts.entry(value).or_insert(0) += 1;
+                    value_voters.entry(value).or_default().push(voter);
                 }
             }
 
             // Check if any value has reached threshold
-            let threshold = peer_set.to_num_peers().threshold();
-            for (value, count) in value_counts {
-                if usize::cast_from(count) >= threshold {
+            for (value, voters) in value_voters {
+                if peer_set.is_threshold(&voters) {
                     // Consensus reached! Set the value
-                    let mut consensus_tbl = dbtx.open_table(&tables::consensus_values::TABLE)?;
-                    consensus_tbl.insert(&key, &value)?;
+                    versioned::insert(
+                        &mut consensus_tbl,
+                        &mut history_tbl,
+                        round.to_number().into(),
+                        &key,
+                        &value,
+                    )?;
 
                     // Remove pending proposal for this key if it exists
                     let mut pending_tbl = dbtx.open_table(&tables::pending_proposals::TABLE)?;
                     pending_tbl.remove(&key)?;
 
                     // Clear votes for this key from current peer set since consensus is reached
-                    let mut votes_tbl = dbtx.open_table(&tables::key_value_votes::TABLE)?;
                     votes_tbl.retain(|(vote_key, voter), _| {
                         *vote_key != key || !peer_set.contains(voter)
                     })?;
@@ -348,38 +409,102 @@ impl IModule for MetaModule {
         self.propose_citems_rx.clone()
     }
 
+    async fn on_own_citem_processed(
+        &self,
+        round: BlockRound,
+        citem: &CItemRaw,
+        outcome: OwnCItemOutcome,
+    ) {
+        let OwnCItemOutcome::Rejected { err } = outcome else {
+            return;
+        };
+        let Ok(citem) = MetaCitem::decode_from_raw(citem) else {
+            return;
+        };
+        warn!(target: LOG_TARGET, %round, %err, ?citem, "Own citem rejected");
+
+        // An approval is retried as whatever the current votes call for, as
+        // the approved vote might have changed or been cleared, while a
+        // rejected proposal would only keep getting rejected
+        if let MetaCitem::ProposeValue { key, value } = citem {
+            self.db
+                .write_with_expect(|dbtx| {
+                    let mut tbl = dbtx.open_table(&tables::pending_proposals::TABLE)?;
+                    if tbl
+                        .get(&key)?
+                        .is_some_and(|pending| pending.value() == value)
+                    {
+                        tbl.remove(&key)?;
+                    }
+                    Ok(())
+                })
+                .await;
+        }
+        self.refresh_consensus_proposals().await;
+    }
+
     fn process_citem(
         &self,
         dbtx: &ModuleWriteTransactionCtx,
-        _round: BlockRound,
+        round: BlockRound,
         peer_pubkey: PeerPubkey,
-        peer_set: &PeerSet,
+        peer_set: &WeightedPeerSet,
         citem: &CItemRaw,
     ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
         assert!(peer_set.contains(&peer_pubkey));
         let citem = MetaCitem::decode_from_raw(citem).context(TxSnafu)?;
+        let key = citem.key();
 
         let res = match citem {
             MetaCitem::ProposeValue { key, value } => {
-                self.process_citem_vote_key_value(dbtx, peer_pubkey, peer_set, key, value)
+                self.process_citem_vote_key_value(dbtx, round, peer_pubkey, peer_set, key, value)
             }
             MetaCitem::ApproveVote {
                 key,
                 peer_pubkey: approved_peer,
-            } => self.process_citem_approve_vote(dbtx, peer_pubkey, peer_set, key, approved_peer),
+            } => self.process_citem_approve_vote(
+                dbtx,
+                round,
+                peer_pubkey,
+                peer_set,
+                key,
+                approved_peer,
+            ),
         }?;
 
-        // Refresh proposals after processing
-        let proposals = self.refresh_consensus_proposals_dbtx(dbtx)?;
+        // Refresh only the proposal for the processed key, to stay within
+        // the declared access
+        let proposal = self.key_proposal_dbtx(dbtx, key)?;
         let tx = self.propose_citems_tx.clone();
 
         dbtx.on_commit(move || {
-            tx.send_replace(proposals);
+            tx.send_modify(|proposals| {
+                let proposal_key = |citem: &CItemRaw| {
+                    MetaCitem::decode_from_raw(citem)
+                        .ok()
+                        .map(|citem| citem.key())
+                };
+                proposals.retain(|citem| proposal_key(citem) != Some(key));
+                proposals.extend(proposal);
+                proposals.sort_by_key(proposal_key);
+            });
         });
 
         Ok(res)
     }
 
+    fn citem_access(&self, citem: &CItemRaw) -> Option<AccessSet> {
+        let key = MetaCitem::decode_from_raw(citem).ok()?.key();
+
+        Some(
+            AccessSet::new()
+                .with_key(&tables::key_value_votes::TABLE, &key)
+                .with_key(&tables::consensus_values::TABLE, &key)
+                .with_key(&tables::consensus_values::HISTORY_TABLE, &key)
+                .with_key(&tables::pending_proposals::TABLE, &key),
+        )
+    }
+
     fn process_input(
         &self,
         _dbtx: &ModuleWriteTransactionCtx,
@@ -401,7 +526,8 @@ impl IModule for MetaModule {
     fn process_effects(
         &self,
         dbtx: &ModuleWriteTransactionCtx,
-        peer_set: &PeerSet,
+        round: BlockRound,
+        peer_set: &WeightedPeerSet,
         effects: &[ModuleCItemEffect],
     ) -> DbTxResult<(), Whatever> {
         for effect in effects {
@@ -413,10 +539,17 @@ impl IModule for MetaModule {
             // Handle RemovePeerEffect
             if effect.inner().effect_id == RemovePeerEffect::EFFECT_ID {
                 // A peer was removed, recheck if existing votes can now reach consensus
-                self.recheck_consensus_after_peer_removal(dbtx, peer_set)?;
+                self.recheck_consensus_after_peer_removal(dbtx, round, peer_set)?;
             }
         }
 
         Ok(())
     }
+
+    fn consensus_tables(&self) -> ConsensusTables {
+        ConsensusTables::new()
+            .with(&tables::key_value_votes::TABLE)
+            .with(&tables::consensus_values::TABLE)
+            .with(&tables::consensus_values::HISTORY_TABLE)
+    }
 }
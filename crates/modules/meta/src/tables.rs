@@ -21,7 +21,9 @@ def_table! {
 def_table! {
     /// Tracks the current agreed consensus values for keys
     /// key -> agreed value
-    consensus_values: u8 => Arc<[u8]>
+    ///
+    /// Versioned, to allow querying values agreed as of a past round.
+    versioned consensus_values: u8 => Arc<[u8]>
 }
 
 def_table! {
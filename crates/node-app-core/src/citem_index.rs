@@ -0,0 +1,316 @@
+//! Index of finalized citems
+//!
+//! Maintained by the application logic as it processes blocks, in the same
+//! database transaction, and read by the node, e.g. for the explorer.
+
+use std::collections::BTreeSet;
+use std::ops::Bound;
+
+use bfte_consensus_core::block::{BlockCItemIdx, BlockRound};
+use bfte_consensus_core::citem::CItem;
+use bfte_consensus_core::citem::transaction::TransactionHash;
+use bfte_consensus_core::module::ModuleId;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_db::ctx::WriteTransactionCtx;
+use bfte_db::error::DbResult;
+use bfte_util_db::def_table;
+use bfte_util_db::redb_bincode::{ReadTransaction, ReadableTable as _};
+use bincode::{Decode, Encode};
+
+/// Position of a citem in the finalized history
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CItemLocation {
+    pub round: BlockRound,
+    pub idx: BlockCItemIdx,
+}
+
+impl CItemLocation {
+    pub const MAX: Self = Self {
+        round: BlockRound::MAX,
+        idx: BlockCItemIdx::MAX,
+    };
+
+    pub fn new(round: BlockRound, idx: BlockCItemIdx) -> Self {
+        Self { round, idx }
+    }
+
+    fn first_in_round(round: BlockRound) -> Self {
+        Self::new(round, BlockCItemIdx::ZERO)
+    }
+}
+
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub enum CItemOutcome {
+    Accepted,
+    Rejected { err: String },
+}
+
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct CItemIndexEntry {
+    /// Timestamp of the block the citem was included in
+    pub timestamp: Timestamp,
+    /// Peer the citem is attributed to
+    ///
+    /// The author of a peer citem, or the leader that included a transaction.
+    pub peer_pubkey: PeerPubkey,
+    /// Modules the citem was processed by
+    pub module_ids: Vec<ModuleId>,
+    pub tx_hash: Option<TransactionHash>,
+    pub outcome: CItemOutcome,
+}
+
+impl CItemIndexEntry {
+    pub fn new(
+        citem: &CItem,
+        timestamp: Timestamp,
+        leader_pubkey: PeerPubkey,
+        outcome: CItemOutcome,
+    ) -> Self {
+        let (peer_pubkey, module_ids, tx_hash) = match citem {
+            CItem::PeerCItem(module_citem) => (leader_pubkey, vec![module_citem.module_id()], None),
+            CItem::ForwardedPeerCItem(forwarded) => (
+                forwarded.peer_pubkey,
+                vec![forwarded.citem.module_id()],
+                None,
+            ),
+            CItem::Transaction(transaction) => (
+                leader_pubkey,
+                transaction
+                    .inner
+                    .inputs
+                    .iter()
+                    .map(|input| input.module_id())
+                    .chain(
+                        transaction
+                            .inner
+                            .outputs
+                            .iter()
+                            .map(|output| output.module_id()),
+                    )
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect(),
+                Some(transaction.tx_hash()),
+            ),
+        };
+
+        Self {
+            timestamp,
+            peer_pubkey,
+            module_ids,
+            tx_hash,
+            outcome,
+        }
+    }
+}
+
+/// Criteria of [`query_citems_dbtx`], all of which must match
+#[derive(Debug, Clone, Default)]
+pub struct CItemIndexFilter {
+    pub module_id: Option<ModuleId>,
+    pub peer_pubkey: Option<PeerPubkey>,
+    /// First round to include
+    pub from_round: Option<BlockRound>,
+    /// Last round to include
+    pub to_round: Option<BlockRound>,
+    /// Earliest block timestamp to include
+    pub since: Option<Timestamp>,
+}
+
+impl CItemIndexFilter {
+    fn matches(&self, entry: &CItemIndexEntry) -> bool {
+        self.module_id
+            .is_none_or(|module_id| entry.module_ids.contains(&module_id))
+            && self
+                .peer_pubkey
+                .is_none_or(|peer_pubkey| entry.peer_pubkey == peer_pubkey)
+    }
+}
+
+def_table! {
+    /// All finalized citems
+    app_citems: CItemLocation => CItemIndexEntry
+}
+
+def_table! {
+    /// Citems by modules that processed them
+    app_citems_by_module: (ModuleId, CItemLocation) => ()
+}
+
+def_table! {
+    /// Citems by peer they are attributed to
+    app_citems_by_peer: (PeerPubkey, CItemLocation) => ()
+}
+
+def_table! {
+    /// Citems by modules that processed them, and peer they are attributed to
+    app_citems_by_module_peer: (ModuleId, PeerPubkey, CItemLocation) => ()
+}
+
+def_table! {
+    /// Transaction citems by their hash
+    app_citems_by_tx: TransactionHash => CItemLocation
+}
+
+pub fn init_tables_dbtx(dbtx: &WriteTransactionCtx) -> DbResult<()> {
+    dbtx.open_table(&app_citems::TABLE)?;
+    dbtx.open_table(&app_citems_by_module::TABLE)?;
+    dbtx.open_table(&app_citems_by_peer::TABLE)?;
+    dbtx.open_table(&app_citems_by_module_peer::TABLE)?;
+    dbtx.open_table(&app_citems_by_tx::TABLE)?;
+    Ok(())
+}
+
+pub fn insert_citem_dbtx(
+    dbtx: &WriteTransactionCtx,
+    location: CItemLocation,
+    entry: &CItemIndexEntry,
+) -> DbResult<()> {
+    {
+        let mut tbl = dbtx.open_table(&app_citems_by_module::TABLE)?;
+        for module_id in &entry.module_ids {
+            tbl.insert(&(*module_id, location), &())?;
+        }
+    }
+    {
+        let mut tbl = dbtx.open_table(&app_citems_by_module_peer::TABLE)?;
+        for module_id in &entry.module_ids {
+            tbl.insert(&(*module_id, entry.peer_pubkey, location), &())?;
+        }
+    }
+    dbtx.open_table(&app_citems_by_peer::TABLE)?
+        .insert(&(entry.peer_pubkey, location), &())?;
+    if let Some(tx_hash) = entry.tx_hash {
+        dbtx.open_table(&app_citems_by_tx::TABLE)?
+            .insert(&tx_hash, &location)?;
+    }
+    dbtx.open_table(&app_citems::TABLE)?
+        .insert(&location, entry)?;
+    Ok(())
+}
+
+/// Up to `limit` citems matching `filter`, newest first, starting right
+/// before `before` (if set)
+pub fn query_citems_dbtx(
+    dbtx: &ReadTransaction,
+    filter: &CItemIndexFilter,
+    before: Option<CItemLocation>,
+    limit: usize,
+) -> DbResult<Vec<(CItemLocation, CItemIndexEntry)>> {
+    let start = CItemLocation::first_in_round(filter.from_round.unwrap_or_default());
+    let end = filter
+        .to_round
+        .and_then(BlockRound::next)
+        .map(CItemLocation::first_in_round);
+    let end = match (end, before) {
+        (Some(end), Some(before)) => Bound::Excluded(end.min(before)),
+        (Some(end), None) | (None, Some(end)) => Bound::Excluded(end),
+        (None, None) => Bound::Included(CItemLocation::MAX),
+    };
+
+    let citems_tbl = dbtx.open_table(&app_citems::TABLE)?;
+    let by_module_tbl = dbtx.open_table(&app_citems_by_module::TABLE)?;
+    let by_peer_tbl = dbtx.open_table(&app_citems_by_peer::TABLE)?;
+    let by_module_peer_tbl = dbtx.open_table(&app_citems_by_module_peer::TABLE)?;
+
+    let locations: Box<dyn Iterator<Item = DbResult<CItemLocation>> + '_> =
+        match (filter.module_id, filter.peer_pubkey) {
+            (Some(module_id), Some(peer_pubkey)) => {
+                let range = by_module_peer_tbl.range((
+                    Bound::Included((module_id, peer_pubkey, start)),
+                    end.map(|end| (module_id, peer_pubkey, end)),
+                ))?;
+                Box::new(range.rev().map(move |kv| {
+                    let (k, _) = kv?;
+                    Ok(k.value().2)
+                }))
+            }
+            (Some(module_id), None) => {
+                let range = by_module_tbl.range((
+                    Bound::Included((module_id, start)),
+                    end.map(|end| (module_id, end)),
+                ))?;
+                Box::new(range.rev().map(move |kv| {
+                    let (k, _) = kv?;
+                    Ok(k.value().1)
+                }))
+            }
+            (None, Some(peer_pubkey)) => {
+                let range = by_peer_tbl.range((
+                    Bound::Included((peer_pubkey, start)),
+                    end.map(|end| (peer_pubkey, end)),
+                ))?;
+                Box::new(range.rev().map(move |kv| {
+                    let (k, _) = kv?;
+                    Ok(k.value().1)
+                }))
+            }
+            (None, None) => {
+                let range = citems_tbl.range((Bound::Included(start), end))?;
+                Box::new(range.rev().map(|kv| {
+                    let (k, _) = kv?;
+                    Ok(k.value())
+                }))
+            }
+        };
+
+    let mut citems = vec![];
+    for location in locations {
+        if limit <= citems.len() {
+            break;
+        }
+        let location = location?;
+        let Some(entry) = citems_tbl.get(&location)?.map(|v| v.value()) else {
+            continue;
+        };
+        if filter.since.is_some_and(|since| entry.timestamp < since) {
+            break;
+        }
+        if filter.matches(&entry) {
+            citems.push((location, entry));
+        }
+    }
+
+    Ok(citems)
+}
+
+/// All indexed citems of `round`, in order
+pub fn get_round_citems_dbtx(
+    dbtx: &ReadTransaction,
+    round: BlockRound,
+) -> DbResult<Vec<(CItemLocation, CItemIndexEntry)>> {
+    let end = match round.next() {
+        Some(next) => Bound::Excluded(CItemLocation::first_in_round(next)),
+        None => Bound::Included(CItemLocation::MAX),
+    };
+
+    dbtx.open_table(&app_citems::TABLE)?
+        .range((Bound::Included(CItemLocation::first_in_round(round)), end))?
+        .map(|kv| {
+            let (k, v) = kv?;
+            Ok((k.value(), v.value()))
+        })
+        .collect()
+}
+
+pub fn get_citem_by_tx_hash_dbtx(
+    dbtx: &ReadTransaction,
+    tx_hash: TransactionHash,
+) -> DbResult<Option<(CItemLocation, CItemIndexEntry)>> {
+    let Some(location) = dbtx
+        .open_table(&app_citems_by_tx::TABLE)?
+        .get(&tx_hash)?
+        .map(|v| v.value())
+    else {
+        return Ok(None);
+    };
+
+    Ok(dbtx
+        .open_table(&app_citems::TABLE)?
+        .get(&location)?
+        .map(|v| (location, v.value())))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use bfte_consensus_core::block::{BlockCItemIdx, BlockRound};
+use bfte_consensus_core::citem::transaction::{
+    Transaction, TransactionSignature, TransactionUnsigned,
+};
+use bfte_consensus_core::citem::transaction_nonce::TransactionNonce;
+use bfte_consensus_core::citem::{CItem, CItemRaw, ModuleDyn, OutputRaw};
+use bfte_consensus_core::module::ModuleId;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_db::Database;
+use bfte_db::error::DbResult;
+
+use super::{
+    CItemIndexEntry, CItemIndexFilter, CItemLocation, CItemOutcome, get_citem_by_tx_hash_dbtx,
+    get_round_citems_dbtx, init_tables_dbtx, insert_citem_dbtx, query_citems_dbtx,
+};
+
+const PEER_A: PeerPubkey = PeerPubkey::ZERO;
+const PEER_B: PeerPubkey = PeerPubkey::MAX;
+
+fn peer_citem(module_id: u32) -> CItem {
+    CItem::PeerCItem(ModuleDyn::new(
+        ModuleId::new(module_id),
+        CItemRaw(Arc::from([1u8].as_slice())),
+    ))
+}
+
+fn transaction() -> Transaction {
+    Transaction {
+        inner: TransactionUnsigned {
+            nonce: TransactionNonce::ZERO,
+            inputs: vec![],
+            outputs: vec![ModuleDyn::new(
+                ModuleId::new(3),
+                OutputRaw(Arc::from([2u8].as_slice())),
+            )],
+        },
+        signature: TransactionSignature::ZERO,
+    }
+}
+
+/// Rounds 1 to 5, each with a citem of module 1 by peer A, a citem of module
+/// 2 by peer B and a transaction included by peer A
+async fn populated_db() -> DbResult<Database> {
+    let db = Database::new_in_memory().await?;
+    db.write_with(|dbtx| {
+        init_tables_dbtx(dbtx)?;
+        for round in 1..=5u64 {
+            let timestamp = Timestamp::from(round * 1000);
+            let citems = [
+                (peer_citem(1), PEER_A),
+                (peer_citem(2), PEER_B),
+                (CItem::Transaction(transaction()), PEER_A),
+            ];
+            for (idx, (citem, leader_pubkey)) in citems.iter().enumerate() {
+                insert_citem_dbtx(
+                    dbtx,
+                    CItemLocation::new(
+                        BlockRound::from(round),
+                        BlockCItemIdx::from(u32::try_from(idx).expect("Can't fail")),
+                    ),
+                    &CItemIndexEntry::new(citem, timestamp, *leader_pubkey, CItemOutcome::Accepted),
+                )?;
+            }
+        }
+        Ok(())
+    })
+    .await?;
+    Ok(db)
+}
+
+async fn query(
+    db: &Database,
+    filter: CItemIndexFilter,
+    before: Option<CItemLocation>,
+    limit: usize,
+) -> Vec<(u64, u32)> {
+    db.read_with_expect(|dbtx| query_citems_dbtx(dbtx, &filter, before, limit))
+        .await
+        .into_iter()
+        .map(|(location, _)| (location.round.to_number(), location.idx.to_number()))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn query_paginates_newest_first() -> DbResult<()> {
+    let db = populated_db().await?;
+
+    assert_eq!(
+        query(&db, CItemIndexFilter::default(), None, 4).await,
+        [(5, 2), (5, 1), (5, 0), (4, 2)]
+    );
+    assert_eq!(
+        query(
+            &db,
+            CItemIndexFilter::default(),
+            Some(CItemLocation::new(
+                BlockRound::from(4),
+                BlockCItemIdx::from(2)
+            )),
+            2
+        )
+        .await,
+        [(4, 1), (4, 0)]
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn query_filters() -> DbResult<()> {
+    let db = populated_db().await?;
+
+    let by_module = CItemIndexFilter {
+        module_id: Some(ModuleId::new(2)),
+        ..Default::default()
+    };
+    assert_eq!(
+        query(&db, by_module.clone(), None, 2).await,
+        [(5, 1), (4, 1)]
+    );
+
+    let by_peer_and_module = CItemIndexFilter {
+        peer_pubkey: Some(PEER_A),
+        ..by_module
+    };
+    assert!(query(&db, by_peer_and_module, None, 10).await.is_empty());
+
+    let by_peer_and_module = CItemIndexFilter {
+        module_id: Some(ModuleId::new(1)),
+        peer_pubkey: Some(PEER_A),
+        ..Default::default()
+    };
+    assert_eq!(
+        query(&db, by_peer_and_module, None, 2).await,
+        [(5, 0), (4, 0)]
+    );
+
+    let by_peer_rounds = CItemIndexFilter {
+        peer_pubkey: Some(PEER_A),
+        from_round: Some(BlockRound::from(2)),
+        to_round: Some(BlockRound::from(3)),
+        ..Default::default()
+    };
+    assert_eq!(
+        query(&db, by_peer_rounds, None, 10).await,
+        [(3, 2), (3, 0), (2, 2), (2, 0)]
+    );
+
+    let since = CItemIndexFilter {
+        module_id: Some(ModuleId::new(3)),
+        since: Some(Timestamp::from(4000)),
+        ..Default::default()
+    };
+    assert_eq!(query(&db, since, None, 10).await, [(5, 2), (4, 2)]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lookup_by_tx_hash() -> DbResult<()> {
+    let db = populated_db().await?;
+
+    let (location, entry) = db
+        .read_with_expect(|dbtx| get_citem_by_tx_hash_dbtx(dbtx, transaction().tx_hash()))
+        .await
+        .expect("Must be indexed");
+
+    assert_eq!(
+        location,
+        CItemLocation::new(BlockRound::from(5), BlockCItemIdx::from(2))
+    );
+    assert_eq!(entry.module_ids, [ModuleId::new(3)]);
+    assert_eq!(entry.peer_pubkey, PEER_A);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn large_idxs_sort_in_block_order() -> DbResult<()> {
+    let db = Database::new_in_memory().await?;
+    db.write_with(|dbtx| {
+        init_tables_dbtx(dbtx)?;
+        for idx in [2, 300, 70_000] {
+            insert_citem_dbtx(
+                dbtx,
+                CItemLocation::new(BlockRound::from(1), BlockCItemIdx::from(idx)),
+                &CItemIndexEntry::new(
+                    &peer_citem(1),
+                    Timestamp::from(1000),
+                    PEER_A,
+                    CItemOutcome::Accepted,
+                ),
+            )?;
+        }
+        Ok(())
+    })
+    .await?;
+
+    assert_eq!(
+        query(&db, CItemIndexFilter::default(), None, 10).await,
+        [(1, 70_000), (1, 300), (1, 2)]
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn round_citems() -> DbResult<()> {
+    let db = populated_db().await?;
+
+    let citems = db
+        .read_with_expect(|dbtx| get_round_citems_dbtx(dbtx, BlockRound::from(3)))
+        .await;
+    assert_eq!(
+        citems
+            .iter()
+            .map(|(location, _)| (location.round.to_number(), location.idx.to_number()))
+            .collect::<Vec<_>>(),
+        [(3, 0), (3, 1), (3, 2)]
+    );
+
+    let citems = db
+        .read_with_expect(|dbtx| get_round_citems_dbtx(dbtx, BlockRound::from(6)))
+        .await;
+    assert!(citems.is_empty());
+
+    Ok(())
+}
@@ -1,5 +1,8 @@
 // SPDX-License-Identifier: MIT
 
+pub mod citem_index;
+pub mod processed_citems;
+
 use std::convert::Infallible;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -7,14 +10,20 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use bfte_consensus::consensus::Consensus;
 use bfte_consensus_core::block::{BlockHeader, BlockRound};
+use bfte_consensus_core::bls::BlsKeyRegistration;
 use bfte_consensus_core::citem::CItem;
 use bfte_consensus_core::citem::transaction::Transaction;
 use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::snapshot::StateSnapshot;
 use bfte_db::Database;
+use bfte_module::module::CItemSimulation;
+use bfte_module::module::net::ModuleNet;
+use bfte_module::module::secret::ModuleSecret;
 use bfte_node_shared_modules::SharedModules;
 use bfte_util_error::WhateverResult;
-use tokio::sync::watch;
+use tokio::sync::{mpsc, oneshot, watch};
 
 pub type RunNodeAppFn = Box<
     dyn Fn(
@@ -22,6 +31,7 @@ pub type RunNodeAppFn = Box<
             NodeAppApi,
             SharedModules,
             watch::Sender<Vec<Transaction>>,
+            mpsc::Receiver<SimulateCItemRequest>,
         ) -> Pin<Box<dyn Future<Output = WhateverResult<Infallible>> + Send>>
         + Send
         + Sync
@@ -30,12 +40,38 @@ pub type RunNodeAppFn = Box<
 
 pub type NodeAppApi = Arc<dyn INodeAppApi + Send + Sync + 'static>;
 
+/// Request for the application logic to simulate processing a [`CItem`]
+///
+/// The citem is processed as if it was included in the next block by
+/// `peer_pubkey`, in a database transaction that is always rolled back.
+/// Failing to simulate is an error, while the citem failing to process is a
+/// [`CItemSimulation::Rejected`].
+pub struct SimulateCItemRequest {
+    pub citem: CItem,
+    pub peer_pubkey: PeerPubkey,
+    pub response_tx: oneshot::Sender<WhateverResult<CItemSimulation>>,
+}
+
 /// The API `bfte-node` exposes to `bfte-node-app`
 #[async_trait]
 pub trait INodeAppApi {
     async fn get_consensus(&self) -> Arc<Consensus>;
     async fn get_peer_pubkey(&self) -> Option<PeerPubkey>;
 
+    /// Secret of a module instance, derived from the node's root secret
+    ///
+    /// `None` if the node runs without one (e.g. with a remote signer).
+    async fn get_module_secret(
+        &self,
+        module_kind: ModuleKind,
+        module_id: ModuleId,
+    ) -> Option<ModuleSecret>;
+
+    /// Private messaging for the module instance `module_id`
+    ///
+    /// `None` if we are not a peer.
+    async fn get_module_net(&self, module_id: ModuleId) -> Option<ModuleNet>;
+
     async fn get_consensus_params(&self, round: BlockRound) -> ConsensusParams;
 
     /// Wait for the first finalized block at `round` or higher
@@ -46,4 +82,26 @@ pub trait INodeAppApi {
         &self,
         round: BlockRound,
     ) -> (BlockHeader, PeerPubkey, Arc<[CItem]>);
+
+    /// Record a [`StateSnapshot`] taken by the application logic
+    ///
+    /// The node will attest and serve it to joining nodes.
+    async fn record_state_snapshot(&self, snapshot: StateSnapshot);
+
+    /// Round of the [`StateSnapshot`] restored when joining the consensus
+    ///
+    /// If set, the application logic should start processing blocks from
+    /// this round, as the state before it was already restored.
+    async fn get_restored_state_snapshot_round(&self) -> Option<BlockRound>;
+
+    /// Registration of our BLS key, if we are a peer
+    ///
+    /// The application logic should get it included in the consensus, so
+    /// notarizations can be aggregated once all peers registered theirs.
+    async fn get_bls_key_registration(&self) -> Option<BlsKeyRegistration>;
+
+    /// Notify the node that a peer's key was rotated in the consensus
+    ///
+    /// Allows the node to follow the new key, e.g. in the address gossip.
+    async fn on_peer_key_rotation(&self, old_pubkey: PeerPubkey, new_pubkey: PeerPubkey);
 }
@@ -0,0 +1,102 @@
+//! Recently processed peer citems
+//!
+//! Maintained by the application logic as it processes blocks, to reject
+//! [`ForwardedCItem`]s that were already included in the finalized history,
+//! either forwarded or by the peer itself. Entries are kept only until no
+//! forwarded copy of them can be valid anymore.
+//!
+//! It affects which citems are accepted, so unlike the [`citem_index`], it
+//! is a part of the consensus state, included in state snapshots.
+//!
+//! [`ForwardedCItem`]: bfte_consensus_core::citem::ForwardedCItem
+//! [`citem_index`]: crate::citem_index
+
+use std::collections::BTreeMap;
+
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::citem::PeerCItemHash;
+use bfte_consensus_core::snapshot::StateSnapshotTable;
+use bfte_db::ctx::WriteTransactionCtx;
+use bfte_db::error::DbResult;
+use bfte_util_db::def_table;
+use bfte_util_db::redb_bincode::redb::{self, ReadableTable as _, TableHandle as _};
+use bfte_util_db::redb_bincode::{ReadTransaction, ReadableTable as _};
+
+def_table! {
+    /// Processed peer citems, with the round they must be remembered until
+    app_processed_peer_citems: PeerCItemHash => BlockRound
+}
+
+pub fn init_tables_dbtx(dbtx: &WriteTransactionCtx) -> DbResult<()> {
+    dbtx.open_table(&app_processed_peer_citems::TABLE)?;
+    Ok(())
+}
+
+/// Was a citem with `hash` processed, recently enough to matter in `round`
+pub fn is_processed(
+    tbl: &impl app_processed_peer_citems::ReadableTable,
+    round: BlockRound,
+    hash: PeerCItemHash,
+) -> DbResult<bool> {
+    Ok(tbl.get(&hash)?.is_some_and(|until| round < until.value()))
+}
+
+/// Remember a citem with `hash` as processed until `until`
+pub fn insert_processed_dbtx(
+    dbtx: &WriteTransactionCtx,
+    hash: PeerCItemHash,
+    until: BlockRound,
+) -> DbResult<()> {
+    let mut tbl = dbtx.open_table(&app_processed_peer_citems::TABLE)?;
+    let until = match tbl.get(&hash)? {
+        Some(prev_until) => until.max(prev_until.value()),
+        None => until,
+    };
+    tbl.insert(&hash, &until)?;
+    Ok(())
+}
+
+/// Forget citems that don't matter anymore in `round`
+pub fn prune_processed_dbtx(dbtx: &WriteTransactionCtx, round: BlockRound) -> DbResult<()> {
+    dbtx.open_table(&app_processed_peer_citems::TABLE)?
+        .retain(|_, until| round < *until)?;
+    Ok(())
+}
+
+/// Add the content of the processed citems table to state snapshot `tables`
+pub fn dump_tables_dbtx(
+    dbtx: &ReadTransaction,
+    tables: &mut BTreeMap<String, StateSnapshotTable>,
+) -> DbResult<()> {
+    let name = app_processed_peer_citems::TABLE.as_raw().name();
+    let tbl = dbtx
+        .as_raw()
+        .open_table(redb::TableDefinition::<&[u8], &[u8]>::new(name))?;
+    let mut entries = vec![];
+    for kv in tbl.iter()? {
+        let (k, v) = kv?;
+        entries.push((k.value().to_vec(), v.value().to_vec()));
+    }
+    tables.insert(name.to_owned(), entries);
+    Ok(())
+}
+
+/// Replace the content of the processed citems table with the one in state
+/// snapshot `tables`
+pub fn restore_tables_dbtx(
+    dbtx: &WriteTransactionCtx,
+    tables: &BTreeMap<String, StateSnapshotTable>,
+) -> DbResult<()> {
+    let name = app_processed_peer_citems::TABLE.as_raw().name();
+    let table_def = redb::TableDefinition::<&[u8], &[u8]>::new(name);
+    let dbtx = dbtx.as_raw();
+    dbtx.delete_table(table_def)?;
+    let mut tbl = dbtx.open_table(table_def)?;
+    for (k, v) in tables.get(name).into_iter().flatten() {
+        tbl.insert(k.as_slice(), v.as_slice())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::citem::{CItemRaw, ModuleDyn, PeerCItemHash};
+use bfte_consensus_core::module::ModuleId;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_db::Database;
+use bfte_db::error::DbResult;
+
+use super::{
+    app_processed_peer_citems, dump_tables_dbtx, init_tables_dbtx, insert_processed_dbtx,
+    is_processed, prune_processed_dbtx, restore_tables_dbtx,
+};
+
+fn hash(peer_pubkey: PeerPubkey, byte: u8) -> PeerCItemHash {
+    PeerCItemHash::new(
+        peer_pubkey,
+        &ModuleDyn::new(ModuleId::new(1), CItemRaw(Arc::from([byte].as_slice()))),
+    )
+}
+
+async fn processed(db: &Database, round: u64, hash: PeerCItemHash) -> bool {
+    db.read_with_expect(|dbtx| {
+        is_processed(
+            &dbtx.open_table(&app_processed_peer_citems::TABLE)?,
+            BlockRound::from(round),
+            hash,
+        )
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn processed_citems_expire_and_prune() -> DbResult<()> {
+    let db = Database::new_in_memory().await?;
+    let a = hash(PeerPubkey::ZERO, 1);
+    let b = hash(PeerPubkey::MAX, 1);
+    assert_ne!(a, b);
+
+    db.write_with(|dbtx| {
+        init_tables_dbtx(dbtx)?;
+        insert_processed_dbtx(dbtx, a, BlockRound::from(10))?;
+        // Never shortens the time a citem is remembered for
+        insert_processed_dbtx(dbtx, a, BlockRound::from(5))?;
+        insert_processed_dbtx(dbtx, b, BlockRound::from(20))?;
+        Ok(())
+    })
+    .await?;
+
+    assert!(processed(&db, 9, a).await);
+    assert!(!processed(&db, 10, a).await);
+    assert!(processed(&db, 19, b).await);
+    assert!(!processed(&db, 0, hash(PeerPubkey::ZERO, 2)).await);
+
+    db.write_with(|dbtx| prune_processed_dbtx(dbtx, BlockRound::from(10)))
+        .await?;
+    let mut tables = BTreeMap::new();
+    db.read_with(|dbtx| dump_tables_dbtx(dbtx, &mut tables))
+        .await?;
+    assert_eq!(tables.values().flatten().count(), 1);
+    assert!(processed(&db, 10, b).await);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn processed_citems_snapshot_roundtrip() -> DbResult<()> {
+    let db = Database::new_in_memory().await?;
+    let a = hash(PeerPubkey::ZERO, 1);
+    db.write_with(|dbtx| {
+        init_tables_dbtx(dbtx)?;
+        insert_processed_dbtx(dbtx, a, BlockRound::from(10))
+    })
+    .await?;
+
+    let mut tables = BTreeMap::new();
+    db.read_with(|dbtx| dump_tables_dbtx(dbtx, &mut tables))
+        .await?;
+    assert_eq!(tables.len(), 1);
+
+    let restored = Database::new_in_memory().await?;
+    restored
+        .write_with(|dbtx| {
+            init_tables_dbtx(dbtx)?;
+            insert_processed_dbtx(dbtx, hash(PeerPubkey::MAX, 1), BlockRound::from(10))?;
+            restore_tables_dbtx(dbtx, &tables)
+        })
+        .await?;
+    assert!(processed(&restored, 9, a).await);
+    assert!(!processed(&restored, 9, hash(PeerPubkey::MAX, 1)).await);
+
+    Ok(())
+}
@@ -1,18 +1,35 @@
-use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::block::{BlockCItemIdx, BlockRound};
 use bfte_db::error::DbResult;
 
 use crate::NodeApp;
-use crate::tables::{self, BlockCItemIdx};
+use crate::tables;
 
 impl NodeApp {
+    /// Load the processing position
+    ///
+    /// If nothing was processed yet, and a state snapshot was restored when
+    /// joining, processing starts right at the snapshot round.
     pub async fn load_cur_round_and_idx(&self) -> (BlockRound, BlockCItemIdx) {
-        self.db
+        let cur_round_idx = self
+            .db
             .read_with_expect(|dbtx| {
                 let tbl = dbtx.open_table(&tables::app_cur_round::TABLE)?;
 
-                Ok(tbl.get(&())?.map(|v| v.value()).unwrap_or_default())
+                Ok(tbl.get(&())?.map(|v| v.value()))
             })
-            .await
+            .await;
+
+        if let Some(cur_round_idx) = cur_round_idx {
+            return cur_round_idx;
+        }
+
+        (
+            self.node_api
+                .get_restored_state_snapshot_round()
+                .await
+                .unwrap_or_default(),
+            BlockCItemIdx::new(0),
+        )
     }
 
     pub(crate) fn save_cur_round_and_idx_dbtx(
@@ -0,0 +1,41 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use bfte_consensus::consensus::Consensus;
+use bfte_module_consensus_ctrl::ConsensusCtrlModule;
+use bfte_node_shared_modules::WeakSharedModules;
+use tracing::debug;
+
+use crate::{CONSENSUS_CTRL_MODULE_ID, LOG_TARGET, NodeApp};
+
+impl NodeApp {
+    /// Forward equivocation evidence recorded by the consensus to
+    /// `ConsensusCtrl`, so it gets reported in consensus items
+    pub(crate) async fn run_equivocation_reporting(
+        consensus: Arc<Consensus>,
+        modules: WeakSharedModules,
+    ) {
+        let mut new_equivocation_rx = consensus.new_equivocation_rx();
+
+        loop {
+            let evidence = consensus.get_equivocations().await;
+
+            if !evidence.is_empty() {
+                debug!(target: LOG_TARGET, len = evidence.len(), "Forwarding equivocation evidence");
+                let module = modules
+                    .get_module(CONSENSUS_CTRL_MODULE_ID)
+                    .await
+                    .expect("Must have a app consensus module");
+                (module.inner.as_ref() as &dyn Any)
+                    .downcast_ref::<ConsensusCtrlModule>()
+                    .expect("Must be a core consensus module")
+                    .report_equivocations(evidence)
+                    .await;
+            }
+
+            if new_equivocation_rx.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
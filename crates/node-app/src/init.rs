@@ -1,11 +1,14 @@
 use bfte_db::ctx::WriteTransactionCtx;
 use bfte_db::error::DbResult;
+use bfte_node_app_core::{citem_index, processed_citems};
 
 use crate::{NodeApp, tables};
 
 impl NodeApp {
     pub(super) fn init_tables_dbtx(tx: &WriteTransactionCtx) -> DbResult<()> {
         tx.open_table(&tables::app_cur_round::TABLE)?;
+        citem_index::init_tables_dbtx(tx)?;
+        processed_citems::init_tables_dbtx(tx)?;
         Ok(())
     }
 }
@@ -6,8 +6,12 @@
 //! consuming what the core consensus agreed on (finalized) between the peers,
 //! and sending it new things to agree on.
 mod db;
+mod equivocation;
 mod init;
 mod process_citem;
+mod schedule;
+mod simulate;
+mod snapshot;
 mod tables;
 
 use std::any::Any;
@@ -17,22 +21,23 @@ use std::sync::Arc;
 use std::{mem, ops};
 
 use bfte_consensus::consensus::Consensus;
+use bfte_consensus_core::block::BlockCItemIdx;
 use bfte_consensus_core::citem::transaction::Transaction;
 use bfte_consensus_core::consensus_params::ConsensusParams;
 use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_consensus_core::peer::PeerPubkey;
-use bfte_consensus_core::peer_set::PeerSet;
+use bfte_consensus_core::peer_set::WeightedPeerSet;
+use bfte_consensus_core::snapshot::StateSnapshot;
 use bfte_db::Database;
 use bfte_module::module::config::ModuleConfig;
 use bfte_module::module::db::ModuleWriteTransactionCtx;
 use bfte_module::module::{DynModuleInit, DynModuleWithConfig, IModuleInit, ModuleInitArgs};
 use bfte_module_consensus_ctrl::{ConsensusCtrlModule, ConsensusCtrlModuleInit};
-use bfte_node_app_core::NodeAppApi;
+use bfte_node_app_core::{NodeAppApi, SimulateCItemRequest};
 use bfte_node_shared_modules::SharedModules;
 use bfte_util_error::WhateverResult;
 use snafu::{OptionExt as _, ResultExt as _};
-use tables::BlockCItemIdx;
-use tokio::sync::{RwLockWriteGuard, watch};
+use tokio::sync::{RwLockWriteGuard, mpsc, watch};
 use tracing::{debug, info};
 
 /// Consensus module is auto-initialized and always there at a fixed id
@@ -51,7 +56,8 @@ pub struct NodeApp {
 
     /// Direct reference to the consensus
     ///
-    /// Should only be used to schedule consensus param changes.
+    /// Should only be used to schedule consensus param changes and to follow
+    /// recorded equivocation evidence.
     consensus: Arc<Consensus>,
 
     /// Api to call [`bfte-node`]
@@ -70,6 +76,9 @@ pub struct NodeApp {
     #[allow(dead_code)] // will get there
     pending_transactions_tx: watch::Sender<Vec<Transaction>>,
 
+    /// Requests to simulate processing of citems, see [`simulate`]
+    simulate_citem_rx: mpsc::Receiver<SimulateCItemRequest>,
+
     peer_pubkey: Option<PeerPubkey>,
 }
 
@@ -80,6 +89,7 @@ impl NodeApp {
         modules_inits: ModulesInits,
         modules: SharedModules,
         pending_transactions_tx: watch::Sender<Vec<Transaction>>,
+        simulate_citem_rx: mpsc::Receiver<SimulateCItemRequest>,
     ) -> Self {
         assert!(
             modules_inits.contains_key(&bfte_module_consensus_ctrl::KIND),
@@ -96,6 +106,7 @@ impl NodeApp {
             modules,
             db,
             pending_transactions_tx,
+            simulate_citem_rx,
             peer_pubkey,
             consensus,
         }
@@ -119,6 +130,11 @@ impl NodeApp {
         let mut peer_set = None;
 
         self.record_supported_modules_versions().await;
+        self.register_bls_key().await;
+        tokio::spawn(Self::run_equivocation_reporting(
+            self.consensus.clone(),
+            self.modules.downgrade(),
+        ));
         info!(
            target: LOG_TARGET,
            round = %cur_round_idx.0,
@@ -133,50 +149,60 @@ impl NodeApp {
                 citem_idx = %cur_round_idx.1,
                 "Awaiting block data…"
             );
-            let (block_header, peer_pubkey, citems) =
-                self.node_api.ack_and_wait_next_block(cur_round_idx.0).await;
+            let (block_header, peer_pubkey, citems) = {
+                let next_block = self.node_api.ack_and_wait_next_block(cur_round_idx.0);
+                tokio::pin!(next_block);
+                loop {
+                    tokio::select! {
+                        block = &mut next_block => break block,
+                        Some(req) = self.simulate_citem_rx.recv() => {
+                            self.handle_simulate_citem_request(
+                                cur_round_idx.0,
+                                &mut peer_set,
+                                &mut modules_configs,
+                                req,
+                            )
+                            .await;
+                        }
+                    }
+                }
+            };
             debug!(target: LOG_TARGET, round = %block_header.round, "Processing new block…");
 
-            for (idx, citem) in citems.iter().enumerate() {
-                let idx = BlockCItemIdx::from(u32::try_from(idx).expect("Can't fail"));
-                if cur_round_idx.1 <= idx {
-                    self.reload_invalidated_copies(&mut modules_configs, &mut peer_set)
-                        .await?;
-
-                    self.process_citem(
-                        cur_round_idx,
-                        &block_header,
-                        peer_pubkey,
-                        &mut peer_set,
-                        &mut modules_configs,
-                        citem,
-                    )
-                    .await;
-                }
-                cur_round_idx.1 = idx;
+            // Snapshot needs to be taken before anything from the block was processed
+            if let Some(snapshot_round) =
+                StateSnapshot::round_to_take(cur_round_idx.0, block_header.round)
+                    .filter(|_| cur_round_idx.1 == BlockCItemIdx::new(0))
+            {
+                self.take_state_snapshot(snapshot_round).await;
             }
 
+            self.process_block_citems(
+                cur_round_idx.1,
+                &block_header,
+                peer_pubkey,
+                &mut peer_set,
+                &mut modules_configs,
+                &citems,
+            )
+            .await?;
+
             cur_round_idx = (
                 block_header.round.next().expect("Can't fail"),
                 BlockCItemIdx::new(0),
             );
-            self.db
-                .write_with_expect(|dbtx| {
-                    Self::save_cur_round_and_idx_dbtx(dbtx, cur_round_idx.0, cur_round_idx.1)
-                })
-                .await;
         }
     }
 
     async fn reload_invalidated_copies(
         &self,
         modules_configs: &mut Option<BTreeMap<ModuleId, ModuleConfig>>,
-        peer_set: &mut Option<PeerSet>,
+        peer_set: &mut Option<WeightedPeerSet>,
     ) -> Result<(), bfte_util_error::Whatever> {
         if peer_set.is_none() {
             *peer_set = Some(
                 Self::consensus_ctrl_module_expect_static(&self.modules.read().await)
-                    .get_peer_set()
+                    .get_weighted_peer_set()
                     .await,
             );
         }
@@ -194,7 +220,7 @@ impl NodeApp {
         debug_assert_eq!(
             *peer_set.as_ref().expect("Must be set"),
             Self::consensus_ctrl_module_expect_static(&self.modules.read().await)
-                .get_peer_set()
+                .get_weighted_peer_set()
                 .await
         );
         debug_assert_eq!(
@@ -270,6 +296,18 @@ impl NodeApp {
             .await;
     }
 
+    async fn register_bls_key(&self) {
+        let Some(registration) = self.node_api.get_bls_key_registration().await else {
+            return;
+        };
+
+        let modules_read = self.modules.read().await;
+        let consensus_ctrl = Self::consensus_ctrl_module_expect_static(&modules_read);
+        consensus_ctrl
+            .set_pending_bls_key_registration(registration)
+            .await;
+    }
+
     async fn setup_modules_to(
         &self,
         new_modules_configs: &BTreeMap<ModuleId, ModuleConfig>,
@@ -277,6 +315,7 @@ impl NodeApp {
         let modules = self.modules.write().await;
         let changed = Self::setup_modules_to_static(
             &self.db,
+            &self.node_api,
             modules,
             new_modules_configs,
             &self.modules_inits,
@@ -293,6 +332,7 @@ impl NodeApp {
     #[must_use = "Don't forget to send update"]
     async fn setup_modules_to_static(
         db: &Arc<Database>,
+        node_api: &NodeAppApi,
         mut modules_write: RwLockWriteGuard<'_, BTreeMap<ModuleId, DynModuleWithConfig>>,
         new_modules_configs: &BTreeMap<ModuleId, ModuleConfig>,
         modules_inits: &BTreeMap<ModuleKind, DynModuleInit>,
@@ -327,6 +367,10 @@ impl NodeApp {
                 existing_modules.remove(module_id);
 
                 debug!(target: LOG_TARGET, %module_id, config = ?new_module_config, "Initializing module");
+                let module_secret = node_api
+                    .get_module_secret(new_module_config.kind, *module_id)
+                    .await;
+                let module_net = node_api.get_module_net(*module_id).await;
                 modules_write.insert(
                     *module_id,
                     DynModuleWithConfig {
@@ -338,6 +382,8 @@ impl NodeApp {
                                 new_module_config.version,
                                 modules_inits.clone(),
                                 peer_pubkey,
+                                module_secret,
+                                module_net,
                             ))
                             .await
                             .whatever_context("Failed to setup module")?,
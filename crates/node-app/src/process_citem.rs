@@ -1,27 +1,31 @@
 use std::collections::BTreeMap;
+use std::ops::Range;
 
-use bfte_consensus_core::block::{BlockHeader, BlockRound};
-use bfte_consensus_core::citem::CItem;
+use bfte_consensus_core::block::{BlockCItemIdx, BlockHeader, BlockRound};
+use bfte_consensus_core::citem::{CItem, CItemRaw, ForwardedCItem, ModuleDyn, PeerCItemHash};
 use bfte_consensus_core::module::ModuleId;
 use bfte_consensus_core::peer::PeerPubkey;
-use bfte_consensus_core::peer_set::PeerSet;
+use bfte_consensus_core::peer_set::WeightedPeerSet;
 use bfte_consensus_core::timestamp::Timestamp;
 use bfte_db::ctx::WriteTransactionCtx;
-use bfte_db::error::{DbResult, TxSnafu};
+use bfte_db::error::{DbError, DbResult, DbTxError, DbTxResult, TxSnafu};
 use bfte_module::effect::{EffectKind as _, EffectKindExt as _, ModuleCItemEffect};
 use bfte_module::module::config::ModuleConfig;
 use bfte_module::module::db::ModuleWriteTransactionCtx;
+use bfte_module::module::{DynModuleWithConfig, OwnCItemOutcome};
 use bfte_module_consensus_ctrl::effects::{
-    AddModuleEffect, ConsensusParamsChange, ModuleVersionUpgradeEffect,
+    AddModuleEffect, ConsensusParamsChange, ModuleVersionUpgradeEffect, RotateKeyEffect,
 };
-use bfte_util_error::Whatever;
+use bfte_node_app_core::citem_index::{self, CItemIndexEntry, CItemLocation, CItemOutcome};
+use bfte_node_app_core::processed_citems::{self, app_processed_peer_citems};
 use bfte_util_error::fmt::FmtCompact as _;
+use bfte_util_error::{Whatever, WhateverResult};
 use snafu::{IntoError as _, OptionExt as _, ResultExt as _, Snafu};
 use tracing::debug;
 
 use super::NodeApp;
-use crate::LOG_TARGET;
-use crate::tables::BlockCItemIdx;
+use crate::schedule::CItemAccess;
+use crate::{LOG_TARGET, schedule};
 
 #[derive(Debug, Snafu)]
 pub enum ProcessCItemError {
@@ -46,179 +50,650 @@ pub enum ProcessCItemError {
     },
 }
 
-pub type ProcessCItemResult<T> = Result<T, ProcessCItemError>;
+/// A batch of citems of a block needs to be rolled back and re-run
+///
+/// Either some citems of a wave turned out invalid, or the citems of a wave
+/// after one that produced effects were processed without them.
+#[derive(Debug, Snafu)]
+#[snafu(display("Citems need to be re-run"))]
+pub struct RerunCItemsError {
+    /// Invalid citems, to be skipped when re-running
+    rejected: Vec<(usize, ProcessCItemError)>,
+    /// Results of the citems before this index are valid
+    end_idx: usize,
+    /// End of the wave, that needs to be processed sequentially from
+    /// `end_idx`
+    wave_end_idx: usize,
+}
+
+/// A range of citems of a finalized block, processed in a single database
+/// transaction
+struct CItemsBatch<'a> {
+    block_header: &'a BlockHeader,
+    leader_pubkey: PeerPubkey,
+    citems: &'a [CItem],
+    start_idx: usize,
+    end_idx: usize,
+    /// Citems already known to be invalid, skipped in processing
+    rejected: &'a BTreeMap<usize, String>,
+    /// Indices of citems to process
+    pending: Vec<usize>,
+    /// Declared access of each of the `pending` citems
+    accesses: Vec<Option<CItemAccess>>,
+}
+
+/// The citem processing error of `err`, or the database error to propagate
+fn citem_error(err: DbTxError<ProcessCItemError>) -> DbResult<ProcessCItemError> {
+    match DbError::try_from(err) {
+        Ok(err) => Err(err),
+        Err(source) => Ok(source),
+    }
+}
+
+/// Database transaction of module `module_id`, limited to its declared
+/// `access`, if any
+fn module_dbtx<'a>(
+    dbtx: &'a WriteTransactionCtx,
+    module_id: ModuleId,
+    access: Option<&'a CItemAccess>,
+) -> ModuleWriteTransactionCtx<'a> {
+    match access {
+        Some(access) => ModuleWriteTransactionCtx::new_with_access(
+            module_id,
+            dbtx,
+            access
+                .get(&module_id)
+                .expect("Access is declared for all the modules involved"),
+        ),
+        None => ModuleWriteTransactionCtx::new(module_id, dbtx),
+    }
+}
 
 impl NodeApp {
-    pub(crate) async fn process_citem(
+    /// Process citems of a finalized block, starting at `start_idx`
+    ///
+    /// All citems are processed in a single database transaction, saving the
+    /// progress once, at the end. Redb can create savepoints only in
+    /// transactions that did not write anything yet, so an invalid citem is
+    /// rolled back by discarding the transaction, and re-running only the
+    /// citems before it, committing them along with its rejection. Processing
+    /// then continues after it in a new transaction, so re-processing stays
+    /// linear in the number of citems, no matter how many are invalid. The
+    /// transaction is also committed early after a citem that changed the
+    /// modules setup, as the modules need to be reloaded before processing any
+    /// further citems.
+    ///
+    /// Consecutive citems whose modules declared non-conflicting access are
+    /// processed in parallel waves (see [`schedule`]), with their effects
+    /// applied in the block order afterwards. Citems of a wave don't affect
+    /// each other, so all the invalid ones in it are rejected at once. Only
+    /// effects of a citem can affect the ones after it in the wave, so the
+    /// rest of the wave gets rolled back the same way, and processed
+    /// sequentially.
+    ///
+    /// All processed citems, including the rejected ones, are recorded in the
+    /// [`citem_index`] in the same transaction.
+    pub(crate) async fn process_block_citems(
         &self,
-        (cur_round, cur_citem_idx): (BlockRound, BlockCItemIdx),
+        start_idx: BlockCItemIdx,
         block_header: &BlockHeader,
-        peer_pubkey: PeerPubkey,
-        peer_set: &mut Option<PeerSet>,
+        leader_pubkey: PeerPubkey,
+        peer_set: &mut Option<WeightedPeerSet>,
         modules_configs: &mut Option<BTreeMap<ModuleId, ModuleConfig>>,
-        citem: &CItem,
-    ) {
-        if let Err(err) = self
-            .process_citem_try(
-                (cur_round, cur_citem_idx),
-                block_header.round,
-                block_header.timestamp,
-                peer_pubkey,
-                peer_set.as_mut().expect("Must be set at this point"),
-                modules_configs,
-                citem,
-            )
-            .await
-        {
-            debug!(target: LOG_TARGET, err = %err.fmt_compact(), %cur_round, %cur_citem_idx, "Invalid consensus item" );
-            // If processing failed, we need to reset peer_set and module_configs, as they
-            // might have been altered, while the changes to consensus were
-            // rolled back
-            *peer_set = None;
-            *modules_configs = None;
-
-            // If processing failed, we need to advance the position in another, individual
-            // dbtx, as the existing one was rolled back.
-            self.db
-                .write_with_expect(|dbtx| {
-                    Self::save_cur_round_and_idx_dbtx(dbtx, cur_round, cur_citem_idx.next())
+        citems: &[CItem],
+    ) -> WhateverResult<()> {
+        let mut start_idx = usize::try_from(u32::from(start_idx)).expect("Can't fail");
+        let mut end_idx = citems.len();
+        let mut rejected: BTreeMap<usize, String> = BTreeMap::new();
+        let mut sequential_idxs = 0..0;
+
+        loop {
+            self.reload_invalidated_copies(modules_configs, peer_set)
+                .await?;
+
+            let modules = self.modules.read().await;
+            let mut batch_peer_set = peer_set.clone().expect("Must be set at this point");
+            let mut batch_modules_configs = modules_configs.clone();
+
+            let pending: Vec<usize> = (start_idx..end_idx)
+                .filter(|idx| !rejected.contains_key(idx))
+                .collect();
+            let accesses = pending
+                .iter()
+                .map(|&idx| {
+                    if sequential_idxs.contains(&idx) {
+                        return None;
+                    }
+                    schedule::citem_access(&modules, &citems[idx])
+                })
+                .collect();
+            let batch = CItemsBatch {
+                block_header,
+                leader_pubkey,
+                citems,
+                start_idx,
+                end_idx,
+                rejected: &rejected,
+                pending,
+                accesses,
+            };
+
+            let res = self
+                .db
+                .write_with_expect_falliable(|dbtx| {
+                    self.process_citems_batch_dbtx(
+                        dbtx,
+                        &modules,
+                        &batch,
+                        &mut batch_peer_set,
+                        &mut batch_modules_configs,
+                    )
                 })
                 .await;
+            drop(modules);
+
+            let (effects, batch_end_idx) = match res {
+                Ok(ok) => ok,
+                Err(RerunCItemsError {
+                    rejected: wave_rejected,
+                    end_idx: valid_end_idx,
+                    wave_end_idx,
+                }) => {
+                    for (idx, source) in wave_rejected {
+                        debug!(target: LOG_TARGET, err = %source.fmt_compact(), round = %block_header.round, idx, "Invalid consensus item");
+                        rejected.insert(idx, source.fmt_compact().to_string());
+                    }
+                    if valid_end_idx < wave_end_idx {
+                        debug!(target: LOG_TARGET, round = %block_header.round, idx = valid_end_idx, "Falling back to sequential processing of the rest of the wave");
+                    }
+                    end_idx = valid_end_idx;
+                    sequential_idxs = valid_end_idx..wave_end_idx;
+                    continue;
+                }
+            };
+
+            *peer_set = Some(batch_peer_set);
+            *modules_configs = batch_modules_configs;
+
+            self.process_consensus_change_effects_node(&effects).await;
+
+            for (idx, citem) in citems
+                .iter()
+                .enumerate()
+                .take(batch_end_idx)
+                .skip(start_idx)
+            {
+                let Some(module_citem) = self.own_module_citem(leader_pubkey, citem) else {
+                    continue;
+                };
+                let outcome = match rejected.get(&idx) {
+                    Some(err) => OwnCItemOutcome::Rejected { err: err.clone() },
+                    None => OwnCItemOutcome::Accepted,
+                };
+                self.notify_own_citem_processed(block_header.round, module_citem, outcome)
+                    .await;
+            }
+
+            if batch_end_idx == citems.len() {
+                return Ok(());
+            }
+            start_idx = batch_end_idx;
+            end_idx = citems.len();
+        }
+    }
+
+    /// Process a `batch` of citems wave by wave, returning their effects and
+    /// the index processing should continue at
+    fn process_citems_batch_dbtx(
+        &self,
+        dbtx: &WriteTransactionCtx,
+        modules: &BTreeMap<ModuleId, DynModuleWithConfig>,
+        batch: &CItemsBatch,
+        peer_set: &mut WeightedPeerSet,
+        modules_configs: &mut Option<BTreeMap<ModuleId, ModuleConfig>>,
+    ) -> DbTxResult<(Vec<ModuleCItemEffect>, usize), RerunCItemsError> {
+        let mut effects = vec![];
+
+        for wave in schedule::schedule_waves(&batch.accesses) {
+            let wave_idxs = &batch.pending[wave.clone()];
+            let wave_effects = Self::run_wave_dbtx(
+                dbtx,
+                modules,
+                batch,
+                peer_set,
+                wave_idxs,
+                &batch.accesses[wave],
+            );
+
+            let mut rejected = vec![];
+            let mut valid_len = wave_idxs.len();
+            for (wave_i, (&idx, citem_effects)) in wave_idxs.iter().zip(wave_effects).enumerate() {
+                let citem_effects = match citem_effects {
+                    Ok(citem_effects) => citem_effects,
+                    Err(err) => {
+                        rejected.push((idx, citem_error(err)?));
+                        continue;
+                    }
+                };
+                let has_effects = !citem_effects.is_empty();
+
+                if let Err(err) = self.apply_block_citem_dbtx(
+                    dbtx,
+                    modules,
+                    batch,
+                    idx,
+                    peer_set,
+                    modules_configs,
+                    &citem_effects,
+                ) {
+                    rejected.push((idx, citem_error(err)?));
+                    valid_len = wave_i + 1;
+                    break;
+                }
+                effects.extend(citem_effects);
+
+                if has_effects && wave_i + 1 < wave_idxs.len() {
+                    valid_len = wave_i + 1;
+                    break;
+                }
+
+                if rejected.is_empty() && modules_configs.is_none() && idx + 1 < batch.citems.len()
+                {
+                    let end_idx = idx + 1;
+                    Self::index_and_save_progress_dbtx(dbtx, batch, end_idx)?;
+                    return Ok((effects, end_idx));
+                }
+            }
+
+            if !rejected.is_empty() || valid_len < wave_idxs.len() {
+                return Err(RerunCItemsError {
+                    rejected,
+                    end_idx: wave_idxs[valid_len - 1] + 1,
+                    wave_end_idx: wave_idxs.last().expect("Waves are not empty") + 1,
+                })
+                .context(TxSnafu);
+            }
         }
+
+        Self::index_and_save_progress_dbtx(dbtx, batch, batch.end_idx)?;
+        Ok((effects, batch.end_idx))
+    }
+
+    /// Collect effects of citems at `wave_idxs`, processing the ones in
+    /// different lanes in parallel
+    fn run_wave_dbtx(
+        dbtx: &WriteTransactionCtx,
+        modules: &BTreeMap<ModuleId, DynModuleWithConfig>,
+        batch: &CItemsBatch,
+        peer_set: &WeightedPeerSet,
+        wave_idxs: &[usize],
+        wave_accesses: &[Option<CItemAccess>],
+    ) -> Vec<DbTxResult<Vec<ModuleCItemEffect>, ProcessCItemError>> {
+        let lanes = schedule::wave_lanes(wave_accesses);
+        let items: Vec<_> = wave_idxs.iter().zip(wave_accesses).collect();
+        schedule::run_wave(&items, &lanes, |&(&idx, access)| {
+            Self::collect_citem_effects_dbtx(
+                dbtx,
+                modules,
+                batch.block_header.round,
+                batch.leader_pubkey,
+                peer_set,
+                &batch.citems[idx],
+                access.as_ref(),
+            )
+        })
     }
 
+    /// Record citem at `idx` as processed and apply its `effects`
     #[allow(clippy::too_many_arguments)]
-    pub(crate) async fn process_citem_try(
+    fn apply_block_citem_dbtx(
         &self,
-        (cur_round, cur_citem_idx): (BlockRound, BlockCItemIdx),
+        dbtx: &WriteTransactionCtx,
+        modules: &BTreeMap<ModuleId, DynModuleWithConfig>,
+        batch: &CItemsBatch,
+        idx: usize,
+        peer_set: &mut WeightedPeerSet,
+        modules_configs: &mut Option<BTreeMap<ModuleId, ModuleConfig>>,
+        effects: &[ModuleCItemEffect],
+    ) -> DbTxResult<(), ProcessCItemError> {
+        Self::process_peer_citem_replay_dbtx(
+            dbtx,
+            batch.block_header.round,
+            batch.leader_pubkey,
+            &batch.citems[idx],
+        )?;
+        self.apply_citem_effects_dbtx(
+            dbtx,
+            modules,
+            batch.block_header.round,
+            batch.block_header.timestamp,
+            peer_set,
+            modules_configs,
+            effects,
+        )
+    }
+
+    /// Index citems of the `batch` up to `end_idx`, and save the progress
+    /// to be committed with them
+    fn index_and_save_progress_dbtx(
+        dbtx: &WriteTransactionCtx,
+        batch: &CItemsBatch,
+        end_idx: usize,
+    ) -> DbResult<()> {
+        Self::index_block_citems_dbtx(
+            dbtx,
+            batch.block_header,
+            batch.leader_pubkey,
+            batch.citems,
+            batch.start_idx..end_idx,
+            batch.rejected,
+        )?;
+
+        let round = batch.block_header.round;
+        if end_idx < batch.citems.len() {
+            return Self::save_cur_round_and_idx_dbtx(
+                dbtx,
+                round,
+                BlockCItemIdx::from(u32::try_from(end_idx).expect("Can't fail")),
+            );
+        }
+        let next_round = round.next().expect("Can't fail");
+        processed_citems::prune_processed_dbtx(dbtx, next_round)?;
+        Self::save_cur_round_and_idx_dbtx(dbtx, next_round, BlockCItemIdx::new(0))
+    }
+
+    /// Record citems at `idxs` of the block in the [`citem_index`]
+    fn index_block_citems_dbtx(
+        dbtx: &WriteTransactionCtx,
+        block_header: &BlockHeader,
+        leader_pubkey: PeerPubkey,
+        citems: &[CItem],
+        idxs: Range<usize>,
+        rejected: &BTreeMap<usize, String>,
+    ) -> DbResult<()> {
+        for idx in idxs {
+            let outcome = match rejected.get(&idx) {
+                Some(err) => CItemOutcome::Rejected { err: err.clone() },
+                None => CItemOutcome::Accepted,
+            };
+            citem_index::insert_citem_dbtx(
+                dbtx,
+                CItemLocation::new(
+                    block_header.round,
+                    BlockCItemIdx::from(u32::try_from(idx).expect("Can't fail")),
+                ),
+                &CItemIndexEntry::new(&citems[idx], block_header.timestamp, leader_pubkey, outcome),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The module citem of `citem`, if it was proposed by us
+    fn own_module_citem<'c>(
+        &self,
+        leader_pubkey: PeerPubkey,
+        citem: &'c CItem,
+    ) -> Option<&'c ModuleDyn<CItemRaw>> {
+        let our_pubkey = self.peer_pubkey?;
+        match citem {
+            CItem::PeerCItem(module_citem) if leader_pubkey == our_pubkey => Some(module_citem),
+            CItem::ForwardedPeerCItem(forwarded) if forwarded.peer_pubkey == our_pubkey => {
+                Some(&forwarded.citem)
+            }
+            _ => None,
+        }
+    }
+
+    async fn notify_own_citem_processed(
+        &self,
+        round: BlockRound,
+        module_citem: &ModuleDyn<CItemRaw>,
+        outcome: OwnCItemOutcome,
+    ) {
+        let module_id = module_citem.module_id();
+        let Some(module) = self
+            .modules
+            .read()
+            .await
+            .get(&module_id)
+            .map(|m| m.inner.clone())
+        else {
+            return;
+        };
+
+        module
+            .on_own_citem_processed(round, module_citem.inner(), outcome)
+            .await;
+    }
+
+    /// Process a single citem by its modules, returning its effects
+    ///
+    /// Touches only tables of the modules involved, and with the declared
+    /// `access`, only the tables in it, so citems with non-conflicting access
+    /// can be processed concurrently.
+    pub(crate) fn collect_citem_effects_dbtx(
+        dbtx: &WriteTransactionCtx,
+        modules: &BTreeMap<ModuleId, DynModuleWithConfig>,
         block_round: BlockRound,
-        block_timestamp: Timestamp,
         peer_pubkey: PeerPubkey,
-        peer_set: &mut PeerSet,
-        modules_configs: &mut Option<BTreeMap<ModuleId, ModuleConfig>>,
+        peer_set: &WeightedPeerSet,
         citem: &CItem,
-    ) -> ProcessCItemResult<()> {
-        let modules = self.modules.read().await;
-
-        // First, collect all effects with a read-only transaction
-        self.db
-            .write_with_expect_falliable(|dbtx| {
-                let mut effects = Vec::with_capacity(8);
-
-                match citem {
-                    CItem::PeerCItem(module_citem) => {
-                        let module_id = module_citem.module_id();
-                        if !peer_set.contains(&peer_pubkey) {
-                            None.whatever_context(
-                                "Ignoring citem from a peer pending consensus removal",
-                            )
-                            .context(ProcessingCItemFailedSnafu { module_id })
-                            .context(TxSnafu)?;
-                        }
-                        let module = modules
-                            .get(&module_id)
-                            .context(UnknownModuleIdSnafu { module_id })
-                            .context(TxSnafu)?;
-                        let module_kind = module.config.kind;
-
-                        let module_dbtx = ModuleWriteTransactionCtx::new(module_id, dbtx);
-
-                        effects.extend(
-                            module
-                                .process_citem(
-                                    &module_dbtx,
-                                    block_round,
-                                    peer_pubkey,
-                                    peer_set,
-                                    module_citem.inner(),
-                                )
-                                .map_err(|db_tx_err| {
-                                    db_tx_err.map(|e| {
-                                        (ProcessingCItemFailedSnafu { module_id }).into_error(e)
-                                    })
-                                })?
-                                .into_iter()
-                                .map(|inner| ModuleCItemEffect::new(module_kind, inner)),
-                        );
-                    }
-                    CItem::Transaction(transaction) => {
-                        // Process all inputs
-                        for input in &transaction.inner.inputs {
-                            let module_id = input.module_id();
-                            let module = modules
-                                .get(&module_id)
-                                .context(UnknownModuleIdSnafu { module_id })
-                                .context(TxSnafu)?;
-                            let module_kind = module.config.kind;
-
-                            let module_dbtx = ModuleWriteTransactionCtx::new(module_id, dbtx);
-
-                            effects.extend(
-                                module
-                                    .process_input(&module_dbtx, input.inner())
-                                    .map_err(|db_tx_err| {
-                                        db_tx_err.map(|e| {
-                                            (ProcessingInputFailedSnafu { module_id }).into_error(e)
-                                        })
-                                    })?
-                                    .into_iter()
-                                    .map(|inner| ModuleCItemEffect::new(module_kind, inner)),
-                            );
-                        }
-
-                        // Process all outputs
-                        for output in &transaction.inner.outputs {
-                            let module_id = output.module_id();
-                            let module = modules
-                                .get(&module_id)
-                                .context(UnknownModuleIdSnafu { module_id })
-                                .context(TxSnafu)?;
-                            let module_kind = module.config.kind;
-
-                            let module_dbtx = ModuleWriteTransactionCtx::new(module_id, dbtx);
-
-                            effects.extend(
-                                module
-                                    .process_output(&module_dbtx, output.inner())
-                                    .map_err(|db_tx_err| {
-                                        db_tx_err.map(|e| {
-                                            (ProcessingOutputFailedSnafu { module_id })
-                                                .into_error(e)
-                                        })
-                                    })?
-                                    .into_iter()
-                                    .map(|inner| ModuleCItemEffect::new(module_kind, inner)),
-                            );
-                        }
-                    }
-                }
+        access: Option<&CItemAccess>,
+    ) -> DbTxResult<Vec<ModuleCItemEffect>, ProcessCItemError> {
+        let mut effects = Vec::with_capacity(8);
 
-                self.process_consensus_change_effects_core_pre(
+        match citem {
+            CItem::PeerCItem(module_citem) => {
+                Self::process_peer_citem_dbtx(
                     dbtx,
-                    cur_round,
-                    block_timestamp,
+                    modules,
+                    block_round,
+                    peer_pubkey,
                     peer_set,
-                    &effects,
+                    module_citem,
+                    access,
+                    &mut effects,
                 )?;
-                for (&module_id, module) in &*modules {
-                    let module_dbtx = ModuleWriteTransactionCtx::new(module_id, dbtx);
-
-                    module
-                        .process_effects(&module_dbtx, peer_set, &effects)
-                        .map_err(|db_tx_err| {
-                            db_tx_err
-                                .map(|e| (ProcessingEffectFailedSnafu { module_id }).into_error(e))
-                        })?;
+            }
+            CItem::ForwardedPeerCItem(forwarded) => {
+                let module_id = forwarded.citem.module_id();
+                if forwarded
+                    .verify_sig_peer_pubkey(forwarded.peer_pubkey)
+                    .is_err()
+                {
+                    None.whatever_context("Invalid forwarded citem signature")
+                        .context(ProcessingCItemFailedSnafu { module_id })
+                        .context(TxSnafu)?;
+                }
+                if forwarded.valid_until <= block_round {
+                    None.whatever_context("Expired forwarded citem")
+                        .context(ProcessingCItemFailedSnafu { module_id })
+                        .context(TxSnafu)?;
+                }
+                Self::process_peer_citem_dbtx(
+                    dbtx,
+                    modules,
+                    block_round,
+                    forwarded.peer_pubkey,
+                    peer_set,
+                    &forwarded.citem,
+                    access,
+                    &mut effects,
+                )?;
+            }
+            CItem::Transaction(transaction) => {
+                // Process all inputs
+                for input in &transaction.inner.inputs {
+                    let module_id = input.module_id();
+                    let module = modules
+                        .get(&module_id)
+                        .context(UnknownModuleIdSnafu { module_id })
+                        .context(TxSnafu)?;
+                    let module_kind = module.config.kind;
+
+                    let module_dbtx = module_dbtx(dbtx, module_id, access);
+
+                    effects.extend(
+                        module
+                            .process_input(&module_dbtx, input.inner())
+                            .map_err(|db_tx_err| {
+                                db_tx_err.map(|e| {
+                                    (ProcessingInputFailedSnafu { module_id }).into_error(e)
+                                })
+                            })?
+                            .into_iter()
+                            .map(|inner| ModuleCItemEffect::new(module_kind, inner)),
+                    );
                 }
 
-                self.process_consensus_change_effects_core_post(modules_configs, &effects)?;
-                // Save the current position
-                Self::save_cur_round_and_idx_dbtx(dbtx, cur_round, cur_citem_idx)?;
+                // Process all outputs
+                for output in &transaction.inner.outputs {
+                    let module_id = output.module_id();
+                    let module = modules
+                        .get(&module_id)
+                        .context(UnknownModuleIdSnafu { module_id })
+                        .context(TxSnafu)?;
+                    let module_kind = module.config.kind;
 
-                Ok(())
-            })
-            .await?;
+                    let module_dbtx = module_dbtx(dbtx, module_id, access);
 
+                    effects.extend(
+                        module
+                            .process_output(&module_dbtx, output.inner())
+                            .map_err(|db_tx_err| {
+                                db_tx_err.map(|e| {
+                                    (ProcessingOutputFailedSnafu { module_id }).into_error(e)
+                                })
+                            })?
+                            .into_iter()
+                            .map(|inner| ModuleCItemEffect::new(module_kind, inner)),
+                    );
+                }
+            }
+        }
+
+        Ok(effects)
+    }
+
+    /// Reject a forwarded citem that was already processed, and remember a
+    /// processed peer citem, to reject forwarded copies of it later
+    ///
+    /// Citems are re-signed every time they are re-forwarded, so they are
+    /// tracked by their content, which also matches the peer's own inclusion
+    /// of the citem. Replaying an older forwarded citem (e.g. a vote) after a
+    /// newer one is rejected the same way.
+    pub(crate) fn process_peer_citem_replay_dbtx(
+        dbtx: &WriteTransactionCtx,
+        block_round: BlockRound,
+        leader_pubkey: PeerPubkey,
+        citem: &CItem,
+    ) -> DbTxResult<(), ProcessCItemError> {
+        let (hash, until) = match citem {
+            CItem::PeerCItem(module_citem) => (
+                PeerCItemHash::new(leader_pubkey, module_citem),
+                block_round
+                    .checked_add(ForwardedCItem::MAX_VALID_ROUNDS)
+                    .expect("Can't run out of u64 of rounds"),
+            ),
+            CItem::ForwardedPeerCItem(forwarded) => {
+                let hash = PeerCItemHash::new(forwarded.peer_pubkey, &forwarded.citem);
+                if processed_citems::is_processed(
+                    &dbtx.open_table(&app_processed_peer_citems::TABLE)?,
+                    block_round,
+                    hash,
+                )? {
+                    None.whatever_context("Forwarded citem already processed")
+                        .context(ProcessingCItemFailedSnafu {
+                            module_id: forwarded.citem.module_id(),
+                        })
+                        .context(TxSnafu)?;
+                }
+                (hash, forwarded.valid_until)
+            }
+            CItem::Transaction(_) => return Ok(()),
+        };
+        processed_citems::insert_processed_dbtx(dbtx, hash, until)?;
+        Ok(())
+    }
+
+    /// Apply `effects` of a single citem to the core consensus and all modules
+    ///
+    /// `peer_set` and `modules_configs` are updated along with the database,
+    /// so callers must discard them if the transaction gets rolled back.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn apply_citem_effects_dbtx(
+        &self,
+        dbtx: &WriteTransactionCtx,
+        modules: &BTreeMap<ModuleId, DynModuleWithConfig>,
+        block_round: BlockRound,
+        block_timestamp: Timestamp,
+        peer_set: &mut WeightedPeerSet,
+        modules_configs: &mut Option<BTreeMap<ModuleId, ModuleConfig>>,
+        effects: &[ModuleCItemEffect],
+    ) -> DbTxResult<(), ProcessCItemError> {
+        self.process_consensus_change_effects_core_pre(
+            dbtx,
+            block_round,
+            block_timestamp,
+            peer_set,
+            effects,
+        )?;
+        for (&module_id, module) in modules {
+            let module_dbtx = ModuleWriteTransactionCtx::new(module_id, dbtx);
+
+            module
+                .process_effects(&module_dbtx, block_round, peer_set, effects)
+                .map_err(|db_tx_err| {
+                    db_tx_err.map(|e| (ProcessingEffectFailedSnafu { module_id }).into_error(e))
+                })?;
+        }
+
+        self.process_consensus_change_effects_core_post(modules_configs, effects)?;
+
+        Ok(())
+    }
+
+    /// Process a module citem attributed to `peer_pubkey`
+    #[allow(clippy::too_many_arguments)]
+    fn process_peer_citem_dbtx(
+        dbtx: &WriteTransactionCtx,
+        modules: &BTreeMap<ModuleId, DynModuleWithConfig>,
+        block_round: BlockRound,
+        peer_pubkey: PeerPubkey,
+        peer_set: &WeightedPeerSet,
+        module_citem: &ModuleDyn<CItemRaw>,
+        access: Option<&CItemAccess>,
+        effects: &mut Vec<ModuleCItemEffect>,
+    ) -> DbTxResult<(), ProcessCItemError> {
+        let module_id = module_citem.module_id();
+        if !peer_set.contains(&peer_pubkey) {
+            None.whatever_context("Ignoring citem from a peer pending consensus removal")
+                .context(ProcessingCItemFailedSnafu { module_id })
+                .context(TxSnafu)?;
+        }
+        let module = modules
+            .get(&module_id)
+            .context(UnknownModuleIdSnafu { module_id })
+            .context(TxSnafu)?;
+        let module_kind = module.config.kind;
+
+        let module_dbtx = module_dbtx(dbtx, module_id, access);
+
+        effects.extend(
+            module
+                .process_citem(
+                    &module_dbtx,
+                    block_round,
+                    peer_pubkey,
+                    peer_set,
+                    module_citem.inner(),
+                )
+                .map_err(|db_tx_err| {
+                    db_tx_err.map(|e| (ProcessingCItemFailedSnafu { module_id }).into_error(e))
+                })?
+                .into_iter()
+                .map(|inner| ModuleCItemEffect::new(module_kind, inner)),
+        );
         Ok(())
     }
 
@@ -229,7 +704,7 @@ impl NodeApp {
         dbtx: &WriteTransactionCtx,
         round: BlockRound,
         block_timestamp: Timestamp,
-        peer_set: &mut PeerSet,
+        peer_set: &mut WeightedPeerSet,
         effects: &[ModuleCItemEffect],
     ) -> DbResult<()> {
         for effect in effects {
@@ -245,7 +720,8 @@ impl NodeApp {
 
                 // Modify the copy of peer_set in memory (will get reverted if processing fails
                 // later)
-                *peer_set = change.peer_set.clone();
+                *peer_set =
+                    WeightedPeerSet::new(change.peer_set.clone(), change.peer_weights.clone());
 
                 // Process the change in the core consensus (dbtx will get rolled back if
                 // processing fails later)
@@ -254,12 +730,33 @@ impl NodeApp {
                     round,
                     block_timestamp,
                     change.peer_set,
+                    change.bls_pubkeys,
+                    change.peer_weights,
+                    change.max_block_payload_len,
                 )?;
             }
         }
         Ok(())
     }
 
+    /// Let the node react to committed consensus changes
+    async fn process_consensus_change_effects_node(&self, effects: &[ModuleCItemEffect]) {
+        for effect in effects {
+            if effect.module_kind() != bfte_module_consensus_ctrl::KIND {
+                continue;
+            }
+
+            if effect.inner().effect_id == RotateKeyEffect::EFFECT_ID {
+                let rotation =
+                    RotateKeyEffect::decode(effect.inner()).expect("Can't fail to decode");
+
+                self.node_api
+                    .on_peer_key_rotation(rotation.old_pubkey, rotation.new_pubkey)
+                    .await;
+            }
+        }
+    }
+
     fn process_consensus_change_effects_core_post(
         &self,
         modules_configs: &mut Option<BTreeMap<ModuleId, ModuleConfig>>,
@@ -281,3 +778,6 @@ impl NodeApp {
         Ok(())
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests;
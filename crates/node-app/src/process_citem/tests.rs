@@ -0,0 +1,430 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use bfte_consensus::consensus::Consensus;
+use bfte_consensus_core::block::{BlockCItemIdx, BlockHeader, BlockRound};
+use bfte_consensus_core::bls::BlsKeyRegistration;
+use bfte_consensus_core::citem::{CItem, CItemRaw, ForwardedCItem, InputRaw, ModuleDyn, OutputRaw};
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::module::{ModuleId, ModuleKind};
+use bfte_consensus_core::peer::{PeerPubkey, PeerSeckey};
+use bfte_consensus_core::peer_set::WeightedPeerSet;
+use bfte_consensus_core::signed::Signed;
+use bfte_consensus_core::snapshot::StateSnapshot;
+use bfte_consensus_core::ver::{ConsensusVersionMajor, ConsensusVersionMinor};
+use bfte_db::Database;
+use bfte_db::error::{DbTxResult, TxSnafu};
+use bfte_module::effect::{CItemEffect, ModuleCItemEffect};
+use bfte_module::module::config::ModuleConfig;
+use bfte_module::module::db::{ConsensusTables, ModuleWriteTransactionCtx};
+use bfte_module::module::net::ModuleNet;
+use bfte_module::module::secret::ModuleSecret;
+use bfte_module::module::{
+    DynModuleInit, IModule, IModuleInit, ModuleInitArgs, ModuleInitResult,
+    ModuleSupportedConsensusVersions, OwnCItemOutcome,
+};
+use bfte_module_consensus_ctrl::ConsensusCtrlModuleInit;
+use bfte_module_consensus_ctrl::citem::ConsensusCtrlCitem;
+use bfte_node_app_core::INodeAppApi;
+use bfte_node_shared_modules::SharedModules;
+use bfte_util_error::{Whatever, WhateverResult};
+use snafu::{OptionExt as _, ResultExt as _};
+use tokio::sync::{mpsc, watch};
+
+use crate::{CONSENSUS_CTRL_MODULE_ID, NodeApp};
+
+struct TestNodeAppApi {
+    consensus: Arc<Consensus>,
+    peer_pubkey: PeerPubkey,
+}
+
+#[async_trait]
+impl INodeAppApi for TestNodeAppApi {
+    async fn get_consensus(&self) -> Arc<Consensus> {
+        self.consensus.clone()
+    }
+
+    async fn get_peer_pubkey(&self) -> Option<PeerPubkey> {
+        Some(self.peer_pubkey)
+    }
+
+    async fn get_module_secret(
+        &self,
+        _module_kind: ModuleKind,
+        _module_id: ModuleId,
+    ) -> Option<ModuleSecret> {
+        None
+    }
+
+    async fn get_module_net(&self, _module_id: ModuleId) -> Option<ModuleNet> {
+        None
+    }
+
+    async fn get_consensus_params(&self, round: BlockRound) -> ConsensusParams {
+        self.consensus.get_consensus_params(round).await
+    }
+
+    async fn ack_and_wait_next_block<'f>(
+        &self,
+        _round: BlockRound,
+    ) -> (BlockHeader, PeerPubkey, Arc<[CItem]>) {
+        std::future::pending().await
+    }
+
+    async fn record_state_snapshot(&self, _snapshot: StateSnapshot) {}
+
+    async fn get_restored_state_snapshot_round(&self) -> Option<BlockRound> {
+        None
+    }
+
+    async fn get_bls_key_registration(&self) -> Option<BlsKeyRegistration> {
+        None
+    }
+
+    async fn on_peer_key_rotation(&self, _old_pubkey: PeerPubkey, _new_pubkey: PeerPubkey) {}
+}
+
+/// A [`NodeApp`] of a single peer federation, fed blocks directly
+pub(crate) struct Setup {
+    pub(crate) app: NodeApp,
+    pub(crate) seckey: PeerSeckey,
+    other_seckeys: Vec<PeerSeckey>,
+    params: ConsensusParams,
+    pub(crate) round: BlockRound,
+    peer_set: Option<WeightedPeerSet>,
+    modules_configs: Option<BTreeMap<ModuleId, ModuleConfig>>,
+}
+
+impl Setup {
+    /// Set up with the consensus ctrl module, and `modules_inits` available
+    /// to be added
+    pub(crate) async fn new(modules_inits: Vec<DynModuleInit>) -> WhateverResult<Self> {
+        Self::new_federation(modules_inits, PeerSeckey::generate(), vec![]).await
+    }
+
+    /// Like [`Self::new`], but with `other_seckeys` peers in the federation
+    /// too
+    pub(crate) async fn new_federation(
+        modules_inits: Vec<DynModuleInit>,
+        seckey: PeerSeckey,
+        other_seckeys: Vec<PeerSeckey>,
+    ) -> WhateverResult<Self> {
+        let params = ConsensusParams {
+            peers: [seckey]
+                .iter()
+                .chain(&other_seckeys)
+                .map(|seckey| seckey.pubkey())
+                .collect(),
+            init_core_module_cons_version: ConsensusCtrlModuleInit.latest_version(),
+            ..ConsensusParams::new_test_dummy()
+        };
+
+        let db = Arc::new(
+            Database::new_in_memory()
+                .await
+                .whatever_context("Failed to open database")?,
+        );
+        let consensus = Arc::new(
+            Consensus::init(&params, db.clone(), Some(seckey.pubkey()), None)
+                .await
+                .whatever_context("Failed to init consensus")?,
+        );
+
+        let modules_inits = modules_inits
+            .into_iter()
+            .chain([Arc::new(ConsensusCtrlModuleInit) as DynModuleInit])
+            .map(|module_init| (module_init.kind(), module_init))
+            .collect();
+        let mut app = NodeApp::new(
+            db,
+            Arc::new(TestNodeAppApi {
+                consensus,
+                peer_pubkey: seckey.pubkey(),
+            }),
+            modules_inits,
+            SharedModules::new(),
+            watch::channel(vec![]).0,
+            mpsc::channel(1).1,
+        )
+        .await;
+        let modules_configs = app.init_consensus_ctrl(params.clone()).await?;
+        app.setup_modules_to(&modules_configs).await?;
+
+        Ok(Self {
+            app,
+            seckey,
+            other_seckeys,
+            params,
+            round: BlockRound::from(0),
+            peer_set: None,
+            modules_configs: None,
+        })
+    }
+
+    pub(crate) fn peer_pubkey(&self) -> PeerPubkey {
+        self.seckey.pubkey()
+    }
+
+    /// Process a block of `citems`, led by us
+    pub(crate) async fn process_block(&mut self, citems: &[CItem]) -> WhateverResult<()> {
+        let block_header = BlockHeader::new_dummy(self.round, &self.params);
+        self.app
+            .process_block_citems(
+                BlockCItemIdx::new(0),
+                &block_header,
+                self.peer_pubkey(),
+                &mut self.peer_set,
+                &mut self.modules_configs,
+                citems,
+            )
+            .await?;
+        self.round = self.round.next().expect("Can't fail");
+        Ok(())
+    }
+
+    /// Add a module of `module_kind`, returning its id
+    pub(crate) async fn add_module(&mut self, module_kind: ModuleKind) -> WhateverResult<ModuleId> {
+        let consensus_version = self
+            .app
+            .modules_inits
+            .get(&module_kind)
+            .whatever_context("Unknown module kind")?
+            .latest_version();
+        let vote = ModuleDyn::new(
+            CONSENSUS_CTRL_MODULE_ID,
+            ConsensusCtrlCitem::VoteAddModule {
+                module_kind,
+                consensus_version,
+            }
+            .encode_to_raw(),
+        );
+        let citems: Vec<_> = [CItem::PeerCItem(vote.clone())]
+            .into_iter()
+            .chain(
+                self.other_seckeys
+                    .iter()
+                    .map(|&seckey| self.forwarded_by(seckey, vote.clone())),
+            )
+            .collect();
+        self.process_block(&citems).await?;
+        // Modules get reloaded before processing the next block
+        self.process_block(&[]).await?;
+
+        self.app
+            .modules
+            .read()
+            .await
+            .iter()
+            .find(|(_, module)| module.config.kind == module_kind)
+            .map(|(module_id, _)| *module_id)
+            .whatever_context("Module not added")
+    }
+
+    /// Our `citem`, forwarded to the leader
+    pub(crate) fn forwarded(&self, citem: ModuleDyn<CItemRaw>) -> CItem {
+        self.forwarded_by(self.seckey, citem)
+    }
+
+    /// `citem` of the peer with `seckey`, forwarded to the leader
+    pub(crate) fn forwarded_by(&self, seckey: PeerSeckey, citem: ModuleDyn<CItemRaw>) -> CItem {
+        CItem::ForwardedPeerCItem(Signed::new_sign(
+            ForwardedCItem {
+                peer_pubkey: seckey.pubkey(),
+                valid_until: self
+                    .round
+                    .checked_add(ForwardedCItem::MAX_VALID_ROUNDS)
+                    .expect("Can't fail"),
+                citem,
+            },
+            seckey,
+        ))
+    }
+}
+
+const RECORDING_KIND: ModuleKind = ModuleKind::new(u32::MAX);
+
+/// What [`RecordingModule`] observed
+#[derive(Default)]
+struct Recorded {
+    /// Outcomes of own citems, as delivered to the module
+    outcomes: Mutex<Vec<(Vec<u8>, OwnCItemOutcome)>>,
+    /// Number of citems processed, including the rolled back ones
+    processed: AtomicUsize,
+}
+
+/// Accepts only citems of a single odd byte, and records outcomes of own
+/// citems
+struct RecordingModuleInit(Arc<Recorded>);
+
+#[async_trait]
+impl IModuleInit for RecordingModuleInit {
+    fn kind(&self) -> ModuleKind {
+        RECORDING_KIND
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Recording"
+    }
+
+    fn supported_versions(&self) -> ModuleSupportedConsensusVersions {
+        BTreeMap::from([(ConsensusVersionMajor::new(0), ConsensusVersionMinor::new(0))])
+    }
+
+    async fn init(
+        &self,
+        _args: ModuleInitArgs,
+    ) -> ModuleInitResult<Arc<dyn IModule + Send + Sync + 'static>> {
+        Ok(Arc::new(RecordingModule(self.0.clone())))
+    }
+}
+
+struct RecordingModule(Arc<Recorded>);
+
+#[async_trait]
+impl IModule for RecordingModule {
+    async fn propose_citems_rx(&self) -> watch::Receiver<Vec<CItemRaw>> {
+        watch::channel(vec![]).1
+    }
+
+    async fn on_own_citem_processed(
+        &self,
+        _round: BlockRound,
+        citem: &CItemRaw,
+        outcome: OwnCItemOutcome,
+    ) {
+        self.0
+            .outcomes
+            .lock()
+            .expect("Locking failed")
+            .push((citem.to_vec(), outcome));
+    }
+
+    fn process_citem(
+        &self,
+        _dbtx: &ModuleWriteTransactionCtx,
+        _round: BlockRound,
+        _peer_pubkey: PeerPubkey,
+        _peer_set: &WeightedPeerSet,
+        citem: &CItemRaw,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        self.0.processed.fetch_add(1, Ordering::SeqCst);
+        if !matches!(citem.0.as_ref(), [byte] if byte % 2 == 1) {
+            None.whatever_context("Invalid citem").context(TxSnafu)?;
+        }
+        Ok(vec![])
+    }
+
+    fn process_input(
+        &self,
+        _dbtx: &ModuleWriteTransactionCtx,
+        _input: &InputRaw,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        None.whatever_context("No inputs").context(TxSnafu)?
+    }
+
+    fn process_output(
+        &self,
+        _dbtx: &ModuleWriteTransactionCtx,
+        _output: &OutputRaw,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        None.whatever_context("No outputs").context(TxSnafu)?
+    }
+
+    fn process_effects(
+        &self,
+        _dbtx: &ModuleWriteTransactionCtx,
+        _round: BlockRound,
+        _peer_set: &WeightedPeerSet,
+        _effects: &[ModuleCItemEffect],
+    ) -> DbTxResult<(), Whatever> {
+        Ok(())
+    }
+
+    fn consensus_tables(&self) -> ConsensusTables {
+        ConsensusTables::new()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn own_citem_outcomes_are_delivered() -> WhateverResult<()> {
+    let recorded = Arc::new(Recorded::default());
+    let mut setup = Setup::new(vec![Arc::new(RecordingModuleInit(recorded.clone()))]).await?;
+    let module_id = setup.add_module(RECORDING_KIND).await?;
+    let citem = |byte: u8| ModuleDyn::new(module_id, CItemRaw(Arc::from([byte].as_slice())));
+
+    let citems = [
+        CItem::PeerCItem(citem(1)),
+        CItem::PeerCItem(citem(2)),
+        setup.forwarded(citem(3)),
+        setup.forwarded(citem(4)),
+        // Already included by the peer itself
+        setup.forwarded(citem(1)),
+    ];
+    setup.process_block(&citems).await?;
+    // Already included as forwarded
+    let citems = [setup.forwarded(citem(3))];
+    setup.process_block(&citems).await?;
+
+    let outcomes: Vec<_> = recorded
+        .outcomes
+        .lock()
+        .expect("Locking failed")
+        .iter()
+        .map(|(citem, outcome)| (citem[0], matches!(outcome, OwnCItemOutcome::Accepted)))
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            (1, true),
+            (2, false),
+            (3, true),
+            (4, false),
+            (1, false),
+            (3, false)
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_citems_are_not_reprocessed_quadratically() -> WhateverResult<()> {
+    let recorded = Arc::new(Recorded::default());
+    let mut setup = Setup::new(vec![Arc::new(RecordingModuleInit(recorded.clone()))]).await?;
+    let module_id = setup.add_module(RECORDING_KIND).await?;
+
+    let citems: Vec<_> = (0..100u8)
+        .map(|byte| {
+            CItem::PeerCItem(ModuleDyn::new(
+                module_id,
+                CItemRaw(Arc::from([byte].as_slice())),
+            ))
+        })
+        .collect();
+    recorded.processed.store(0, Ordering::SeqCst);
+    setup.process_block(&citems).await?;
+
+    // Every invalid citem rolls back, and re-processes, only the valid one
+    // before it
+    assert!(recorded.processed.load(Ordering::SeqCst) <= 2 * citems.len());
+    let accepted: Vec<_> = recorded
+        .outcomes
+        .lock()
+        .expect("Locking failed")
+        .iter()
+        .filter(|(_, outcome)| matches!(outcome, OwnCItemOutcome::Accepted))
+        .map(|(citem, _)| citem[0])
+        .collect();
+    assert_eq!(
+        accepted,
+        (0..100u8).filter(|byte| byte % 2 == 1).collect::<Vec<_>>()
+    );
+
+    Ok(())
+}
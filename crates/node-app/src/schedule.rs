@@ -0,0 +1,218 @@
+//! Scheduling independent citems of a block for parallel processing
+
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::thread;
+
+use bfte_consensus_core::citem::CItem;
+use bfte_consensus_core::module::ModuleId;
+use bfte_module::module::DynModuleWithConfig;
+use bfte_module::module::access::AccessSet;
+
+/// Everything processing of a citem can access, per module
+pub(crate) type CItemAccess = BTreeMap<ModuleId, AccessSet>;
+
+/// What processing of `citem` can access, if all the involved modules
+/// declared it
+pub(crate) fn citem_access(
+    modules: &BTreeMap<ModuleId, DynModuleWithConfig>,
+    citem: &CItem,
+) -> Option<CItemAccess> {
+    let mut access = CItemAccess::new();
+    let mut extend = |module_id: ModuleId, module_access: AccessSet| {
+        access.entry(module_id).or_default().extend(&module_access);
+    };
+
+    match citem {
+        CItem::PeerCItem(module_citem) => {
+            let module_id = module_citem.module_id();
+            extend(
+                module_id,
+                modules
+                    .get(&module_id)?
+                    .citem_access(module_citem.inner())?,
+            );
+        }
+        CItem::ForwardedPeerCItem(forwarded) => {
+            let module_id = forwarded.citem.module_id();
+            extend(
+                module_id,
+                modules
+                    .get(&module_id)?
+                    .citem_access(forwarded.citem.inner())?,
+            );
+        }
+        CItem::Transaction(transaction) => {
+            for input in &transaction.inner.inputs {
+                let module_id = input.module_id();
+                extend(
+                    module_id,
+                    modules.get(&module_id)?.input_access(input.inner())?,
+                );
+            }
+            for output in &transaction.inner.outputs {
+                let module_id = output.module_id();
+                extend(
+                    module_id,
+                    modules.get(&module_id)?.output_access(output.inner())?,
+                );
+            }
+        }
+    }
+
+    Some(access)
+}
+
+fn conflicts(a: &CItemAccess, b: &CItemAccess) -> bool {
+    a.iter().any(|(module_id, access)| {
+        b.get(module_id)
+            .is_some_and(|other_access| access.conflicts_with(other_access))
+    })
+}
+
+fn shares_table(a: &CItemAccess, b: &CItemAccess) -> bool {
+    a.iter().any(|(module_id, access)| {
+        b.get(module_id).is_some_and(|other_access| {
+            access
+                .tables()
+                .any(|table_name| other_access.tables().any(|other| other == table_name))
+        })
+    })
+}
+
+/// Split citems into consecutive waves of citems that can be processed in
+/// parallel
+///
+/// Citems in a wave have pairwise non-conflicting access sets. Citems with an
+/// unknown access set always form a wave of their own.
+pub(crate) fn schedule_waves(accesses: &[Option<CItemAccess>]) -> Vec<Range<usize>> {
+    let mut waves = vec![];
+    let mut wave_start = 0;
+    let mut wave_access = Some(CItemAccess::new());
+
+    for (idx, access) in accesses.iter().enumerate() {
+        let fits = match (&wave_access, access) {
+            (Some(wave_access), Some(access)) => !conflicts(wave_access, access),
+            _ => false,
+        };
+
+        if !fits && wave_start < idx {
+            waves.push(wave_start..idx);
+            wave_start = idx;
+            wave_access = Some(CItemAccess::new());
+        }
+
+        match (&mut wave_access, access) {
+            (Some(wave_access), Some(access)) => {
+                for (module_id, module_access) in access {
+                    wave_access
+                        .entry(*module_id)
+                        .or_default()
+                        .extend(module_access);
+                }
+            }
+            _ => wave_access = None,
+        }
+    }
+
+    if wave_start < accesses.len() {
+        waves.push(wave_start..accesses.len());
+    }
+
+    waves
+}
+
+/// Split citems of a wave into lanes that can run in parallel
+///
+/// Citems accessing different parts of the same table end up in the same lane,
+/// as the table can't be open more than once at a time. Returns indices into
+/// `accesses`, ascending within each lane.
+pub(crate) fn wave_lanes(accesses: &[Option<CItemAccess>]) -> Vec<Vec<usize>> {
+    let mut lanes: Vec<(CItemAccess, Vec<usize>)> = vec![];
+
+    for (idx, access) in accesses.iter().enumerate() {
+        let Some(access) = access else {
+            lanes.push((CItemAccess::new(), vec![idx]));
+            continue;
+        };
+
+        let mut lane_access = access.clone();
+        let mut lane_idxs = vec![idx];
+        let mut i = 0;
+        while i < lanes.len() {
+            if shares_table(&lanes[i].0, &lane_access) {
+                let (other_access, other_idxs) = lanes.remove(i);
+                for (module_id, module_access) in &other_access {
+                    lane_access
+                        .entry(*module_id)
+                        .or_default()
+                        .extend(module_access);
+                }
+                lane_idxs.extend(other_idxs);
+                // Merging can make the lane share tables with lanes already
+                // checked
+                i = 0;
+            } else {
+                i += 1;
+            }
+        }
+        lane_idxs.sort_unstable();
+        lanes.push((lane_access, lane_idxs));
+    }
+
+    let mut lanes: Vec<_> = lanes.into_iter().map(|(_, idxs)| idxs).collect();
+    lanes.sort_unstable();
+    lanes
+}
+
+/// Run `f` over all `items`, `lanes` (see [`wave_lanes`]) in parallel,
+/// returning results in the order of `items`
+pub(crate) fn run_wave<T, R>(
+    items: &[T],
+    lanes: &[Vec<usize>],
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    if lanes.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let threads = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1);
+    let chunk_len = lanes.len().div_ceil(threads);
+
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+    thread::scope(|s| {
+        let f = &f;
+        let handles: Vec<_> = lanes
+            .chunks(chunk_len)
+            .map(|chunk| {
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .flatten()
+                        .map(|&i| (i, f(&items[i])))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (i, res) in handle.join().expect("Citem processing thread panicked") {
+                results[i] = Some(res);
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|res| res.expect("Every item is in a lane"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::citem::{CItem, CItemRaw, InputRaw, ModuleDyn, OutputRaw};
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::module::{ModuleId, ModuleKind};
+use bfte_consensus_core::peer::{PeerPubkey, PeerSeckey};
+use bfte_consensus_core::peer_set::WeightedPeerSet;
+use bfte_consensus_core::snapshot::StateSnapshotTable;
+use bfte_db::error::DbTxResult;
+use bfte_module::effect::{CItemEffect, ModuleCItemEffect};
+use bfte_module::module::access::AccessSet;
+use bfte_module::module::db::{ConsensusTables, ModuleWriteTransactionCtx, dump_modules_tables};
+use bfte_module::module::{
+    DynModule, DynModuleInit, IModule, IModuleInit, ModuleInitArgs, ModuleInitResult,
+    ModuleSupportedConsensusVersions, OwnCItemOutcome,
+};
+use bfte_module_consensus_ctrl::citem::ConsensusCtrlCitem;
+use bfte_module_meta::MetaModuleInit;
+use bfte_module_meta::citem::MetaCitem;
+use bfte_node_app_core::citem_index::{self, CItemOutcome};
+use bfte_util_error::{Whatever, WhateverResult};
+use tokio::sync::watch;
+
+use super::{CItemAccess, citem_access, schedule_waves, wave_lanes};
+use crate::CONSENSUS_CTRL_MODULE_ID;
+use crate::process_citem::tests::Setup;
+
+fn access(tables: &[&str]) -> Option<CItemAccess> {
+    Some(
+        [(
+            ModuleId::new(1),
+            tables.iter().copied().collect::<AccessSet>(),
+        )]
+        .into(),
+    )
+}
+
+#[test]
+fn waves_split_on_conflicts() {
+    assert_eq!(
+        schedule_waves(&[
+            access(&["a"]),
+            access(&["b", "c"]),
+            access(&["c"]),
+            access(&["a"]),
+            None,
+            access(&["a"]),
+            access(&["b"]),
+            None,
+            None,
+        ]),
+        [0..2, 2..4, 4..5, 5..7, 7..8, 8..9]
+    );
+    assert!(schedule_waves(&[]).is_empty());
+}
+
+#[test]
+fn lanes_group_shared_tables() {
+    assert_eq!(
+        wave_lanes(&[
+            access(&["a"]),
+            access(&["b"]),
+            access(&["a", "c"]),
+            None,
+            access(&["d"]),
+            access(&["c", "d"]),
+        ]),
+        [vec![0, 2, 4, 5], vec![1], vec![3]]
+    );
+    assert!(wave_lanes(&[]).is_empty());
+}
+
+/// Hides access declarations of the wrapped module, so all its citems are
+/// processed sequentially
+struct UndeclaredModuleInit(DynModuleInit);
+
+#[async_trait]
+impl IModuleInit for UndeclaredModuleInit {
+    fn kind(&self) -> ModuleKind {
+        self.0.kind()
+    }
+
+    fn singleton(&self) -> bool {
+        self.0.singleton()
+    }
+
+    fn display_name(&self) -> &'static str {
+        self.0.display_name()
+    }
+
+    fn supported_versions(&self) -> ModuleSupportedConsensusVersions {
+        self.0.supported_versions()
+    }
+
+    async fn init(
+        &self,
+        args: ModuleInitArgs,
+    ) -> ModuleInitResult<Arc<dyn IModule + Send + Sync + 'static>> {
+        Ok(Arc::new(UndeclaredModule(self.0.init(args).await?)))
+    }
+}
+
+struct UndeclaredModule(DynModule);
+
+#[async_trait]
+impl IModule for UndeclaredModule {
+    async fn propose_citems_rx(&self) -> watch::Receiver<Vec<CItemRaw>> {
+        self.0.propose_citems_rx().await
+    }
+
+    async fn on_own_citem_processed(
+        &self,
+        round: BlockRound,
+        citem: &CItemRaw,
+        outcome: OwnCItemOutcome,
+    ) {
+        self.0.on_own_citem_processed(round, citem, outcome).await;
+    }
+
+    fn process_citem(
+        &self,
+        dbtx: &ModuleWriteTransactionCtx,
+        round: BlockRound,
+        peer_pubkey: PeerPubkey,
+        peer_set: &WeightedPeerSet,
+        citem: &CItemRaw,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        self.0
+            .process_citem(dbtx, round, peer_pubkey, peer_set, citem)
+    }
+
+    fn process_input(
+        &self,
+        dbtx: &ModuleWriteTransactionCtx,
+        input: &InputRaw,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        self.0.process_input(dbtx, input)
+    }
+
+    fn process_output(
+        &self,
+        dbtx: &ModuleWriteTransactionCtx,
+        output: &OutputRaw,
+    ) -> DbTxResult<Vec<CItemEffect>, Whatever> {
+        self.0.process_output(dbtx, output)
+    }
+
+    fn process_effects(
+        &self,
+        dbtx: &ModuleWriteTransactionCtx,
+        round: BlockRound,
+        peer_set: &WeightedPeerSet,
+        effects: &[ModuleCItemEffect],
+    ) -> DbTxResult<(), Whatever> {
+        self.0.process_effects(dbtx, round, peer_set, effects)
+    }
+
+    fn consensus_tables(&self) -> ConsensusTables {
+        self.0.consensus_tables()
+    }
+}
+
+/// Meta votes of all the peers, overriding each other's, and consensus ctrl
+/// votes in between, none reaching the threshold
+///
+/// Some meta citems are invalid approvals, or included twice, to be rejected.
+fn citems(setup: &Setup, meta_module_id: ModuleId, seckeys: &[PeerSeckey]) -> Vec<CItem> {
+    let outsider_pubkey = PeerSeckey::generate().pubkey();
+    (0..60u8)
+        .flat_map(|i| {
+            let seckey = seckeys[usize::from(i) % seckeys.len()];
+            let meta_citem = if i % 7 == 3 {
+                MetaCitem::ApproveVote {
+                    key: i % 3,
+                    peer_pubkey: outsider_pubkey,
+                }
+            } else {
+                MetaCitem::ProposeValue {
+                    key: i % 3,
+                    value: Arc::from([i].as_slice()),
+                }
+            };
+            let meta = ModuleDyn::new(meta_module_id, meta_citem.encode_to_raw());
+            let meta_again = (i % 11 == 5).then(|| meta.clone());
+            let ctrl = (i % 5 == 0).then(|| {
+                ModuleDyn::new(
+                    CONSENSUS_CTRL_MODULE_ID,
+                    ConsensusCtrlCitem::VoteSetMaxBlockPayloadLen(
+                        ConsensusParams::MIN_MAX_BLOCK_PAYLOAD_LEN + u32::from(i),
+                    )
+                    .encode_to_raw(),
+                )
+            });
+            [Some(meta), meta_again, ctrl]
+                .into_iter()
+                .flatten()
+                .map(move |citem| setup.forwarded_by(seckey, citem))
+        })
+        .collect()
+}
+
+/// Process [`citems`] in a block, returning whether any of them were
+/// scheduled in parallel, their outcomes, and the resulting state of the
+/// modules
+async fn process_citems(
+    seckeys: &[PeerSeckey],
+    declared: bool,
+) -> WhateverResult<(
+    bool,
+    Vec<CItemOutcome>,
+    BTreeMap<String, StateSnapshotTable>,
+)> {
+    let meta_init: DynModuleInit = if declared {
+        Arc::new(MetaModuleInit)
+    } else {
+        Arc::new(UndeclaredModuleInit(Arc::new(MetaModuleInit)))
+    };
+    let mut setup =
+        Setup::new_federation(vec![meta_init], seckeys[0], seckeys[1..].to_vec()).await?;
+    let meta_module_id = setup.add_module(bfte_module_meta::KIND).await?;
+
+    let citems = citems(&setup, meta_module_id, seckeys);
+    let parallel = {
+        let modules = setup.app.modules.read().await;
+        let accesses: Vec<_> = citems
+            .iter()
+            .map(|citem| citem_access(&modules, citem))
+            .collect();
+        schedule_waves(&accesses).iter().any(|wave| 1 < wave.len())
+    };
+    let round = setup.round;
+    setup.process_block(&citems).await?;
+
+    let consensus_tables = setup
+        .app
+        .modules
+        .read()
+        .await
+        .iter()
+        .map(|(module_id, module)| (*module_id, module.consensus_tables()))
+        .collect();
+    let state = setup
+        .app
+        .db
+        .read_with_expect(|dbtx| dump_modules_tables(dbtx, &consensus_tables))
+        .await;
+    let outcomes = setup
+        .app
+        .db
+        .read_with_expect(|dbtx| citem_index::get_round_citems_dbtx(dbtx, round))
+        .await
+        .into_iter()
+        .map(|(_, entry)| entry.outcome)
+        .collect();
+
+    Ok((parallel, outcomes, state))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn parallel_matches_sequential() -> WhateverResult<()> {
+    let seckeys: Vec<_> = (0..4).map(|_| PeerSeckey::generate()).collect();
+
+    let (parallel, parallel_outcomes, parallel_state) = process_citems(&seckeys, true).await?;
+    let (sequential, sequential_outcomes, sequential_state) =
+        process_citems(&seckeys, false).await?;
+    assert!(parallel);
+    assert!(!sequential);
+    assert!(
+        parallel_outcomes
+            .iter()
+            .any(|outcome| matches!(outcome, CItemOutcome::Rejected { .. }))
+    );
+    assert_eq!(parallel_outcomes, sequential_outcomes);
+    assert_eq!(parallel_state, sequential_state);
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Simulating processing of citems against the current state
+
+use std::collections::BTreeMap;
+
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::citem::CItem;
+use bfte_consensus_core::module::ModuleId;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::peer_set::WeightedPeerSet;
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_module::module::CItemSimulation;
+use bfte_module::module::config::ModuleConfig;
+use bfte_node_app_core::SimulateCItemRequest;
+use bfte_util_error::WhateverResult;
+use bfte_util_error::fmt::FmtCompact as _;
+use tracing::{debug, warn};
+
+use crate::{LOG_TARGET, NodeApp};
+
+impl NodeApp {
+    pub(crate) async fn handle_simulate_citem_request(
+        &self,
+        round: BlockRound,
+        peer_set: &mut Option<WeightedPeerSet>,
+        modules_configs: &mut Option<BTreeMap<ModuleId, ModuleConfig>>,
+        req: SimulateCItemRequest,
+    ) {
+        let res = self
+            .simulate_citem(
+                round,
+                peer_set,
+                modules_configs,
+                &req.citem,
+                req.peer_pubkey,
+            )
+            .await;
+        if let Err(err) = &res {
+            warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to simulate citem");
+        }
+
+        if req.response_tx.send(res).is_err() {
+            debug!(target: LOG_TARGET, "Citem simulation requester went away");
+        }
+    }
+
+    /// Process `citem` as if `peer_pubkey` included it in a block at `round`,
+    /// and roll back all the changes
+    ///
+    /// Must be called between blocks, when `peer_set` and `modules_configs`
+    /// match the committed state.
+    pub(crate) async fn simulate_citem(
+        &self,
+        round: BlockRound,
+        peer_set: &mut Option<WeightedPeerSet>,
+        modules_configs: &mut Option<BTreeMap<ModuleId, ModuleConfig>>,
+        citem: &CItem,
+        peer_pubkey: PeerPubkey,
+    ) -> WhateverResult<CItemSimulation> {
+        self.reload_invalidated_copies(modules_configs, peer_set)
+            .await?;
+
+        let modules = self.modules.read().await;
+        let mut sim_peer_set = peer_set.clone().expect("Must be set at this point");
+        let mut sim_modules_configs = modules_configs.clone();
+
+        let res = self
+            .db
+            .dry_write_with_expect_falliable(|dbtx| {
+                let effects = Self::collect_citem_effects_dbtx(
+                    dbtx,
+                    &modules,
+                    round,
+                    peer_pubkey,
+                    &sim_peer_set,
+                    citem,
+                    None,
+                )?;
+                Self::process_peer_citem_replay_dbtx(dbtx, round, peer_pubkey, citem)?;
+                self.apply_citem_effects_dbtx(
+                    dbtx,
+                    &modules,
+                    round,
+                    Timestamp::now(),
+                    &mut sim_peer_set,
+                    &mut sim_modules_configs,
+                    &effects,
+                )?;
+                Ok(effects)
+            })
+            .await;
+
+        Ok(match res {
+            Ok(effects) => CItemSimulation::Accepted { effects },
+            Err(err) => CItemSimulation::Rejected {
+                err: err.fmt_compact().to_string(),
+            },
+        })
+    }
+}
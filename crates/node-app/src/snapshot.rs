@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::snapshot::StateSnapshot;
+use bfte_module::module::db::dump_modules_tables;
+use bfte_node_app_core::processed_citems;
+use tracing::info;
+
+use crate::{LOG_TARGET, NodeApp};
+
+impl NodeApp {
+    /// Take a [`StateSnapshot`] and pass it to the node to attest and serve
+    ///
+    /// Must be called when all the blocks before `round` were processed, and
+    /// nothing else.
+    pub(crate) async fn take_state_snapshot(&self, round: BlockRound) {
+        let consensus_tables: BTreeMap<_, _> = self
+            .modules
+            .read()
+            .await
+            .iter()
+            .map(|(module_id, module)| (*module_id, module.consensus_tables()))
+            .collect();
+        let tables = self
+            .db
+            .read_with_expect(|dbtx| {
+                let mut tables = dump_modules_tables(dbtx, &consensus_tables)?;
+                processed_citems::dump_tables_dbtx(dbtx, &mut tables)?;
+                Ok(tables)
+            })
+            .await;
+        let consensus_params = self.consensus.get_consensus_params_schedule().await;
+
+        let snapshot = StateSnapshot {
+            round,
+            consensus_params,
+            tables,
+        };
+
+        info!(
+            target: LOG_TARGET,
+            %round,
+            num_tables = snapshot.tables.len(),
+            "Taking state snapshot"
+        );
+
+        self.node_api.record_state_snapshot(snapshot).await;
+    }
+}
@@ -35,14 +35,30 @@ use tracing::info;
 const LOG_TARGET: &str = "bfte::node::ui";
 const ROUTE_UI: &str = "/ui";
 const ROUTE_EXPLORER: &str = "/ui/explorer";
+const ROUTE_EXPLORER_BLOCK: &str = "/ui/explorer/block/{round}";
 const ROUTE_LOGIN: &str = "/ui/login";
 const ROUTE_MODULE: &str = "/ui/module/{module-id}";
 const ROUTE_MODULE_ADD_PEER_VOTE: &str = "/ui/module/{module-id}/add_peer_vote";
+const ROUTE_MODULE_ADD_PEER_VOTE_PREVIEW: &str = "/ui/module/{module-id}/add_peer_vote/preview";
 const ROUTE_MODULE_REMOVE_PEER_VOTE: &str = "/ui/module/{module-id}/remove_peer_vote";
+const ROUTE_MODULE_REMOVE_PEER_VOTE_PREVIEW: &str =
+    "/ui/module/{module-id}/remove_peer_vote/preview";
+const ROUTE_MODULE_SET_PEER_WEIGHT_VOTE: &str = "/ui/module/{module-id}/set_peer_weight_vote";
+const ROUTE_MODULE_SET_PEER_WEIGHT_VOTE_PREVIEW: &str =
+    "/ui/module/{module-id}/set_peer_weight_vote/preview";
+const ROUTE_MODULE_SET_MAX_BLOCK_PAYLOAD_LEN_VOTE: &str =
+    "/ui/module/{module-id}/set_max_block_payload_len_vote";
+const ROUTE_MODULE_SET_MAX_BLOCK_PAYLOAD_LEN_VOTE_PREVIEW: &str =
+    "/ui/module/{module-id}/set_max_block_payload_len_vote/preview";
+const ROUTE_MODULE_ROTATE_KEY: &str = "/ui/module/{module-id}/rotate_key";
 const ROUTE_MODULE_ADD_MODULE_VOTE: &str = "/ui/module/{module-id}/add_module_vote";
+const ROUTE_MODULE_ADD_MODULE_VOTE_PREVIEW: &str = "/ui/module/{module-id}/add_module_vote/preview";
+const ROUTE_MODULE_AUTO_REMOVE_EQUIVOCATING: &str =
+    "/ui/module/{module-id}/auto_remove_equivocating";
 const ROUTE_MODULE_META_KEY: &str = "/ui/module/{module-id}/meta_key/{key}";
 const ROUTE_MODULE_META_KEY_REDIRECT: &str = "/ui/module/{module-id}/meta_key_redirect";
 const ROUTE_MODULE_META_VOTE: &str = "/ui/module/{module-id}/meta_key/{key}/vote";
+const ROUTE_MODULE_META_VOTE_PREVIEW: &str = "/ui/module/{module-id}/meta_key/{key}/vote/preview";
 const ROUTE_INIT_CONSENSUS: &str = "/ui/init";
 const ROUTE_INVITE: &str = "/ui/invite";
 const ROUTE_DS_CURRENT_ROUND: &str = "/datastar/current-round";
@@ -2,9 +2,16 @@ use axum::Router;
 use axum::routing::{get, post};
 
 use crate::{
-    ArcUiState, ROUTE_DS_CURRENT_ROUND, ROUTE_EXPLORER, ROUTE_INIT_CONSENSUS, ROUTE_INVITE, ROUTE_LOGIN,
-    ROUTE_MODULE, ROUTE_MODULE_ADD_MODULE_VOTE, ROUTE_MODULE_ADD_PEER_VOTE, ROUTE_MODULE_META_KEY,
-    ROUTE_MODULE_META_KEY_REDIRECT, ROUTE_MODULE_META_VOTE, ROUTE_MODULE_REMOVE_PEER_VOTE, ROUTE_UI,
+    ArcUiState, ROUTE_DS_CURRENT_ROUND, ROUTE_EXPLORER, ROUTE_EXPLORER_BLOCK, ROUTE_INIT_CONSENSUS,
+    ROUTE_INVITE, ROUTE_LOGIN, ROUTE_MODULE, ROUTE_MODULE_ADD_MODULE_VOTE,
+    ROUTE_MODULE_ADD_MODULE_VOTE_PREVIEW, ROUTE_MODULE_ADD_PEER_VOTE,
+    ROUTE_MODULE_ADD_PEER_VOTE_PREVIEW, ROUTE_MODULE_AUTO_REMOVE_EQUIVOCATING,
+    ROUTE_MODULE_META_KEY, ROUTE_MODULE_META_KEY_REDIRECT, ROUTE_MODULE_META_VOTE,
+    ROUTE_MODULE_META_VOTE_PREVIEW, ROUTE_MODULE_REMOVE_PEER_VOTE,
+    ROUTE_MODULE_REMOVE_PEER_VOTE_PREVIEW, ROUTE_MODULE_ROTATE_KEY,
+    ROUTE_MODULE_SET_MAX_BLOCK_PAYLOAD_LEN_VOTE,
+    ROUTE_MODULE_SET_MAX_BLOCK_PAYLOAD_LEN_VOTE_PREVIEW, ROUTE_MODULE_SET_PEER_WEIGHT_VOTE,
+    ROUTE_MODULE_SET_PEER_WEIGHT_VOTE_PREVIEW, ROUTE_UI,
 };
 
 pub(crate) mod consensus_status;
@@ -19,20 +26,61 @@ pub(crate) fn make_router() -> Router<ArcUiState> {
         .route("/", get(consensus_status::root))
         .route(ROUTE_UI, get(consensus_status::get))
         .route(ROUTE_EXPLORER, get(explorer::get))
+        .route(ROUTE_EXPLORER_BLOCK, get(explorer::block::get))
         .route(ROUTE_LOGIN, get(login::get).post(login::post))
         .route(ROUTE_MODULE, get(module::get))
         .route(ROUTE_MODULE_ADD_PEER_VOTE, post(module::post_add_peer_vote))
+        .route(
+            ROUTE_MODULE_ADD_PEER_VOTE_PREVIEW,
+            post(module::preview::post_add_peer_vote_preview),
+        )
         .route(
             ROUTE_MODULE_REMOVE_PEER_VOTE,
             post(module::post_remove_peer_vote),
         )
+        .route(
+            ROUTE_MODULE_REMOVE_PEER_VOTE_PREVIEW,
+            post(module::preview::post_remove_peer_vote_preview),
+        )
+        .route(
+            ROUTE_MODULE_SET_PEER_WEIGHT_VOTE,
+            post(module::post_set_peer_weight_vote),
+        )
+        .route(
+            ROUTE_MODULE_SET_PEER_WEIGHT_VOTE_PREVIEW,
+            post(module::preview::post_set_peer_weight_vote_preview),
+        )
+        .route(
+            ROUTE_MODULE_SET_MAX_BLOCK_PAYLOAD_LEN_VOTE,
+            post(module::post_set_max_block_payload_len_vote),
+        )
+        .route(
+            ROUTE_MODULE_SET_MAX_BLOCK_PAYLOAD_LEN_VOTE_PREVIEW,
+            post(module::preview::post_set_max_block_payload_len_vote_preview),
+        )
+        .route(ROUTE_MODULE_ROTATE_KEY, post(module::post_rotate_key))
         .route(
             ROUTE_MODULE_ADD_MODULE_VOTE,
             post(module::post_add_module_vote),
         )
+        .route(
+            ROUTE_MODULE_ADD_MODULE_VOTE_PREVIEW,
+            post(module::preview::post_add_module_vote_preview),
+        )
+        .route(
+            ROUTE_MODULE_AUTO_REMOVE_EQUIVOCATING,
+            post(module::post_auto_remove_equivocating),
+        )
         .route(ROUTE_MODULE_META_KEY, get(module::get_meta_key))
-        .route(ROUTE_MODULE_META_KEY_REDIRECT, get(module::get_meta_key_redirect))
+        .route(
+            ROUTE_MODULE_META_KEY_REDIRECT,
+            get(module::get_meta_key_redirect),
+        )
         .route(ROUTE_MODULE_META_VOTE, post(module::post_meta_vote))
+        .route(
+            ROUTE_MODULE_META_VOTE_PREVIEW,
+            post(module::preview::post_meta_vote_preview),
+        )
         .route(ROUTE_INIT_CONSENSUS, get(init::get).post(init::post))
         .route(ROUTE_INVITE, get(invite::get))
         .route(ROUTE_DS_CURRENT_ROUND, get(consensus_status::updates))
@@ -44,6 +44,13 @@ async fn get_peer_pubkey(state: &ArcUiState) -> WhateverResult<String> {
     })
 }
 
+async fn get_bls_key_registration(state: &ArcUiState) -> WhateverResult<String> {
+    Ok(match state.node_api.get_bls_key_registration().await? {
+        Some(registration) => registration.to_string(),
+        None => "Not available".to_string(),
+    })
+}
+
 async fn get_database_status(state: &ArcUiState) -> WhateverResult<(String, bool)> {
     let is_ephemeral = state.node_api.is_database_ephemeral()?;
     let status = if is_ephemeral {
@@ -59,6 +66,9 @@ pub async fn get(state: State<ArcUiState>) -> RequestResult<impl IntoResponse> {
     let peer_pubkey = get_peer_pubkey(&state)
         .await
         .unwrap_or_else(|_| "Not available".to_string());
+    let bls_key_registration = get_bls_key_registration(&state)
+        .await
+        .unwrap_or_else(|_| "Not available".to_string());
     let (database_status, is_ephemeral) = get_database_status(&state)
         .await
         .unwrap_or_else(|_| ("Unknown".to_string(), false));
@@ -110,6 +120,10 @@ pub async fn get(state: State<ArcUiState>) -> RequestResult<impl IntoResponse> {
                     "Own peer public key: "
                     code style="word-break: break-all;" { (peer_pubkey) }
                 }
+                p {
+                    "Own BLS key registration: "
+                    code style="word-break: break-all;" { (bls_key_registration) }
+                }
                 p {
                     "Database: "
                     @if is_ephemeral {
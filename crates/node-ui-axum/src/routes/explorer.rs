@@ -1,22 +1,187 @@
-use axum::extract::State;
+pub(crate) mod block;
+
+use std::str::FromStr;
+
+use axum::extract::{Query, State};
 use axum::response::IntoResponse;
+use bfte_consensus_core::block::{BlockCItemIdx, BlockRound};
+use bfte_consensus_core::citem::transaction::TransactionHash;
+use bfte_consensus_core::equivocation::EquivocationEvidence;
+use bfte_consensus_core::module::ModuleId;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_node_app_core::citem_index::{
+    CItemIndexEntry, CItemIndexFilter, CItemLocation, CItemOutcome,
+};
 use bfte_node_ui::ConsensusHistoryEntry;
-use maud::html;
-use snafu::ResultExt as _;
+use maud::{Markup, html};
+use serde::Deserialize;
+use snafu::{OptionExt as _, ResultExt as _};
 
-use crate::ArcUiState;
-use crate::error::{OtherSnafu, RequestResult};
+use crate::error::{InvalidDataSnafu, OtherSnafu, RequestResult};
 use crate::misc::Maud;
 use crate::page::NavbarSelector;
+use crate::{ArcUiState, ROUTE_EXPLORER};
+
+/// Number of citems shown on a single page of the explorer
+const CITEMS_PAGE_LEN: usize = 50;
+
+/// Filters of the citems list of the explorer, all optional
+///
+/// Empty values are treated as unset, so the filter form can be submitted
+/// as is.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ExplorerQuery {
+    module: String,
+    peer: String,
+    from_round: String,
+    to_round: String,
+    /// One of [`SINCE_OPTIONS`]
+    since: String,
+    tx: String,
+    before_round: Option<u64>,
+    before_idx: Option<u32>,
+}
+
+/// Values and labels of the `since` filter, with its length in seconds
+const SINCE_OPTIONS: [(&str, &str, u64); 4] = [
+    ("hour", "Last hour", 60 * 60),
+    ("day", "Last day", 24 * 60 * 60),
+    ("week", "Last week", 7 * 24 * 60 * 60),
+    ("month", "Last 30 days", 30 * 24 * 60 * 60),
+];
+
+fn parse_opt<T: FromStr>(value: &str) -> RequestResult<Option<T>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(value.parse().ok().context(InvalidDataSnafu)?))
+}
+
+impl ExplorerQuery {
+    fn to_filter(&self) -> RequestResult<CItemIndexFilter> {
+        let since = match self.since.as_str() {
+            "" => None,
+            since => {
+                let (_, _, secs) = SINCE_OPTIONS
+                    .iter()
+                    .find(|(value, _, _)| *value == since)
+                    .context(InvalidDataSnafu)?;
+                Some(Timestamp::from(
+                    Timestamp::now()
+                        .to_number()
+                        .saturating_sub(secs * 1_000_000),
+                ))
+            }
+        };
+
+        Ok(CItemIndexFilter {
+            module_id: parse_opt::<u32>(&self.module)?.map(ModuleId::new),
+            peer_pubkey: parse_opt::<PeerPubkey>(&self.peer)?,
+            from_round: parse_opt::<u64>(&self.from_round)?.map(BlockRound::from),
+            to_round: parse_opt::<u64>(&self.to_round)?.map(BlockRound::from),
+            since,
+        })
+    }
+
+    fn before(&self) -> Option<CItemLocation> {
+        Some(CItemLocation::new(
+            BlockRound::from(self.before_round?),
+            BlockCItemIdx::from(self.before_idx.unwrap_or_default()),
+        ))
+    }
+
+    /// Query of the next page, following `last`
+    fn next_page(&self, last: CItemLocation) -> Self {
+        Self {
+            before_round: Some(last.round.to_number()),
+            before_idx: Some(last.idx.to_number()),
+            ..self.clone()
+        }
+    }
+
+    /// Same query, but filtered by `peer_pubkey`
+    fn with_peer(&self, peer_pubkey: PeerPubkey) -> Self {
+        Self {
+            peer: peer_pubkey.to_string(),
+            before_round: None,
+            before_idx: None,
+            ..self.clone()
+        }
+    }
+
+    /// Same query, but filtered by `module_id`
+    fn with_module(&self, module_id: ModuleId) -> Self {
+        Self {
+            module: module_id.to_string(),
+            before_round: None,
+            before_idx: None,
+            ..self.clone()
+        }
+    }
 
-pub async fn get(state: State<ArcUiState>) -> RequestResult<impl IntoResponse> {
+    fn to_url(&self) -> String {
+        let before = [
+            ("before_round", self.before_round.map(|v| v.to_string())),
+            ("before_idx", self.before_idx.map(|v| v.to_string())),
+        ];
+        let params = [
+            ("module", &self.module),
+            ("peer", &self.peer),
+            ("from_round", &self.from_round),
+            ("to_round", &self.to_round),
+            ("since", &self.since),
+            ("tx", &self.tx),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| (name, value.to_owned()))
+        .chain(
+            before
+                .into_iter()
+                .filter_map(|(name, value)| Some((name, value?))),
+        )
+        .map(|(name, value)| format!("{name}={}", urlencoding::encode(&value)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+        format!("{ROUTE_EXPLORER}?{params}")
+    }
+}
+
+pub async fn get(
+    state: State<ArcUiState>,
+    Query(query): Query<ExplorerQuery>,
+) -> RequestResult<impl IntoResponse> {
     let history = state
         .node_api
         .get_consensus_history(1000)
         .await
         .context(OtherSnafu)?;
+    let equivocations = state
+        .node_api
+        .get_equivocations()
+        .await
+        .context(OtherSnafu)?;
+
+    let citems = match parse_opt::<TransactionHash>(&query.tx)? {
+        Some(tx_hash) => state
+            .node_api
+            .get_citem_by_tx_hash(tx_hash)
+            .await
+            .context(OtherSnafu)?
+            .into_iter()
+            .collect(),
+        None => state
+            .node_api
+            .get_citems(query.to_filter()?, query.before(), CITEMS_PAGE_LEN)
+            .await
+            .context(OtherSnafu)?,
+    };
 
-    let content = render_explorer_page(&history);
+    let content = render_explorer_page(&history, &equivocations, &query, &citems);
     Ok(Maud(
         state
             .render_html_page(
@@ -28,10 +193,18 @@ pub async fn get(state: State<ArcUiState>) -> RequestResult<impl IntoResponse> {
     ))
 }
 
-fn render_explorer_page(history: &[ConsensusHistoryEntry]) -> maud::PreEscaped<String> {
+fn render_explorer_page(
+    history: &[ConsensusHistoryEntry],
+    equivocations: &[EquivocationEvidence],
+    query: &ExplorerQuery,
+    citems: &[(CItemLocation, CItemIndexEntry)],
+) -> maud::PreEscaped<String> {
     html! {
         div {
             h2 { "Consensus Explorer" }
+
+            (render_citems_section(query, citems))
+
             p { "Showing the last " (history.len()) " consensus rounds" }
 
             section {
@@ -53,7 +226,8 @@ fn render_explorer_page(history: &[ConsensusHistoryEntry]) -> maud::PreEscaped<S
                                 tr {
                                     td {
                                         @if let Some(ref header) = entry.block_header {
-                                            em
+                                            a
+                                                href=(format!("{ROUTE_EXPLORER}/block/{}", entry.round))
                                                 data-tooltip=(header.hash())
                                                 data-placement="right"
                                                 {
@@ -107,6 +281,161 @@ fn render_explorer_page(history: &[ConsensusHistoryEntry]) -> maud::PreEscaped<S
                     }
                 }
             }
+
+            section {
+                h3 { "Equivocations" }
+                @if equivocations.is_empty() {
+                    p { "No equivocations observed." }
+                } @else {
+                    table {
+                        thead {
+                            tr {
+                                th { "Round" }
+                                th { "Peer" }
+                                th { "First Block" }
+                                th { "Second Block" }
+                            }
+                        }
+                        tbody {
+                            @for evidence in equivocations {
+                                tr {
+                                    td { (format!("{}", evidence.round().to_number())) }
+                                    td {
+                                        em
+                                            data-tooltip=(evidence.peer_pubkey)
+                                            data-placement="right"
+                                        {
+                                            (evidence.peer_pubkey.to_short())
+                                        }
+                                    }
+                                    td { (evidence.first.inner.hash()) }
+                                    td { (evidence.second.inner.hash()) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
         }
     }
 }
+
+fn render_citems_section(
+    query: &ExplorerQuery,
+    citems: &[(CItemLocation, CItemIndexEntry)],
+) -> Markup {
+    html! {
+        section {
+            h3 { "Consensus Items" }
+            form method="get" action=(ROUTE_EXPLORER) {
+                div class="grid" {
+                    label {
+                        "Module"
+                        input type="number" name="module" min="0" value=(query.module);
+                    }
+                    label {
+                        "Peer"
+                        input type="text" name="peer" value=(query.peer);
+                    }
+                    label {
+                        "Since"
+                        select name="since" {
+                            option value="" selected[query.since.is_empty()] { "Any time" }
+                            @for (value, label, _) in SINCE_OPTIONS {
+                                option value=(value) selected[query.since == value] { (label) }
+                            }
+                        }
+                    }
+                }
+                div class="grid" {
+                    label {
+                        "From round"
+                        input type="number" name="from_round" min="0" value=(query.from_round);
+                    }
+                    label {
+                        "To round"
+                        input type="number" name="to_round" min="0" value=(query.to_round);
+                    }
+                    label {
+                        "Transaction hash"
+                        input type="text" name="tx" value=(query.tx);
+                    }
+                }
+                button type="submit" { "Filter" }
+            }
+
+            @if citems.is_empty() {
+                p { "No matching consensus items." }
+            } @else {
+                table {
+                    thead {
+                        tr {
+                            th { "Location" }
+                            th { "Timestamp" }
+                            th { "Peer" }
+                            th { "Modules" }
+                            th { "Transaction" }
+                            th { "Outcome" }
+                        }
+                    }
+                    tbody {
+                        @for (location, entry) in citems {
+                            tr {
+                                td {
+                                    a href=(format!("{ROUTE_EXPLORER}/block/{}", location.round)) {
+                                        (format!("{}#{}", location.round, location.idx))
+                                    }
+                                }
+                                td { (format_timestamp(entry.timestamp)) }
+                                td {
+                                    a
+                                        href=(query.with_peer(entry.peer_pubkey).to_url())
+                                        data-tooltip=(entry.peer_pubkey)
+                                        data-placement="right"
+                                    {
+                                        (entry.peer_pubkey.to_short())
+                                    }
+                                }
+                                td {
+                                    @for module_id in &entry.module_ids {
+                                        a href=(query.with_module(*module_id).to_url()) { (module_id) }
+                                        " "
+                                    }
+                                }
+                                td {
+                                    @if let Some(tx_hash) = entry.tx_hash {
+                                        (tx_hash)
+                                    }
+                                }
+                                td {
+                                    @match &entry.outcome {
+                                        CItemOutcome::Accepted => {
+                                            "Accepted"
+                                        }
+                                        CItemOutcome::Rejected { err } => {
+                                            em data-tooltip=(err) data-placement="left" { "Rejected" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                @if let Some((last, _)) = citems.last().filter(|_| citems.len() == CITEMS_PAGE_LEN) {
+                    a href=(query.next_page(*last).to_url()) { "Older" }
+                }
+            }
+        }
+    }
+}
+
+fn format_timestamp(timestamp: Timestamp) -> String {
+    timestamp
+        .to_datetime()
+        .and_then(|datetime| {
+            datetime
+                .format(&time::format_description::well_known::Rfc3339)
+                .ok()
+        })
+        .unwrap_or_else(|| "Invalid".to_string())
+}
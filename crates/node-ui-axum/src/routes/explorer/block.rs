@@ -0,0 +1,210 @@
+//! Details of a single finalized block, with its citems decoded by the
+//! modules they belong to
+
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::citem::{CItem, CItemRaw};
+use bfte_consensus_core::module::ModuleId;
+use bfte_node_app_core::citem_index::{CItemIndexEntry, CItemOutcome};
+use bfte_node_ui::BlockDetails;
+use maud::{Markup, html};
+use snafu::{OptionExt as _, ResultExt as _};
+
+use super::format_timestamp;
+use crate::error::{OtherSnafu, RequestResult, SomethingNotFoundSnafu};
+use crate::misc::Maud;
+use crate::page::NavbarSelector;
+use crate::{ArcUiState, ROUTE_EXPLORER, UiState};
+
+pub async fn get(
+    state: State<ArcUiState>,
+    Path(round): Path<u64>,
+) -> RequestResult<impl IntoResponse> {
+    let round = BlockRound::from(round);
+    let block = state
+        .node_api
+        .get_block_details(round)
+        .await
+        .context(OtherSnafu)?
+        .context(SomethingNotFoundSnafu)?;
+
+    let content = state.render_block_page(&block).await;
+    Ok(Maud(
+        state
+            .render_html_page(
+                Some(NavbarSelector::Explorer),
+                &format!("Block {round}"),
+                content,
+            )
+            .await,
+    ))
+}
+
+impl UiState {
+    /// Human readable form of `citem` of module `module_id`, if its module
+    /// supports decoding it
+    async fn decode_citem(&self, module_id: ModuleId, citem: &CItemRaw) -> Option<String> {
+        let module = self.modules.get_module(module_id).await?;
+        let value = self
+            .modules_inits
+            .get(&module.config.kind)?
+            .decode_citem(citem)?;
+        serde_json::to_string_pretty(&value).ok()
+    }
+
+    async fn render_block_page(&self, block: &BlockDetails) -> Markup {
+        let header = &block.block_header;
+
+        let citems = match &block.citems {
+            Some(citems) => {
+                let mut rendered = vec![];
+                for (idx, (citem, entry)) in citems.iter().enumerate() {
+                    rendered.push(self.render_citem(idx, citem, entry.as_ref()).await);
+                }
+                Some(rendered)
+            }
+            None => None,
+        };
+
+        html! {
+            div {
+                h2 { "Block " (header.round) }
+                p { a href=(ROUTE_EXPLORER) { "Back to the explorer" } }
+
+                section {
+                    h3 { "Header" }
+                    table {
+                        tbody {
+                            tr { th { "Hash" } td { (header.hash()) } }
+                            tr { th { "Version" } td { (header.header_version) } }
+                            tr { th { "Sequence" } td { (header.seq) } }
+                            tr { th { "Round" } td { (header.round) } }
+                            tr { th { "Timestamp" } td { (format_timestamp(header.timestamp)) } }
+                            tr { th { "Previous block" } td { (header.prev_block_hash) } }
+                            tr { th { "Payload" } td { (header.payload_hash) " (" (header.payload_len.to_number()) " bytes)" } }
+                            tr { th { "Citems root" } td { (header.citems_root) } }
+                            tr { th { "Consensus params" } td { (header.consensus_params_hash) " (" (header.consensus_params_len.to_number()) " bytes)" } }
+                        }
+                    }
+                }
+
+                section {
+                    h3 { "Signatures" }
+                    table {
+                        thead {
+                            tr {
+                                th { "Peer" }
+                                th { "Signature" }
+                            }
+                        }
+                        tbody {
+                            @for (peer_pubkey, sig) in &block.signatures {
+                                tr {
+                                    td { (peer_pubkey) }
+                                    td {
+                                        @if let Some(sig) = sig {
+                                            (sig)
+                                        } @else {
+                                            em { "Aggregated" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    @if let Some(aggregate) = &block.aggregate_signature {
+                        p { "Aggregate signature: " (aggregate.sig) }
+                    }
+                }
+
+                section {
+                    h3 { "Consensus Items" }
+                    @match citems {
+                        Some(citems) if citems.is_empty() => {
+                            p { "The block contains no consensus items." }
+                        }
+                        Some(citems) => {
+                            @for citem in citems {
+                                (citem)
+                            }
+                        }
+                        None => {
+                            p { "The block payload is not available on this node." }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn render_citem(
+        &self,
+        idx: usize,
+        citem: &CItem,
+        entry: Option<&CItemIndexEntry>,
+    ) -> Markup {
+        let body = match citem {
+            CItem::PeerCItem(module_citem) => {
+                let decoded = self
+                    .decode_citem(module_citem.module_id(), module_citem.inner())
+                    .await;
+                html! {
+                    p { "Peer citem of module " (module_citem.module_id()) }
+                    (render_decoded(decoded.as_deref(), module_citem.inner()))
+                }
+            }
+            CItem::ForwardedPeerCItem(forwarded) => {
+                let module_citem = &forwarded.inner.citem;
+                let decoded = self
+                    .decode_citem(module_citem.module_id(), module_citem.inner())
+                    .await;
+                html! {
+                    p {
+                        "Citem of module " (module_citem.module_id())
+                        " forwarded on behalf of " (forwarded.inner.peer_pubkey)
+                        ", valid until round " (forwarded.inner.valid_until)
+                    }
+                    p { "Signature: " (forwarded.sig) }
+                    (render_decoded(decoded.as_deref(), module_citem.inner()))
+                }
+            }
+            CItem::Transaction(transaction) => html! {
+                p { "Transaction " (transaction.tx_hash()) }
+                p { "Signature: 0x" (hex::encode(transaction.signature.as_slice())) }
+                ul {
+                    @for input in &transaction.inner.inputs {
+                        li { "Input of module " (input.module_id()) ": 0x" (hex::encode(&input.inner().0)) }
+                    }
+                    @for output in &transaction.inner.outputs {
+                        li { "Output of module " (output.module_id()) ": 0x" (hex::encode(&output.inner().0)) }
+                    }
+                }
+            },
+        };
+
+        html! {
+            article {
+                header {
+                    "#" (idx) ": "
+                    @match entry.map(|entry| &entry.outcome) {
+                        Some(CItemOutcome::Accepted) => { "Accepted" }
+                        Some(CItemOutcome::Rejected { err }) => { "Rejected: " (err) }
+                        None => { "Outcome unknown" }
+                    }
+                }
+                (body)
+            }
+        }
+    }
+}
+
+fn render_decoded(decoded: Option<&str>, raw: &CItemRaw) -> Markup {
+    html! {
+        @if let Some(decoded) = decoded {
+            pre { code { (decoded) } }
+        } @else {
+            p { "Raw: 0x" (hex::encode(&raw.0)) }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 mod consensus_ctrl;
 mod meta;
+pub(crate) mod preview;
 
 use std::any::Any;
 use std::sync::Arc;
@@ -7,18 +8,20 @@ use std::sync::Arc;
 use axum::Form;
 use axum::extract::{Path, Query, State};
 use axum::response::{IntoResponse, Redirect};
+use bfte_consensus_core::bls::BlsKeyRegistration;
 use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_consensus_core::peer::PeerPubkey;
 use bfte_consensus_core::ver::{ConsensusVersion, ConsensusVersionMajor, ConsensusVersionMinor};
+use bfte_consensus_core::weight::PeerWeight;
 use bfte_module_consensus_ctrl::ConsensusCtrlModule;
 use bfte_module_meta::MetaModule;
 use bfte_util_error::fmt::FmtCompact as _;
 use maud::{Markup, html};
 use serde::Deserialize;
-use snafu::ResultExt as _;
+use snafu::{OptionExt as _, ResultExt as _};
 use tracing::warn;
 
-use crate::error::{OtherSnafu, RequestResult};
+use crate::error::{InvalidDataSnafu, OtherSnafu, RequestResult};
 use crate::misc::Maud;
 use crate::page::NavbarSelector;
 use crate::{ArcUiState, LOG_TARGET, UiState};
@@ -46,6 +49,25 @@ pub struct RemovePeerVoteForm {
     peer_pubkey: PeerPubkey,
 }
 
+#[derive(Deserialize)]
+pub struct SetPeerWeightVoteForm {
+    peer_pubkey: PeerPubkey,
+    weight: PeerWeight,
+}
+
+#[derive(Deserialize)]
+pub struct SetMaxBlockPayloadLenVoteForm {
+    max_block_payload_len: u32,
+}
+
+#[derive(Deserialize)]
+pub struct RotateKeyForm {
+    new_pubkey: PeerPubkey,
+    /// BLS key registration of the new key, empty if BLS keys are not in use
+    #[serde(default)]
+    new_bls_registration: String,
+}
+
 #[derive(Debug)]
 pub struct ModuleKindVersion {
     pub kind: ModuleKind,
@@ -103,6 +125,11 @@ pub struct AddModuleVoteForm {
     module_kind: ModuleKindVersion,
 }
 
+#[derive(Deserialize)]
+pub struct AutoRemoveEquivocatingForm {
+    enabled: bool,
+}
+
 #[derive(Debug)]
 pub struct MetaValue(pub Arc<[u8]>);
 
@@ -193,6 +220,111 @@ pub async fn post_remove_peer_vote(
     Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response())
 }
 
+#[axum::debug_handler]
+pub async fn post_set_peer_weight_vote(
+    Path(module_id): Path<ModuleId>,
+    state: State<ArcUiState>,
+    Form(form): Form<SetPeerWeightVoteForm>,
+) -> RequestResult<impl IntoResponse> {
+    let Some(module) = state.modules.get_module(module_id).await else {
+        return Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response());
+    };
+
+    if module.config.kind == bfte_module_consensus_ctrl::KIND {
+        let Some(consensus_module_ref) =
+            (module.inner.as_ref() as &dyn Any).downcast_ref::<ConsensusCtrlModule>()
+        else {
+            return Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response());
+        };
+
+        consensus_module_ref
+            .set_pending_set_peer_weight_vote(form.peer_pubkey, form.weight)
+            .await
+            .inspect_err(|err| {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Could not submit set peer weight vote");
+            })
+            .context(OtherSnafu)?;
+    }
+
+    Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response())
+}
+
+#[axum::debug_handler]
+pub async fn post_set_max_block_payload_len_vote(
+    Path(module_id): Path<ModuleId>,
+    state: State<ArcUiState>,
+    Form(form): Form<SetMaxBlockPayloadLenVoteForm>,
+) -> RequestResult<impl IntoResponse> {
+    let Some(module) = state.modules.get_module(module_id).await else {
+        return Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response());
+    };
+
+    if module.config.kind == bfte_module_consensus_ctrl::KIND {
+        let Some(consensus_module_ref) =
+            (module.inner.as_ref() as &dyn Any).downcast_ref::<ConsensusCtrlModule>()
+        else {
+            return Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response());
+        };
+
+        consensus_module_ref
+            .set_pending_set_max_block_payload_len_vote(form.max_block_payload_len)
+            .await
+            .inspect_err(|err| {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Could not submit set max block payload length vote");
+            })
+            .context(OtherSnafu)?;
+    }
+
+    Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response())
+}
+
+#[axum::debug_handler]
+pub async fn post_rotate_key(
+    Path(module_id): Path<ModuleId>,
+    state: State<ArcUiState>,
+    Form(form): Form<RotateKeyForm>,
+) -> RequestResult<impl IntoResponse> {
+    let Some(module) = state.modules.get_module(module_id).await else {
+        return Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response());
+    };
+
+    if module.config.kind == bfte_module_consensus_ctrl::KIND {
+        let Some(consensus_module_ref) =
+            (module.inner.as_ref() as &dyn Any).downcast_ref::<ConsensusCtrlModule>()
+        else {
+            return Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response());
+        };
+
+        let new_bls_registration = form.new_bls_registration.trim();
+        let bls_registration = if new_bls_registration.is_empty() {
+            None
+        } else {
+            Some(
+                new_bls_registration
+                    .parse::<BlsKeyRegistration>()
+                    .ok()
+                    .context(InvalidDataSnafu)?,
+            )
+        };
+
+        let rotation = state
+            .node_api
+            .sign_key_rotation(form.new_pubkey)
+            .await
+            .context(OtherSnafu)?;
+
+        consensus_module_ref
+            .set_pending_key_rotation(rotation, bls_registration)
+            .await
+            .inspect_err(|err| {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Could not submit key rotation");
+            })
+            .context(OtherSnafu)?;
+    }
+
+    Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response())
+}
+
 #[axum::debug_handler]
 pub async fn post_add_module_vote(
     Path(module_id): Path<ModuleId>,
@@ -222,6 +354,31 @@ pub async fn post_add_module_vote(
     Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response())
 }
 
+#[axum::debug_handler]
+pub async fn post_auto_remove_equivocating(
+    Path(module_id): Path<ModuleId>,
+    state: State<ArcUiState>,
+    Form(form): Form<AutoRemoveEquivocatingForm>,
+) -> RequestResult<impl IntoResponse> {
+    let Some(module) = state.modules.get_module(module_id).await else {
+        return Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response());
+    };
+
+    if module.config.kind == bfte_module_consensus_ctrl::KIND {
+        let Some(consensus_module_ref) =
+            (module.inner.as_ref() as &dyn Any).downcast_ref::<ConsensusCtrlModule>()
+        else {
+            return Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response());
+        };
+
+        consensus_module_ref
+            .set_auto_remove_equivocating_peers(form.enabled)
+            .await;
+    }
+
+    Ok(Redirect::to(&format!("/ui/module/{module_id}")).into_response())
+}
+
 #[axum::debug_handler]
 pub async fn get_meta_key(
     Path((module_id, key)): Path<(ModuleId, u8)>,
@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 
+use bfte_consensus_core::consensus_params::ConsensusParams;
 use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_module::module::config::ModuleConfig;
 use maud::{Markup, html};
@@ -24,7 +25,18 @@ impl UiState {
         let peer_set = consensus_module_ref.get_peer_set().await;
         let add_peer_votes = consensus_module_ref.get_add_peer_votes().await;
         let remove_peer_votes = consensus_module_ref.get_remove_peer_votes().await;
+        let peer_weights = consensus_module_ref.get_peer_weights().await;
+        let set_peer_weight_votes = consensus_module_ref.get_set_peer_weight_votes().await;
+        let max_block_payload_len = consensus_module_ref.get_max_block_payload_len().await;
+        let set_max_block_payload_len_votes = consensus_module_ref
+            .get_set_max_block_payload_len_votes()
+            .await;
         let add_module_votes = consensus_module_ref.get_add_module_votes().await;
+        let equivocations = consensus_module_ref.get_equivocations().await;
+        let pending_key_rotation = consensus_module_ref.get_pending_key_rotation().await;
+        let auto_remove_equivocating = consensus_module_ref
+            .get_auto_remove_equivocating_peers()
+            .await;
         html! {
             header {
                 h1 { "Consensus Ctrl" }
@@ -36,7 +48,12 @@ impl UiState {
                 h3 { "Current Peers" }
                 ul {
                     @for peer in &peer_set {
-                        li { (format!("{peer}")) }
+                        li {
+                            (format!("{peer}"))
+                            @if let Some(weight) = peer_weights.get(peer) {
+                                (format!(" (weight: {weight})"))
+                            }
+                        }
                     }
                 }
             }
@@ -53,16 +70,23 @@ impl UiState {
                 }
                 div role="status" {
                     p id="error-response-form-add";
+                    div id="preview-response-form-add";
                 }
                 form
                     method="post"
                     x-target="_none"
                     "x-target.error"="error-response-form-add:error-response"
+                    "x-target.200"="preview-response-form-add:preview-response"
                     "x-target.away"="_top"
                     action=(format!("/ui/module/{}/add_peer_vote", module_id))
                 {
                     fieldset role="group" {
                         input type="text" name="peer_pubkey" placeholder="Peer's public key" required;
+                        button
+                            type="submit"
+                            class="secondary"
+                            formaction=(format!("/ui/module/{}/add_peer_vote/preview", module_id))
+                        { "Preview" }
                         input type="submit" value="Add";
                     }
                 }
@@ -80,21 +104,151 @@ impl UiState {
                 }
                 div role="status" {
                     p id="error-response-form-remove";
+                    div id="preview-response-form-remove";
                 }
                 form
                     method="post"
                     x-target="_none"
                     "x-target.error"="error-response-form-remove:error-response"
+                    "x-target.200"="preview-response-form-remove:preview-response"
                     "x-target.away"="_top"
                     action=(format!("/ui/module/{}/remove_peer_vote", module_id))
                 {
                     fieldset role="group" {
                         input type="text" name="peer_pubkey" placeholder="Peer's public key" required;
+                        button
+                            type="submit"
+                            class="secondary"
+                            formaction=(format!("/ui/module/{}/remove_peer_vote/preview", module_id))
+                        { "Preview" }
                         input type="submit" value ="Remove";
                     }
                 }
             }
 
+            section {
+                h3 { "Set Peer Weight" }
+                @if !set_peer_weight_votes.is_empty() {
+                    h4 { "Pending Votes:" }
+                    ul {
+                        @for (voter, (voted_for, weight)) in &set_peer_weight_votes {
+                            li { (format!("{} → {} = {}", voter.to_short(), voted_for, weight)) }
+                        }
+                    }
+                }
+                div role="status" {
+                    p id="error-response-form-weight";
+                    div id="preview-response-form-weight";
+                }
+                form
+                    method="post"
+                    x-target="_none"
+                    "x-target.error"="error-response-form-weight:error-response"
+                    "x-target.200"="preview-response-form-weight:preview-response"
+                    "x-target.away"="_top"
+                    action=(format!("/ui/module/{}/set_peer_weight_vote", module_id))
+                {
+                    fieldset role="group" {
+                        input type="text" name="peer_pubkey" placeholder="Peer's public key" required;
+                        input type="number" name="weight" min="1" placeholder="Weight" required;
+                        button
+                            type="submit"
+                            class="secondary"
+                            formaction=(format!("/ui/module/{}/set_peer_weight_vote/preview", module_id))
+                        { "Preview" }
+                        input type="submit" value="Set";
+                    }
+                }
+            }
+
+            section {
+                h3 { "Max Block Payload Length" }
+                p { (format!("Current: {max_block_payload_len} bytes")) }
+                @if !set_max_block_payload_len_votes.is_empty() {
+                    h4 { "Pending Votes:" }
+                    ul {
+                        @for (voter, len) in &set_max_block_payload_len_votes {
+                            li { (format!("{} → {}", voter.to_short(), len)) }
+                        }
+                    }
+                }
+                div role="status" {
+                    p id="error-response-form-payload-len";
+                    div id="preview-response-form-payload-len";
+                }
+                form
+                    method="post"
+                    x-target="_none"
+                    "x-target.error"="error-response-form-payload-len:error-response"
+                    "x-target.200"="preview-response-form-payload-len:preview-response"
+                    "x-target.away"="_top"
+                    action=(format!("/ui/module/{}/set_max_block_payload_len_vote", module_id))
+                {
+                    fieldset role="group" {
+                        input
+                            type="number"
+                            name="max_block_payload_len"
+                            min=(ConsensusParams::MIN_MAX_BLOCK_PAYLOAD_LEN)
+                            placeholder="Length in bytes"
+                            required;
+                        button
+                            type="submit"
+                            class="secondary"
+                            formaction=(format!("/ui/module/{}/set_max_block_payload_len_vote/preview", module_id))
+                        { "Preview" }
+                        input type="submit" value="Set";
+                    }
+                }
+            }
+
+            section {
+                h3 { "Rotate Key" }
+                @if let Some(rotation) = &pending_key_rotation {
+                    p { (format!("Pending rotation: {} → {}", rotation.old_pubkey.to_short(), rotation.new_pubkey)) }
+                }
+                p { "Hand over our place in the consensus to a new key. Once processed, this node needs to be restarted with the new secret." }
+                p { "If BLS keys are in use, the BLS key registration of the new key is required. It's shown on the overview page of a node running with the new secret." }
+                div role="status" {
+                    p id="error-response-form-rotate";
+                }
+                form
+                    method="post"
+                    x-target="_none"
+                    "x-target.error"="error-response-form-rotate:error-response"
+                    "x-target.away"="_top"
+                    action=(format!("/ui/module/{}/rotate_key", module_id))
+                {
+                    fieldset role="group" {
+                        input type="text" name="new_pubkey" placeholder="New public key" required;
+                        input type="text" name="new_bls_registration" placeholder="New BLS key registration";
+                        input type="submit" value="Rotate";
+                    }
+                }
+            }
+
+            section {
+                h3 { "Equivocations" }
+                @if equivocations.is_empty() {
+                    p { "No equivocations reported." }
+                } @else {
+                    ul {
+                        @for evidence in &equivocations {
+                            li { (format!("{} in round {}", evidence.peer_pubkey, evidence.round())) }
+                        }
+                    }
+                }
+                form method="post" action=(format!("/ui/module/{}/auto_remove_equivocating", module_id)) {
+                    input type="hidden" name="enabled" value=(if auto_remove_equivocating { "false" } else { "true" });
+                    @if auto_remove_equivocating {
+                        p { "Automatically voting to remove equivocating peers." }
+                        input type="submit" value="Disable";
+                    } @else {
+                        p { "Not voting to remove equivocating peers automatically." }
+                        input type="submit" value="Enable";
+                    }
+                }
+            }
+
             h2 { "Modules" }
 
             section {
@@ -189,7 +343,17 @@ impl UiState {
             }
         } else {
             html! {
-                form method="post" action=(format!("/ui/module/{}/add_module_vote", module_id)) {
+                div role="status" {
+                    p id="error-response-form-add-module";
+                    div id="preview-response-form-add-module";
+                }
+                form
+                    method="post"
+                    x-target="_top"
+                    "x-target.error"="error-response-form-add-module:error-response"
+                    "x-target.200"="preview-response-form-add-module:preview-response"
+                    action=(format!("/ui/module/{}/add_module_vote", module_id))
+                {
                     fieldset role="group" {
                         select name="module_kind" required {
                             option value="" { "Select module to add..." }
@@ -199,6 +363,11 @@ impl UiState {
                                 }
                             }
                         }
+                        button
+                            type="submit"
+                            class="secondary"
+                            formaction=(format!("/ui/module/{}/add_module_vote/preview", module_id))
+                        { "Preview" }
                         input type="submit" value="Add Module";
                     }
                 }
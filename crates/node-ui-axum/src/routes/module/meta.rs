@@ -147,12 +147,27 @@ impl UiState {
 
             section {
                 h2 { "Submit Your Vote" }
-                form method="post" action=(format!("/ui/module/{}/meta_key/{}/vote", module_id, key)) {
+                div role="status" {
+                    p id="error-response-form-meta-vote";
+                    div id="preview-response-form-meta-vote";
+                }
+                form
+                    method="post"
+                    x-target="_top"
+                    "x-target.error"="error-response-form-meta-vote:error-response"
+                    "x-target.200"="preview-response-form-meta-vote:preview-response"
+                    action=(format!("/ui/module/{}/meta_key/{}/vote", module_id, key))
+                {
                     fieldset {
                         label for="value" { "Value to vote for:" }
                         input type="text" name="value" id="value" placeholder="Enter value (text or hex with 0x prefix)" required;
                         small { "You can enter plain text or hex values (prefix with 0x)" }
                     }
+                    button
+                        type="submit"
+                        class="secondary"
+                        formaction=(format!("/ui/module/{}/meta_key/{}/vote/preview", module_id, key))
+                    { "Preview" }
                     input type="submit" value="Submit Vote";
                 }
             }
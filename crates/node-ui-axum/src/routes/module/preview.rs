@@ -0,0 +1,177 @@
+//! Previews of citems module forms would propose, simulated against the
+//! current state
+
+use axum::Form;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use bfte_consensus_core::citem::{CItem, CItemRaw, ModuleDyn};
+use bfte_consensus_core::module::{ModuleId, ModuleKind};
+use bfte_module::module::CItemSimulation;
+use bfte_module_consensus_ctrl::citem::ConsensusCtrlCitem;
+use bfte_module_meta::citem::MetaCitem;
+use maud::{Markup, html};
+use snafu::{OptionExt as _, ResultExt as _};
+
+use super::{
+    AddModuleVoteForm, AddPeerVoteForm, MetaVoteForm, RemovePeerVoteForm,
+    SetMaxBlockPayloadLenVoteForm, SetPeerWeightVoteForm,
+};
+use crate::error::{OtherSnafu, RequestResult, SomethingNotFoundSnafu};
+use crate::misc::Maud;
+use crate::{ArcUiState, UiState};
+
+#[axum::debug_handler]
+pub async fn post_add_peer_vote_preview(
+    Path(module_id): Path<ModuleId>,
+    state: State<ArcUiState>,
+    Form(form): Form<AddPeerVoteForm>,
+) -> RequestResult<impl IntoResponse> {
+    state
+        .render_citem_preview(
+            module_id,
+            bfte_module_consensus_ctrl::KIND,
+            ConsensusCtrlCitem::VoteAddPeer(form.peer_pubkey).encode_to_raw(),
+        )
+        .await
+}
+
+#[axum::debug_handler]
+pub async fn post_remove_peer_vote_preview(
+    Path(module_id): Path<ModuleId>,
+    state: State<ArcUiState>,
+    Form(form): Form<RemovePeerVoteForm>,
+) -> RequestResult<impl IntoResponse> {
+    state
+        .render_citem_preview(
+            module_id,
+            bfte_module_consensus_ctrl::KIND,
+            ConsensusCtrlCitem::VoteRemovePeer(form.peer_pubkey).encode_to_raw(),
+        )
+        .await
+}
+
+#[axum::debug_handler]
+pub async fn post_set_peer_weight_vote_preview(
+    Path(module_id): Path<ModuleId>,
+    state: State<ArcUiState>,
+    Form(form): Form<SetPeerWeightVoteForm>,
+) -> RequestResult<impl IntoResponse> {
+    state
+        .render_citem_preview(
+            module_id,
+            bfte_module_consensus_ctrl::KIND,
+            ConsensusCtrlCitem::VoteSetPeerWeight {
+                peer: form.peer_pubkey,
+                weight: form.weight,
+            }
+            .encode_to_raw(),
+        )
+        .await
+}
+
+#[axum::debug_handler]
+pub async fn post_set_max_block_payload_len_vote_preview(
+    Path(module_id): Path<ModuleId>,
+    state: State<ArcUiState>,
+    Form(form): Form<SetMaxBlockPayloadLenVoteForm>,
+) -> RequestResult<impl IntoResponse> {
+    state
+        .render_citem_preview(
+            module_id,
+            bfte_module_consensus_ctrl::KIND,
+            ConsensusCtrlCitem::VoteSetMaxBlockPayloadLen(form.max_block_payload_len)
+                .encode_to_raw(),
+        )
+        .await
+}
+
+#[axum::debug_handler]
+pub async fn post_add_module_vote_preview(
+    Path(module_id): Path<ModuleId>,
+    state: State<ArcUiState>,
+    Form(form): Form<AddModuleVoteForm>,
+) -> RequestResult<impl IntoResponse> {
+    state
+        .render_citem_preview(
+            module_id,
+            bfte_module_consensus_ctrl::KIND,
+            ConsensusCtrlCitem::VoteAddModule {
+                module_kind: form.module_kind.kind,
+                consensus_version: form.module_kind.version,
+            }
+            .encode_to_raw(),
+        )
+        .await
+}
+
+#[axum::debug_handler]
+pub async fn post_meta_vote_preview(
+    Path((module_id, key)): Path<(ModuleId, u8)>,
+    state: State<ArcUiState>,
+    Form(form): Form<MetaVoteForm>,
+) -> RequestResult<impl IntoResponse> {
+    state
+        .render_citem_preview(
+            module_id,
+            bfte_module_meta::KIND,
+            MetaCitem::ProposeValue {
+                key,
+                value: form.value.0,
+            }
+            .encode_to_raw(),
+        )
+        .await
+}
+
+impl UiState {
+    /// Simulate `citem` of module `module_id` of `kind` and render the outcome
+    async fn render_citem_preview(
+        &self,
+        module_id: ModuleId,
+        kind: ModuleKind,
+        citem: CItemRaw,
+    ) -> RequestResult<Maud> {
+        self.modules
+            .get_module(module_id)
+            .await
+            .filter(|module| module.config.kind == kind)
+            .context(SomethingNotFoundSnafu)?;
+
+        let simulation = self
+            .node_api
+            .simulate_citem(CItem::PeerCItem(ModuleDyn::new(module_id, citem)))
+            .await
+            .context(OtherSnafu)?;
+
+        Ok(Maud(render_citem_simulation(&simulation)))
+    }
+}
+
+fn render_citem_simulation(simulation: &CItemSimulation) -> Markup {
+    html! {
+        div id="preview-response" {
+            @match simulation {
+                CItemSimulation::Accepted { effects } => {
+                    p { "Preview: would be accepted." }
+                    @if !effects.is_empty() {
+                        ul {
+                            @for effect in effects {
+                                li {
+                                    (format!(
+                                        "Effect {} of module kind {}: 0x{}",
+                                        effect.inner().effect_id,
+                                        effect.module_kind(),
+                                        hex::encode(&effect.inner().raw),
+                                    ))
+                                }
+                            }
+                        }
+                    }
+                }
+                CItemSimulation::Rejected { err } => {
+                    p { "Preview: would be rejected: " (err) }
+                }
+            }
+        }
+    }
+}
@@ -5,9 +5,17 @@ use std::pin::Pin;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use bfte_consensus_core::Signature;
 use bfte_consensus_core::block::{BlockHeader, BlockRound};
+use bfte_consensus_core::bls::{BlsAggregateSignature, BlsKeyRegistration};
+use bfte_consensus_core::citem::CItem;
+use bfte_consensus_core::citem::transaction::TransactionHash;
+use bfte_consensus_core::equivocation::EquivocationEvidence;
+use bfte_consensus_core::key_rotation::SignedKeyRotation;
 use bfte_consensus_core::peer::PeerPubkey;
 use bfte_invite::Invite;
+use bfte_module::module::CItemSimulation;
+use bfte_node_app_core::citem_index::{CItemIndexEntry, CItemIndexFilter, CItemLocation};
 use bfte_node_shared_modules::WeakSharedModules;
 use bfte_util_error::WhateverResult;
 use tokio::sync::watch;
@@ -32,6 +40,19 @@ pub struct ConsensusHistoryEntry {
     pub signatory_peers: Vec<PeerPubkey>,
 }
 
+/// A finalized block, as shown by the explorer
+#[derive(Debug, Clone)]
+pub struct BlockDetails {
+    pub block_header: BlockHeader,
+    /// Peers that notarized the block, with their individual signature, or
+    /// `None` if they signed as part of an aggregate signature
+    pub signatures: Vec<(PeerPubkey, Option<Signature>)>,
+    pub aggregate_signature: Option<BlsAggregateSignature>,
+    /// Citems of the block, with their index entries, or `None` if the
+    /// payload is not available locally
+    pub citems: Option<Vec<(CItem, Option<CItemIndexEntry>)>>,
+}
+
 /// The API `bfte-node` exposes to `bfte-node-ui`
 ///
 /// UI implementation can use this API to get stuff from the node.
@@ -59,4 +80,37 @@ pub trait INodeUiApi {
         &self,
         limit: usize,
     ) -> WhateverResult<Vec<ConsensusHistoryEntry>>;
+
+    async fn get_equivocations(&self) -> WhateverResult<Vec<EquivocationEvidence>>;
+
+    /// The block finalized in `round`, or `None` if `round` is not finalized
+    /// yet or was a dummy round
+    async fn get_block_details(&self, round: BlockRound) -> WhateverResult<Option<BlockDetails>>;
+
+    /// Up to `limit` finalized citems matching `filter`, newest first,
+    /// starting right before `before` (if set)
+    async fn get_citems(
+        &self,
+        filter: CItemIndexFilter,
+        before: Option<CItemLocation>,
+        limit: usize,
+    ) -> WhateverResult<Vec<(CItemLocation, CItemIndexEntry)>>;
+
+    async fn get_citem_by_tx_hash(
+        &self,
+        tx_hash: TransactionHash,
+    ) -> WhateverResult<Option<(CItemLocation, CItemIndexEntry)>>;
+
+    /// Sign a handover of our place in the consensus to `new_pubkey`
+    async fn sign_key_rotation(&self, new_pubkey: PeerPubkey) -> WhateverResult<SignedKeyRotation>;
+
+    /// BLS key registration of our own key, `None` if we have no key
+    ///
+    /// Needed by the current holder of a place in the consensus to rotate
+    /// into our key.
+    async fn get_bls_key_registration(&self) -> WhateverResult<Option<BlsKeyRegistration>>;
+
+    /// Simulate processing of `citem` against the current state, as if we
+    /// included it in the next block
+    async fn simulate_citem(&self, citem: CItem) -> WhateverResult<CItemSimulation>;
 }
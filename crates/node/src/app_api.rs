@@ -5,20 +5,28 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use bfte_consensus::consensus::Consensus;
 use bfte_consensus_core::block::{BlockHeader, BlockRound};
+use bfte_consensus_core::bls::BlsKeyRegistration;
 use bfte_consensus_core::citem::CItem;
 use bfte_consensus_core::citem::transaction::Transaction;
 use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::snapshot::StateSnapshot;
 use bfte_db::Database;
-use bfte_node_app_core::{INodeAppApi, RunNodeAppFn};
+use bfte_module::module::net::ModuleNet;
+use bfte_module::module::secret::ModuleSecret;
+use bfte_node_app_core::{INodeAppApi, RunNodeAppFn, SimulateCItemRequest};
 use bfte_node_shared_modules::SharedModules;
 use bfte_util_error::WhateverResult;
 use bfte_util_error::fmt::FmtCompact as _;
 use n0_future::task::AbortOnDropHandle;
-use tokio::sync::watch;
+use tokio::sync::{mpsc, watch};
+use tracing::warn;
 
-use crate::Node;
+use crate::derive_secret_ext::DeriveSecretExt as _;
 use crate::handle::{NodeHandle, NodeRef};
+use crate::module_net::NodeModuleNet;
+use crate::{LOG_TARGET, Node};
 
 struct NodeAppApi {
     handle: NodeHandle,
@@ -43,6 +51,29 @@ impl INodeAppApi for NodeAppApi {
         self.node_ref_wait().await.peer_pubkey
     }
 
+    async fn get_module_secret(
+        &self,
+        module_kind: ModuleKind,
+        module_id: ModuleId,
+    ) -> Option<ModuleSecret> {
+        let root_secret = self.node_ref_wait().await.root_secret()?;
+
+        Some(
+            root_secret
+                .get_module_secret(module_kind, module_id)
+                .expect("Node's secret must be root"),
+        )
+    }
+
+    async fn get_module_net(&self, module_id: ModuleId) -> Option<ModuleNet> {
+        self.node_ref_wait().await.peer_pubkey?;
+
+        Some(Arc::new(NodeModuleNet {
+            handle: self.handle.clone(),
+            module_id,
+        }))
+    }
+
     async fn get_consensus_params(&self, round: BlockRound) -> ConsensusParams {
         self.node_ref_wait()
             .await
@@ -116,7 +147,7 @@ impl INodeAppApi for NodeAppApi {
 
         let params = consensus.get_consensus_params(block.round).await;
 
-        let leader_idx = block.round.leader_idx(params.num_peers());
+        let leader_idx = params.leader_idx(block.round);
 
         let peer_pubkey = params
             .peers
@@ -128,6 +159,44 @@ impl INodeAppApi for NodeAppApi {
 
         (block, *peer_pubkey, block_payload)
     }
+
+    async fn record_state_snapshot(&self, snapshot: StateSnapshot) {
+        self.node_ref_wait()
+            .await
+            .record_state_snapshot(snapshot)
+            .await;
+    }
+
+    async fn get_restored_state_snapshot_round(&self) -> Option<BlockRound> {
+        self.node_ref_wait()
+            .await
+            .get_restored_state_snapshot_round()
+            .await
+    }
+
+    async fn get_bls_key_registration(&self) -> Option<BlsKeyRegistration> {
+        let node_ref = self.node_ref_wait().await;
+        let signer = node_ref.signer()?;
+
+        signer
+            .bls_key_registration()
+            .await
+            .inspect_err(|err| {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to get BLS key registration from signer");
+            })
+            .ok()
+    }
+
+    async fn on_peer_key_rotation(&self, old_pubkey: PeerPubkey, new_pubkey: PeerPubkey) {
+        if let Err(err) = self
+            .node_ref_wait()
+            .await
+            .handle_peer_key_rotation(old_pubkey, new_pubkey)
+            .await
+        {
+            warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to handle peer key rotation");
+        }
+    }
 }
 
 impl Node {
@@ -137,6 +206,7 @@ impl Node {
         app: RunNodeAppFn,
         shared_modules: SharedModules,
         pending_transactions_tx: watch::Sender<Vec<Transaction>>,
+        simulate_citem_rx: mpsc::Receiver<SimulateCItemRequest>,
     ) -> AbortOnDropHandle<WhateverResult<Infallible>> {
         AbortOnDropHandle::new(tokio::spawn(async move {
             app(
@@ -144,6 +214,7 @@ impl Node {
                 Arc::new(NodeAppApi { handle }),
                 shared_modules,
                 pending_transactions_tx,
+                simulate_citem_rx,
             )
             .await
             .inspect_err(|err| {
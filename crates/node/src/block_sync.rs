@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::msg::WaitNotarizedBlockResponse;
+use bfte_consensus_core::peer::{PeerIdx, PeerPubkey};
+use bfte_util_error::WhateverResult;
+use bfte_util_error::fmt::FmtCompact as _;
+use rand::seq::SliceRandom as _;
+use snafu::{ResultExt as _, whatever};
+use tokio::select;
+use tokio::task::JoinSet;
+use tracing::{debug, info, instrument, warn};
+
+use crate::Node;
+use crate::connection_pool::ConnectionPool;
+use crate::rpc::{self, BlockRange};
+
+const LOG_TARGET: &str = "bfte::node::block-sync";
+
+/// How many rounds behind the finality consensus we need to be to use range
+/// sync
+///
+/// Small lag is handled just fine by the consensus round logic itself.
+const BLOCK_SYNC_MIN_LAG: u64 = 16;
+
+/// Number of rounds requested from a peer in a single range request
+const BLOCK_SYNC_CHUNK_ROUNDS: u64 = 256;
+
+/// Maximum number of range requests in flight at the same time
+const BLOCK_SYNC_MAX_PARALLEL: usize = 4;
+
+impl Node {
+    /// Catch up with the consensus by fetching ranges of finalized blocks
+    ///
+    /// Without it, a node that was offline for a while would need a separate
+    /// roundtrip through the consensus round logic for every missed block.
+    #[instrument(
+        name = "block_sync"
+        target = LOG_TARGET,
+        skip_all,
+    )]
+    pub(crate) async fn run_block_sync(self: Arc<Self>) {
+        let mut finality_consensus_rx = self.consensus_wait().await.finality_consensus_rx();
+        let mut interval = tokio::time::interval(Duration::from_secs(10));
+
+        loop {
+            select! {
+                _ = interval.tick() => {},
+                res = finality_consensus_rx.changed() => {
+                    if res.is_err() {
+                        // If we're shutting down, just sleep and get dropped
+                        future::pending().await
+                    }
+                },
+            }
+
+            // Keep going for as long as we're making progress
+            loop {
+                match self.sync_block_ranges().await {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(err) => {
+                        debug!(
+                            target: LOG_TARGET,
+                            err = %err.fmt_compact(),
+                            "Failed to sync block ranges"
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fetch and process a batch of block ranges from multiple peers in
+    /// parallel
+    ///
+    /// Returns `true` if the current round advanced.
+    async fn sync_block_ranges(&self) -> WhateverResult<bool> {
+        let consensus = self.consensus_expect();
+        let (cur_round, params) = consensus.get_current_round_and_params().await;
+        let finality_consensus = *consensus.finality_consensus_rx().borrow();
+
+        if finality_consensus.to_number() < cur_round.to_number().saturating_add(BLOCK_SYNC_MIN_LAG)
+        {
+            return Ok(false);
+        }
+
+        let mut peers: Vec<_> = params
+            .iter_peers()
+            .filter(|(_, peer_pubkey)| Some(*peer_pubkey) != self.peer_pubkey)
+            .collect();
+        if peers.is_empty() {
+            return Ok(false);
+        }
+        peers.shuffle(&mut rand::thread_rng());
+
+        info!(
+            target: LOG_TARGET,
+            %cur_round,
+            %finality_consensus,
+            "Syncing block ranges…"
+        );
+
+        let mut requests = JoinSet::new();
+        let mut chunk_start = cur_round;
+        for chunk_i in 0..BLOCK_SYNC_MAX_PARALLEL {
+            if finality_consensus <= chunk_start {
+                break;
+            }
+            let chunk_end = chunk_start
+                .checked_add(BLOCK_SYNC_CHUNK_ROUNDS)
+                .unwrap_or(BlockRound::MAX)
+                .min(finality_consensus);
+
+            // Every chunk starts with a different peer, and fails over to the
+            // following ones.
+            let mut chunk_peers = peers.clone();
+            chunk_peers.rotate_left(chunk_i % peers.len());
+
+            let connection_pool = self.connection_pool().clone();
+            let params = params.clone();
+            requests.spawn(async move {
+                let res = Self::fetch_block_range(
+                    connection_pool,
+                    chunk_start,
+                    chunk_end,
+                    chunk_peers,
+                    params,
+                )
+                .await;
+                (chunk_i, chunk_end, res)
+            });
+
+            chunk_start = chunk_end;
+        }
+
+        let mut ranges = BTreeMap::new();
+        while let Some(res) = requests.join_next().await {
+            let (chunk_i, chunk_end, res) = res.whatever_context("Block range task failed")?;
+            ranges.insert(chunk_i, (chunk_end, res));
+        }
+
+        // Blocks need to be processed in order, so we stop at the first
+        // chunk that is missing or incomplete.
+        'chunks: for (chunk_end, res) in ranges.into_values() {
+            let Some((peer_idx, range)) = res else {
+                break;
+            };
+
+            for (block, payload) in range.blocks {
+                // Do not race too much ahead over what node-app was able to process.
+                let Ok(_) = self
+                    .node_app_ack_rx
+                    .clone()
+                    .wait_for(|node_app_ack| {
+                        block.round
+                            < node_app_ack
+                                .checked_add(
+                                    ConsensusParams::CONSENSUS_PARAMS_CORE_APPLY_DELAY_BASE,
+                                )
+                                .expect("Can't run out of u64 rounds ")
+                    })
+                    .await
+                else {
+                    future::pending().await
+                };
+
+                let block_round = block.round;
+                if let Err(err) = consensus
+                    .process_notarized_block_response(
+                        peer_idx,
+                        WaitNotarizedBlockResponse { block, payload },
+                    )
+                    .await
+                {
+                    warn!(
+                        target: LOG_TARGET,
+                        %peer_idx,
+                        %block_round,
+                        err = %err.fmt_compact(),
+                        "Failed to process synced block"
+                    );
+                    break 'chunks;
+                }
+            }
+
+            if range.round_end < chunk_end {
+                break;
+            }
+        }
+
+        Ok(cur_round < consensus.get_current_round().await)
+    }
+
+    /// Fetch blocks in `round_start..round_end`, trying `peers` in order
+    /// until one of them succeeds
+    async fn fetch_block_range(
+        connection_pool: ConnectionPool,
+        round_start: BlockRound,
+        round_end: BlockRound,
+        peers: Vec<(PeerIdx, PeerPubkey)>,
+        params: ConsensusParams,
+    ) -> Option<(PeerIdx, BlockRange)> {
+        for (peer_idx, peer_pubkey) in peers {
+            match Self::fetch_block_range_from_peer(
+                &connection_pool,
+                round_start,
+                round_end,
+                peer_pubkey,
+                params.clone(),
+            )
+            .await
+            {
+                Ok(range) => {
+                    debug!(
+                        target: LOG_TARGET,
+                        %peer_idx,
+                        %round_start,
+                        round_end = %range.round_end,
+                        num_blocks = range.blocks.len(),
+                        "Fetched block range"
+                    );
+                    return Some((peer_idx, range));
+                }
+                Err(err) => {
+                    debug!(
+                        target: LOG_TARGET,
+                        %peer_idx,
+                        %peer_pubkey,
+                        %round_start,
+                        %round_end,
+                        err = %err.fmt_compact(),
+                        "Failed to fetch block range from peer"
+                    );
+                }
+            }
+        }
+        None
+    }
+
+    async fn fetch_block_range_from_peer(
+        connection_pool: &ConnectionPool,
+        round_start: BlockRound,
+        round_end: BlockRound,
+        peer_pubkey: PeerPubkey,
+        params: ConsensusParams,
+    ) -> WhateverResult<BlockRange> {
+        let mut conn = connection_pool
+            .connect(peer_pubkey)
+            .await
+            .whatever_context("Failed to connect to peer")?;
+
+        match rpc::get_storage_info(&mut conn).await {
+            Ok(storage_info) => {
+                if round_start < storage_info.payloads_pruned_before {
+                    whatever!(
+                        "Peer pruned payloads before round {}",
+                        storage_info.payloads_pruned_before
+                    );
+                }
+            }
+            Err(err) => {
+                // Peers predating pruning don't prune anything
+                debug!(
+                    target: LOG_TARGET,
+                    %peer_pubkey,
+                    err = %err.fmt_compact(),
+                    "Failed to get peer storage info, assuming archival"
+                );
+            }
+        }
+
+        let range = rpc::get_block_range(&mut conn, round_start, round_end, params).await?;
+
+        if range.round_end <= round_start {
+            whatever!("Peer has no finalized blocks in the range");
+        }
+
+        Ok(range)
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::BTreeSet;
+use std::future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::citem::{CItem, ForwardedCItem, PeerCItemHash};
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::signed::Signed;
+use bfte_node_app_core::processed_citems::{self, app_processed_peer_citems};
+use bfte_signer::SignerExt as _;
+use bfte_util_error::WhateverResult;
+use bfte_util_error::fmt::FmtCompact as _;
+use snafu::{ResultExt as _, whatever};
+use tracing::{debug, instrument, trace};
+
+use crate::{Node, rpc};
+
+const LOG_TARGET: &str = "bfte::node::citem-forwarding";
+
+/// How often pending citems are (re-)forwarded to upcoming leaders
+const CITEM_FORWARD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Number of rounds, starting with the current one, whose leaders citems are
+/// forwarded to
+const CITEM_FORWARD_LEADER_ROUNDS: u64 = 3;
+
+/// Maximum number of citems a peer can have forwarded to us at a time
+const CITEM_FORWARD_MAX_CITEMS: usize = 64;
+
+impl Node {
+    /// Forward citems proposed by our modules to leaders of upcoming rounds
+    ///
+    /// Without it, citems of a peer would get into blocks only in rounds it
+    /// is the leader of. Citems keep being re-forwarded until modules stop
+    /// proposing them, but once a citem was included, its forwarded copies
+    /// are rejected by the application logic.
+    #[instrument(
+        name = "citem_forwarding"
+        target = LOG_TARGET,
+        skip_all,
+    )]
+    pub(crate) async fn run_citem_forwarding(self: Arc<Self>) {
+        let Some(our_pubkey) = self.peer_pubkey else {
+            return future::pending().await;
+        };
+        let consensus = self.consensus_wait().await;
+        let mut interval = tokio::time::interval(CITEM_FORWARD_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let citems = self
+                .weak_shared_modules
+                .wait_fresh_consensus_proposal(
+                    consensus.finality_consensus_rx(),
+                    self.node_app_ack_rx.clone(),
+                )
+                .await;
+
+            let (cur_round, params) = consensus.get_current_round_and_params().await;
+            if params.find_peer_idx(our_pubkey).is_none() {
+                continue;
+            }
+
+            let citems = match self
+                .sign_forwarded_citems(our_pubkey, cur_round, citems)
+                .await
+            {
+                Ok(citems) => citems,
+                Err(err) => {
+                    debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to sign forwarded citems");
+                    continue;
+                }
+            };
+
+            for leader in Self::upcoming_leaders(cur_round, &params) {
+                if leader == our_pubkey {
+                    continue;
+                }
+
+                trace!(target: LOG_TARGET, %leader, len = citems.len(), "Forwarding citems…");
+                if let Err(err) = self.forward_citems_to(leader, citems.clone()).await {
+                    debug!(
+                        target: LOG_TARGET,
+                        %leader,
+                        err = %err.fmt_compact(),
+                        "Failed to forward citems"
+                    );
+                }
+            }
+        }
+    }
+
+    async fn sign_forwarded_citems(
+        &self,
+        peer_pubkey: PeerPubkey,
+        cur_round: BlockRound,
+        citems: Vec<CItem>,
+    ) -> WhateverResult<Vec<Signed<ForwardedCItem>>> {
+        let valid_until = cur_round
+            .checked_add(ForwardedCItem::MAX_VALID_ROUNDS)
+            .expect("Can't run out of u64 of rounds");
+
+        let mut signed = Vec::with_capacity(citems.len());
+        for citem in citems {
+            let CItem::PeerCItem(citem) = citem else {
+                continue;
+            };
+            signed.push(
+                self.signer_expect()
+                    .sign(ForwardedCItem {
+                        peer_pubkey,
+                        valid_until,
+                        citem,
+                    })
+                    .await
+                    .whatever_context("Failed to sign")?,
+            );
+        }
+        Ok(signed)
+    }
+
+    fn upcoming_leaders(cur_round: BlockRound, params: &ConsensusParams) -> BTreeSet<PeerPubkey> {
+        (0..CITEM_FORWARD_LEADER_ROUNDS)
+            .filter_map(|i| cur_round.checked_add(i))
+            .map(|round| params.peers[params.leader_idx(round).as_usize()])
+            .collect()
+    }
+
+    async fn forward_citems_to(
+        &self,
+        leader: PeerPubkey,
+        citems: Vec<Signed<ForwardedCItem>>,
+    ) -> WhateverResult<()> {
+        let mut conn = self
+            .connection_pool()
+            .connect(leader)
+            .await
+            .whatever_context("Failed to connect")?;
+
+        rpc::forward_citems(&mut conn, citems).await
+    }
+
+    /// Store citems forwarded by a peer, for inclusion in our next proposal
+    ///
+    /// Replaces any citems previously forwarded by the same peer.
+    pub(crate) async fn receive_forwarded_citems(
+        &self,
+        citems: Vec<Signed<ForwardedCItem>>,
+    ) -> WhateverResult<()> {
+        let Some(first) = citems.first() else {
+            return Ok(());
+        };
+        let peer_pubkey = first.peer_pubkey;
+
+        if self.peer_pubkey.is_none() {
+            whatever!("Not a peer");
+        }
+        if CITEM_FORWARD_MAX_CITEMS < citems.len() {
+            whatever!("Too many forwarded citems: {}", citems.len());
+        }
+        let (cur_round, params) = self
+            .consensus_wait()
+            .await
+            .get_current_round_and_params()
+            .await;
+        if params.find_peer_idx(peer_pubkey).is_none() {
+            whatever!("Forwarded citems sender is not a peer: {peer_pubkey}");
+        }
+        for citem in &citems {
+            if citem.peer_pubkey != peer_pubkey {
+                whatever!("Forwarded citems from multiple peers");
+            }
+            if citem.valid_until <= cur_round {
+                whatever!("Forwarded citem already expired");
+            }
+            citem
+                .verify_sig_peer_pubkey(peer_pubkey)
+                .whatever_context("Invalid forwarded citem signature")?;
+        }
+
+        self.forwarded_citems()
+            .lock()
+            .expect("Locking failed")
+            .insert(peer_pubkey, citems);
+        self.forwarded_citems_tx().send_replace(());
+
+        Ok(())
+    }
+
+    /// Are there any citems forwarded to us, still valid in `round`
+    pub(crate) fn has_forwarded_citems(&self, round: BlockRound) -> bool {
+        self.forwarded_citems()
+            .lock()
+            .expect("Locking failed")
+            .values()
+            .flatten()
+            .any(|citem| round < citem.valid_until)
+    }
+
+    /// Take all citems forwarded to us, that can be included in a block
+    /// proposal for `round`
+    ///
+    /// Citems already processed, either forwarded or included by the peer
+    /// itself, are dropped, as they would be rejected anyway.
+    pub(crate) async fn take_forwarded_citems(
+        &self,
+        round: BlockRound,
+        params: &ConsensusParams,
+    ) -> Vec<CItem> {
+        let forwarded: Vec<_> =
+            std::mem::take(&mut *self.forwarded_citems().lock().expect("Locking failed"))
+                .into_iter()
+                .filter(|(peer_pubkey, _)| {
+                    Some(*peer_pubkey) != self.peer_pubkey
+                        && params.find_peer_idx(*peer_pubkey).is_some()
+                })
+                .flat_map(|(_, citems)| citems)
+                .filter(|citem| round < citem.valid_until)
+                .collect();
+
+        self.db()
+            .read_with_expect(|dbtx| {
+                let tbl = dbtx.open_table(&app_processed_peer_citems::TABLE)?;
+                let mut citems = Vec::with_capacity(forwarded.len());
+                for citem in forwarded {
+                    let hash = PeerCItemHash::new(citem.peer_pubkey, &citem.citem);
+                    if !processed_citems::is_processed(&tbl, round, hash)? {
+                        citems.push(CItem::ForwardedPeerCItem(citem));
+                    }
+                }
+                Ok(citems)
+            })
+            .await
+    }
+}
@@ -0,0 +1,40 @@
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::citem::CItem;
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::proof::CItemProof;
+use bfte_util_error::WhateverResult;
+use snafu::{OptionExt as _, ResultExt as _};
+
+use crate::{Node, rpc};
+
+impl Node {
+    /// Fetch a proof of `citem_idx`-th citem in a block at `round` from a
+    /// peer, and verify it starting from `trusted_params`
+    ///
+    /// Returns the proof (to pass to other light clients) and the proven
+    /// citem.
+    pub async fn fetch_citem_proof(
+        &self,
+        peer_pubkey: PeerPubkey,
+        trusted_params: &ConsensusParams,
+        round: BlockRound,
+        citem_idx: u32,
+    ) -> WhateverResult<(CItemProof, CItem)> {
+        let mut conn = self
+            .connection_pool()
+            .connect(peer_pubkey)
+            .await
+            .whatever_context("Failed to connect")?;
+
+        let proof = rpc::get_citem_proof(&mut conn, trusted_params.apply_round, round, citem_idx)
+            .await?
+            .whatever_context("Peer could not produce the proof")?;
+
+        let citem = proof
+            .verify(trusted_params)
+            .whatever_context("Invalid citem proof")?;
+
+        Ok((proof, citem))
+    }
+}
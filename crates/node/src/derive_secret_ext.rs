@@ -1,12 +1,20 @@
+use bfte_consensus_core::module::{ModuleId, ModuleKind};
 use bfte_consensus_core::peer::PeerSeckey;
 use bfte_derive_secret::{ChildId, LevelResult};
+use bfte_module::module::secret::ModuleSecret;
 
 const PEER_SECKEY_CHILD_ID: ChildId = ChildId::new(0);
 const IROH_SECRET_CHILD_ID: ChildId = ChildId::new(1);
+const MODULES_SECRET_CHILD_ID: ChildId = ChildId::new(2);
 
 pub trait DeriveSecretExt {
     fn get_peer_seckey(self) -> LevelResult<PeerSeckey>;
     fn get_iroh_secret(self) -> LevelResult<iroh::SecretKey>;
+    fn get_module_secret(
+        self,
+        module_kind: ModuleKind,
+        module_id: ModuleId,
+    ) -> LevelResult<ModuleSecret>;
 }
 
 impl DeriveSecretExt for bfte_derive_secret::DeriveableSecret {
@@ -19,4 +27,17 @@ impl DeriveSecretExt for bfte_derive_secret::DeriveableSecret {
         self.ensure_level(0)?;
         Ok(self.derive(IROH_SECRET_CHILD_ID).reveal_bytes().into())
     }
+
+    fn get_module_secret(
+        self,
+        module_kind: ModuleKind,
+        module_id: ModuleId,
+    ) -> LevelResult<ModuleSecret> {
+        self.ensure_level(0)?;
+        Ok(ModuleSecret::new(
+            self.derive(MODULES_SECRET_CHILD_ID)
+                .derive(u32::from(module_kind).into())
+                .derive(u32::from(module_id).into()),
+        ))
+    }
 }
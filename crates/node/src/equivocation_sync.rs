@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_util_error::WhateverResult;
+use bfte_util_error::fmt::FmtCompact as _;
+use rand::seq::SliceRandom as _;
+use snafu::ResultExt as _;
+use tracing::{debug, instrument};
+
+use crate::Node;
+use crate::rpc;
+
+const LOG_TARGET: &str = "bfte::node::equivocation";
+
+impl Node {
+    /// Periodically pull equivocation evidence from random peers
+    ///
+    /// Evidence is only observed by peers that happened to receive the
+    /// conflicting messages, so it needs to be spread around for everyone
+    /// (including ConsensusCtrl module) to act on it.
+    #[instrument(
+        name = "equivocation_sync"
+        target = LOG_TARGET,
+        skip_all,
+    )]
+    pub(crate) async fn run_equivocation_sync(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+
+        loop {
+            interval.tick().await;
+
+            let (_, consensus_params) =
+                self.consensus_expect().get_current_round_and_params().await;
+
+            let Some(peer_pubkey) = consensus_params
+                .peers
+                .as_slice()
+                .iter()
+                .copied()
+                .filter(|peer_pubkey| Some(*peer_pubkey) != self.peer_pubkey)
+                .collect::<Vec<_>>()
+                .choose(&mut rand::thread_rng())
+                .copied()
+            else {
+                continue;
+            };
+
+            if let Err(err) = self.query_equivocations(peer_pubkey).await {
+                debug!(
+                    target: LOG_TARGET,
+                    %peer_pubkey,
+                    err = %err.fmt_compact(),
+                    "Failed to query equivocations"
+                );
+            }
+        }
+    }
+
+    async fn query_equivocations(&self, peer_pubkey: PeerPubkey) -> WhateverResult<()> {
+        let mut conn = self
+            .connection_pool()
+            .connect(peer_pubkey)
+            .await
+            .whatever_context("Failed to connect")?;
+
+        for evidence in rpc::get_equivocations(&mut conn).await? {
+            if let Err(err) = self.consensus_expect().record_equivocation(&evidence).await {
+                debug!(
+                    target: LOG_TARGET,
+                    %peer_pubkey,
+                    offender = %evidence.peer_pubkey,
+                    err = %err.fmt_compact(),
+                    "Ignoring equivocation evidence"
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
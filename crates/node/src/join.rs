@@ -1,16 +1,21 @@
 use std::sync::Arc;
 
 use bfte_consensus::consensus::Consensus;
-use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::block::{BlockHash, BlockHeader, BlockRound, BlockSeq};
+use bfte_consensus_core::consensus_params::{
+    ConsensusParams, ConsensusParamsHash, ConsensusParamsLen, ConsensusParamsRaw,
+};
 use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::signed::Notarized;
 use bfte_db::Database;
 use bfte_db::error::DbError;
 use bfte_invite::Invite;
 use bfte_node_core::address::PeerAddress;
+use bfte_util_error::fmt::FmtCompact as _;
 use bfte_util_error::{Whatever, WhateverResult};
 use iroh::endpoint::Connection;
 use snafu::{ResultExt as _, Snafu, whatever};
-use tracing::warn;
+use tracing::{debug, warn};
 
 use crate::connection_pool::ALPN_BFTE_V0;
 use crate::{LOG_TARGET, Node, rpc};
@@ -40,6 +45,78 @@ pub enum NodeJoinError {
 
 pub type NodeJoinResult<T> = Result<T, NodeJoinError>;
 
+/// Peer serving historical blocks and consensus params to a joining node
+///
+/// Nothing it returns is trusted: blocks are verified to be the ones
+/// committed to by hashes the caller already trusts.
+pub(crate) trait ChainSource {
+    /// Get the latest non-dummy finalized block at or before `round`
+    async fn get_block(&mut self, round: BlockRound) -> WhateverResult<Notarized<BlockHeader>>;
+
+    /// Get consensus params used in `round`, verified against their hash
+    async fn get_consensus_params(
+        &mut self,
+        round: BlockRound,
+        consensus_params_hash: ConsensusParamsHash,
+        consensus_params_len: ConsensusParamsLen,
+    ) -> WhateverResult<ConsensusParamsRaw>;
+
+    /// Get the block in `round` with a known `block_hash`
+    async fn get_block_hashed(
+        &mut self,
+        round: BlockRound,
+        block_hash: BlockHash,
+    ) -> WhateverResult<Notarized<BlockHeader>> {
+        let resp = self.get_block(round).await?;
+
+        if resp.round != round {
+            whatever!(
+                "Mismatched round block from peer: {} != {}",
+                resp.round,
+                round
+            );
+        }
+        if resp.hash() != block_hash {
+            whatever!("Mismatched hash block from peer");
+        }
+
+        Ok(resp)
+    }
+
+    /// Get the non-dummy block directly preceding `block`
+    async fn get_block_prev(
+        &mut self,
+        block: BlockHeader,
+    ) -> WhateverResult<Notarized<BlockHeader>> {
+        let Some(round) = block.round.prev() else {
+            whatever!("No blocks before round 0");
+        };
+
+        let resp = self.get_block(round).await?;
+
+        if resp.hash() != block.prev_block_hash {
+            whatever!("Mismatched hash of previous block from peer");
+        }
+
+        Ok(resp)
+    }
+}
+
+impl ChainSource for Connection {
+    async fn get_block(&mut self, round: BlockRound) -> WhateverResult<Notarized<BlockHeader>> {
+        rpc::get_block(self, round).await
+    }
+
+    async fn get_consensus_params(
+        &mut self,
+        round: BlockRound,
+        consensus_params_hash: ConsensusParamsHash,
+        consensus_params_len: ConsensusParamsLen,
+    ) -> WhateverResult<ConsensusParamsRaw> {
+        rpc::get_consensus_params(self, round, consensus_params_hash, consensus_params_len).await
+    }
+}
+
 impl Node {
     pub async fn consensus_join_static(
         db: Arc<Database>,
@@ -73,6 +150,7 @@ impl Node {
         our_peer_pubkey: Option<PeerPubkey>,
     ) -> WhateverResult<Consensus> {
         let mut init_params = None;
+        let mut pin_round_params = None;
 
         // Use the embedded init_params, to get initial consensus params for the
         // federation
@@ -102,7 +180,7 @@ impl Node {
 
         // Use the embedded pin (some recent block)
         if let Some((pin_round, pin_block_hash)) = invite.pin {
-            let pin_block = rpc::get_block_hashed(conn, pin_round, pin_block_hash).await?;
+            let pin_block = conn.get_block_hashed(pin_round, pin_block_hash).await?;
 
             let pin_params = rpc::get_consensus_params(
                 conn,
@@ -115,8 +193,8 @@ impl Node {
             let pin_params = ConsensusParams::from_raw(&pin_params)
                 .whatever_context("Failed to parse consensus params")?;
 
-            for peer_pubkey in pin_params.peers {
-                match rpc::get_peer_address(conn, peer_pubkey).await? {
+            for peer_pubkey in pin_params.peers.as_slice() {
+                match rpc::get_peer_address(conn, *peer_pubkey).await? {
                     Some(update) => Self::handle_address_update(&db, update).await?,
                     None => {
                         warn!(target: LOG_TARGET, %peer_pubkey, "Missing other peer address");
@@ -124,16 +202,99 @@ impl Node {
                 }
             }
 
-            // TODO: figure out `init_params` using chain of consensus_params,
-            // if they were not present in the invite
+            pin_round_params = Some((pin_round, pin_params.clone()));
+
+            if init_params.is_none() {
+                init_params = Some(Self::derive_init_params(conn, pin_block, pin_params).await?);
+            }
         };
 
         let Some(init_params) = init_params else {
             whatever!("Init params not available in the invite");
         };
 
-        Consensus::init(&init_params, db, our_peer_pubkey, invite.pin)
+        let consensus = Consensus::init(&init_params, db.clone(), our_peer_pubkey, invite.pin)
             .await
-            .whatever_context("Failed to initialize consensus")
+            .whatever_context("Failed to initialize consensus")?;
+
+        let Some((pin_round, pin_params)) = pin_round_params else {
+            return Ok(consensus);
+        };
+
+        // Peers from the pinned params can attest a recent state snapshot,
+        // which saves replaying the whole history
+        if let Err(err) =
+            Self::restore_state_snapshot(conn, &db, &consensus, pin_round, &pin_params).await
+        {
+            warn!(
+                target: LOG_TARGET,
+                err = %err.fmt_compact(),
+                "Could not restore state snapshot, will replay the whole history"
+            );
+        }
+
+        Ok(consensus)
+    }
+
+    /// Find the initial consensus params by walking back the chain of
+    /// consensus params, starting from the pinned block
+    ///
+    /// Every [`ConsensusParams`] commits to a historical block in
+    /// [`ConsensusParams::prev_mid_block`], from well before it was scheduled,
+    /// and every block commits to [`ConsensusParams`] used in its round. This
+    /// allows reaching the init params in `O(log(N))` steps. Every block on
+    /// the way is verified to be notarized by its own params.
+    async fn derive_init_params(
+        source: &mut impl ChainSource,
+        mut block: Notarized<BlockHeader>,
+        mut params: ConsensusParams,
+    ) -> WhateverResult<ConsensusParams> {
+        loop {
+            debug_assert_eq!(block.consensus_params_hash, params.hash());
+            block
+                .verify_sigs(&params)
+                .whatever_context("Invalid block notarization")?;
+
+            if params.apply_round == BlockRound::ZERO {
+                return Ok(params);
+            }
+
+            debug!(
+                target: LOG_TARGET,
+                round = %block.round,
+                apply_round = %params.apply_round,
+                "Walking back consensus params chain"
+            );
+
+            block = match params.prev_mid_block {
+                Some((mid_round, mid_hash)) if mid_round < block.round => {
+                    source.get_block_hashed(mid_round, mid_hash).await?
+                }
+                _ => {
+                    // No historical block was available when these params were
+                    // created, so we can only go back one block at the time.
+                    if block.seq == BlockSeq::ZERO {
+                        whatever!("Reached the first block without finding init params");
+                    }
+                    source.get_block_prev(block.inner).await?
+                }
+            };
+
+            if block.consensus_params_hash != params.hash() {
+                let raw = source
+                    .get_consensus_params(
+                        block.round,
+                        block.consensus_params_hash,
+                        block.consensus_params_len,
+                    )
+                    .await?;
+
+                params = ConsensusParams::from_raw(&raw)
+                    .whatever_context("Failed to parse consensus params")?;
+            }
+        }
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+
+use bfte_consensus_core::block::{BlockHeader, BlockPayloadRaw, BlockRound};
+use bfte_consensus_core::consensus_params::{
+    ConsensusParams, ConsensusParamsHash, ConsensusParamsLen, ConsensusParamsRaw,
+};
+use bfte_consensus_core::peer::{PeerIdx, PeerSeckey};
+use bfte_consensus_core::peer_set::PeerSet;
+use bfte_consensus_core::signed::{Notarized, Signable as _};
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_util_error::WhateverResult;
+use snafu::whatever;
+
+use super::ChainSource;
+use crate::Node;
+
+/// In-memory chain of blocks, served as a peer would
+struct TestChain {
+    blocks: BTreeMap<BlockRound, Notarized<BlockHeader>>,
+    params: Vec<ConsensusParams>,
+}
+
+impl ChainSource for TestChain {
+    async fn get_block(&mut self, round: BlockRound) -> WhateverResult<Notarized<BlockHeader>> {
+        let Some((_, block)) = self.blocks.range(..=round).next_back() else {
+            whatever!("No block at or before {round}");
+        };
+        Ok(block.clone())
+    }
+
+    async fn get_consensus_params(
+        &mut self,
+        _round: BlockRound,
+        consensus_params_hash: ConsensusParamsHash,
+        _consensus_params_len: ConsensusParamsLen,
+    ) -> WhateverResult<ConsensusParamsRaw> {
+        let Some(params) = self
+            .params
+            .iter()
+            .find(|params| params.hash() == consensus_params_hash)
+        else {
+            whatever!("No such consensus params");
+        };
+        Ok(params.to_raw())
+    }
+}
+
+fn peer_set(seckeys: &[PeerSeckey]) -> PeerSet {
+    seckeys.iter().map(|seckey| seckey.pubkey()).collect()
+}
+
+fn notarize(
+    block: BlockHeader,
+    params: &ConsensusParams,
+    seckeys: &[PeerSeckey],
+) -> Notarized<BlockHeader> {
+    Notarized::new(
+        block,
+        seckeys.iter().map(|seckey| {
+            let peer_idx = params
+                .peers
+                .as_slice()
+                .iter()
+                .position(|peer_pubkey| *peer_pubkey == seckey.pubkey())
+                .expect("Must be a peer");
+            (
+                PeerIdx::new(u8::try_from(peer_idx).expect("Can't fail")),
+                block.sign_with(*seckey),
+            )
+        }),
+    )
+}
+
+fn block(
+    round: BlockRound,
+    timestamp: Timestamp,
+    prev: Option<BlockHeader>,
+    params: &ConsensusParams,
+) -> BlockHeader {
+    BlockHeader::builder()
+        .maybe_prev(prev)
+        .timestamp(timestamp)
+        .round(round)
+        .consensus_params(params)
+        .payload(&BlockPayloadRaw::empty())
+        .build()
+}
+
+/// A chain going through two peer set changes
+///
+/// The first change leaves no historical block to commit to, so it can only
+/// be walked back block by block, while the second one commits to the first
+/// block after the first change.
+struct Setup {
+    chain: TestChain,
+    seckeys: [Vec<PeerSeckey>; 3],
+    params: [ConsensusParams; 3],
+    blocks: [Notarized<BlockHeader>; 4],
+}
+
+impl Setup {
+    fn new() -> Self {
+        let seckeys = [0, 1, 2].map(|_| vec![PeerSeckey::generate(), PeerSeckey::generate()]);
+
+        let params0 = ConsensusParams {
+            peers: peer_set(&seckeys[0]),
+            ..ConsensusParams::new_test_dummy()
+        };
+        let b0 = notarize(
+            block(0.into(), Timestamp::ZERO, None, &params0),
+            &params0,
+            &seckeys[0],
+        );
+        let b1 = notarize(
+            block(1.into(), Timestamp::ZERO, Some(b0.inner), &params0),
+            &params0,
+            &seckeys[0],
+        );
+
+        let params1 = params0.clone().make_change(
+            1.into(),
+            Timestamp::ZERO,
+            peer_set(&seckeys[1]),
+            vec![],
+            vec![],
+            params0.max_block_payload_len,
+            None,
+        );
+        let b2 = notarize(
+            block(
+                params1.apply_round,
+                Timestamp::ZERO,
+                Some(b1.inner),
+                &params1,
+            ),
+            &params1,
+            &seckeys[1],
+        );
+
+        let params2 = params1.clone().make_change(
+            b2.round,
+            Timestamp::ZERO,
+            peer_set(&seckeys[2]),
+            vec![],
+            vec![],
+            params1.max_block_payload_len,
+            Some((b2.round, b2.hash())),
+        );
+        let b3 = notarize(
+            block(
+                params2.apply_round,
+                Timestamp::ZERO,
+                Some(b2.inner),
+                &params2,
+            ),
+            &params2,
+            &seckeys[2],
+        );
+
+        let blocks = [b0, b1, b2, b3];
+        let params = [params0, params1, params2];
+
+        Self {
+            chain: TestChain {
+                blocks: blocks
+                    .iter()
+                    .map(|block| (block.round, block.clone()))
+                    .collect(),
+                params: params.to_vec(),
+            },
+            seckeys,
+            params,
+            blocks,
+        }
+    }
+
+    /// Make the chain serve a different, but validly notarized, block in
+    /// place of `blocks[i]`
+    fn fork_block(&mut self, i: usize, params_i: usize) {
+        let orig = self.blocks[i].inner;
+        let prev = i.checked_sub(1).map(|prev_i| self.blocks[prev_i].inner);
+        let params = &self.params[params_i];
+        let fork = block(orig.round, Timestamp::now(), prev, params);
+        assert_ne!(fork.hash(), orig.hash());
+        self.chain
+            .blocks
+            .insert(orig.round, notarize(fork, params, &self.seckeys[params_i]));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn derive_init_params_walks_valid_chain() -> WhateverResult<()> {
+    let mut setup = Setup::new();
+
+    // Through the committed historical block, then block by block
+    let init_params = Node::derive_init_params(
+        &mut setup.chain,
+        setup.blocks[3].clone(),
+        setup.params[2].clone(),
+    )
+    .await?;
+    assert_eq!(init_params, setup.params[0]);
+
+    // Block by block only
+    let init_params = Node::derive_init_params(
+        &mut setup.chain,
+        setup.blocks[2].clone(),
+        setup.params[1].clone(),
+    )
+    .await?;
+    assert_eq!(init_params, setup.params[0]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn derive_init_params_rejects_broken_link() {
+    let mut setup = Setup::new();
+    // Not the block committed to in the `prev_mid_block` of the last params
+    setup.fork_block(2, 1);
+
+    assert!(
+        Node::derive_init_params(
+            &mut setup.chain,
+            setup.blocks[3].clone(),
+            setup.params[2].clone(),
+        )
+        .await
+        .is_err()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn derive_init_params_rejects_forked_prev_block() {
+    let mut setup = Setup::new();
+    // Not the block the first block after the peer set change extends
+    setup.fork_block(1, 0);
+
+    assert!(
+        Node::derive_init_params(
+            &mut setup.chain,
+            setup.blocks[2].clone(),
+            setup.params[1].clone(),
+        )
+        .await
+        .is_err()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_block_prev_verifies_hash_link() -> WhateverResult<()> {
+    let mut setup = Setup::new();
+
+    let prev = setup.chain.get_block_prev(setup.blocks[2].inner).await?;
+    assert_eq!(prev.hash(), setup.blocks[1].hash());
+
+    setup.fork_block(1, 0);
+    assert!(
+        setup
+            .chain
+            .get_block_prev(setup.blocks[2].inner)
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
@@ -12,26 +12,37 @@
 //!
 //! See [`run_consensus`] for the core consensus round loop logic.
 mod app_api;
+mod block_sync;
+mod citem_forwarding;
+mod citem_proof;
 mod connection_pool;
 pub mod derive_secret_ext;
 mod envs;
+mod equivocation_sync;
 mod finality_vote_query_task;
 mod handle;
 mod invite;
 mod join;
+mod migrations;
+mod module_net;
 mod node;
 mod pass;
 mod peer_address;
+mod prune;
 pub(crate) mod rpc;
 mod rpc_server;
 mod run_consensus;
+mod simulate;
+mod state_snapshot;
 mod tables;
 mod ui_api;
 
 use std::time::Duration;
 
 use backon::FibonacciBuilder;
+pub use bfte_consensus::consensus::{DEFAULT_PRUNING_RETENTION, PruningConfig, StorageMode};
 pub use node::Node;
+pub use peer_address::AddressUpdate;
 
 const LOG_TARGET: &str = "bfte::node";
 const RPC_BACKOFF: FibonacciBuilder = FibonacciBuilder::new()
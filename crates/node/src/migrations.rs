@@ -0,0 +1,54 @@
+use bfte_consensus_core::block::{BlockCItemIdx, BlockRound};
+use bfte_db::ctx::WriteTransactionCtx;
+use bfte_db::error::DbResult;
+use bfte_db::migration::Migration;
+use bfte_util_db::redb_bincode::{ReadableTable as _, TableDefinition};
+
+/// Schema migrations of the node database
+///
+/// The database is shared by the consensus, the node, the local signer and
+/// all the modules, so this covers all of their tables.
+///
+/// Append-only: the position of a step determines the version it migrates
+/// to, so existing steps must never be removed or reordered.
+pub(crate) const DB_MIGRATIONS: &[Migration] = &[Migration {
+    name: "app_cur_round_first_unprocessed",
+    migrate: app_cur_round_first_unprocessed_tx,
+}];
+
+/// Node app position, with the citem index encoded as a plain `u32`
+const APP_CUR_ROUND_V0: TableDefinition<'static, (), (BlockRound, u32)> =
+    TableDefinition::new("app_cur_round");
+/// The node app position, as of [`app_cur_round_first_unprocessed_tx`]
+const APP_CUR_ROUND_V1: TableDefinition<'static, (), (BlockRound, BlockCItemIdx)> =
+    TableDefinition::new("app_cur_round");
+
+/// Switch the node app position to the first citem not processed yet, as a
+/// [`BlockCItemIdx`]
+///
+/// Before, the index of the last processed citem was saved, and `0` at the
+/// start of each round. The saved value alone can't tell apart the remaining
+/// cases, so they are resolved towards the common ones: `0` stays the start
+/// of a round (even if its first citem was processed already), and any other
+/// index is a processed citem (even if it was saved as the next one right
+/// after a rejected citem).
+fn app_cur_round_first_unprocessed_tx(ctx: &WriteTransactionCtx) -> DbResult<()> {
+    let cur_round = ctx
+        .open_table(&APP_CUR_ROUND_V0)?
+        .get(&())?
+        .map(|v| v.value());
+
+    if let Some((round, idx)) = cur_round {
+        let idx = match idx {
+            0 => BlockCItemIdx::new(0),
+            idx => BlockCItemIdx::new(idx).next_expect(),
+        };
+        ctx.open_table(&APP_CUR_ROUND_V1)?
+            .insert(&(), &(round, idx))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+
+use bfte_consensus_core::block::{BlockCItemIdx, BlockRound};
+use bfte_db::Database;
+use bfte_db::migration::{DB_VERSION_TABLE, code_version};
+use bfte_util_db::redb_bincode::{ReadableTable as _, TableDefinition};
+use bfte_util_error::WhateverResult;
+use snafu::ResultExt as _;
+
+use super::{APP_CUR_ROUND_V0, APP_CUR_ROUND_V1, DB_MIGRATIONS};
+use crate::Node;
+
+/// Consensus schema version, defined (but never written) by the baseline code
+const CONS_DB_VERSION_V0: TableDefinition<'static, (), u64> = TableDefinition::new("db_version");
+
+/// Path to a fresh database file, unique for the `test`
+fn db_path(test: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("bfte-node-migration-{}-{test}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("db.redb")
+}
+
+/// Write a database as laid out by the code from before migrations were
+/// introduced, with the node app at `cur_round`
+///
+/// The baseline code can't be run here, so this re-creates the tables of it
+/// that migrations handle, with the same names and encodings: the node app
+/// position, and the consensus schema version, which was left empty.
+async fn write_baseline_database(
+    path: &PathBuf,
+    cur_round: (BlockRound, u32),
+) -> WhateverResult<()> {
+    let db = Database::open(path.clone(), &[])
+        .await
+        .whatever_context("Failed to open database")?;
+    db.write_with(|ctx| {
+        ctx.open_table(&CONS_DB_VERSION_V0)?;
+        ctx.open_table(&APP_CUR_ROUND_V0)?.insert(&(), &cur_round)?;
+        Ok(())
+    })
+    .await
+    .whatever_context("Failed to write database")
+}
+
+/// Open the database at `path` as the node does, returning its version and
+/// the node app position
+async fn open_migrated(
+    path: PathBuf,
+) -> WhateverResult<(Option<u64>, Option<(BlockRound, BlockCItemIdx)>)> {
+    let db = Node::open_db(Some(path))
+        .await
+        .whatever_context("Failed to open database")?;
+
+    Ok(db
+        .read_with_expect(|ctx| {
+            Ok((
+                ctx.open_table(&DB_VERSION_TABLE)?
+                    .get(&())?
+                    .map(|v| v.value()),
+                ctx.open_table(&APP_CUR_ROUND_V1)?
+                    .get(&())?
+                    .map(|v| v.value()),
+            ))
+        })
+        .await)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn open_db_migrates_baseline_database() -> WhateverResult<()> {
+    // The last processed citem was saved, now it's the first not processed one
+    let path = db_path("baseline-mid-round");
+    write_baseline_database(&path, (BlockRound::from(6), 300)).await?;
+    let (db_ver, cur_round) = open_migrated(path).await?;
+    assert_eq!(db_ver, Some(code_version(DB_MIGRATIONS)));
+    assert_eq!(
+        cur_round,
+        Some((BlockRound::from(6), BlockCItemIdx::new(301)))
+    );
+
+    // Start of a round stays as is
+    let path = db_path("baseline-round-start");
+    write_baseline_database(&path, (BlockRound::from(7), 0)).await?;
+    let (db_ver, cur_round) = open_migrated(path).await?;
+    assert_eq!(db_ver, Some(code_version(DB_MIGRATIONS)));
+    assert_eq!(
+        cur_round,
+        Some((BlockRound::from(7), BlockCItemIdx::new(0)))
+    );
+
+    Ok(())
+}
@@ -0,0 +1,358 @@
+use std::collections::BTreeSet;
+use std::future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bfte_consensus_core::module::ModuleId;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::signed::{Hashable, Signable, Signed};
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_module::module::net::{
+    IModuleNet, MODULE_MESSAGE_MAX_PAYLOAD_LEN, ModuleMessage, ModuleMessageId,
+};
+use bfte_signer::SignerExt as _;
+use bfte_util_error::WhateverResult;
+use bfte_util_error::fmt::FmtCompact as _;
+use bincode::{Decode, Encode};
+use snafu::{ResultExt as _, whatever};
+use tracing::{debug, instrument, trace, warn};
+
+use crate::handle::NodeHandle;
+use crate::{Node, rpc, tables};
+
+const LOG_TARGET: &str = "bfte::node::module-net";
+
+/// Delay before retrying delivery of module messages to unreachable peers
+const MODULE_MSG_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// A private message between instances of the module `module_id`, as sent
+/// over the wire
+#[derive(Clone, Debug, Encode, Decode)]
+pub struct ModuleMessageEnvelope {
+    pub module_id: ModuleId,
+    pub sender: PeerPubkey,
+    pub recipient: PeerPubkey,
+    /// Increasing for every message of the sender, used to deduplicate
+    /// redeliveries
+    pub seq: u64,
+    pub payload: Vec<u8>,
+}
+
+impl Hashable for ModuleMessageEnvelope {}
+
+impl Signable for ModuleMessageEnvelope {
+    const TAG: [u8; 4] = *b"mmsg";
+}
+
+/// [`IModuleNet`] of a single module instance
+pub(crate) struct NodeModuleNet {
+    pub(crate) handle: NodeHandle,
+    pub(crate) module_id: ModuleId,
+}
+
+#[async_trait]
+impl IModuleNet for NodeModuleNet {
+    async fn send(&self, recipient: PeerPubkey, payload: Vec<u8>) -> WhateverResult<()> {
+        self.handle
+            .node_ref()
+            .whatever_context("Node shutting down")?
+            .queue_module_message(self.module_id, recipient, payload)
+            .await
+    }
+
+    async fn recv(&self) -> ModuleMessage {
+        let Ok(node_ref) = self.handle.node_ref() else {
+            future::pending().await
+        };
+
+        node_ref.wait_module_message(self.module_id).await
+    }
+
+    async fn ack(&self, id: ModuleMessageId) {
+        let Ok(node_ref) = self.handle.node_ref() else {
+            return;
+        };
+
+        node_ref.ack_module_message(self.module_id, id).await;
+    }
+}
+
+impl Node {
+    /// Sign and persist a module message, for [`Self::run_module_msg_delivery`]
+    /// to deliver
+    async fn queue_module_message(
+        &self,
+        module_id: ModuleId,
+        recipient: PeerPubkey,
+        payload: Vec<u8>,
+    ) -> WhateverResult<()> {
+        let Some(sender) = self.peer_pubkey else {
+            whatever!("Can't send module messages without being a peer");
+        };
+        if recipient == sender {
+            whatever!("Can't send module messages to self");
+        }
+        if MODULE_MESSAGE_MAX_PAYLOAD_LEN < payload.len() {
+            whatever!("Module message payload too large: {}", payload.len());
+        }
+
+        // Messages must be queued in `seq` order, as recipients drop ones with
+        // `seq` lower than already received
+        let _guard = self.module_msg_send_lock().lock().await;
+
+        let seq = self
+            .db()
+            .write_with_expect(|ctx| {
+                let mut tbl = ctx.open_table(&tables::module_msg_next_seq::TABLE)?;
+                // Starting from the current time, so a sender restored without
+                // its database does not reuse old values
+                let seq = tbl
+                    .get(&())?
+                    .map(|g| g.value())
+                    .unwrap_or_else(|| Timestamp::now().to_number());
+                tbl.insert(&(), &(seq + 1))?;
+                Ok(seq)
+            })
+            .await;
+
+        let msg = self
+            .signer_expect()
+            .sign(ModuleMessageEnvelope {
+                module_id,
+                sender,
+                recipient,
+                seq,
+                payload,
+            })
+            .await
+            .whatever_context("Failed to sign module message")?;
+
+        self.db()
+            .write_with_expect(|ctx| {
+                ctx.open_table(&tables::module_msg_outbox::TABLE)?
+                    .insert(&(recipient, seq), &msg)?;
+
+                let outbox_tx = self.module_msg_outbox_tx().clone();
+                ctx.on_commit(move || {
+                    outbox_tx.send_replace(());
+                });
+                Ok(())
+            })
+            .await;
+
+        Ok(())
+    }
+
+    /// Store a module message pushed by its sender
+    ///
+    /// Messages already received (by `seq`) are ignored, so the sender can
+    /// safely retry.
+    pub(crate) async fn receive_module_message(
+        &self,
+        msg: Signed<ModuleMessageEnvelope>,
+    ) -> WhateverResult<()> {
+        if Some(msg.recipient) != self.peer_pubkey {
+            whatever!("Module message not addressed to us");
+        }
+        if MODULE_MESSAGE_MAX_PAYLOAD_LEN < msg.payload.len() {
+            whatever!("Module message payload too large: {}", msg.payload.len());
+        }
+        let (_, params) = self
+            .consensus_wait()
+            .await
+            .get_current_round_and_params()
+            .await;
+        if params.find_peer_idx(msg.sender).is_none() {
+            whatever!("Module message sender is not a peer: {}", msg.sender);
+        }
+        msg.verify_sig_peer_pubkey(msg.sender)
+            .whatever_context("Invalid module message signature")?;
+
+        self.db()
+            .write_with_expect(|ctx| {
+                let mut tbl_last_seq = ctx.open_table(&tables::module_msg_last_seq::TABLE)?;
+                if tbl_last_seq
+                    .get(&msg.sender)?
+                    .is_some_and(|last_seq| msg.seq <= last_seq.value())
+                {
+                    trace!(target: LOG_TARGET, sender = %msg.sender, seq = msg.seq, "Ignoring already received module message");
+                    return Ok(());
+                }
+                tbl_last_seq.insert(&msg.sender, &msg.seq)?;
+
+                let mut tbl_inbox = ctx.open_table(&tables::module_msg_inbox::TABLE)?;
+                let id = tbl_inbox
+                    .range(&(msg.module_id, 0)..=&(msg.module_id, ModuleMessageId::MAX))?
+                    .next_back()
+                    .transpose()?
+                    .map(|(k, _)| k.value().1 + 1)
+                    .unwrap_or_default();
+                tbl_inbox.insert(&(msg.module_id, id), &(msg.sender, msg.payload.clone()))?;
+
+                let inbox_tx = self.module_msg_inbox_tx().clone();
+                ctx.on_commit(move || {
+                    inbox_tx.send_replace(());
+                });
+                Ok(())
+            })
+            .await;
+
+        Ok(())
+    }
+
+    async fn wait_module_message(&self, module_id: ModuleId) -> ModuleMessage {
+        let mut inbox_rx = self.module_msg_inbox_tx().subscribe();
+
+        loop {
+            let msg = self
+                .db()
+                .read_with_expect(|ctx| {
+                    Ok(ctx
+                        .open_table(&tables::module_msg_inbox::TABLE)?
+                        .range(&(module_id, 0)..=&(module_id, ModuleMessageId::MAX))?
+                        .next()
+                        .transpose()?
+                        .map(|(k, v)| {
+                            let (sender, payload) = v.value();
+                            ModuleMessage {
+                                id: k.value().1,
+                                sender,
+                                payload,
+                            }
+                        }))
+                })
+                .await;
+
+            if let Some(msg) = msg {
+                return msg;
+            }
+
+            if inbox_rx.changed().await.is_err() {
+                future::pending().await
+            }
+        }
+    }
+
+    async fn ack_module_message(&self, module_id: ModuleId, id: ModuleMessageId) {
+        self.db()
+            .write_with_expect(|ctx| {
+                ctx.open_table(&tables::module_msg_inbox::TABLE)?
+                    .remove(&(module_id, id))?;
+                Ok(())
+            })
+            .await;
+    }
+
+    /// Deliver queued module messages to their recipients
+    #[instrument(
+        name = "module_msg_delivery"
+        target = LOG_TARGET,
+        skip_all,
+    )]
+    pub(crate) async fn run_module_msg_delivery(self: Arc<Self>) {
+        let mut outbox_rx = self.module_msg_outbox_tx().subscribe();
+
+        loop {
+            let recipients = self.get_module_msg_outbox_recipients().await;
+
+            let mut all_delivered = true;
+            for recipient in recipients {
+                if let Err(err) = self.deliver_module_messages(recipient).await {
+                    all_delivered = false;
+                    debug!(
+                        target: LOG_TARGET,
+                        %recipient,
+                        err = %err.fmt_compact(),
+                        "Failed to deliver module messages"
+                    );
+                }
+            }
+
+            if all_delivered {
+                if outbox_rx.changed().await.is_err() {
+                    future::pending().await
+                }
+            } else {
+                let _ = tokio::time::timeout(MODULE_MSG_RETRY_DELAY, outbox_rx.changed()).await;
+            }
+        }
+    }
+
+    async fn get_module_msg_outbox_recipients(&self) -> BTreeSet<PeerPubkey> {
+        self.db()
+            .read_with_expect(|ctx| {
+                ctx.open_table(&tables::module_msg_outbox::TABLE)?
+                    .range(..)?
+                    .map(|kv| {
+                        let (k, _) = kv?;
+                        Ok(k.value().0)
+                    })
+                    .collect()
+            })
+            .await
+    }
+
+    /// Deliver all queued messages for `recipient`, in order
+    async fn deliver_module_messages(&self, recipient: PeerPubkey) -> WhateverResult<()> {
+        let (_, params) = self
+            .consensus_wait()
+            .await
+            .get_current_round_and_params()
+            .await;
+        if params.find_peer_idx(recipient).is_none() {
+            warn!(
+                target: LOG_TARGET,
+                %recipient,
+                "Dropping module messages to a peer no longer in the consensus"
+            );
+            self.remove_module_messages_to(recipient).await;
+            return Ok(());
+        }
+
+        let mut conn = self
+            .connection_pool()
+            .connect(recipient)
+            .await
+            .whatever_context("Failed to connect")?;
+
+        loop {
+            let Some(msg) = self
+                .db()
+                .read_with_expect(|ctx| {
+                    Ok(ctx
+                        .open_table(&tables::module_msg_outbox::TABLE)?
+                        .range(&(recipient, 0)..=&(recipient, u64::MAX))?
+                        .next()
+                        .transpose()?
+                        .map(|(_, v)| v.value()))
+                })
+                .await
+            else {
+                return Ok(());
+            };
+
+            let seq = msg.seq;
+            rpc::push_module_message(&mut conn, msg).await?;
+
+            trace!(target: LOG_TARGET, %recipient, seq, "Delivered module message");
+            self.db()
+                .write_with_expect(|ctx| {
+                    ctx.open_table(&tables::module_msg_outbox::TABLE)?
+                        .remove(&(recipient, seq))?;
+                    Ok(())
+                })
+                .await;
+        }
+    }
+
+    async fn remove_module_messages_to(&self, recipient: PeerPubkey) {
+        self.db()
+            .write_with_expect(|ctx| {
+                ctx.open_table(&tables::module_msg_outbox::TABLE)?
+                    .retain_in(&(recipient, 0)..=&(recipient, u64::MAX), |_, _| false)?;
+                Ok(())
+            })
+            .await;
+    }
+}
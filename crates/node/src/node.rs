@@ -8,21 +8,26 @@ use std::option::Option;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, OnceLock, Weak};
+use std::time::Instant;
 
-use bfte_consensus::consensus::{Consensus, OpenError};
+use bfte_consensus::consensus::{Consensus, OpenError, PruningConfig};
 use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::citem::ForwardedCItem;
 use bfte_consensus_core::citem::transaction::Transaction;
 use bfte_consensus_core::consensus_params::ConsensusParams;
-use bfte_consensus_core::peer::{PeerPubkey, PeerSeckey};
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::signed::Signed;
 use bfte_consensus_core::timestamp::Timestamp;
 use bfte_consensus_core::ver::ConsensusVersion;
 use bfte_db::Database;
 use bfte_db::error::DbError;
 use bfte_derive_secret::{DeriveableSecret, LevelError};
 use bfte_invite::Invite;
-use bfte_node_app_core::RunNodeAppFn;
+use bfte_node_app_core::{RunNodeAppFn, SimulateCItemRequest};
 use bfte_node_shared_modules::{SharedModules, WeakSharedModules};
 use bfte_node_ui::RunUiFn;
+use bfte_signer::DynSigner;
+use bfte_signer::local::LocalSigner;
 use bfte_util_error::fmt::FmtCompact as _;
 use bfte_util_error::{Whatever, WhateverResult};
 use bfte_util_fmt_opt::AsFmtOption as _;
@@ -34,12 +39,14 @@ use n0_future::task::AbortOnDropHandle;
 use rand::Rng as _;
 use rand::distributions::Alphanumeric;
 use snafu::{ResultExt as _, Snafu};
-use tokio::sync::{Mutex, Notify, watch};
+use tokio::sync::{Mutex, Notify, mpsc, watch};
 use tokio::task::JoinSet;
 use tracing::{debug, info, warn};
 
 use crate::join::NodeJoinResult;
-use crate::{LOG_TARGET, connection_pool, derive_secret_ext, handle, rpc_server};
+use crate::{
+    LOG_TARGET, connection_pool, derive_secret_ext, handle, migrations, rpc_server, simulate,
+};
 
 pub struct Node {
     #[allow(dead_code)]
@@ -51,10 +58,14 @@ pub struct Node {
     /// database everything is in
     db: Arc<Database>,
 
-    /// Optional root secret we're running the peer with
+    /// Optional root secret, modules' secrets are derived from
     root_secret: Option<DeriveableSecret>,
-    /// Optional peer pubkey derived from [`root_secret`]
+    /// Optional signer we're running the peer with
+    signer: Option<DynSigner>,
+    /// Optional peer pubkey of [`signer`]
     pub(crate) peer_pubkey: Option<PeerPubkey>,
+    /// Peer pubkey of the loaded secret our peer key can be rotated to
+    pub(crate) next_peer_pubkey: Option<PeerPubkey>,
 
     /// Iroh endpoint
     iroh_endpoint: iroh::Endpoint,
@@ -66,6 +77,8 @@ pub struct Node {
     consensus: OnceLock<Arc<Consensus>>,
     consensus_initialized_rx: watch::Receiver<bool>,
     consensus_initialized_tx: watch::Sender<bool>,
+    /// How much of the finalized consensus data to keep
+    pub(crate) pruning_config: PruningConfig,
 
     /// Connection pool
     connection_pool: ConnectionPool,
@@ -76,6 +89,11 @@ pub struct Node {
 
     pub(crate) pending_transactions_rx: watch::Receiver<Vec<Transaction>>,
 
+    /// Requests to simulate processing of citems, handled by the node app
+    simulate_citem_tx: mpsc::Sender<SimulateCItemRequest>,
+    /// Time of the last simulation request of each peer, for rate limiting
+    simulate_citem_last_requests: std::sync::Mutex<BTreeMap<PeerPubkey, Instant>>,
+
     /// Tasks querying peers for finality votes
     pub(crate) finality_tasks: Mutex<BTreeMap<PeerPubkey, AbortOnDropHandle<()>>>,
     #[allow(dead_code /* only for drop */)]
@@ -91,6 +109,18 @@ pub struct Node {
     /// Set each time a peer address requires refreshing
     peer_addr_needed: Arc<Notify>,
 
+    /// Serializes queueing of outgoing module messages
+    module_msg_send_lock: Mutex<()>,
+    /// Set each time an outgoing module message is queued
+    module_msg_outbox_tx: watch::Sender<()>,
+    /// Set each time a module message is received
+    module_msg_inbox_tx: watch::Sender<()>,
+
+    /// Citems forwarded to us by other peers, to include in our proposals
+    forwarded_citems: std::sync::Mutex<BTreeMap<PeerPubkey, Vec<Signed<ForwardedCItem>>>>,
+    /// Set each time citems are forwarded to us
+    forwarded_citems_tx: watch::Sender<()>,
+
     /// If bootstrapping new consensus, use this version for the module
     ///
     /// It is so specific, because Node can initialize consensus on the UI
@@ -109,6 +139,11 @@ pub enum NodeInitError {
         source: LevelError,
     },
     NoSecret,
+    #[snafu(display("Signer pubkey {signer_pubkey} doesn't match the secret's {secret_pubkey}"))]
+    SignerMismatch {
+        signer_pubkey: PeerPubkey,
+        secret_pubkey: PeerPubkey,
+    },
     IrohEndpoint {
         source: anyhow::Error,
     },
@@ -132,7 +167,9 @@ impl Node {
     pub async fn open_db(db_path: Option<PathBuf>) -> NodeInitResult<Database> {
         let db = if let Some(db_path) = db_path {
             info!(target: LOG_TARGET, path = %db_path.display(), "Opening redb database…");
-            Database::open(db_path).await.context(DbSnafu)?
+            Database::open(db_path, migrations::DB_MIGRATIONS)
+                .await
+                .context(DbSnafu)?
         } else {
             warn!(target: LOG_TARGET, "Using ephemeral in-memory database!");
             Database::new_in_memory().await.context(DbSnafu)?
@@ -146,16 +183,43 @@ impl Node {
     #[builder]
     pub async fn new(
         root_secret: Option<DeriveableSecret>,
+        /// Signer to use instead of the one derived from `root_secret`
+        signer: Option<DynSigner>,
+        /// Secret of the peer key to rotate ours to
+        ///
+        /// Rotating our peer key is refused without it, so the node can be
+        /// restarted with it once the rotation takes effect.
+        next_root_secret: Option<DeriveableSecret>,
         db: Arc<Database>,
         consensus_ctrl_module_init_consensus_version: ConsensusVersion,
         ui: Option<RunUiFn>,
         app: Option<RunNodeAppFn>,
         force_ui_password: Option<String>,
+        #[builder(default)] pruning_config: PruningConfig,
     ) -> NodeInitResult<Arc<Self>> {
-        let peer_pubkey = if let Some(root_secret) = root_secret {
-            Some(root_secret.get_peer_seckey()?.pubkey())
-        } else {
-            None
+        let signer = match (signer, root_secret) {
+            (Some(signer), Some(root_secret)) => {
+                let secret_pubkey = root_secret.get_peer_seckey()?.pubkey();
+                if signer.pubkey() != secret_pubkey {
+                    return SignerMismatchSnafu {
+                        signer_pubkey: signer.pubkey(),
+                        secret_pubkey,
+                    }
+                    .fail();
+                }
+                Some(signer)
+            }
+            (Some(signer), None) => Some(signer),
+            (None, Some(root_secret)) => Some(Arc::new(LocalSigner::new(
+                root_secret.get_peer_seckey()?,
+                db.clone(),
+            )) as DynSigner),
+            (None, None) => None,
+        };
+        let peer_pubkey = signer.as_ref().map(|signer| signer.pubkey());
+        let next_peer_pubkey = match next_root_secret {
+            Some(next_root_secret) => Some(next_root_secret.get_peer_seckey()?.pubkey()),
+            None => None,
         };
         let iroh_endpoint = Self::make_iroh_endpoint(if let Some(root_secret) = root_secret {
             Some(root_secret.get_iroh_secret()?)
@@ -199,6 +263,8 @@ impl Node {
                 watch::channel(consensus.is_some());
             let (pending_transactions_tx, pending_transactions_rx) = watch::channel(vec![]);
             let (node_app_ack_tx, node_app_ack_rx) = watch::channel(BlockRound::ZERO);
+            let (simulate_citem_tx, simulate_citem_rx) =
+                mpsc::channel(simulate::SIMULATE_CITEM_QUEUE_LEN);
 
             let app_task = app.map(|app| {
                 Self::spawn_app_task(
@@ -207,6 +273,7 @@ impl Node {
                     app,
                     shared_modules,
                     pending_transactions_tx,
+                    simulate_citem_rx,
                 )
             });
             let node = Node {
@@ -214,13 +281,16 @@ impl Node {
                 handle_raw: weak.clone(),
                 iroh_router,
                 peer_pubkey,
+                next_peer_pubkey,
                 db: db.clone(),
                 connection_pool: ConnectionPool::new(handle, db, iroh_endpoint.clone()),
                 root_secret,
+                signer,
                 iroh_endpoint,
                 consensus_initialized_tx,
                 consensus_initialized_rx,
                 consensus: OnceLock::new(),
+                pruning_config,
                 finality_tasks: Mutex::new(BTreeMap::default()),
                 ui_task,
                 app_task,
@@ -228,9 +298,16 @@ impl Node {
                 ui_pass_hash: std::sync::Mutex::new(ui_pass_hash),
                 ui_pass_is_temporary: AtomicBool::new(ui_pass_is_temporary),
                 peer_addr_needed: Arc::new(Notify::new()),
+                module_msg_send_lock: Mutex::new(()),
+                module_msg_outbox_tx: watch::Sender::new(()),
+                module_msg_inbox_tx: watch::Sender::new(()),
+                forwarded_citems: std::sync::Mutex::new(BTreeMap::default()),
+                forwarded_citems_tx: watch::Sender::new(()),
                 node_app_ack_rx,
                 node_app_ack_tx,
                 pending_transactions_rx,
+                simulate_citem_tx,
+                simulate_citem_last_requests: std::sync::Mutex::new(BTreeMap::default()),
                 consensus_ctrl_module_init_consensus_version,
             };
 
@@ -258,12 +335,10 @@ impl Node {
 impl Node {
     pub async fn consensus_init_static(
         db: Arc<Database>,
-        root_secret: DeriveableSecret,
+        pubkey: PeerPubkey,
         extra_peers: Vec<PeerPubkey>,
         init_core_module_cons_version: ConsensusVersion,
     ) -> NodeInitResult<Consensus> {
-        let pubkey = root_secret.get_peer_seckey()?.pubkey();
-
         let params = ConsensusParams {
             prev_mid_block: None,
             peers: [vec![pubkey], extra_peers].concat().into(),
@@ -272,6 +347,9 @@ impl Node {
             timestamp: Timestamp::now(),
             schedule_round: 0.into(),
             apply_round: 0.into(),
+            bls_pubkeys: vec![],
+            peer_weights: vec![],
+            max_block_payload_len: ConsensusParams::DEFAULT_MAX_BLOCK_PAYLOAD_LEN,
         };
 
         Ok(Consensus::init(&params, db, Some(pubkey), None).await?)
@@ -319,12 +397,12 @@ impl Node {
     }
 
     pub async fn consensus_init(&self, extra_peers: Vec<PeerPubkey>) -> NodeInitResult<()> {
-        let Some(root_secret) = self.root_secret() else {
+        let Some(peer_pubkey) = self.peer_pubkey else {
             return NoSecretSnafu.fail();
         };
         let consensus = Self::consensus_init_static(
             self.db().clone(),
-            root_secret,
+            peer_pubkey,
             extra_peers,
             self.consensus_ctrl_module_init_consensus_version,
         )
@@ -350,11 +428,10 @@ impl Node {
             .spawn()
     }
 
-    pub(crate) fn get_peer_secret_expect(&self) -> PeerSeckey {
-        self.root_secret
-            .expect("Must contain root secret to participate")
-            .get_peer_seckey()
-            .expect("Level verified by now")
+    pub(crate) fn signer_expect(&self) -> &DynSigner {
+        self.signer
+            .as_ref()
+            .expect("Must contain signer to participate")
     }
 
     pub(crate) fn consensus(&self) -> Option<&Arc<Consensus>> {
@@ -398,6 +475,12 @@ impl Node {
         tasks.spawn(self.clone().run_consensus());
         tasks.spawn(self.clone().run_push_gossip());
         tasks.spawn(self.clone().run_pull_gossip());
+        tasks.spawn(self.clone().run_equivocation_sync());
+        tasks.spawn(self.clone().run_block_sync());
+        tasks.spawn(self.clone().run_state_snapshot_attestation_sync());
+        tasks.spawn(self.clone().run_module_msg_delivery());
+        tasks.spawn(self.clone().run_citem_forwarding());
+        tasks.spawn(self.clone().run_pruning());
 
         tasks
             .join_next()
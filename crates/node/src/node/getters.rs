@@ -1,7 +1,14 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 
+use bfte_consensus_core::citem::ForwardedCItem;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::signed::Signed;
 use bfte_db::Database;
+use bfte_derive_secret::DeriveableSecret;
+use bfte_node_app_core::SimulateCItemRequest;
+use bfte_signer::DynSigner;
 
 use super::Node;
 use crate::connection_pool::ConnectionPool;
@@ -31,7 +38,43 @@ impl Node {
         &self.peer_addr_needed
     }
 
-    pub(crate) fn root_secret(&self) -> Option<bfte_derive_secret::DeriveableSecret> {
+    pub(crate) fn module_msg_send_lock(&self) -> &tokio::sync::Mutex<()> {
+        &self.module_msg_send_lock
+    }
+
+    pub(crate) fn module_msg_outbox_tx(&self) -> &tokio::sync::watch::Sender<()> {
+        &self.module_msg_outbox_tx
+    }
+
+    pub(crate) fn module_msg_inbox_tx(&self) -> &tokio::sync::watch::Sender<()> {
+        &self.module_msg_inbox_tx
+    }
+
+    pub(crate) fn forwarded_citems(
+        &self,
+    ) -> &std::sync::Mutex<BTreeMap<PeerPubkey, Vec<Signed<ForwardedCItem>>>> {
+        &self.forwarded_citems
+    }
+
+    pub(crate) fn forwarded_citems_tx(&self) -> &tokio::sync::watch::Sender<()> {
+        &self.forwarded_citems_tx
+    }
+
+    pub(crate) fn simulate_citem_tx(&self) -> &tokio::sync::mpsc::Sender<SimulateCItemRequest> {
+        &self.simulate_citem_tx
+    }
+
+    pub(crate) fn simulate_citem_last_requests(
+        &self,
+    ) -> &std::sync::Mutex<BTreeMap<PeerPubkey, std::time::Instant>> {
+        &self.simulate_citem_last_requests
+    }
+
+    pub(crate) fn root_secret(&self) -> Option<DeriveableSecret> {
         self.root_secret
     }
+
+    pub(crate) fn signer(&self) -> Option<&DynSigner> {
+        self.signer.as_ref()
+    }
 }
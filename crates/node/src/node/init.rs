@@ -1,5 +1,6 @@
 use bfte_db::ctx::WriteTransactionCtx;
 use bfte_db::error::DbResult;
+use bfte_node_app_core::processed_citems;
 
 use super::Node;
 use crate::tables;
@@ -7,6 +8,11 @@ use crate::tables;
 impl Node {
     pub(super) fn init_tables_tx(tx: &WriteTransactionCtx) -> DbResult<()> {
         tx.open_table(&tables::ui_pass_hash::TABLE)?;
+        tx.open_table(&tables::state_snapshots::TABLE)?;
+        tx.open_table(&tables::state_snapshot_attestations::TABLE)?;
+        tx.open_table(&tables::state_snapshot_restored::TABLE)?;
+        // Read when proposing blocks, possibly before node-app starts
+        processed_citems::init_tables_dbtx(tx)?;
         Ok(())
     }
 }
@@ -9,6 +9,7 @@ use bfte_consensus_core::signed::{Hashable, Signable, Signed};
 use bfte_consensus_core::timestamp::Timestamp;
 use bfte_db::Database;
 use bfte_node_core::address::PeerAddress;
+use bfte_signer::SignerExt as _;
 use bfte_util_bincode::decode_whole;
 use bfte_util_db::def_table;
 use bfte_util_db::random::get_random;
@@ -301,18 +302,17 @@ impl Node {
     }
 
     async fn get_own_address_update(&self) -> WhateverResult<Option<Signed<AddressUpdate>>> {
-        if self.root_secret().is_none() {
+        let Some(signer) = self.signer() else {
             return Ok(None);
-        }
-        let seckey = self.get_peer_secret_expect();
-        let update = Signed::new_sign(
-            AddressUpdate {
+        };
+        let update = signer
+            .sign(AddressUpdate {
                 timestamp: Timestamp::now(),
-                peer_pubkey: seckey.pubkey(),
+                peer_pubkey: signer.pubkey(),
                 addr: PeerAddress::Iroh(self.iroh_endpoint().node_id().into()),
-            },
-            seckey,
-        );
+            })
+            .await
+            .whatever_context("Failed to sign address update")?;
         Ok(Some(update))
     }
 
@@ -360,7 +360,7 @@ impl Node {
         }
     }
 
-    pub(crate) async fn get_peer_addr(
+    pub async fn get_peer_addr(
         db: &Database,
         peer_pubkey: PeerPubkey,
     ) -> WhateverResult<Option<Signed<AddressUpdate>>> {
@@ -383,6 +383,44 @@ impl Node {
             .await)
     }
 
+    /// Follow a peer's key rotation in the address gossip
+    ///
+    /// Address of the old key is dropped, and address of the new key is
+    /// requested from other peers.
+    pub(crate) async fn handle_peer_key_rotation(
+        &self,
+        old_pubkey: PeerPubkey,
+        new_pubkey: PeerPubkey,
+    ) -> WhateverResult<()> {
+        if Some(old_pubkey) == self.peer_pubkey {
+            // Rotating our key requires its secret loaded, see `next_root_secret`
+            warn!(
+                target: LOG_TARGET,
+                %old_pubkey,
+                %new_pubkey,
+                "Our peer key was rotated; restart the node with the next secret as the secret"
+            );
+        }
+
+        self.db()
+            .write_with_expect(|ctx| {
+                ctx.open_table(&peer_addresses::TABLE)?
+                    .remove(&old_pubkey)?;
+                ctx.open_table(&peer_addresses_we_need::TABLE)?
+                    .remove(&old_pubkey)?;
+
+                let mut tbl_tracking = ctx.open_table(&peer_addresses_we_track::TABLE)?;
+                if tbl_tracking.remove(&old_pubkey)?.is_some() {
+                    tbl_tracking.insert(&new_pubkey, &())?;
+                }
+
+                Ok(())
+            })
+            .await;
+
+        self.mark_peer_addr_as_needed(new_pubkey).await
+    }
+
     pub(crate) async fn mark_peer_addr_as_needed(
         &self,
         peer_pubkey: PeerPubkey,
@@ -410,3 +448,6 @@ impl Node {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,48 @@
+use bfte_consensus_core::peer::PeerSeckey;
+use bfte_consensus_core::signed::{Signable as _, Signed};
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_db::Database;
+use bfte_node_core::address::{IrohAddress, PeerAddress};
+use bfte_util_error::WhateverResult;
+use snafu::ResultExt as _;
+
+use super::AddressUpdate;
+use crate::Node;
+
+fn update(seckey: PeerSeckey) -> AddressUpdate {
+    AddressUpdate {
+        timestamp: Timestamp::now(),
+        peer_pubkey: seckey.pubkey(),
+        addr: PeerAddress::Iroh(IrohAddress::default()),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_address_updates_are_rejected() -> WhateverResult<()> {
+    let db = Database::new_in_memory()
+        .await
+        .whatever_context("Failed to create database")?;
+    let victim_seckey = PeerSeckey::generate();
+    let adversary_seckey = PeerSeckey::generate();
+
+    let honest_update = Signed::new_sign(update(victim_seckey), victim_seckey);
+    Node::handle_address_update(&db, honest_update.clone()).await?;
+
+    let own = update(adversary_seckey);
+    let hijacked = update(victim_seckey);
+    for update in [
+        // Claims to be the victim, but signed with own key
+        Signed::new_sign(hijacked.clone(), adversary_seckey),
+        // Reuses own (valid) signature for a different message
+        Signed::new(hijacked, own.sign_with(adversary_seckey)),
+    ] {
+        assert!(Node::handle_address_update(&db, update).await.is_err());
+    }
+
+    let stored = Node::get_peer_addr(&db, victim_seckey.pubkey())
+        .await?
+        .expect("Honest update must be stored");
+    assert_eq!(stored.sig, honest_update.sig);
+
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use std::future;
+use std::sync::Arc;
+
+use tracing::instrument;
+
+use crate::Node;
+
+const LOG_TARGET: &str = "bfte::node::prune";
+
+impl Node {
+    /// Prune finalized consensus data according to the node's
+    /// [`bfte_consensus::consensus::PruningConfig`]
+    ///
+    /// Payloads are only pruned after the node app acknowledged processing
+    /// them.
+    #[instrument(
+        name = "prune"
+        target = LOG_TARGET,
+        skip_all,
+    )]
+    pub(crate) async fn run_pruning(self: Arc<Self>) {
+        self.consensus_expect()
+            .run_pruning(self.pruning_config, self.node_app_ack_rx.clone())
+            .await;
+
+        // If we're shutting down, just sleep and get dropped
+        future::pending().await
+    }
+}
@@ -1,17 +1,28 @@
-use bfte_consensus_core::block::{BlockHash, BlockHeader, BlockRound};
+use bfte_consensus::consensus::StorageMode;
+use bfte_consensus_core::block::{BlockHeader, BlockPayloadRaw, BlockRound};
+use bfte_consensus_core::citem::{CItem, ForwardedCItem};
 use bfte_consensus_core::consensus_params::{
-    ConsensusParamsHash, ConsensusParamsLen, ConsensusParamsRaw,
+    ConsensusParams, ConsensusParamsHash, ConsensusParamsLen, ConsensusParamsRaw,
+};
+use bfte_consensus_core::equivocation::EquivocationEvidence;
+use bfte_consensus_core::msg::{
+    WaitFinalityVoteRequest, WaitFinalityVoteResponse, WaitNotarizedBlockRequest,
+    WaitNotarizedBlockResponse, WaitNotarizedBlockResponseNoBls, WaitVoteRequest, WaitVoteResponse,
+    WaitVoteResponseNoBls,
 };
-use bfte_consensus_core::msg::{WaitFinalityVoteRequest, WaitFinalityVoteResponse};
 use bfte_consensus_core::peer::PeerPubkey;
-use bfte_consensus_core::signed::{Notarized, Signed};
+use bfte_consensus_core::proof::CItemProof;
+use bfte_consensus_core::signed::{Hashable, Notarized, NotarizedNoBls, Signable, Signed};
+use bfte_consensus_core::snapshot::{StateSnapshotHeader, StateSnapshotRaw};
 use bfte_consensus_core::ver::ConsensusVersion;
+use bfte_module::module::CItemSimulation;
 use bfte_util_error::WhateverResult;
 use bincode::{Decode, Encode};
 use iroh_dpc_rpc::RpcExt as _;
 use iroh_dpc_rpc::bincode::RpcExtBincode as _;
 use snafu::{ResultExt as _, whatever};
 
+use crate::module_net::ModuleMessageEnvelope;
 use crate::peer_address::AddressUpdate;
 
 pub const RPC_ID_HELLO: u16 = 0x00;
@@ -20,12 +31,46 @@ pub const RPC_ID_HELLO: u16 = 0x00;
 pub const RPC_ID_WAIT_VOTE: u16 = 0x11;
 pub const RPC_ID_WAIT_NOTARIZED_BLOCK: u16 = 0x12;
 pub const RPC_ID_WAIT_FINALITY_VOTE: u16 = 0x13;
+pub const RPC_ID_FORWARD_CITEMS: u16 = 0x14;
+/// [`RPC_ID_WAIT_VOTE`] with BLS signatures, used if
+/// [`ConsensusParams::uses_bls`]
+pub const RPC_ID_WAIT_VOTE_BLS: u16 = 0x15;
+/// [`RPC_ID_WAIT_NOTARIZED_BLOCK`] with BLS aggregates, used if
+/// [`ConsensusParams::uses_bls`]
+pub const RPC_ID_WAIT_NOTARIZED_BLOCK_BLS: u16 = 0x16;
 
 // Other
 pub const RPC_ID_PUSH_PEER_ADDR_UPDATE: u16 = 0x20;
 pub const RPC_ID_GET_PEER_ADDR_UPDATE: u16 = 0x21;
 pub const RPC_ID_GET_BLOCK: u16 = 0x23;
 pub const RPC_ID_GET_CONSENSUS_PARAMS: u16 = 0x24;
+pub const RPC_ID_GET_EQUIVOCATIONS: u16 = 0x25;
+pub const RPC_ID_GET_BLOCK_RANGE: u16 = 0x26;
+pub const RPC_ID_GET_STATE_SNAPSHOT_ATTESTATIONS: u16 = 0x27;
+pub const RPC_ID_GET_STATE_SNAPSHOT: u16 = 0x28;
+pub const RPC_ID_GET_CITEM_PROOF: u16 = 0x29;
+pub const RPC_ID_SIMULATE_CITEM: u16 = 0x2a;
+pub const RPC_ID_GET_STORAGE_INFO: u16 = 0x2b;
+/// [`RPC_ID_GET_BLOCK`] with BLS aggregates
+pub const RPC_ID_GET_BLOCK_BLS: u16 = 0x2c;
+
+// Modules
+pub const RPC_ID_PUSH_MODULE_MESSAGE: u16 = 0x30;
+
+/// Maximum number of blocks returned in a single [`GetBlockRangeRequest`]
+pub const BLOCK_RANGE_MAX_BLOCKS: usize = 256;
+
+/// Get what the peer stores, and what it pruned
+#[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
+pub struct GetStorageInfoRequest;
+
+#[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
+pub struct GetStorageInfoResponse {
+    pub storage_mode: StorageMode,
+    /// Payloads of blocks in rounds before this one were pruned, and can't be
+    /// fetched from the peer
+    pub payloads_pruned_before: BlockRound,
+}
 
 /// Wait for the peer vote on the block in the round
 #[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
@@ -38,6 +83,12 @@ pub struct GetBlockResponse {
     pub block: Notarized<BlockHeader>,
 }
 
+/// [`GetBlockResponse`] of [`RPC_ID_GET_BLOCK`], without BLS aggregates
+#[derive(Decode, Encode, Clone)]
+pub struct GetBlockResponseNoBls {
+    pub block: NotarizedNoBls<BlockHeader>,
+}
+
 /// Wait for the peer vote on the block in the round
 #[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
 pub struct GetConsensusVersionRequest {
@@ -67,38 +118,195 @@ pub struct GetPeerAddressResponse {
     pub update: Option<Signed<AddressUpdate>>,
 }
 
+/// Get all the equivocation evidence the peer has recorded
+#[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
+pub struct GetEquivocationsRequest;
+
+#[derive(Decode, Encode, Clone)]
+pub struct GetEquivocationsResponse {
+    pub evidence: Vec<EquivocationEvidence>,
+}
+
+/// Get finalized blocks along with their payloads in `round_start..round_end`
+///
+/// The response is a stream of [`GetBlockRangeResponseItem`]s. Every
+/// [`GetBlockRangeResponseItem::Block`] is followed by a BAO-encoded payload
+/// of the block, and the stream is terminated with
+/// [`GetBlockRangeResponseItem::End`].
+#[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
+pub struct GetBlockRangeRequest {
+    pub round_start: BlockRound,
+    pub round_end: BlockRound,
+}
+
+#[derive(Decode, Encode, Clone)]
+pub enum GetBlockRangeResponseItem {
+    Block(Notarized<BlockHeader>),
+    /// No more blocks
+    ///
+    /// All rounds before `round_end` were covered by the response, which
+    /// might be less than requested, if the responding peer did not
+    /// finalize them yet, or [`BLOCK_RANGE_MAX_BLOCKS`] was reached.
+    End {
+        round_end: BlockRound,
+    },
+}
+
+/// Get attestations of the latest state snapshot the peer has, at or before
+/// `max_round`
+#[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
+pub struct GetStateSnapshotAttestationsRequest {
+    pub max_round: BlockRound,
+}
+
+#[derive(Decode, Encode, Clone)]
+pub struct GetStateSnapshotAttestationsResponse {
+    pub attestations: Vec<(PeerPubkey, Signed<StateSnapshotHeader>)>,
+}
+
+/// Get the state snapshot taken at `round`
+///
+/// The response is a BAO-encoded [`StateSnapshotRaw`].
+#[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
+pub struct GetStateSnapshotRequest {
+    pub round: BlockRound,
+}
+
+/// Get a [`CItemProof`] of `citem_idx`-th citem in a block at `round`,
+/// starting from params used at `trusted_round`
+#[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
+pub struct GetCItemProofRequest {
+    pub trusted_round: BlockRound,
+    pub round: BlockRound,
+    pub citem_idx: u32,
+}
+
+#[derive(Decode, Encode, Clone)]
+pub struct GetCItemProofResponse {
+    pub proof: Option<CItemProof>,
+}
+
+/// Simulate processing of a citem against the current state of the node, as
+/// if `peer_pubkey` included it in the next block
+///
+/// Served only to consensus peers, for their own citems, so sent signed by
+/// `peer_pubkey`.
+#[derive(Decode, Encode, Clone)]
+pub struct SimulateCItemRequest {
+    pub citem: CItem,
+    pub peer_pubkey: PeerPubkey,
+}
+
+impl Hashable for SimulateCItemRequest {}
+
+impl Signable for SimulateCItemRequest {
+    const TAG: [u8; 4] = *b"simc";
+}
+
+#[derive(Decode, Encode, Clone)]
+pub struct SimulateCItemResponse {
+    pub simulation: CItemSimulation,
+}
+
+/// Forward citems of a non-leader peer to a round leader
+#[derive(Decode, Encode, Clone)]
+pub struct ForwardCItemsRequest {
+    pub citems: Vec<Signed<ForwardedCItem>>,
+}
+
+/// The citems were accepted for inclusion in the leader's proposals
+#[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
+pub struct ForwardCItemsResponse;
+
+/// Push a private module message to its recipient
+#[derive(Decode, Encode, Clone)]
+pub struct PushModuleMessageRequest {
+    pub msg: Signed<ModuleMessageEnvelope>,
+}
+
+/// The message was stored by the recipient (or had been already)
+#[derive(Decode, Encode, Clone, Copy, PartialEq, Eq)]
+pub struct PushModuleMessageResponse;
+
+/// Response to [`get_block_range`]
+pub(crate) struct BlockRange {
+    pub blocks: Vec<(Notarized<BlockHeader>, BlockPayloadRaw)>,
+    /// All rounds before this one were covered
+    pub round_end: BlockRound,
+}
+
+/// Peers predating pruning don't support it, and are always archival
+pub(crate) async fn get_storage_info(
+    conn: &mut iroh::endpoint::Connection,
+) -> WhateverResult<GetStorageInfoResponse> {
+    conn.make_request_response_bincode(RPC_ID_GET_STORAGE_INFO, GetStorageInfoRequest)
+        .await
+        .whatever_context("Failed request get_storage_info")
+}
+
+/// Get the latest non-dummy finalized block at or before `round`
+///
+/// The params of the block are not known yet, so falls back to
+/// [`RPC_ID_GET_BLOCK`] for peers predating BLS signatures.
+///
+/// The response is not verified in any way.
 pub(crate) async fn get_block(
     conn: &mut iroh::endpoint::Connection,
     round: BlockRound,
 ) -> WhateverResult<Notarized<BlockHeader>> {
-    let resp: Notarized<BlockHeader> = conn
+    if let Ok(block) = conn
+        .make_request_response_bincode(RPC_ID_GET_BLOCK_BLS, GetBlockRequest { round })
+        .await
+    {
+        return Ok(block);
+    }
+    let block: NotarizedNoBls<BlockHeader> = conn
         .make_request_response_bincode(RPC_ID_GET_BLOCK, GetBlockRequest { round })
         .await
         .whatever_context("Failed request get_block")?;
+    Ok(block.into())
+}
 
-    if resp.round != round {
-        whatever!(
-            "Mismatched round block from peer: {} != {}",
-            resp.round,
-            round
-        );
+/// Wait for the peer's vote, see [`WaitVoteRequest`]
+///
+/// `uses_bls` must be [`ConsensusParams::uses_bls`] of the round.
+pub(crate) async fn wait_vote(
+    conn: &mut iroh::endpoint::Connection,
+    req: WaitVoteRequest,
+    uses_bls: bool,
+) -> WhateverResult<WaitVoteResponse> {
+    if uses_bls {
+        return conn
+            .make_request_response_bincode(RPC_ID_WAIT_VOTE_BLS, req)
+            .await
+            .whatever_context("Failed wait vote request");
     }
-
-    Ok(resp)
+    let resp: WaitVoteResponseNoBls = conn
+        .make_request_response_bincode(RPC_ID_WAIT_VOTE, req)
+        .await
+        .whatever_context("Failed wait vote request")?;
+    Ok(resp.into())
 }
 
-pub(crate) async fn get_block_hashed(
+/// Wait for the peer's notarized block, see [`WaitNotarizedBlockRequest`]
+///
+/// `uses_bls` must be [`ConsensusParams::uses_bls`] of the current round.
+pub(crate) async fn wait_notarized_block(
     conn: &mut iroh::endpoint::Connection,
-    round: BlockRound,
-    block_hash: BlockHash,
-) -> WhateverResult<Notarized<BlockHeader>> {
-    let resp = get_block(conn, round).await?;
-
-    if resp.hash() != block_hash {
-        whatever!("Mismatched hash block from peer");
+    req: WaitNotarizedBlockRequest,
+    uses_bls: bool,
+) -> WhateverResult<WaitNotarizedBlockResponse> {
+    if uses_bls {
+        return conn
+            .make_request_response_bincode(RPC_ID_WAIT_NOTARIZED_BLOCK_BLS, req)
+            .await
+            .whatever_context("Notarized block rpc failed");
     }
-
-    Ok(resp)
+    let resp: WaitNotarizedBlockResponseNoBls = conn
+        .make_request_response_bincode(RPC_ID_WAIT_NOTARIZED_BLOCK, req)
+        .await
+        .whatever_context("Notarized block rpc failed")?;
+    Ok(resp.into())
 }
 
 pub(crate) async fn get_consensus_params(
@@ -171,3 +379,226 @@ pub(crate) async fn wait_finality_vote(
 
     Ok(resp)
 }
+
+pub(crate) async fn get_equivocations(
+    conn: &mut iroh::endpoint::Connection,
+) -> WhateverResult<Vec<EquivocationEvidence>> {
+    let resp: GetEquivocationsResponse = conn
+        .make_request_response_bincode(RPC_ID_GET_EQUIVOCATIONS, GetEquivocationsRequest)
+        .await
+        .whatever_context("Failed request get_equivocations")?;
+
+    for evidence in &resp.evidence {
+        evidence
+            .verify()
+            .whatever_context("Invalid equivocation evidence")?;
+    }
+
+    Ok(resp.evidence)
+}
+
+/// Check a block header of a [`GetBlockRangeResponseItem::Block`] before
+/// fetching its payload
+fn verify_block_range_header(
+    block: &Notarized<BlockHeader>,
+    params: &ConsensusParams,
+) -> WhateverResult<()> {
+    block
+        .verify_sigs(params)
+        .whatever_context("Invalid block notarization")?;
+
+    let payload_len = u32::from(block.payload_len);
+    if params.max_block_payload_len < payload_len {
+        whatever!("Payload too large: {payload_len}");
+    }
+
+    Ok(())
+}
+
+/// Fetch finalized blocks with payloads in `round_start..round_end`
+///
+/// Every header is verified to be notarized under `params` before its
+/// payload is fetched, and the payload is then BAO-verified against it
+/// during streaming. Blocks are also checked to be in order and in the range.
+///
+/// Blocks using different params than `params` can't be verified until the
+/// blocks before them were processed, so the range ends before the first of
+/// them.
+pub(crate) async fn get_block_range(
+    conn: &mut iroh::endpoint::Connection,
+    round_start: BlockRound,
+    round_end: BlockRound,
+    params: ConsensusParams,
+) -> WhateverResult<BlockRange> {
+    let params_hash = params.hash();
+    let (blocks, resp_round_end) = conn
+        .make_rpc_raw(RPC_ID_GET_BLOCK_RANGE, move |mut w, mut r| async move {
+            w.write_message_bincode(&GetBlockRangeRequest {
+                round_start,
+                round_end,
+            })
+            .await?;
+
+            let mut blocks = vec![];
+            // One extra iteration for the terminating item
+            for _ in 0..=BLOCK_RANGE_MAX_BLOCKS {
+                match r
+                    .read_message_bincode::<GetBlockRangeResponseItem>()
+                    .await?
+                {
+                    GetBlockRangeResponseItem::Block(block) => {
+                        if block.consensus_params_hash != params_hash {
+                            return Ok((blocks, Ok(Some(block.round))));
+                        }
+                        if let Err(err) = verify_block_range_header(&block, &params) {
+                            return Ok((blocks, Err(err)));
+                        }
+                        let payload = r
+                            .read_message_bao(
+                                block.payload_len.into(),
+                                block.payload_hash.to_bytes().into(),
+                            )
+                            .await?;
+                        blocks.push((block, BlockPayloadRaw::from(payload)));
+                    }
+                    GetBlockRangeResponseItem::End { round_end } => {
+                        return Ok((blocks, Ok(Some(round_end))));
+                    }
+                }
+            }
+            Ok((blocks, Ok(None)))
+        })
+        .await
+        .whatever_context("Failed request get_block_range")?;
+
+    let Some(resp_round_end) = resp_round_end? else {
+        whatever!("Too many blocks in the response");
+    };
+
+    if resp_round_end < round_start || round_end < resp_round_end {
+        whatever!("Invalid round_end in the response: {resp_round_end}");
+    }
+
+    let mut prev_round = None;
+    for (block, _) in &blocks {
+        if block.round < round_start || resp_round_end <= block.round {
+            whatever!("Block outside of the range: {}", block.round);
+        }
+        if prev_round.is_some_and(|prev| block.round <= prev) {
+            whatever!("Blocks out of order: {}", block.round);
+        }
+        if block.is_dummy() {
+            whatever!("Dummy block in the response: {}", block.round);
+        }
+        prev_round = Some(block.round);
+    }
+
+    Ok(BlockRange {
+        blocks,
+        round_end: resp_round_end,
+    })
+}
+
+/// Get attestations of the latest state snapshot at or before `max_round`
+///
+/// All attestations are verified to be signed by the peers they claim to be
+/// from, and to be for the same snapshot. Whether these peers are to be
+/// trusted is up to the caller.
+pub(crate) async fn get_state_snapshot_attestations(
+    conn: &mut iroh::endpoint::Connection,
+    max_round: BlockRound,
+) -> WhateverResult<Vec<(PeerPubkey, Signed<StateSnapshotHeader>)>> {
+    let resp: GetStateSnapshotAttestationsResponse = conn
+        .make_request_response_bincode(
+            RPC_ID_GET_STATE_SNAPSHOT_ATTESTATIONS,
+            GetStateSnapshotAttestationsRequest { max_round },
+        )
+        .await
+        .whatever_context("Failed request get_state_snapshot_attestations")?;
+
+    for (peer_pubkey, attestation) in &resp.attestations {
+        if max_round < attestation.round {
+            whatever!("Attestation past max_round: {}", attestation.round);
+        }
+        if resp
+            .attestations
+            .first()
+            .is_some_and(|(_, first)| first.inner != attestation.inner)
+        {
+            whatever!("Attestations for different snapshots");
+        }
+        attestation
+            .verify_sig_peer_pubkey(*peer_pubkey)
+            .whatever_context("Invalid state snapshot attestation signature")?;
+    }
+
+    Ok(resp.attestations)
+}
+
+pub(crate) async fn get_state_snapshot(
+    conn: &mut iroh::endpoint::Connection,
+    header: StateSnapshotHeader,
+) -> WhateverResult<StateSnapshotRaw> {
+    conn.make_rpc_raw(RPC_ID_GET_STATE_SNAPSHOT, move |mut w, mut r| async move {
+        w.write_message_bincode(&GetStateSnapshotRequest {
+            round: header.round,
+        })
+        .await?;
+        let resp = r
+            .read_message_bao(header.len.into(), header.hash.to_bytes().into())
+            .await?;
+
+        Ok(StateSnapshotRaw::from(resp))
+    })
+    .await
+    .whatever_context("Failed request get_state_snapshot")
+}
+
+/// Get a proof of a citem inclusion
+///
+/// The proof is not verified here, as only the caller knows the trusted
+/// params to verify it against.
+pub(crate) async fn get_citem_proof(
+    conn: &mut iroh::endpoint::Connection,
+    trusted_round: BlockRound,
+    round: BlockRound,
+    citem_idx: u32,
+) -> WhateverResult<Option<CItemProof>> {
+    let resp: GetCItemProofResponse = conn
+        .make_request_response_bincode(
+            RPC_ID_GET_CITEM_PROOF,
+            GetCItemProofRequest {
+                trusted_round,
+                round,
+                citem_idx,
+            },
+        )
+        .await
+        .whatever_context("Failed request get_citem_proof")?;
+
+    Ok(resp.proof)
+}
+
+pub(crate) async fn push_module_message(
+    conn: &mut iroh::endpoint::Connection,
+    msg: Signed<ModuleMessageEnvelope>,
+) -> WhateverResult<()> {
+    let _: PushModuleMessageResponse = conn
+        .make_request_response_bincode(RPC_ID_PUSH_MODULE_MESSAGE, PushModuleMessageRequest { msg })
+        .await
+        .whatever_context("Failed request push_module_message")?;
+
+    Ok(())
+}
+
+pub(crate) async fn forward_citems(
+    conn: &mut iroh::endpoint::Connection,
+    citems: Vec<Signed<ForwardedCItem>>,
+) -> WhateverResult<()> {
+    let _: ForwardCItemsResponse = conn
+        .make_request_response_bincode(RPC_ID_FORWARD_CITEMS, ForwardCItemsRequest { citems })
+        .await
+        .whatever_context("Failed request forward_citems")?;
+
+    Ok(())
+}
@@ -1,6 +1,8 @@
+use bfte_consensus_core::block::BlockRound;
 use bfte_consensus_core::msg::{
     FinalityVoteUpdate, WaitFinalityVoteRequest, WaitFinalityVoteResponse,
-    WaitNotarizedBlockRequest, WaitNotarizedBlockResponse, WaitVoteRequest, WaitVoteResponse,
+    WaitNotarizedBlockRequest, WaitNotarizedBlockResponse, WaitNotarizedBlockResponseNoBls,
+    WaitVoteRequest, WaitVoteResponse, WaitVoteResponseNoBls,
 };
 use bfte_consensus_core::signed::Signed;
 use bfte_util_error::WhateverResult;
@@ -13,10 +15,20 @@ use crate::Node;
 use crate::handle::{NodeHandle, NodeRefResultExt as _};
 use crate::peer_address::AddressUpdate;
 use crate::rpc::{
-    GetBlockRequest, GetBlockResponse, GetConsensusVersionRequest, GetPeerAddressRequest,
-    GetPeerAddressResponse, RPC_ID_GET_BLOCK, RPC_ID_GET_CONSENSUS_PARAMS,
-    RPC_ID_GET_PEER_ADDR_UPDATE, RPC_ID_HELLO, RPC_ID_PUSH_PEER_ADDR_UPDATE,
-    RPC_ID_WAIT_FINALITY_VOTE, RPC_ID_WAIT_NOTARIZED_BLOCK, RPC_ID_WAIT_VOTE,
+    BLOCK_RANGE_MAX_BLOCKS, ForwardCItemsRequest, ForwardCItemsResponse, GetBlockRangeRequest,
+    GetBlockRangeResponseItem, GetBlockRequest, GetBlockResponse, GetBlockResponseNoBls,
+    GetCItemProofRequest, GetCItemProofResponse, GetConsensusVersionRequest,
+    GetEquivocationsRequest, GetEquivocationsResponse, GetPeerAddressRequest,
+    GetPeerAddressResponse, GetStateSnapshotAttestationsRequest,
+    GetStateSnapshotAttestationsResponse, GetStateSnapshotRequest, GetStorageInfoRequest,
+    GetStorageInfoResponse, PushModuleMessageRequest, PushModuleMessageResponse,
+    RPC_ID_FORWARD_CITEMS, RPC_ID_GET_BLOCK, RPC_ID_GET_BLOCK_BLS, RPC_ID_GET_BLOCK_RANGE,
+    RPC_ID_GET_CITEM_PROOF, RPC_ID_GET_CONSENSUS_PARAMS, RPC_ID_GET_EQUIVOCATIONS,
+    RPC_ID_GET_PEER_ADDR_UPDATE, RPC_ID_GET_STATE_SNAPSHOT, RPC_ID_GET_STATE_SNAPSHOT_ATTESTATIONS,
+    RPC_ID_GET_STORAGE_INFO, RPC_ID_HELLO, RPC_ID_PUSH_MODULE_MESSAGE,
+    RPC_ID_PUSH_PEER_ADDR_UPDATE, RPC_ID_SIMULATE_CITEM, RPC_ID_WAIT_FINALITY_VOTE,
+    RPC_ID_WAIT_NOTARIZED_BLOCK, RPC_ID_WAIT_NOTARIZED_BLOCK_BLS, RPC_ID_WAIT_VOTE,
+    RPC_ID_WAIT_VOTE_BLS, SimulateCItemRequest, SimulateCItemResponse,
 };
 
 const LOG_TARGET: &str = "bfte::node::rpc::server";
@@ -35,11 +47,16 @@ impl RpcServer {
         DpcRpc::builder(self)
             .handler(RPC_ID_HELLO, Self::handle_hello)
             .handler(RPC_ID_WAIT_VOTE, Self::handle_wait_vote)
+            .handler(RPC_ID_WAIT_VOTE_BLS, Self::handle_wait_vote_bls)
             .handler(RPC_ID_WAIT_FINALITY_VOTE, Self::handle_wait_finality_vote)
             .handler(
                 RPC_ID_WAIT_NOTARIZED_BLOCK,
                 Self::handle_wait_notarized_block,
             )
+            .handler(
+                RPC_ID_WAIT_NOTARIZED_BLOCK_BLS,
+                Self::handle_wait_notarized_block_bls,
+            )
             .handler(
                 RPC_ID_PUSH_PEER_ADDR_UPDATE,
                 Self::handle_push_peer_addr_update,
@@ -53,6 +70,19 @@ impl RpcServer {
                 Self::handle_get_consensus_params,
             )
             .handler(RPC_ID_GET_BLOCK, Self::handle_get_block)
+            .handler(RPC_ID_GET_BLOCK_BLS, Self::handle_get_block_bls)
+            .handler(RPC_ID_GET_BLOCK_RANGE, Self::handle_get_block_range)
+            .handler(RPC_ID_GET_EQUIVOCATIONS, Self::handle_get_equivocations)
+            .handler(
+                RPC_ID_GET_STATE_SNAPSHOT_ATTESTATIONS,
+                Self::handle_get_state_snapshot_attestations,
+            )
+            .handler(RPC_ID_GET_STATE_SNAPSHOT, Self::handle_get_state_snapshot)
+            .handler(RPC_ID_GET_CITEM_PROOF, Self::handle_get_citem_proof)
+            .handler(RPC_ID_SIMULATE_CITEM, Self::handle_simulate_citem)
+            .handler(RPC_ID_GET_STORAGE_INFO, Self::handle_get_storage_info)
+            .handler(RPC_ID_FORWARD_CITEMS, Self::handle_forward_citems)
+            .handler(RPC_ID_PUSH_MODULE_MESSAGE, Self::handle_push_module_message)
             .build()
     }
 
@@ -73,6 +103,37 @@ impl RpcServer {
         Ok(())
     }
 
+    async fn handle_get_storage_info(self, send: RpcWrite, recv: RpcRead) {
+        if let Err(err) = self.handle_get_storage_info_try(send, recv).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request get_storage_info");
+        }
+    }
+
+    async fn handle_get_storage_info_try(
+        self,
+        mut send: RpcWrite,
+        mut recv: RpcRead,
+    ) -> WhateverResult<()> {
+        let GetStorageInfoRequest = recv
+            .read_message_bincode()
+            .await
+            .whatever_context("Failed to read request")?;
+
+        let node_ref = &self.handle.node_ref().into_whatever()?;
+        let payloads_pruned_before = match node_ref.consensus() {
+            Some(consensus) => consensus.get_payloads_pruned_before().await,
+            None => BlockRound::ZERO,
+        };
+
+        send.write_message_bincode(&GetStorageInfoResponse {
+            storage_mode: node_ref.pruning_config.mode,
+            payloads_pruned_before,
+        })
+        .await
+        .whatever_context("Failed to write response")?;
+        Ok(())
+    }
+
     #[instrument(
         target = LOG_TARGET,
         skip_all,
@@ -80,16 +141,30 @@ impl RpcServer {
     )]
     async fn handle_wait_vote(self, send: RpcWrite, recv: RpcRead) {
         trace!(target: LOG_TARGET, "Start handling wait_vote request");
-        if let Err(err) = self.handle_wait_vote_try(send, recv).await {
+        if let Err(err) = self.handle_wait_vote_try(send, recv, false).await {
             debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request wait_vote");
         }
         trace!(target: LOG_TARGET, "End handling wait_vote request");
     }
 
+    #[instrument(
+        target = LOG_TARGET,
+        skip_all,
+        ret(level = Level::TRACE)
+    )]
+    async fn handle_wait_vote_bls(self, send: RpcWrite, recv: RpcRead) {
+        trace!(target: LOG_TARGET, "Start handling wait_vote_bls request");
+        if let Err(err) = self.handle_wait_vote_try(send, recv, true).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request wait_vote_bls");
+        }
+        trace!(target: LOG_TARGET, "End handling wait_vote_bls request");
+    }
+
     async fn handle_wait_vote_try(
         self,
         mut send: RpcWrite,
         mut recv: RpcRead,
+        with_bls: bool,
     ) -> WhateverResult<()> {
         let req = recv
             .read_message_bincode::<WaitVoteRequest>()
@@ -160,9 +235,13 @@ impl RpcServer {
             trace!(target: LOG_TARGET, "Got more votes");
         };
 
-        send.write_message_bincode::<WaitVoteResponse>(&resp)
-            .await
-            .whatever_context("Write error")?;
+        if with_bls {
+            send.write_message_bincode::<WaitVoteResponse>(&resp).await
+        } else {
+            send.write_message_bincode::<WaitVoteResponseNoBls>(&resp.into_no_bls())
+                .await
+        }
+        .whatever_context("Write error")?;
         Ok(())
     }
 
@@ -197,7 +276,7 @@ impl RpcServer {
             whatever!("We have no peer pubkey")
         };
 
-        let seckey = node_ref.get_peer_secret_expect();
+        let signer = node_ref.signer_expect().clone();
 
         let mut finality_self_vote_rx = node_ref.consensus_wait().await.finality_self_vote_rx();
 
@@ -206,8 +285,18 @@ impl RpcServer {
             .await
             .whatever_context("Shutting down")?;
 
+        let update = signer
+            .sign_finality_vote(FinalityVoteUpdate(finality_self_vote))
+            .await
+            .whatever_context("Failed to sign finality vote")?;
+        node_ref
+            .consensus_wait()
+            .await
+            .record_own_finality_vote(update.clone())
+            .await;
+
         send.write_message_bincode::<WaitFinalityVoteResponse>(&WaitFinalityVoteResponse {
-            update: Signed::new_sign(FinalityVoteUpdate(finality_self_vote), seckey),
+            update,
         })
         .await
         .whatever_context("Write error")?;
@@ -221,16 +310,33 @@ impl RpcServer {
     )]
     async fn handle_wait_notarized_block(self, send: RpcWrite, recv: RpcRead) {
         trace!(target: LOG_TARGET, "Start handling wait_notarized_block request");
-        if let Err(err) = self.handle_wait_notarized_block_try(send, recv).await {
+        if let Err(err) = self
+            .handle_wait_notarized_block_try(send, recv, false)
+            .await
+        {
             debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request wait_notarized_block");
         }
         trace!(target: LOG_TARGET, "End handling wait_notarized_block request");
     }
 
+    #[instrument(
+        target = LOG_TARGET,
+        skip_all,
+        ret(level = Level::TRACE)
+    )]
+    async fn handle_wait_notarized_block_bls(self, send: RpcWrite, recv: RpcRead) {
+        trace!(target: LOG_TARGET, "Start handling wait_notarized_block_bls request");
+        if let Err(err) = self.handle_wait_notarized_block_try(send, recv, true).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request wait_notarized_block_bls");
+        }
+        trace!(target: LOG_TARGET, "End handling wait_notarized_block_bls request");
+    }
+
     async fn handle_wait_notarized_block_try(
         self,
         mut send: RpcWrite,
         mut recv: RpcRead,
+        with_bls: bool,
     ) -> WhateverResult<()> {
         let req = recv
             .read_message_bincode::<WaitNotarizedBlockRequest>()
@@ -258,6 +364,7 @@ impl RpcServer {
                 .await
                 .get_notarized_block_resp(req)
                 .await
+                .whatever_context("Can't respond with notarized block")?
             {
                 break resp;
             }
@@ -270,9 +377,19 @@ impl RpcServer {
             trace!(target: LOG_TARGET, "Got more rounds");
         };
 
-        send.write_message_bincode::<WaitNotarizedBlockResponse>(&resp)
-            .await
-            .whatever_context("Write error")?;
+        if with_bls {
+            send.write_message_bincode::<WaitNotarizedBlockResponse>(&resp)
+                .await
+        } else {
+            // Only peers using BLS notarize with the aggregate, and they
+            // should have asked for it
+            let Some(resp) = resp.into_no_bls() else {
+                whatever!("Block notarized with a BLS aggregate");
+            };
+            send.write_message_bincode::<WaitNotarizedBlockResponseNoBls>(&resp)
+                .await
+        }
+        .whatever_context("Write error")?;
         Ok(())
     }
 
@@ -367,16 +484,25 @@ impl RpcServer {
 
     async fn handle_get_block(self, send: RpcWrite, recv: RpcRead) {
         trace!(target: LOG_TARGET, "Start handling get_block request");
-        if let Err(err) = self.handle_get_block_try(send, recv).await {
+        if let Err(err) = self.handle_get_block_try(send, recv, false).await {
             debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request get_block");
         }
         trace!(target: LOG_TARGET, "End handling get_block request");
     }
 
+    async fn handle_get_block_bls(self, send: RpcWrite, recv: RpcRead) {
+        trace!(target: LOG_TARGET, "Start handling get_block_bls request");
+        if let Err(err) = self.handle_get_block_try(send, recv, true).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request get_block_bls");
+        }
+        trace!(target: LOG_TARGET, "End handling get_block_bls request");
+    }
+
     async fn handle_get_block_try(
         self,
         mut send: RpcWrite,
         mut recv: RpcRead,
+        with_bls: bool,
     ) -> WhateverResult<()> {
         let req = recv
             .read_message_bincode::<GetBlockRequest>()
@@ -391,11 +517,278 @@ impl RpcServer {
             .get_finalized_block(req.round)
             .await
         {
-            send.write_message_bincode::<GetBlockResponse>(&GetBlockResponse { block })
+            if with_bls {
+                send.write_message_bincode::<GetBlockResponse>(&GetBlockResponse { block })
+                    .await
+            } else {
+                let Some(block) = block.into_no_bls() else {
+                    whatever!("Block notarized with a BLS aggregate");
+                };
+                send.write_message_bincode::<GetBlockResponseNoBls>(&GetBlockResponseNoBls {
+                    block,
+                })
                 .await
-                .whatever_context("Write error")?;
+            }
+            .whatever_context("Write error")?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_get_block_range(self, send: RpcWrite, recv: RpcRead) {
+        trace!(target: LOG_TARGET, "Start handling get_block_range request");
+        if let Err(err) = self.handle_get_block_range_try(send, recv).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request get_block_range");
+        }
+        trace!(target: LOG_TARGET, "End handling get_block_range request");
+    }
+
+    async fn handle_get_block_range_try(
+        self,
+        mut send: RpcWrite,
+        mut recv: RpcRead,
+    ) -> WhateverResult<()> {
+        let req = recv
+            .read_message_bincode::<GetBlockRangeRequest>()
+            .await
+            .whatever_context("Failed to read request")?;
+
+        let node_ref = &self.handle.node_ref().into_whatever()?;
+        let consensus = node_ref.consensus_wait().await;
+
+        // Read one block at a time, to never hold more than one payload in memory
+        let mut round_end = req.round_start;
+        for _ in 0..BLOCK_RANGE_MAX_BLOCKS {
+            let (blocks, covered_end) = consensus
+                .get_finalized_blocks_range(round_end, req.round_end, 1)
+                .await;
+            round_end = covered_end;
+            let Some((block, payload)) = blocks.into_iter().next() else {
+                break;
+            };
+
+            let payload_hash = block.payload_hash;
+            send.write_message_bincode(&GetBlockRangeResponseItem::Block(block))
+                .await
+                .whatever_context("Failed to write response")?;
+
+            let out_hash = send
+                .write_message_bao(&payload.as_inner_slice())
+                .await
+                .whatever_context("Failed to write response")?;
+
+            assert_eq!(out_hash.as_bytes(), &payload_hash.to_bytes());
         }
 
+        send.write_message_bincode(&GetBlockRangeResponseItem::End { round_end })
+            .await
+            .whatever_context("Failed to write response")?;
+
+        Ok(())
+    }
+
+    async fn handle_get_equivocations(self, send: RpcWrite, recv: RpcRead) {
+        if let Err(err) = self.handle_get_equivocations_try(send, recv).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request get_equivocations");
+        }
+    }
+
+    async fn handle_get_equivocations_try(
+        self,
+        mut send: RpcWrite,
+        mut recv: RpcRead,
+    ) -> WhateverResult<()> {
+        let GetEquivocationsRequest = recv
+            .read_message_bincode()
+            .await
+            .whatever_context("Failed to read request")?;
+
+        let node_ref = &self.handle.node_ref().into_whatever()?;
+
+        let evidence = node_ref.consensus_wait().await.get_equivocations().await;
+
+        send.write_message_bincode(&GetEquivocationsResponse { evidence })
+            .await
+            .whatever_context("Failed to write response")?;
+
+        Ok(())
+    }
+
+    async fn handle_get_state_snapshot_attestations(self, send: RpcWrite, recv: RpcRead) {
+        if let Err(err) = self
+            .handle_get_state_snapshot_attestations_try(send, recv)
+            .await
+        {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request get_state_snapshot_attestations");
+        }
+    }
+
+    async fn handle_get_state_snapshot_attestations_try(
+        self,
+        mut send: RpcWrite,
+        mut recv: RpcRead,
+    ) -> WhateverResult<()> {
+        let req = recv
+            .read_message_bincode::<GetStateSnapshotAttestationsRequest>()
+            .await
+            .whatever_context("Failed to read request")?;
+
+        let node_ref = &self.handle.node_ref().into_whatever()?;
+
+        let attestations = node_ref
+            .get_state_snapshot_attestations(req.max_round)
+            .await;
+
+        send.write_message_bincode(&GetStateSnapshotAttestationsResponse { attestations })
+            .await
+            .whatever_context("Failed to write response")?;
+
+        Ok(())
+    }
+
+    async fn handle_get_state_snapshot(self, send: RpcWrite, recv: RpcRead) {
+        trace!(target: LOG_TARGET, "Start handling get_state_snapshot request");
+        if let Err(err) = self.handle_get_state_snapshot_try(send, recv).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request get_state_snapshot");
+        }
+        trace!(target: LOG_TARGET, "End handling get_state_snapshot request");
+    }
+
+    async fn handle_get_state_snapshot_try(
+        self,
+        mut send: RpcWrite,
+        mut recv: RpcRead,
+    ) -> WhateverResult<()> {
+        let req = recv
+            .read_message_bincode::<GetStateSnapshotRequest>()
+            .await
+            .whatever_context("Failed to read request")?;
+
+        let node_ref = &self.handle.node_ref().into_whatever()?;
+
+        let Some(raw) = node_ref.get_state_snapshot(req.round).await else {
+            whatever!("No state snapshot at round {}", req.round);
+        };
+
+        let out_hash = send
+            .write_message_bao(&raw.as_inner_slice())
+            .await
+            .whatever_context("Failed to write response")?;
+
+        assert_eq!(out_hash.as_bytes(), &raw.hash().to_bytes());
+
+        Ok(())
+    }
+
+    async fn handle_get_citem_proof(self, send: RpcWrite, recv: RpcRead) {
+        if let Err(err) = self.handle_get_citem_proof_try(send, recv).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request get_citem_proof");
+        }
+    }
+
+    async fn handle_get_citem_proof_try(
+        self,
+        mut send: RpcWrite,
+        mut recv: RpcRead,
+    ) -> WhateverResult<()> {
+        let req = recv
+            .read_message_bincode::<GetCItemProofRequest>()
+            .await
+            .whatever_context("Failed to read request")?;
+
+        let node_ref = &self.handle.node_ref().into_whatever()?;
+
+        let proof = node_ref
+            .consensus_wait()
+            .await
+            .get_citem_proof(req.trusted_round, req.round, req.citem_idx)
+            .await;
+
+        send.write_message_bincode(&GetCItemProofResponse { proof })
+            .await
+            .whatever_context("Failed to write response")?;
+
+        Ok(())
+    }
+
+    async fn handle_simulate_citem(self, send: RpcWrite, recv: RpcRead) {
+        if let Err(err) = self.handle_simulate_citem_try(send, recv).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request simulate_citem");
+        }
+    }
+
+    async fn handle_simulate_citem_try(
+        self,
+        mut send: RpcWrite,
+        mut recv: RpcRead,
+    ) -> WhateverResult<()> {
+        let req = recv
+            .read_message_bincode::<Signed<SimulateCItemRequest>>()
+            .await
+            .whatever_context("Failed to read request")?;
+
+        let node_ref = &self.handle.node_ref().into_whatever()?;
+
+        let simulation = node_ref.simulate_peer_citem(req).await?;
+
+        send.write_message_bincode(&SimulateCItemResponse { simulation })
+            .await
+            .whatever_context("Failed to write response")?;
+
+        Ok(())
+    }
+
+    async fn handle_push_module_message(self, send: RpcWrite, recv: RpcRead) {
+        if let Err(err) = self.handle_push_module_message_try(send, recv).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request push_module_message");
+        }
+    }
+
+    async fn handle_push_module_message_try(
+        self,
+        mut send: RpcWrite,
+        mut recv: RpcRead,
+    ) -> WhateverResult<()> {
+        let req = recv
+            .read_message_bincode::<PushModuleMessageRequest>()
+            .await
+            .whatever_context("Failed to read request")?;
+
+        let node_ref = &self.handle.node_ref().into_whatever()?;
+
+        node_ref.receive_module_message(req.msg).await?;
+
+        send.write_message_bincode(&PushModuleMessageResponse)
+            .await
+            .whatever_context("Failed to write response")?;
+
+        Ok(())
+    }
+
+    async fn handle_forward_citems(self, send: RpcWrite, recv: RpcRead) {
+        if let Err(err) = self.handle_forward_citems_try(send, recv).await {
+            debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed handling request forward_citems");
+        }
+    }
+
+    async fn handle_forward_citems_try(
+        self,
+        mut send: RpcWrite,
+        mut recv: RpcRead,
+    ) -> WhateverResult<()> {
+        let req = recv
+            .read_message_bincode::<ForwardCItemsRequest>()
+            .await
+            .whatever_context("Failed to read request")?;
+
+        let node_ref = &self.handle.node_ref().into_whatever()?;
+
+        node_ref.receive_forwarded_citems(req.citems).await?;
+
+        send.write_message_bincode(&ForwardCItemsResponse)
+            .await
+            .whatever_context("Failed to write response")?;
+
         Ok(())
     }
 }
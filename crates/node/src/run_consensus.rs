@@ -5,20 +5,20 @@ use std::{env, future};
 
 use backon::Retryable as _;
 use bfte_consensus::vote_set::VoteSet;
+use bfte_consensus_core::block::proposal::select_proposal_citems;
 use bfte_consensus_core::block::{BlockHeader, BlockPayloadRaw, BlockRound};
 use bfte_consensus_core::citem::CItem;
 use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::module::ModuleId;
 use bfte_consensus_core::msg::{
     WaitNotarizedBlockRequest, WaitNotarizedBlockResponse, WaitVoteRequest, WaitVoteResponse,
 };
 use bfte_consensus_core::peer::{PeerIdx, PeerPubkey};
-use bfte_consensus_core::signed::Signed;
 use bfte_consensus_core::timestamp::Timestamp;
 use bfte_util_core::is_env_var_set;
 use bfte_util_error::WhateverResult;
 use bfte_util_error::fmt::FmtCompact as _;
 use bfte_util_fmt_opt::AsFmtOption as _;
-use iroh_dpc_rpc::bincode::RpcExtBincode as _;
 use snafu::{ResultExt as _, Whatever};
 use tokio::select;
 use tokio::task::JoinSet;
@@ -27,9 +27,13 @@ use tracing::{debug, info, instrument, trace, warn};
 
 use crate::connection_pool::ConnectionPool;
 use crate::envs::BFTE_TEST_ROUND_DELAY;
-use crate::rpc::{RPC_ID_WAIT_NOTARIZED_BLOCK, RPC_ID_WAIT_VOTE};
+use crate::rpc;
 use crate::{LOG_TARGET, Node, RPC_BACKOFF};
 
+/// Modules whose citems are proposed first and without a quota: the
+/// consensus-ctrl module, always installed first
+const PRIORITY_MODULE_IDS: &[ModuleId] = &[ModuleId::new(0)];
+
 impl Node {
     pub async fn run_consensus(self: Arc<Self>) {
         loop {
@@ -118,7 +122,7 @@ impl Node {
             prev_seq = %prev_notarized_block.map(|b| b.seq).fmt_option(),
             num_peers = %params.num_peers(),
             our_peer_idx = %our_peer_idx.fmt_option(),
-            leader_idx = %round.leader_idx(params.num_peers()),
+            leader_idx = %params.leader_idx(round),
             %finality_consensus,
             "Running core consensus round…"
         );
@@ -190,7 +194,7 @@ impl Node {
     ) {
         let existing_votes = existing_dummy_votes | existing_non_dummy_votes;
         if let Some(our_peer_idx) = our_peer_idx {
-            if round.leader_idx(params.num_peers()) == our_peer_idx {
+            if params.leader_idx(round) == our_peer_idx {
                 if existing_votes.contains(our_peer_idx) {
                     info!(target: LOG_TARGET, "Already voted in this round. Will not generate proposal.");
                 } else {
@@ -244,6 +248,7 @@ impl Node {
                     .expect("Must have an entry"),
                 // if we already have a non-dummy vote, we only care about dummy ones
                 existing_non_dummy_votes.contains(peer_idx),
+                params.uses_bls(),
             ));
         }
     }
@@ -278,6 +283,7 @@ impl Node {
                     .get(peer_idx.as_usize())
                     .expect("Must have an entry"),
                 prev_notarized_block,
+                params.uses_bls(),
             ));
         }
     }
@@ -295,10 +301,17 @@ impl Node {
         if let Some(our_peer_idx) = our_peer_idx {
             if !existing_dummy_votes.contains(our_peer_idx) {
                 // Pre-sign timeout vote, but release it later
-                let dummy_vote = Signed::new_sign(
-                    BlockHeader::new_dummy(round, params),
-                    self.get_peer_secret_expect(),
-                );
+                let dummy_vote = match self
+                    .signer_expect()
+                    .sign_block(BlockHeader::new_dummy(round, params), false)
+                    .await
+                {
+                    Ok((dummy_vote, _)) => dummy_vote,
+                    Err(err) => {
+                        warn!(target: LOG_TARGET, err = %err.fmt_compact(), %round, "Failed to sign timeout vote");
+                        return;
+                    }
+                };
 
                 let modules = self.weak_shared_modules.clone();
                 let mut current_round_with_timeout_rx = current_round_with_timeout_rx.clone();
@@ -334,7 +347,10 @@ impl Node {
                         );
                         tokio::time::sleep(duration).await;
                         RoundEvent::VoteSelfTimeout {
-                            resp: WaitVoteResponse::Vote { block: dummy_vote },
+                            resp: WaitVoteResponse::Vote {
+                                block: dummy_vote,
+                                bls_sig: None,
+                            },
                             peer_idx: our_peer_idx,
                         }
                     }
@@ -353,16 +369,24 @@ impl Node {
     ) {
         if let Some(our_peer_idx) = our_peer_idx {
             if !existing_non_dummy_votes.contains(our_peer_idx)
-                && round.leader_idx(params.num_peers()) != our_peer_idx
+                && params.leader_idx(round) != our_peer_idx
             {
                 let consensus = self.consensus_expect().clone();
                 let mut new_proposal_rx = consensus.new_proposal_rx();
-                let our_seckey = self.get_peer_secret_expect();
+                let signer = self.signer_expect().clone();
+                let uses_bls = params.uses_bls();
                 round_tasks.spawn({
                     async move {
                         loop {
                             if let Some(proposal) = consensus.get_proposal(round).await {
-                                let signed = Signed::new_sign(proposal, our_seckey);
+                                let (signed, bls_sig) =
+                                    match signer.sign_block(proposal, uses_bls).await {
+                                        Ok(res) => res,
+                                        Err(err) => {
+                                            warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Failed to sign vote on the current proposal");
+                                            return future::pending().await;
+                                        }
+                                    };
 
                                 debug!(
                                     target: LOG_TARGET,
@@ -370,7 +394,10 @@ impl Node {
                                     "Voting on the current proposal"
                                 );
                                 return RoundEvent::VoteSelf {
-                                    resp: WaitVoteResponse::Vote { block: signed },
+                                    resp: WaitVoteResponse::Vote {
+                                        block: signed,
+                                        bls_sig,
+                                    },
                                     peer_idx: our_peer_idx,
                                 };
                             }
@@ -415,6 +442,7 @@ impl Node {
                     resp,
                     round,
                     peer_pubkey,
+                    uses_bls,
                 } => {
                     let is_dummy = resp.block().is_dummy();
                     if let Err(err) = consensus.process_vote_response(peer_idx, resp).await {
@@ -432,6 +460,7 @@ impl Node {
                             peer_pubkey,
                             // if we already have a non-dummy vote, we only care about dummy ones
                             true,
+                            uses_bls,
                         ));
                     }
                 }
@@ -489,6 +518,7 @@ impl Node {
         let mut node_app_ack_rx = self.node_app_ack_rx.clone();
         let consensus = self.consensus_expect();
         let mut pending_transactions_rx = self.pending_transactions_rx.clone();
+        let mut forwarded_citems_rx = self.forwarded_citems_tx().subscribe();
         let mut finality_consensus_rx = consensus.finality_consensus_rx();
         let finality_self_vote_rx = consensus.finality_self_vote_rx();
 
@@ -502,6 +532,9 @@ impl Node {
             if !pending_transactions_rx.borrow().is_empty() {
                 break;
             }
+            if self.has_forwarded_citems(cur_round) {
+                break;
+            }
 
             let wait_pending_params_change_async = async {
                 // If consensus has any params changes pending, we want to produce
@@ -573,6 +606,12 @@ impl Node {
                     continue;
                 },
 
+                // If other peers forwarded us their citems, we break.
+                _ = forwarded_citems_rx.changed() => {
+                    debug!(target: LOG_TARGET, "Got forwarded citems");
+                    continue;
+                },
+
                 _ = wait_finality_self_vote_mismatch_async => {
                     // If the previous round was a dummy, we want to propose
                     // a block, even if empty, just so all the peers can agree
@@ -593,6 +632,11 @@ impl Node {
             };
         }
 
+        let consensus_params = consensus.get_consensus_params(cur_round).await;
+        pending_citems.extend(
+            self.take_forwarded_citems(cur_round, &consensus_params)
+                .await,
+        );
         pending_citems.extend(
             pending_transactions_rx
                 .borrow()
@@ -600,12 +644,23 @@ impl Node {
                 .map(|tx| CItem::Transaction(tx.to_owned())),
         );
 
+        let pending_citems =
+            select_proposal_citems(&consensus_params, PRIORITY_MODULE_IDS, pending_citems);
+
         debug!(target: LOG_TARGET, %cur_round, items = %pending_citems.len(), "Building new block proposal");
         let (block, payload) = self.generate_proposal(cur_round, &pending_citems).await;
 
-        let seckey = self.get_peer_secret_expect();
+        let uses_bls = consensus_params.uses_bls();
+        let (block, bls_sig) = match self.signer_expect().sign_block(block, uses_bls).await {
+            Ok(res) => res,
+            Err(err) => {
+                warn!(target: LOG_TARGET, err = %err.fmt_compact(), %cur_round, "Failed to sign block proposal");
+                return future::pending().await;
+            }
+        };
         let resp = WaitVoteResponse::Proposal {
-            block: Signed::new_sign(block, seckey),
+            block,
+            bls_sig,
             payload,
         };
 
@@ -632,6 +687,7 @@ impl Node {
                 .round(round)
                 .consensus_params(&consensus_params)
                 .payload(&payload)
+                .citems(citems)
                 .timestamp(Timestamp::now())
                 .build(),
             payload,
@@ -644,6 +700,7 @@ impl Node {
         peer_idx: PeerIdx,
         peer_pubkey: PeerPubkey,
         only_dummy: bool,
+        uses_bls: bool,
     ) -> RoundEvent {
         {
             debug!(
@@ -660,19 +717,16 @@ impl Node {
                     .await
                     .whatever_context("Failed to connect to peer")?;
                 trace!(target: LOG_TARGET, %peer_idx, %peer_pubkey, %only_dummy, "Making RPC for vote from peer");
-                let resp = conn
-                    .make_request_response_bincode(
-                        RPC_ID_WAIT_VOTE,
-                        WaitVoteRequest { round, only_dummy },
-                    )
-                    .await
-                    .whatever_context("Failed wait vote request")?;
+                let resp =
+                    rpc::wait_vote(&mut conn, WaitVoteRequest { round, only_dummy }, uses_bls)
+                        .await?;
                 trace!(target: LOG_TARGET, %peer_idx, %peer_pubkey, %only_dummy, "Got vote from peer");
                 Ok(RoundEvent::Vote {
                     peer_idx,
                     resp,
                     round,
                     peer_pubkey,
+                    uses_bls,
                 })
             }
         }
@@ -695,6 +749,7 @@ impl Node {
         peer_idx: PeerIdx,
         peer_pubkey: PeerPubkey,
         prev_notarized_block: Option<BlockHeader>,
+        uses_bls: bool,
     ) -> RoundEvent {
         debug!(
             target: LOG_TARGET,
@@ -709,18 +764,17 @@ impl Node {
                     .connect(peer_pubkey)
                     .await
                     .whatever_context("Failed to connect to peer")?;
-                let resp = conn
-                    .make_request_response_bincode(
-                        RPC_ID_WAIT_NOTARIZED_BLOCK,
-                        WaitNotarizedBlockRequest {
-                            cur_round: round,
-                            min_notarized_round: prev_notarized_block
-                                .map(|b| b.round.next().expect("Can't fail"))
-                                .unwrap_or_default(),
-                        },
-                    )
-                    .await
-                    .whatever_context("Notarized block rpc failed")?;
+                let resp = rpc::wait_notarized_block(
+                    &mut conn,
+                    WaitNotarizedBlockRequest {
+                        cur_round: round,
+                        min_notarized_round: prev_notarized_block
+                            .map(|b| b.round.next().expect("Can't fail"))
+                            .unwrap_or_default(),
+                    },
+                    uses_bls,
+                )
+                .await?;
                 Ok(RoundEvent::Notarized { peer_idx, resp })
             }
         }
@@ -747,6 +801,7 @@ pub(crate) enum RoundEvent {
         round: BlockRound,
         peer_idx: PeerIdx,
         peer_pubkey: PeerPubkey,
+        uses_bls: bool,
     },
     /// Our own proposal
     VoteSelfProposal {
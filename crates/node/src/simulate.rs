@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+use bfte_consensus_core::citem::CItem;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::signed::Signed;
+use bfte_module::module::CItemSimulation;
+use bfte_node_app_core::SimulateCItemRequest;
+use bfte_util_error::WhateverResult;
+use snafu::{ResultExt as _, whatever};
+use tokio::sync::oneshot;
+
+use crate::{Node, rpc};
+
+/// Maximum number of simulation requests waiting for the node app
+pub(crate) const SIMULATE_CITEM_QUEUE_LEN: usize = 16;
+
+/// Minimum time between simulation requests of a single peer
+pub(crate) const SIMULATE_CITEM_PEER_INTERVAL: Duration = Duration::from_millis(250);
+
+impl Node {
+    /// Simulate processing of `citem` against the current state, as if
+    /// `peer_pubkey` included it in the next block
+    ///
+    /// Nothing gets committed. Requests are handled by the node app between
+    /// blocks, and rejected right away if too many are already queued.
+    pub(crate) async fn simulate_citem(
+        &self,
+        citem: CItem,
+        peer_pubkey: PeerPubkey,
+    ) -> WhateverResult<CItemSimulation> {
+        let (response_tx, response_rx) = oneshot::channel();
+
+        self.simulate_citem_tx()
+            .try_send(SimulateCItemRequest {
+                citem,
+                peer_pubkey,
+                response_tx,
+            })
+            .ok()
+            .whatever_context("Node app is busy or not running")?;
+
+        response_rx
+            .await
+            .whatever_context("Node app went away")?
+            .whatever_context("Node app failed to simulate citem")
+    }
+
+    /// Like [`Self::simulate_citem`], but for a request of another peer
+    ///
+    /// Only consensus peers can simulate, only their own citems, and not
+    /// more often than every [`SIMULATE_CITEM_PEER_INTERVAL`].
+    pub(crate) async fn simulate_peer_citem(
+        &self,
+        req: Signed<rpc::SimulateCItemRequest>,
+    ) -> WhateverResult<CItemSimulation> {
+        let peer_pubkey = req.peer_pubkey;
+
+        let (_, params) = self
+            .consensus_wait()
+            .await
+            .get_current_round_and_params()
+            .await;
+        if params.find_peer_idx(peer_pubkey).is_none() {
+            whatever!("Simulation requester is not a peer: {peer_pubkey}");
+        }
+        req.verify_sig_peer_pubkey(peer_pubkey)
+            .whatever_context("Invalid simulation request signature")?;
+
+        {
+            let mut last_requests = self
+                .simulate_citem_last_requests()
+                .lock()
+                .expect("Locking failed");
+            let now = Instant::now();
+            if last_requests
+                .get(&peer_pubkey)
+                .is_some_and(|last| now < *last + SIMULATE_CITEM_PEER_INTERVAL)
+            {
+                whatever!("Too many simulation requests from {peer_pubkey}");
+            }
+            last_requests.insert(peer_pubkey, now);
+        }
+
+        let rpc::SimulateCItemRequest { citem, .. } = req.inner;
+        self.simulate_citem(citem, peer_pubkey).await
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bfte_consensus::consensus::Consensus;
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::signed::Signed;
+use bfte_consensus_core::snapshot::{StateSnapshot, StateSnapshotHeader, StateSnapshotRaw};
+use bfte_db::Database;
+use bfte_module::module::db::restore_modules_tables;
+use bfte_node_app_core::processed_citems;
+use bfte_signer::SignerExt as _;
+use bfte_util_error::WhateverResult;
+use bfte_util_error::fmt::FmtCompact as _;
+use iroh::endpoint::Connection;
+use rand::seq::SliceRandom as _;
+use snafu::{ResultExt as _, whatever};
+use tracing::{debug, info, instrument, warn};
+
+use crate::{Node, rpc, tables};
+
+const LOG_TARGET: &str = "bfte::node::state-snapshot";
+
+/// How many latest state snapshots to keep around
+const STATE_SNAPSHOTS_KEEP: usize = 2;
+
+impl Node {
+    /// Store a state snapshot taken by node-app, and attest it if we're a
+    /// peer
+    pub(crate) async fn record_state_snapshot(&self, snapshot: StateSnapshot) {
+        let round = snapshot.round;
+        let raw = snapshot.to_raw();
+        let header = raw.header(round);
+
+        let attestation = match self.signer() {
+            Some(signer) => match signer.sign(header).await {
+                Ok(attestation) => Some((signer.pubkey(), attestation)),
+                Err(err) => {
+                    warn!(target: LOG_TARGET, err = %err.fmt_compact(), %round, "Failed to attest state snapshot");
+                    None
+                }
+            },
+            None => None,
+        };
+
+        self.db()
+            .write_with_expect(|ctx| {
+                let mut tbl = ctx.open_table(&tables::state_snapshots::TABLE)?;
+                let mut tbl_attestations =
+                    ctx.open_table(&tables::state_snapshot_attestations::TABLE)?;
+
+                tbl.insert(&round, &raw)?;
+                if let Some((peer_pubkey, attestation)) = attestation {
+                    tbl_attestations.insert(&(round, peer_pubkey), &attestation)?;
+                }
+
+                let Some(prune_before) = tbl
+                    .range(..)?
+                    .rev()
+                    .nth(STATE_SNAPSHOTS_KEEP - 1)
+                    .transpose()?
+                    .map(|(k, _)| k.value())
+                else {
+                    return Ok(());
+                };
+
+                tbl.retain_in(&BlockRound::MIN..&prune_before, |_, _| false)?;
+                tbl_attestations.retain_in(
+                    &(BlockRound::MIN, PeerPubkey::MIN)..&(prune_before, PeerPubkey::MIN),
+                    |_, _| false,
+                )?;
+
+                Ok(())
+            })
+            .await;
+
+        info!(
+            target: LOG_TARGET,
+            %round,
+            hash = %header.hash,
+            len = %header.len,
+            "Recorded state snapshot"
+        );
+    }
+
+    /// Latest state snapshot at or before `max_round` and all its known
+    /// attestations
+    pub(crate) async fn get_state_snapshot_attestations(
+        &self,
+        max_round: BlockRound,
+    ) -> Vec<(PeerPubkey, Signed<StateSnapshotHeader>)> {
+        self.db()
+            .read_with_expect(|ctx| {
+                let Some(round) = ctx
+                    .open_table(&tables::state_snapshots::TABLE)?
+                    .range(..=max_round)?
+                    .next_back()
+                    .transpose()?
+                    .map(|(k, _)| k.value())
+                else {
+                    return Ok(vec![]);
+                };
+
+                ctx.open_table(&tables::state_snapshot_attestations::TABLE)?
+                    .range(&(round, PeerPubkey::MIN)..=&(round, PeerPubkey::MAX))?
+                    .map(|kv| {
+                        let (k, v) = kv?;
+                        Ok((k.value().1, v.value()))
+                    })
+                    .collect()
+            })
+            .await
+    }
+
+    pub(crate) async fn get_state_snapshot(&self, round: BlockRound) -> Option<StateSnapshotRaw> {
+        self.db()
+            .read_with_expect(|ctx| {
+                Ok(ctx
+                    .open_table(&tables::state_snapshots::TABLE)?
+                    .get(&round)?
+                    .map(|g| g.value()))
+            })
+            .await
+    }
+
+    async fn get_latest_state_snapshot_header(&self) -> Option<StateSnapshotHeader> {
+        self.db()
+            .read_with_expect(|ctx| {
+                Ok(ctx
+                    .open_table(&tables::state_snapshots::TABLE)?
+                    .range(..)?
+                    .next_back()
+                    .transpose()?
+                    .map(|(k, v)| v.value().header(k.value())))
+            })
+            .await
+    }
+
+    pub(crate) async fn get_restored_state_snapshot_round(&self) -> Option<BlockRound> {
+        self.db()
+            .read_with_expect(|ctx| {
+                Ok(ctx
+                    .open_table(&tables::state_snapshot_restored::TABLE)?
+                    .get(&())?
+                    .map(|g| g.value()))
+            })
+            .await
+    }
+
+    /// Periodically pull attestations of our latest state snapshot from
+    /// random peers
+    ///
+    /// Every peer attests its own snapshots, so attestations need to be
+    /// collected for joining nodes to be able to verify them.
+    #[instrument(
+        name = "state_snapshot_attestation_sync"
+        target = LOG_TARGET,
+        skip_all,
+    )]
+    pub(crate) async fn run_state_snapshot_attestation_sync(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+
+        loop {
+            interval.tick().await;
+
+            let Some(own_header) = self.get_latest_state_snapshot_header().await else {
+                continue;
+            };
+
+            let (_, consensus_params) =
+                self.consensus_expect().get_current_round_and_params().await;
+
+            let Some(peer_pubkey) = consensus_params
+                .peers
+                .as_slice()
+                .iter()
+                .copied()
+                .filter(|peer_pubkey| Some(*peer_pubkey) != self.peer_pubkey)
+                .collect::<Vec<_>>()
+                .choose(&mut rand::thread_rng())
+                .copied()
+            else {
+                continue;
+            };
+
+            if let Err(err) = self
+                .query_state_snapshot_attestations(peer_pubkey, own_header)
+                .await
+            {
+                debug!(
+                    target: LOG_TARGET,
+                    %peer_pubkey,
+                    err = %err.fmt_compact(),
+                    "Failed to query state snapshot attestations"
+                );
+            }
+        }
+    }
+
+    async fn query_state_snapshot_attestations(
+        &self,
+        peer_pubkey: PeerPubkey,
+        own_header: StateSnapshotHeader,
+    ) -> WhateverResult<()> {
+        let mut conn = self
+            .connection_pool()
+            .connect(peer_pubkey)
+            .await
+            .whatever_context("Failed to connect")?;
+
+        let attestations =
+            rpc::get_state_snapshot_attestations(&mut conn, own_header.round).await?;
+
+        let Some((_, peer_header)) = attestations.first() else {
+            return Ok(());
+        };
+        if peer_header.round != own_header.round {
+            // Peer did not take (or already pruned) the snapshot
+            return Ok(());
+        }
+        if peer_header.inner != own_header {
+            warn!(
+                target: LOG_TARGET,
+                %peer_pubkey,
+                round = %own_header.round,
+                own_hash = %own_header.hash,
+                peer_hash = %peer_header.hash,
+                "State snapshot mismatch with the peer"
+            );
+            return Ok(());
+        }
+
+        self.db()
+            .write_with_expect(|ctx| {
+                let mut tbl = ctx.open_table(&tables::state_snapshot_attestations::TABLE)?;
+                for (attester, attestation) in &attestations {
+                    tbl.insert(&(own_header.round, *attester), attestation)?;
+                }
+                Ok(())
+            })
+            .await;
+
+        Ok(())
+    }
+
+    /// Restore the latest state snapshot attested by a threshold of peers in
+    /// `consensus_params`
+    ///
+    /// The snapshot must not be past `max_round`. On success, node-app will
+    /// start processing blocks from the round of the snapshot, instead of
+    /// replaying the whole history.
+    pub(crate) async fn restore_state_snapshot(
+        conn: &mut Connection,
+        db: &Database,
+        consensus: &Consensus,
+        max_round: BlockRound,
+        consensus_params: &ConsensusParams,
+    ) -> WhateverResult<BlockRound> {
+        let attestations = rpc::get_state_snapshot_attestations(conn, max_round).await?;
+
+        let Some((_, header)) = attestations.first() else {
+            whatever!("No state snapshot available");
+        };
+        let header = header.inner;
+
+        let attesters: BTreeSet<_> = attestations
+            .iter()
+            .filter_map(|(peer_pubkey, _)| consensus_params.find_peer_idx(*peer_pubkey))
+            .collect();
+        let attesters_weight = consensus_params.weight_of(attesters.iter().copied());
+        if attesters_weight < consensus_params.total_weight().threshold() {
+            whatever!(
+                "Not enough state snapshot attestations: {} < {}",
+                attesters_weight,
+                consensus_params.total_weight().threshold()
+            );
+        }
+
+        debug!(
+            target: LOG_TARGET,
+            round = %header.round,
+            len = %header.len,
+            "Downloading state snapshot…"
+        );
+        let raw = rpc::get_state_snapshot(conn, header).await?;
+        let snapshot =
+            StateSnapshot::from_raw(&raw).whatever_context("Failed to parse state snapshot")?;
+
+        if snapshot.round != header.round {
+            whatever!("State snapshot round mismatch: {}", snapshot.round);
+        }
+
+        db.write_with_expect(|ctx| {
+            restore_modules_tables(ctx, &snapshot.tables)?;
+            processed_citems::restore_tables_dbtx(ctx, &snapshot.tables)?;
+            consensus.restore_consensus_params_schedule_tx(ctx, &snapshot.consensus_params)?;
+
+            ctx.open_table(&tables::state_snapshots::TABLE)?
+                .insert(&snapshot.round, &raw)?;
+            let mut tbl_attestations =
+                ctx.open_table(&tables::state_snapshot_attestations::TABLE)?;
+            for (peer_pubkey, attestation) in &attestations {
+                tbl_attestations.insert(&(snapshot.round, *peer_pubkey), attestation)?;
+            }
+            ctx.open_table(&tables::state_snapshot_restored::TABLE)?
+                .insert(&(), &snapshot.round)?;
+
+            Ok(())
+        })
+        .await;
+
+        info!(
+            target: LOG_TARGET,
+            round = %snapshot.round,
+            num_tables = snapshot.tables.len(),
+            "Restored state snapshot"
+        );
+
+        Ok(snapshot.round)
+    }
+}
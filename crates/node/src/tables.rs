@@ -1,6 +1,50 @@
+use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::module::ModuleId;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::signed::Signed;
+use bfte_consensus_core::snapshot::{StateSnapshotHeader, StateSnapshotRaw};
+use bfte_module::module::net::ModuleMessageId;
 use bfte_util_db::def_table;
 
+use crate::module_net::ModuleMessageEnvelope;
+
 def_table! {
     /// Tracks consensus database/schema version
     ui_pass_hash: () => [u8; 32]
 }
+
+def_table! {
+    /// Latest state snapshots taken by node-app (or restored when joining)
+    state_snapshots: BlockRound => StateSnapshotRaw
+}
+
+def_table! {
+    /// Peers' attestations of the state snapshots we have
+    state_snapshot_attestations: (BlockRound, PeerPubkey) => Signed<StateSnapshotHeader>
+}
+
+def_table! {
+    /// Round of the state snapshot restored when joining the consensus
+    state_snapshot_restored: () => BlockRound
+}
+
+def_table! {
+    /// Next `seq` to use for an outgoing module message
+    module_msg_next_seq: () => u64
+}
+
+def_table! {
+    /// Outgoing module messages, by recipient and `seq`, until delivered
+    module_msg_outbox: (PeerPubkey, u64) => Signed<ModuleMessageEnvelope>
+}
+
+def_table! {
+    /// Received module messages, until acknowledged by the module: sender and
+    /// payload
+    module_msg_inbox: (ModuleId, ModuleMessageId) => (PeerPubkey, Vec<u8>)
+}
+
+def_table! {
+    /// Highest `seq` of a module message received from a peer
+    module_msg_last_seq: PeerPubkey => u64
+}
@@ -1,16 +1,25 @@
+use std::collections::BTreeMap;
 use std::convert::Infallible;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 
 use async_trait::async_trait;
 use bfte_consensus_core::block::BlockRound;
+use bfte_consensus_core::bls::BlsKeyRegistration;
+use bfte_consensus_core::citem::CItem;
+use bfte_consensus_core::citem::transaction::TransactionHash;
+use bfte_consensus_core::equivocation::EquivocationEvidence;
+use bfte_consensus_core::key_rotation::{KeyRotation, SignedKeyRotation};
 use bfte_consensus_core::peer::PeerPubkey;
 use bfte_invite::Invite;
+use bfte_module::module::CItemSimulation;
+use bfte_node_app_core::citem_index::{self, CItemIndexEntry, CItemIndexFilter, CItemLocation};
 use bfte_node_shared_modules::WeakSharedModules;
-use bfte_node_ui::{ConsensusHistoryEntry, INodeUiApi, RunUiFn};
+use bfte_node_ui::{BlockDetails, ConsensusHistoryEntry, INodeUiApi, RunUiFn};
+use bfte_signer::SignerExt as _;
 use bfte_util_error::WhateverResult;
 use n0_future::task::AbortOnDropHandle;
-use snafu::{OptionExt as _, ResultExt as _};
+use snafu::{OptionExt as _, ResultExt as _, whatever};
 use tokio::sync::watch;
 
 use crate::Node;
@@ -44,7 +53,7 @@ impl INodeUiApi for NodeUiApi {
     }
 
     fn has_root_secret(&self) -> WhateverResult<bool> {
-        Ok(self.node_ref()?.root_secret().is_some())
+        Ok(self.node_ref()?.peer_pubkey.is_some())
     }
 
     fn is_ui_password_temporary(&self) -> WhateverResult<bool> {
@@ -142,6 +151,144 @@ impl INodeUiApi for NodeUiApi {
 
         Ok(history)
     }
+
+    async fn get_equivocations(&self) -> WhateverResult<Vec<EquivocationEvidence>> {
+        Ok(self
+            .node_ref()?
+            .consensus()
+            .as_ref()
+            .whatever_context("Consensus not initialized")?
+            .get_equivocations()
+            .await)
+    }
+
+    async fn get_block_details(&self, round: BlockRound) -> WhateverResult<Option<BlockDetails>> {
+        let node_ref = self.node_ref()?;
+        let consensus_option = node_ref.consensus();
+        let consensus = consensus_option
+            .as_ref()
+            .whatever_context("Consensus not initialized")?;
+
+        let Some(block) = consensus
+            .get_finalized_block(round)
+            .await
+            .filter(|block| block.inner.round == round)
+        else {
+            return Ok(None);
+        };
+
+        let consensus_params = consensus.get_round_params(round).await;
+        let signatures = block
+            .signers()
+            .into_iter()
+            .filter_map(|peer_idx| {
+                let peer_pubkey = consensus_params
+                    .peers
+                    .as_slice()
+                    .get(peer_idx.as_usize())
+                    .copied()?;
+                Some((peer_pubkey, block.sigs.get(&peer_idx).copied()))
+            })
+            .collect();
+
+        let citems = match consensus.get_block_payload(block.inner.payload_hash).await {
+            Some(payload) => {
+                let citems = payload.decode_citems()?;
+                let mut entries = node_ref
+                    .db()
+                    .read_with(|dbtx| citem_index::get_round_citems_dbtx(dbtx, round))
+                    .await
+                    .whatever_context("Failed to query citem index")?
+                    .into_iter()
+                    .map(|(location, entry)| (location.idx.to_number(), entry))
+                    .collect::<BTreeMap<_, _>>();
+                Some(
+                    citems
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, citem)| {
+                            let entry =
+                                u32::try_from(idx).ok().and_then(|idx| entries.remove(&idx));
+                            (citem.clone(), entry)
+                        })
+                        .collect(),
+                )
+            }
+            None => None,
+        };
+
+        Ok(Some(BlockDetails {
+            block_header: block.inner,
+            signatures,
+            aggregate_signature: block.aggregate,
+            citems,
+        }))
+    }
+
+    async fn get_citems(
+        &self,
+        filter: CItemIndexFilter,
+        before: Option<CItemLocation>,
+        limit: usize,
+    ) -> WhateverResult<Vec<(CItemLocation, CItemIndexEntry)>> {
+        self.node_ref()?
+            .db()
+            .read_with(|dbtx| citem_index::query_citems_dbtx(dbtx, &filter, before, limit))
+            .await
+            .whatever_context("Failed to query citem index")
+    }
+
+    async fn get_citem_by_tx_hash(
+        &self,
+        tx_hash: TransactionHash,
+    ) -> WhateverResult<Option<(CItemLocation, CItemIndexEntry)>> {
+        self.node_ref()?
+            .db()
+            .read_with(|dbtx| citem_index::get_citem_by_tx_hash_dbtx(dbtx, tx_hash))
+            .await
+            .whatever_context("Failed to query citem index")
+    }
+
+    async fn sign_key_rotation(&self, new_pubkey: PeerPubkey) -> WhateverResult<SignedKeyRotation> {
+        let node_ref = self.node_ref()?;
+        let Some(old_pubkey) = node_ref.peer_pubkey else {
+            whatever!("Cannot rotate key: not a peer");
+        };
+        // Otherwise the node would be left running as a non-peer, with no
+        // way to take over the new key
+        if node_ref.next_peer_pubkey != Some(new_pubkey) {
+            whatever!("Cannot rotate key: secret of the new key must be loaded first");
+        }
+        node_ref
+            .signer_expect()
+            .sign(KeyRotation {
+                old_pubkey,
+                new_pubkey,
+            })
+            .await
+            .whatever_context("Failed to sign key rotation")
+    }
+
+    async fn get_bls_key_registration(&self) -> WhateverResult<Option<BlsKeyRegistration>> {
+        let node_ref = self.node_ref()?;
+        let Some(signer) = node_ref.signer() else {
+            return Ok(None);
+        };
+        Ok(Some(
+            signer
+                .bls_key_registration()
+                .await
+                .whatever_context("Failed to get BLS key registration")?,
+        ))
+    }
+
+    async fn simulate_citem(&self, citem: CItem) -> WhateverResult<CItemSimulation> {
+        let node_ref = self.node_ref()?;
+        let Some(peer_pubkey) = node_ref.peer_pubkey else {
+            whatever!("Cannot simulate citems: not a peer");
+        };
+        node_ref.simulate_citem(citem, peer_pubkey).await
+    }
 }
 
 impl Node {
@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use bfte_consensus_core::block::BlockRound;
+use bfte_db::Database;
+use bfte_db::error::TxSnafu;
+use snafu::ResultExt as _;
+
+use crate::{DoubleSignSnafu, SignResult, SignedMsgKind, tables};
+
+/// Persistent record of signed messages, refusing conflicting ones
+pub(crate) struct DoubleSignGuard {
+    db: Arc<Database>,
+    /// Last recorded round and hash per kind, as persisted in the database
+    recorded: Mutex<BTreeMap<SignedMsgKind, (BlockRound, [u8; 32])>>,
+}
+
+impl DoubleSignGuard {
+    pub(crate) fn new(db: Arc<Database>) -> Self {
+        Self {
+            db,
+            recorded: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Record signing a `kind` message with `hash` in `round`
+    ///
+    /// Fails if it conflicts with a message signed before. Must be called
+    /// (and succeed) before the signature is produced.
+    pub(crate) async fn record(
+        &self,
+        kind: SignedMsgKind,
+        round: BlockRound,
+        hash: blake3::Hash,
+    ) -> SignResult<()> {
+        let hash = *hash.as_bytes();
+
+        // The same message is often signed again (e.g. every peer asks for
+        // our vote), and there's nothing new to persist then
+        if self
+            .recorded
+            .lock()
+            .expect("Locking failed")
+            .get(&kind)
+            .is_some_and(|recorded| *recorded == (round, hash))
+        {
+            return Ok(());
+        }
+
+        self.db
+            .write_with_expect_falliable(|dbtx| {
+                let mut tbl = dbtx.open_table(&tables::signer_highest_signed::TABLE)?;
+
+                if let Some((signed_round, signed_hash)) = tbl.get(&kind)?.map(|v| v.value()) {
+                    if round < signed_round || (round == signed_round && hash != signed_hash) {
+                        return DoubleSignSnafu {
+                            kind,
+                            round,
+                            signed_round,
+                        }
+                        .fail()
+                        .context(TxSnafu);
+                    }
+                    if round == signed_round {
+                        return Ok(());
+                    }
+                }
+
+                tbl.insert(&kind, &(round, hash))?;
+                Ok(())
+            })
+            .await?;
+
+        self.recorded
+            .lock()
+            .expect("Locking failed")
+            .insert(kind, (round, hash));
+        Ok(())
+    }
+}
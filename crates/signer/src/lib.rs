@@ -0,0 +1,147 @@
+#![doc = include_str!("../README.md")]
+
+mod guard;
+pub mod local;
+mod proto;
+pub mod remote;
+pub mod server;
+mod tables;
+
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bfte_consensus_core::Signature;
+use bfte_consensus_core::block::{BlockHeader, BlockRound};
+use bfte_consensus_core::bls::{BlsKeyRegistration, BlsSignature};
+use bfte_consensus_core::msg::FinalityVoteUpdate;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::signed::{Signable, Signed};
+use bincode::{Decode, Encode};
+use snafu::Snafu;
+
+const LOG_TARGET: &str = "bfte::signer";
+
+/// Kind of a message protected from double-signing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+pub enum SignedMsgKind {
+    /// Non-dummy block header: a proposal, or a vote on it
+    Block,
+    /// Dummy block header: a timeout vote
+    DummyBlock,
+    FinalityVote,
+}
+
+impl fmt::Display for SignedMsgKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SignedMsgKind::Block => "block",
+            SignedMsgKind::DummyBlock => "dummy block",
+            SignedMsgKind::FinalityVote => "finality vote",
+        })
+    }
+}
+
+impl SignedMsgKind {
+    pub fn of_block(block: &BlockHeader) -> Self {
+        if block.is_dummy() {
+            SignedMsgKind::DummyBlock
+        } else {
+            SignedMsgKind::Block
+        }
+    }
+
+    /// Protected message kind of a [`Signable`] type tag, if any
+    pub fn from_tag(tag: [u8; 4]) -> Option<Self> {
+        if tag == BlockHeader::TAG {
+            Some(SignedMsgKind::Block)
+        } else if tag == FinalityVoteUpdate::TAG {
+            Some(SignedMsgKind::FinalityVote)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum SignError {
+    #[snafu(display(
+        "Refusing to sign {kind} in round {round}: conflicting one already signed in round {signed_round}"
+    ))]
+    DoubleSign {
+        kind: SignedMsgKind,
+        round: BlockRound,
+        signed_round: BlockRound,
+    },
+    #[snafu(display("Refusing to sign {kind} without double-sign protection"))]
+    Protected { kind: SignedMsgKind },
+    #[snafu(display("Remote signer refused: {reason}"))]
+    Refused { reason: String },
+    #[snafu(display("Remote signer communication failed"))]
+    Io { source: std::io::Error },
+    #[snafu(display("Invalid remote signer response"))]
+    InvalidResponse,
+}
+
+pub type SignResult<T> = Result<T, SignError>;
+
+/// Signs messages on behalf of a peer
+///
+/// Messages that could conflict with each other (blocks and votes) are only
+/// signed if they don't conflict with anything signed before.
+#[async_trait]
+pub trait ISigner {
+    /// Public key of the peer this signer signs for
+    fn pubkey(&self) -> PeerPubkey;
+
+    /// Registration of the BLS key of the peer
+    async fn bls_key_registration(&self) -> SignResult<BlsKeyRegistration>;
+
+    /// Sign a block header: a proposal, a vote on it, or a dummy (timeout)
+    /// vote
+    ///
+    /// If `bls` is set, a BLS signature of it is returned as well.
+    async fn sign_block(
+        &self,
+        block: BlockHeader,
+        bls: bool,
+    ) -> SignResult<(Signed<BlockHeader>, Option<BlsSignature>)>;
+
+    async fn sign_finality_vote(
+        &self,
+        vote: FinalityVoteUpdate,
+    ) -> SignResult<Signed<FinalityVoteUpdate>>;
+
+    /// Sign a [`Signable`] message given only its type `tag` and `hash`
+    ///
+    /// Only for messages not protected from double-signing, see
+    /// [`SignedMsgKind::from_tag`].
+    async fn sign_hash(&self, tag: [u8; 4], hash: blake3::Hash) -> SignResult<Signature>;
+}
+
+pub type DynSigner = Arc<dyn ISigner + Send + Sync + 'static>;
+
+#[async_trait]
+pub trait SignerExt {
+    /// Sign a message not protected from double-signing
+    async fn sign<T>(&self, inner: T) -> SignResult<Signed<T>>
+    where
+        T: Signable + Send + 'static;
+}
+
+#[async_trait]
+impl<S> SignerExt for S
+where
+    S: ISigner + Sync + ?Sized,
+{
+    async fn sign<T>(&self, inner: T) -> SignResult<Signed<T>>
+    where
+        T: Signable + Send + 'static,
+    {
+        let sig = self.sign_hash(T::TAG, inner.hash()).await?;
+        Ok(Signed::new(inner, sig))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bfte_consensus_core::Signature;
+use bfte_consensus_core::block::BlockHeader;
+use bfte_consensus_core::bls::{BlsKeyRegistration, BlsSeckey, BlsSignature};
+use bfte_consensus_core::msg::FinalityVoteUpdate;
+use bfte_consensus_core::peer::{PeerPubkey, PeerSeckey};
+use bfte_consensus_core::signed::{
+    Hashable, Signable as _, Signed, sign_hash_with, signable_sign_hash,
+};
+use bfte_db::Database;
+use bfte_db::migration::Migration;
+
+use crate::guard::DoubleSignGuard;
+use crate::{ISigner, ProtectedSnafu, SignResult, SignedMsgKind};
+
+/// Schema migrations of a database dedicated to a [`LocalSigner`]
+///
+/// Append-only, see [`Migration`].
+pub const DB_MIGRATIONS: &[Migration] = &[];
+
+/// [`ISigner`] holding the [`PeerSeckey`] in memory
+///
+/// Signed messages are recorded in the passed database.
+pub struct LocalSigner {
+    seckey: PeerSeckey,
+    guard: DoubleSignGuard,
+}
+
+impl LocalSigner {
+    pub fn new(seckey: PeerSeckey, db: Arc<Database>) -> Self {
+        Self {
+            seckey,
+            guard: DoubleSignGuard::new(db),
+        }
+    }
+}
+
+#[async_trait]
+impl ISigner for LocalSigner {
+    fn pubkey(&self) -> PeerPubkey {
+        self.seckey.pubkey()
+    }
+
+    async fn bls_key_registration(&self) -> SignResult<BlsKeyRegistration> {
+        Ok(BlsSeckey::derive_from(self.seckey).registration(self.seckey.pubkey()))
+    }
+
+    async fn sign_block(
+        &self,
+        block: BlockHeader,
+        bls: bool,
+    ) -> SignResult<(Signed<BlockHeader>, Option<BlsSignature>)> {
+        self.guard
+            .record(
+                SignedMsgKind::of_block(&block),
+                block.round,
+                Hashable::hash(&block),
+            )
+            .await?;
+
+        let bls_sig = bls.then(|| block.sign_bls_with(BlsSeckey::derive_from(self.seckey)));
+        Ok((Signed::new_sign(block, self.seckey), bls_sig))
+    }
+
+    async fn sign_finality_vote(
+        &self,
+        vote: FinalityVoteUpdate,
+    ) -> SignResult<Signed<FinalityVoteUpdate>> {
+        self.guard
+            .record(SignedMsgKind::FinalityVote, vote.0, vote.hash())
+            .await?;
+
+        Ok(Signed::new_sign(vote, self.seckey))
+    }
+
+    async fn sign_hash(&self, tag: [u8; 4], hash: blake3::Hash) -> SignResult<Signature> {
+        if let Some(kind) = SignedMsgKind::from_tag(tag) {
+            return ProtectedSnafu { kind }.fail();
+        }
+
+        Ok(sign_hash_with(signable_sign_hash(tag, hash), self.seckey))
+    }
+}
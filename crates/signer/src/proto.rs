@@ -0,0 +1,69 @@
+//! Protocol between [`crate::remote::RemoteSigner`] and [`crate::server`]
+//!
+//! Each message is a `u32` (big endian) length followed by a bincode-encoded
+//! [`SignRequest`] or [`SignResponse`].
+
+use std::io;
+
+use bfte_consensus_core::Signature;
+use bfte_consensus_core::bincode::CONSENSUS_BINCODE_CONFIG;
+use bfte_consensus_core::block::BlockHeader;
+use bfte_consensus_core::bls::{BlsKeyRegistration, BlsSignature};
+use bfte_consensus_core::msg::FinalityVoteUpdate;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_util_bincode::decode_whole;
+use bincode::{Decode, Encode};
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+
+/// Max length of a single message
+const MAX_MESSAGE_LEN: u32 = 64 * 1024;
+
+#[derive(Debug, Encode, Decode)]
+pub(crate) enum SignRequest {
+    Pubkey,
+    BlsKeyRegistration,
+    Block { block: BlockHeader, bls: bool },
+    FinalityVote(FinalityVoteUpdate),
+    Hash { tag: [u8; 4], hash: [u8; 32] },
+}
+
+#[derive(Debug, Encode, Decode)]
+pub(crate) enum SignResponse {
+    Pubkey(PeerPubkey),
+    BlsKeyRegistration(BlsKeyRegistration),
+    Signature {
+        sig: Signature,
+        bls_sig: Option<BlsSignature>,
+    },
+    Refused(String),
+}
+
+pub(crate) async fn write_message<T: Encode>(
+    stream: &mut (impl AsyncWrite + Unpin),
+    msg: &T,
+) -> io::Result<()> {
+    let bytes = bincode::encode_to_vec(msg, CONSENSUS_BINCODE_CONFIG).expect("Can't fail");
+    let len = u32::try_from(bytes.len()).expect("Messages are small");
+
+    stream.write_u32(len).await?;
+    stream.write_all(&bytes).await?;
+    stream.flush().await
+}
+
+pub(crate) async fn read_message<T: Decode<()>>(
+    stream: &mut (impl AsyncRead + Unpin),
+) -> io::Result<T> {
+    let len = stream.read_u32().await?;
+    if MAX_MESSAGE_LEN < len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Message too long",
+        ));
+    }
+
+    let mut bytes = vec![0; usize::try_from(len).expect("Can't fail")];
+    stream.read_exact(&mut bytes).await?;
+
+    decode_whole(&bytes, CONSENSUS_BINCODE_CONFIG)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
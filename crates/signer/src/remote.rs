@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use bfte_consensus_core::Signature;
+use bfte_consensus_core::block::BlockHeader;
+use bfte_consensus_core::bls::{BlsKeyRegistration, BlsSignature};
+use bfte_consensus_core::msg::FinalityVoteUpdate;
+use bfte_consensus_core::peer::PeerPubkey;
+use bfte_consensus_core::signed::Signed;
+use snafu::ResultExt as _;
+use tokio::net::UnixStream;
+
+use crate::proto::{self, SignRequest, SignResponse};
+use crate::{ISigner, InvalidResponseSnafu, IoSnafu, RefusedSnafu, SignResult};
+
+/// [`ISigner`] forwarding requests to a signer process (see
+/// [`crate::server`]) over a Unix socket
+pub struct RemoteSigner {
+    socket_path: PathBuf,
+    pubkey: PeerPubkey,
+}
+
+impl RemoteSigner {
+    /// Connect to a signer process listening on `socket_path`
+    pub async fn connect(socket_path: impl Into<PathBuf>) -> SignResult<Self> {
+        let socket_path = socket_path.into();
+
+        let SignResponse::Pubkey(pubkey) = Self::call_at(&socket_path, SignRequest::Pubkey).await?
+        else {
+            return InvalidResponseSnafu.fail();
+        };
+
+        Ok(Self {
+            socket_path,
+            pubkey,
+        })
+    }
+
+    async fn call_at(socket_path: &Path, req: SignRequest) -> SignResult<SignResponse> {
+        let mut stream = UnixStream::connect(socket_path).await.context(IoSnafu)?;
+
+        proto::write_message(&mut stream, &req)
+            .await
+            .context(IoSnafu)?;
+
+        match proto::read_message(&mut stream).await.context(IoSnafu)? {
+            SignResponse::Refused(reason) => RefusedSnafu { reason }.fail(),
+            resp => Ok(resp),
+        }
+    }
+
+    async fn call_sign(&self, req: SignRequest) -> SignResult<(Signature, Option<BlsSignature>)> {
+        let SignResponse::Signature { sig, bls_sig } =
+            Self::call_at(&self.socket_path, req).await?
+        else {
+            return InvalidResponseSnafu.fail();
+        };
+        Ok((sig, bls_sig))
+    }
+}
+
+#[async_trait]
+impl ISigner for RemoteSigner {
+    fn pubkey(&self) -> PeerPubkey {
+        self.pubkey
+    }
+
+    async fn bls_key_registration(&self) -> SignResult<BlsKeyRegistration> {
+        let SignResponse::BlsKeyRegistration(registration) =
+            Self::call_at(&self.socket_path, SignRequest::BlsKeyRegistration).await?
+        else {
+            return InvalidResponseSnafu.fail();
+        };
+        Ok(registration)
+    }
+
+    async fn sign_block(
+        &self,
+        block: BlockHeader,
+        bls: bool,
+    ) -> SignResult<(Signed<BlockHeader>, Option<BlsSignature>)> {
+        let (sig, bls_sig) = self.call_sign(SignRequest::Block { block, bls }).await?;
+        if bls_sig.is_some() != bls {
+            return InvalidResponseSnafu.fail();
+        }
+        Ok((Signed::new(block, sig), bls_sig))
+    }
+
+    async fn sign_finality_vote(
+        &self,
+        vote: FinalityVoteUpdate,
+    ) -> SignResult<Signed<FinalityVoteUpdate>> {
+        let (sig, _) = self
+            .call_sign(SignRequest::FinalityVote(vote.clone()))
+            .await?;
+        Ok(Signed::new(vote, sig))
+    }
+
+    async fn sign_hash(&self, tag: [u8; 4], hash: blake3::Hash) -> SignResult<Signature> {
+        let (sig, _) = self
+            .call_sign(SignRequest::Hash {
+                tag,
+                hash: *hash.as_bytes(),
+            })
+            .await?;
+        Ok(sig)
+    }
+}
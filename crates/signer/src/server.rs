@@ -0,0 +1,113 @@
+//! Signer process side of [`crate::remote::RemoteSigner`]
+
+use std::convert::Infallible;
+use std::io;
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::Path;
+use std::sync::Arc;
+
+use bfte_util_error::WhateverResult;
+use bfte_util_error::fmt::FmtCompact as _;
+use snafu::ResultExt as _;
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{debug, info, warn};
+
+use crate::local::LocalSigner;
+use crate::proto::{self, SignRequest, SignResponse};
+use crate::{ISigner as _, LOG_TARGET};
+
+/// Serve `signer` on a Unix socket at `socket_path`
+///
+/// A stale socket file at `socket_path` is replaced. The socket is
+/// accessible only to the current user.
+pub async fn run(socket_path: &Path, signer: LocalSigner) -> WhateverResult<Infallible> {
+    if tokio::fs::try_exists(socket_path)
+        .await
+        .whatever_context("Failed to check signer socket")?
+    {
+        tokio::fs::remove_file(socket_path)
+            .await
+            .whatever_context("Failed to remove stale signer socket")?;
+    }
+
+    let listener =
+        UnixListener::bind(socket_path).whatever_context("Failed to bind signer socket")?;
+    tokio::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o600))
+        .await
+        .whatever_context("Failed to set signer socket permissions")?;
+
+    info!(
+        target: LOG_TARGET,
+        path = %socket_path.display(),
+        pubkey = %signer.pubkey(),
+        "Signer listening"
+    );
+
+    let signer = Arc::new(signer);
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .whatever_context("Failed to accept signer connection")?;
+
+        tokio::spawn({
+            let signer = signer.clone();
+            async move {
+                if let Err(err) = handle_connection(&signer, stream).await {
+                    debug!(target: LOG_TARGET, err = %err.fmt_compact(), "Signer connection failed");
+                }
+            }
+        });
+    }
+}
+
+async fn handle_connection(signer: &LocalSigner, mut stream: UnixStream) -> io::Result<()> {
+    loop {
+        let req = match proto::read_message::<SignRequest>(&mut stream).await {
+            Ok(req) => req,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        let resp = handle_request(signer, req).await;
+
+        proto::write_message(&mut stream, &resp).await?;
+    }
+}
+
+async fn handle_request(signer: &LocalSigner, req: SignRequest) -> SignResponse {
+    let res = match req {
+        SignRequest::Pubkey => Ok(SignResponse::Pubkey(signer.pubkey())),
+        SignRequest::BlsKeyRegistration => signer
+            .bls_key_registration()
+            .await
+            .map(SignResponse::BlsKeyRegistration),
+        SignRequest::Block { block, bls } => {
+            signer
+                .sign_block(block, bls)
+                .await
+                .map(|(signed, bls_sig)| SignResponse::Signature {
+                    sig: signed.sig,
+                    bls_sig,
+                })
+        }
+        SignRequest::FinalityVote(vote) => {
+            signer
+                .sign_finality_vote(vote)
+                .await
+                .map(|signed| SignResponse::Signature {
+                    sig: signed.sig,
+                    bls_sig: None,
+                })
+        }
+        SignRequest::Hash { tag, hash } => signer
+            .sign_hash(tag, blake3::Hash::from_bytes(hash))
+            .await
+            .map(|sig| SignResponse::Signature { sig, bls_sig: None }),
+    };
+
+    res.unwrap_or_else(|err| {
+        warn!(target: LOG_TARGET, err = %err.fmt_compact(), "Refusing to sign");
+        SignResponse::Refused(err.fmt_compact().to_string())
+    })
+}
@@ -0,0 +1,10 @@
+use bfte_consensus_core::block::BlockRound;
+use bfte_util_db::def_table;
+
+use crate::SignedMsgKind;
+
+def_table! {
+    /// Highest round signed, along with the hash of the message signed in it,
+    /// per message kind
+    signer_highest_signed: SignedMsgKind => (BlockRound, [u8; 32])
+}
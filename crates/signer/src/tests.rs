@@ -0,0 +1,125 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use bfte_consensus_core::block::{BlockHeader, BlockPayloadRaw, BlockRound};
+use bfte_consensus_core::consensus_params::ConsensusParams;
+use bfte_consensus_core::key_rotation::KeyRotation;
+use bfte_consensus_core::msg::FinalityVoteUpdate;
+use bfte_consensus_core::peer::PeerSeckey;
+use bfte_consensus_core::signed::Signable as _;
+use bfte_consensus_core::timestamp::Timestamp;
+use bfte_db::Database;
+use bfte_util_error::BoxedErrorResult;
+use rand::Rng as _;
+
+use crate::local::LocalSigner;
+use crate::remote::RemoteSigner;
+use crate::{ISigner, SignError, SignerExt as _, server};
+
+fn make_block(round: u64, payload: &[u8]) -> BlockHeader {
+    BlockHeader::builder()
+        .consensus_params(&ConsensusParams::new_test_dummy())
+        .round(BlockRound::from(round))
+        .payload(&BlockPayloadRaw::from(payload.to_vec()))
+        .timestamp(Timestamp::ZERO)
+        .build()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn local_signer_refuses_double_signing() -> BoxedErrorResult<()> {
+    let db = Arc::new(Database::new_in_memory().await?);
+    let signer = LocalSigner::new(PeerSeckey::generate(), db);
+
+    let (signed, bls_sig) = signer.sign_block(make_block(2, &[0]), true).await?;
+    signed.verify_sig_peer_pubkey(signer.pubkey())?;
+    assert!(bls_sig.is_some());
+
+    // Signing the same block again is fine
+    signer.sign_block(make_block(2, &[0]), false).await?;
+
+    assert!(matches!(
+        signer.sign_block(make_block(2, &[1]), false).await,
+        Err(SignError::DoubleSign { .. })
+    ));
+    assert!(matches!(
+        signer.sign_block(make_block(1, &[1]), false).await,
+        Err(SignError::DoubleSign { .. })
+    ));
+    signer.sign_block(make_block(3, &[1]), false).await?;
+    signer.sign_block(make_block(3, &[1]), false).await?;
+    assert!(matches!(
+        signer.sign_block(make_block(3, &[0]), false).await,
+        Err(SignError::DoubleSign { .. })
+    ));
+
+    // Timeout votes are tracked separately
+    let params = ConsensusParams::new_test_dummy();
+    signer
+        .sign_block(BlockHeader::new_dummy(2.into(), &params), false)
+        .await?;
+
+    signer
+        .sign_finality_vote(FinalityVoteUpdate::new(5.into()))
+        .await?;
+    assert!(matches!(
+        signer
+            .sign_finality_vote(FinalityVoteUpdate::new(4.into()))
+            .await,
+        Err(SignError::DoubleSign { .. })
+    ));
+
+    // Protected messages can't be signed by tag and hash
+    assert!(matches!(
+        signer
+            .sign_hash(BlockHeader::TAG, blake3::hash(b"block"))
+            .await,
+        Err(SignError::Protected { .. })
+    ));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn remote_signer_sanity() -> BoxedErrorResult<()> {
+    let socket_path = std::env::temp_dir().join(format!(
+        "bfte-signer-test-{}.sock",
+        rand::thread_rng().r#gen::<u64>()
+    ));
+    let seckey = PeerSeckey::generate();
+    let db = Arc::new(Database::new_in_memory().await?);
+
+    let _server = tokio::spawn({
+        let socket_path = socket_path.clone();
+        async move { server::run(&socket_path, LocalSigner::new(seckey, db)).await }
+    });
+
+    let remote = loop {
+        if let Ok(remote) = RemoteSigner::connect(&socket_path).await {
+            break remote;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+    assert_eq!(remote.pubkey(), seckey.pubkey());
+
+    let (signed, bls_sig) = remote.sign_block(make_block(1, &[0]), true).await?;
+    signed.verify_sig_peer_pubkey(seckey.pubkey())?;
+    assert!(bls_sig.is_some());
+
+    let rotation = KeyRotation {
+        old_pubkey: seckey.pubkey(),
+        new_pubkey: PeerSeckey::generate().pubkey(),
+    };
+    remote.sign(rotation).await?.verify()?;
+
+    // A new client (e.g. the node restarted with its database lost) can't
+    // sign a conflicting block either
+    let remote = RemoteSigner::connect(&socket_path).await?;
+    assert!(matches!(
+        remote.sign_block(make_block(1, &[1]), false).await,
+        Err(SignError::Refused { .. })
+    ));
+
+    let _ = std::fs::remove_file(&socket_path);
+
+    Ok(())
+}
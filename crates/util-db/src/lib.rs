@@ -1,24 +1,47 @@
 // SPDX-License-Identifier: MIT
 
 pub mod random;
+pub mod versioned;
 
 pub use ::redb_bincode;
 
+/// Define a table
+///
+/// Prefixing the name with `versioned` additionally defines a `HISTORY_TABLE`
+/// (named `{name}_history`) recording all the changes, which allows reading
+/// the table as of a past round. See [`versioned`].
 #[macro_export]
 macro_rules! def_table {
+    ($(#[$outer:meta])*
+        versioned $name:ident : $k:ty => $v:ty) => {
+        #[allow(unused)]
+        $(#[$outer])*
+        pub mod $name {
+            use super::*;
+            $crate::def_table!(@items $name : $k => $v);
+            pub type HistoryKey = (Key, $crate::versioned::HistoryRound);
+            pub type HistoryValue = Option<Value>;
+            pub type HistoryDefinition<'a> = $crate::redb_bincode::TableDefinition<'a, HistoryKey, HistoryValue>;
+            pub type HistoryTable<'a> = $crate::redb_bincode::Table<'a, HistoryKey, HistoryValue>;
+            pub const HISTORY_TABLE: HistoryDefinition = $crate::redb_bincode::TableDefinition::new(concat!(stringify!($name), "_history"));
+        }
+    };
     ($(#[$outer:meta])*
         $name:ident : $k:ty => $v:ty) => {
         #[allow(unused)]
         $(#[$outer])*
         pub mod $name {
             use super::*;
-            pub type Key = $k;
-            pub type Value = $v;
-            pub type Definition<'a> = $crate::redb_bincode::TableDefinition<'a, Key, Value>;
-            pub trait ReadableTable: $crate::redb_bincode::ReadableTable<Key, Value> {}
-            impl<RT> ReadableTable for RT where RT: $crate::redb_bincode::ReadableTable<Key, Value> {}
-            pub type Table<'a> = $crate::redb_bincode::Table<'a, Key, Value>;
-            pub const TABLE: Definition = $crate::redb_bincode::TableDefinition::new(stringify!($name));
+            $crate::def_table!(@items $name : $k => $v);
         }
     };
+    (@items $name:ident : $k:ty => $v:ty) => {
+        pub type Key = $k;
+        pub type Value = $v;
+        pub type Definition<'a> = $crate::redb_bincode::TableDefinition<'a, Key, Value>;
+        pub trait ReadableTable: $crate::redb_bincode::ReadableTable<Key, Value> {}
+        impl<RT> ReadableTable for RT where RT: $crate::redb_bincode::ReadableTable<Key, Value> {}
+        pub type Table<'a> = $crate::redb_bincode::Table<'a, Key, Value>;
+        pub const TABLE: Definition = $crate::redb_bincode::TableDefinition::new(stringify!($name));
+    };
 }
@@ -0,0 +1,111 @@
+//! Versioned tables
+//!
+//! A table defined with `versioned` in [`crate::def_table`] is accompanied by
+//! a history table recording every change as `(key, round) -> value`, with
+//! `None` value marking a removal. Changes must be done using [`insert`] and
+//! [`remove`] to keep both tables in sync.
+
+use std::collections::BTreeMap;
+
+use bincode::{Decode, Encode};
+use redb_bincode::{ReadableTable, StorageError, Table};
+
+/// Round a change of a versioned table was made in
+///
+/// Fixed size big endian encoded, so history of a key is ordered by round.
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HistoryRound([u8; 8]);
+
+impl HistoryRound {
+    pub const ZERO: Self = Self([0; 8]);
+    pub const MAX: Self = Self([0xff; 8]);
+}
+
+impl From<u64> for HistoryRound {
+    fn from(value: u64) -> Self {
+        Self(value.to_be_bytes())
+    }
+}
+
+impl From<HistoryRound> for u64 {
+    fn from(value: HistoryRound) -> Self {
+        u64::from_be_bytes(value.0)
+    }
+}
+
+/// Insert `value` under `key`, recording the change as made in `round`
+pub fn insert<K, V>(
+    tbl: &mut Table<K, V>,
+    history_tbl: &mut Table<(K, HistoryRound), Option<V>>,
+    round: HistoryRound,
+    key: &K,
+    value: &V,
+) -> Result<(), StorageError>
+where
+    K: Decode<()> + Encode + Clone,
+    V: Decode<()> + Encode + Clone,
+{
+    tbl.insert(key, value)?;
+    history_tbl.insert(&(key.clone(), round), &Some(value.clone()))?;
+    Ok(())
+}
+
+/// Remove `key`, recording the change as made in `round`
+pub fn remove<K, V>(
+    tbl: &mut Table<K, V>,
+    history_tbl: &mut Table<(K, HistoryRound), Option<V>>,
+    round: HistoryRound,
+    key: &K,
+) -> Result<(), StorageError>
+where
+    K: Decode<()> + Encode + Clone,
+    V: Decode<()> + Encode,
+{
+    if tbl.remove(key)?.is_some() {
+        history_tbl.insert(&(key.clone(), round), &None)?;
+    }
+    Ok(())
+}
+
+/// Value of `key` as of the end of `round`
+pub fn get_at<K, V>(
+    history_tbl: &impl ReadableTable<(K, HistoryRound), Option<V>>,
+    key: &K,
+    round: HistoryRound,
+) -> Result<Option<V>, StorageError>
+where
+    K: Decode<()> + Encode + Clone,
+    V: Decode<()> + Encode,
+{
+    Ok(history_tbl
+        .range((key.clone(), HistoryRound::ZERO)..=(key.clone(), round))?
+        .next_back()
+        .transpose()?
+        .and_then(|(_, v)| v.value()))
+}
+
+/// All entries as of the end of `round`
+pub fn entries_at<K, V>(
+    history_tbl: &impl ReadableTable<(K, HistoryRound), Option<V>>,
+    round: HistoryRound,
+) -> Result<BTreeMap<K, V>, StorageError>
+where
+    K: Decode<()> + Encode + Ord,
+    V: Decode<()> + Encode,
+{
+    let mut entries = BTreeMap::new();
+
+    for kv in history_tbl.range(..)? {
+        let (k, v) = kv?;
+        let (key, change_round) = k.value();
+        if round < change_round {
+            continue;
+        }
+        match v.value() {
+            Some(value) => entries.insert(key, value),
+            None => entries.remove(&key),
+        };
+    }
+
+    Ok(entries)
+}